@@ -0,0 +1,109 @@
+use actix_web::{get, web, HttpResponse};
+
+use crate::error::ApiError;
+use crate::shared::models::{AddressParam, PaginationAndFilterParams};
+use crate::shared::neo;
+use crate::shared::storage::ReadStorage;
+use crate::shared::utils::normalize_filter;
+
+use super::internals;
+use super::models::{AddressSummary, Portfolio, PortfolioToken};
+
+#[get("/v1/address/{address}")]
+async fn get_address_summary(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<AddressParam>,
+) -> Result<HttpResponse, ApiError> {
+    let address = path.into_inner().into_inner();
+
+    let seen_range = storage.get_seen_range(address.clone()).await?;
+    let balances = storage.get_current_balances(address.clone()).await?;
+
+    if seen_range.is_none() && balances.is_empty() {
+        return Err(ApiError::NotFound(
+            "No activity for that address.".to_string(),
+        ));
+    }
+
+    let (first_seen, last_seen) = seen_range.unzip();
+
+    Ok(HttpResponse::Ok().json(AddressSummary {
+        address: address.clone(),
+        script_hash: format!("0x{}", neo::address_to_hash160(&address)),
+        first_seen,
+        last_seen,
+        sender_transactions: storage.count_sender_transactions(address.clone()).await?,
+        participant_transactions: storage.count_address_transfers(address.clone()).await?,
+        total_fees: storage.get_total_fees(address.clone()).await?,
+        balances,
+        deployed_contracts: storage.get_deployed_contracts(address.clone()).await?,
+    }))
+}
+
+#[get("/v1/address/{address}/portfolio")]
+async fn get_address_portfolio(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<AddressParam>,
+) -> Result<HttpResponse, ApiError> {
+    let address = path.into_inner().into_inner();
+
+    let balances = storage.get_current_balances(address.clone()).await?;
+
+    if balances.is_empty() {
+        return Err(ApiError::NotFound(
+            "No balances for that address.".to_string(),
+        ));
+    }
+
+    let mut tokens = Vec::new();
+    for balance in balances {
+        let (price_usd, price_date) = storage
+            .get_latest_price(&balance.token_contract)
+            .await?
+            .unzip();
+
+        tokens.push(PortfolioToken {
+            value_usd: balance
+                .decimals
+                .zip(price_usd)
+                .map(|(decimals, price)| internals::value_usd(balance.balance, decimals, price)),
+            token_contract: balance.token_contract,
+            symbol: balance.symbol,
+            decimals: balance.decimals,
+            balance: balance.balance,
+            price_usd,
+            price_date,
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(Portfolio {
+        address,
+        total_usd: tokens
+            .iter()
+            .filter_map(|t| t.value_usd)
+            .fold(0.0, |total, value| total + value),
+        tokens,
+    }))
+}
+
+#[get("/v1/address/{address}/portfolio/history")]
+async fn get_address_portfolio_history(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<AddressParam>,
+    query_parameter: web::Query<PaginationAndFilterParams>,
+) -> Result<HttpResponse, ApiError> {
+    let address = path.into_inner().into_inner();
+    let (date_init, date_end) = normalize_filter(&query_parameter)?;
+
+    let history = storage
+        .get_portfolio_history(address.clone(), date_init, date_end)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(history))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_address_summary)
+        .service(get_address_portfolio)
+        .service(get_address_portfolio_history);
+}
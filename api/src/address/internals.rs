@@ -0,0 +1,404 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+
+use chrono::{Duration, NaiveDate};
+
+use std::collections::HashMap;
+
+use crate::address::models::{PortfolioDay, SeenAt, TokenBalance};
+use crate::block::internals as block_internals;
+use crate::contract::internals as contract_internals;
+use crate::error::ApiError;
+use crate::shared::models::{CONTRACT_MANAGEMENT_HASH, GAS_PRECISION, NATIVE_TOKENS};
+use crate::shared::neo;
+
+// blocks where the address first and last appears as a sender or in any notification
+pub fn get_seen_range_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    address: String,
+) -> Result<Option<(SeenAt, SeenAt)>, ApiError> {
+    let sql = "
+        SELECT MIN(block_index), MAX(block_index)
+        FROM (
+            SELECT block_index FROM transactions WHERE sender = ?
+            UNION ALL
+            SELECT t.block_index
+            FROM transactions t
+            INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+            INNER JOIN transaction_notification_state_values nsv ON tn.id = nsv.transaction_notification_id
+            WHERE nsv.value = ?
+        )";
+
+    let base64 = neo::address_to_base64(&address);
+    let range = conn.query_row(sql, params![address, base64], |row| {
+        Ok((row.get::<_, Option<u64>>(0)?, row.get::<_, Option<u64>>(1)?))
+    })?;
+
+    match range {
+        (Some(first), Some(last)) => Ok(Some((
+            SeenAt {
+                block_index: first,
+                time: block_internals::get_block_time(conn, first.to_string())?,
+            },
+            SeenAt {
+                block_index: last,
+                time: block_internals::get_block_time(conn, last.to_string())?,
+            },
+        ))),
+        _ => Ok(None),
+    }
+}
+
+pub fn get_total_fees_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    address: String,
+) -> Result<f64, ApiError> {
+    let sql = "
+        SELECT COALESCE(SUM(CAST(sysfee AS INTEGER) + CAST(netfee AS INTEGER)), 0)
+        FROM transactions
+        WHERE sender = ?";
+
+    let fees = conn.query_row(sql, params![address], |row| row.get::<_, i64>(0))?;
+
+    Ok(fees as f64 / GAS_PRECISION)
+}
+
+// the latest stored day for each token holds the current balance
+pub fn get_current_balances_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    address: String,
+) -> Result<Vec<TokenBalance>, ApiError> {
+    let sql = "
+        SELECT b.token_contract, b.balance, b.block_index, b.date, c.symbol, c.decimals
+        FROM daily_address_balances b
+        LEFT JOIN contracts c ON c.hash = b.token_contract
+        WHERE b.address = ? AND b.date = (
+            SELECT MAX(date)
+            FROM daily_address_balances
+            WHERE address = b.address AND token_contract = b.token_contract
+        )";
+
+    let mut stmt = conn.prepare(sql)?;
+
+    let balance_iter = stmt.query_map(params![address], |row| {
+        Ok(TokenBalance {
+            token_contract: row.get(0)?,
+            balance: row.get(1)?,
+            block_index: row.get(2)?,
+            date: row.get(3)?,
+            symbol: row.get(4)?,
+            decimals: row.get(5)?,
+        })
+    })?;
+
+    let mut balances = balance_iter.collect::<Result<Vec<TokenBalance>, _>>()?;
+
+    for balance in balances.iter_mut() {
+        if let Some((_, symbol, decimals)) = NATIVE_TOKENS
+            .iter()
+            .find(|(hash, _, _)| *hash == balance.token_contract)
+        {
+            balance.symbol = Some(symbol.to_string());
+            balance.decimals = Some(*decimals);
+        }
+    }
+
+    Ok(balances)
+}
+
+pub fn get_deployed_contracts_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    address: String,
+) -> Result<Vec<String>, ApiError> {
+    let sql = "
+        SELECT nsv.value
+        FROM transactions t
+        INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+        INNER JOIN transaction_notification_state_values nsv ON tn.id = nsv.transaction_notification_id
+        WHERE t.sender = ? AND tn.contract = ? AND tn.event_name = 'Deploy'";
+
+    let mut stmt = conn.prepare(sql)?;
+
+    let contract_iter = stmt.query_map(params![address, CONTRACT_MANAGEMENT_HASH], |row| {
+        row.get::<_, String>(0)
+    })?;
+
+    let contracts = contract_iter
+        .map(|value| value.map(|v| neo::base64_to_script_hash(&v)))
+        .collect::<Result<Vec<String>, _>>()?;
+
+    Ok(contracts)
+}
+
+// USD value of a raw integer balance
+pub fn value_usd(balance: i64, decimals: u8, price: f64) -> f64 {
+    balance as f64 / 10f64.powi(decimals as i32) * price
+}
+
+// latest indexed price of a token, with the day it was sampled
+pub fn get_latest_price_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: &str,
+) -> Result<Option<(f64, String)>, ApiError> {
+    let sql = "
+        SELECT price, date
+        FROM daily_token_price_history
+        WHERE token_contract = ?
+        ORDER BY date DESC
+        LIMIT 1";
+
+    match conn.query_row(sql, params![token], |row| {
+        Ok((row.get::<_, f64>(0)?, row.get::<_, String>(1)?))
+    }) {
+        Ok(price) => Ok(Some(price)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// longest range a portfolio history request may cover
+const PORTFOLIO_HISTORY_MAX_DAYS: i64 = 3660;
+
+fn parse_date(date: &str) -> Result<NaiveDate, ApiError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| ApiError::BadRequest(format!("Invalid date: {}", date)))
+}
+
+pub fn portfolio_range(
+    date_init: &str,
+    date_end: &str,
+) -> Result<(NaiveDate, NaiveDate), ApiError> {
+    let start = parse_date(date_init)?;
+    let end = parse_date(date_end)?;
+
+    if end < start {
+        return Err(ApiError::BadRequest(
+            "The 'date_end' parameter must not be before 'date_init'.".to_string(),
+        ));
+    }
+    if (end - start).num_days() > PORTFOLIO_HISTORY_MAX_DAYS {
+        return Err(ApiError::BadRequest(format!(
+            "Date range can't exceed {} days.",
+            PORTFOLIO_HISTORY_MAX_DAYS
+        )));
+    }
+
+    Ok((start, end))
+}
+
+// daily totals in USD between the two dates. balances and prices only get a row on days
+// they change, so both carry forward from the last row on or before each day
+pub fn get_portfolio_history_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    address: String,
+    date_init: String,
+    date_end: String,
+) -> Result<Vec<PortfolioDay>, ApiError> {
+    let (start, end) = portfolio_range(&date_init, &date_end)?;
+
+    let balance_sql = "
+        SELECT date, token_contract, balance
+        FROM daily_address_balances
+        WHERE address = ? AND date <= ?
+        ORDER BY date ASC";
+
+    let mut stmt = conn.prepare(balance_sql)?;
+    let balance_iter = stmt.query_map(params![address, date_end], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+    let balances = balance_iter.collect::<Result<Vec<_>, _>>()?;
+
+    let mut decimals = HashMap::new();
+    let mut prices = Vec::new();
+
+    let price_sql = "
+        SELECT date, price
+        FROM daily_token_price_history
+        WHERE token_contract = ? AND date <= ?
+        ORDER BY date ASC";
+
+    for (_, token, _) in balances.iter() {
+        if decimals.contains_key(token) {
+            continue;
+        }
+
+        let (_, token_decimals) = contract_internals::get_token_info(conn, token)?;
+        decimals.insert(token.clone(), token_decimals);
+
+        let mut stmt = conn.prepare(price_sql)?;
+        let price_iter = stmt.query_map(params![token, date_end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                token.clone(),
+                row.get::<_, f64>(1)?,
+            ))
+        })?;
+
+        for price in price_iter {
+            prices.push(price?);
+        }
+    }
+    prices.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(walk_portfolio_days(
+        start, end, &balances, &prices, &decimals,
+    ))
+}
+
+pub fn walk_portfolio_days(
+    start: NaiveDate,
+    end: NaiveDate,
+    balances: &[(String, String, i64)],
+    prices: &[(String, String, f64)],
+    decimals: &HashMap<String, Option<u8>>,
+) -> Vec<PortfolioDay> {
+    let mut current_balances: HashMap<&str, i64> = HashMap::new();
+    let mut current_prices: HashMap<&str, f64> = HashMap::new();
+    let (mut next_balance, mut next_price) = (0, 0);
+
+    let mut days = Vec::new();
+    let mut day = start;
+
+    while day <= end {
+        let date = day.format("%Y-%m-%d").to_string();
+
+        while next_balance < balances.len() && balances[next_balance].0 <= date {
+            let (_, token, balance) = &balances[next_balance];
+            current_balances.insert(token, *balance);
+            next_balance += 1;
+        }
+
+        while next_price < prices.len() && prices[next_price].0 <= date {
+            let (_, token, price) = &prices[next_price];
+            current_prices.insert(token, *price);
+            next_price += 1;
+        }
+
+        let total_usd = current_balances
+            .iter()
+            .filter_map(|(token, balance)| {
+                let token_decimals = decimals.get(*token).copied().flatten()?;
+                let price = current_prices.get(token)?;
+                Some(value_usd(*balance, token_decimals, *price))
+            })
+            .fold(0.0, |total, value| total + value);
+
+        days.push(PortfolioDay { date, total_usd });
+        day += Duration::days(1);
+    }
+
+    days
+}
+
+#[test]
+fn test_walk_portfolio_days() {
+    let date = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+    let gas = "0xd2a4cff31913016155e38e474a2c06d08be276cf".to_string();
+    let unknown = "0x0000000000000000000000000000000000000001".to_string();
+
+    let balances = vec![
+        ("2024-01-01".to_string(), gas.clone(), 100000000),
+        ("2024-01-01".to_string(), unknown.clone(), 5),
+        ("2024-01-03".to_string(), gas.clone(), 300000000),
+    ];
+    let prices = vec![
+        ("2023-12-31".to_string(), gas.clone(), 2.0),
+        ("2024-01-02".to_string(), gas.clone(), 4.0),
+    ];
+    let decimals = HashMap::from([(gas, Some(8)), (unknown, None)]);
+
+    let days = walk_portfolio_days(
+        date("2023-12-31"),
+        date("2024-01-04"),
+        &balances,
+        &prices,
+        &decimals,
+    );
+
+    let totals: Vec<f64> = days.iter().map(|d| d.total_usd).collect();
+    assert_eq!(totals, vec![0.0, 2.0, 4.0, 12.0, 12.0]);
+    assert_eq!(days[4].date, "2024-01-04");
+}
+
+#[test]
+fn test_get_seen_range_internal() {
+    use crate::indexer::rpc::database::{
+        insert_test_notification, insert_test_transaction, memory_chain,
+    };
+
+    const ALICE: &str = "AQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+    let alice = neo::base64_to_address(ALICE);
+    let bob = neo::base64_to_address("AgICAgICAgICAgICAgICAgICAgI=");
+
+    let conn = memory_chain(&[1000, 2000, 3000, 4000]);
+    // alice sends in block 2 and is only named in a transfer in block 3
+    insert_test_transaction(&conn, "0xaa", 2, &alice, "0", "0");
+    insert_test_transaction(&conn, "0xbb", 3, &bob, "0", "0");
+    insert_test_notification(
+        &conn,
+        "0xbb",
+        "0xd2a4cff31913016155e38e474a2c06d08be276cf",
+        "Transfer",
+        &[
+            ("ByteString", "AgICAgICAgICAgICAgICAgICAgI="),
+            ("ByteString", ALICE),
+        ],
+    );
+
+    let (first, last) = get_seen_range_internal(&conn, alice).unwrap().unwrap();
+    assert_eq!((first.block_index, first.time), (2, 2000));
+    assert_eq!((last.block_index, last.time), (3, 3000));
+
+    let (first, last) = get_seen_range_internal(&conn, bob).unwrap().unwrap();
+    assert_eq!((first.block_index, last.block_index), (3, 3));
+
+    let carol = neo::base64_to_address("AwMDAwMDAwMDAwMDAwMDAwMDAwM=");
+    assert!(get_seen_range_internal(&conn, carol).unwrap().is_none());
+}
+
+#[test]
+fn test_get_current_balances_internal() {
+    use crate::indexer::rpc::database::memory_chain;
+
+    const GAS: &str = "0xd2a4cff31913016155e38e474a2c06d08be276cf";
+    const TOKEN: &str = "0x0000000000000000000000000000000000000001";
+    let alice = neo::base64_to_address("AQEBAQEBAQEBAQEBAQEBAQEBAQE=");
+
+    let conn = memory_chain(&[1000, 2000, 3000]);
+    conn.execute(
+        "INSERT INTO contracts (block_index, hash, contract_type, symbol, decimals)
+        VALUES (1, ?, '[\"NEP-17\"]', 'TKN', 2)",
+        [TOKEN],
+    )
+    .unwrap();
+    // gas changed on both days, the token only on the first
+    conn.execute(
+        "INSERT INTO daily_address_balances (block_index, date, address, token_contract, balance)
+        VALUES (1, '2024-01-01', ?1, ?2, 500), (3, '2024-01-02', ?1, ?2, 700),
+            (2, '2024-01-01', ?1, ?3, 9)",
+        params![alice, GAS, TOKEN],
+    )
+    .unwrap();
+
+    let mut balances = get_current_balances_internal(&conn, alice).unwrap();
+    balances.sort_by(|a, b| a.token_contract.cmp(&b.token_contract));
+    assert_eq!(balances.len(), 2);
+
+    assert_eq!(balances[0].token_contract, TOKEN);
+    assert_eq!(balances[0].symbol.as_deref(), Some("TKN"));
+    assert_eq!((balances[0].balance, balances[0].block_index), (9, 2));
+
+    // native tokens have no contracts row, their metadata is built in
+    assert_eq!(balances[1].token_contract, GAS);
+    assert_eq!(balances[1].symbol.as_deref(), Some("GAS"));
+    assert_eq!(balances[1].decimals, Some(8));
+    assert_eq!(
+        (balances[1].balance, balances[1].date.as_str()),
+        (700, "2024-01-02")
+    );
+}
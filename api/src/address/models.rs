@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::shared::models::{Address, Hash160};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AddressSummary {
+    pub address: Address,
+    pub script_hash: Hash160,
+    pub first_seen: Option<SeenAt>,
+    pub last_seen: Option<SeenAt>,
+    pub sender_transactions: usize,
+    pub participant_transactions: usize,
+    pub total_fees: f64,
+    pub balances: Vec<TokenBalance>,
+    pub deployed_contracts: Vec<Hash160>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SeenAt {
+    pub block_index: u64,
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TokenBalance {
+    pub token_contract: Hash160,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub balance: i64, // raw integer balance, divide by 10^decimals for display
+    pub block_index: u64,
+    pub date: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Portfolio {
+    pub address: Address,
+    pub total_usd: f64, // tokens without a price or known decimals are left out
+    pub tokens: Vec<PortfolioToken>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PortfolioToken {
+    pub token_contract: Hash160,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub balance: i64, // raw integer balance
+    pub price_usd: Option<f64>,
+    pub price_date: Option<String>,
+    pub value_usd: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PortfolioDay {
+    pub date: String,
+    pub total_usd: f64,
+}
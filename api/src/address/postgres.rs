@@ -0,0 +1,193 @@
+use tokio_postgres::Client;
+
+use std::collections::HashMap;
+
+use crate::address::internals::{portfolio_range, walk_portfolio_days};
+use crate::address::models::{PortfolioDay, SeenAt, TokenBalance};
+use crate::block::postgres as block_postgres;
+use crate::contract::postgres as contract_postgres;
+use crate::error::ApiError;
+use crate::shared::models::{CONTRACT_MANAGEMENT_HASH, GAS_PRECISION, NATIVE_TOKENS};
+use crate::shared::neo;
+use crate::shared::postgres::{get_int, get_opt_int};
+
+// blocks where the address first and last appears as a sender or in any notification
+pub async fn get_seen_range_internal(
+    client: &Client,
+    address: String,
+) -> Result<Option<(SeenAt, SeenAt)>, ApiError> {
+    let sql = "
+        SELECT MIN(block_index), MAX(block_index)
+        FROM (
+            SELECT block_index FROM transactions WHERE sender = $1
+            UNION ALL
+            SELECT t.block_index
+            FROM transactions t
+            INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+            INNER JOIN transaction_notification_state_values nsv
+                ON tn.id = nsv.transaction_notification_id
+            WHERE nsv.value = $2
+        ) seen";
+
+    let base64 = neo::address_to_base64(&address);
+    let row = client.query_one(sql, &[&address, &base64]).await?;
+
+    match (get_opt_int::<u64>(&row, 0)?, get_opt_int::<u64>(&row, 1)?) {
+        (Some(first), Some(last)) => Ok(Some((
+            SeenAt {
+                block_index: first,
+                time: block_postgres::get_block_time(client, first.to_string()).await?,
+            },
+            SeenAt {
+                block_index: last,
+                time: block_postgres::get_block_time(client, last.to_string()).await?,
+            },
+        ))),
+        _ => Ok(None),
+    }
+}
+
+pub async fn get_total_fees_internal(client: &Client, address: String) -> Result<f64, ApiError> {
+    let sql = "
+        SELECT COALESCE(SUM(sysfee::BIGINT + netfee::BIGINT), 0)::BIGINT
+        FROM transactions
+        WHERE sender = $1";
+
+    let fees: i64 = client.query_one(sql, &[&address]).await?.try_get(0)?;
+
+    Ok(fees as f64 / GAS_PRECISION)
+}
+
+// the latest stored day for each token holds the current balance
+pub async fn get_current_balances_internal(
+    client: &Client,
+    address: String,
+) -> Result<Vec<TokenBalance>, ApiError> {
+    let sql = "
+        SELECT b.token_contract, b.balance, b.block_index, b.date, c.symbol, c.decimals
+        FROM daily_address_balances b
+        LEFT JOIN contracts c ON c.hash = b.token_contract
+        WHERE b.address = $1 AND b.date = (
+            SELECT MAX(date)
+            FROM daily_address_balances
+            WHERE address = b.address AND token_contract = b.token_contract
+        )
+        ORDER BY b.id";
+
+    let mut balances = client
+        .query(sql, &[&address])
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(TokenBalance {
+                token_contract: row.try_get(0)?,
+                balance: row.try_get(1)?,
+                block_index: get_int(row, 2)?,
+                date: row.try_get(3)?,
+                symbol: row.try_get(4)?,
+                decimals: get_opt_int(row, 5)?,
+            })
+        })
+        .collect::<Result<Vec<TokenBalance>, ApiError>>()?;
+
+    for balance in balances.iter_mut() {
+        if let Some((_, symbol, decimals)) = NATIVE_TOKENS
+            .iter()
+            .find(|(hash, _, _)| *hash == balance.token_contract)
+        {
+            balance.symbol = Some(symbol.to_string());
+            balance.decimals = Some(*decimals);
+        }
+    }
+
+    Ok(balances)
+}
+
+pub async fn get_deployed_contracts_internal(
+    client: &Client,
+    address: String,
+) -> Result<Vec<String>, ApiError> {
+    let sql = "
+        SELECT nsv.value
+        FROM transactions t
+        INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+        INNER JOIN transaction_notification_state_values nsv
+            ON tn.id = nsv.transaction_notification_id
+        WHERE t.sender = $1 AND tn.contract = $2 AND tn.event_name = 'Deploy'
+        ORDER BY nsv.id";
+
+    client
+        .query(sql, &[&address, &CONTRACT_MANAGEMENT_HASH])
+        .await?
+        .iter()
+        .map(|row| Ok(neo::base64_to_script_hash(&row.try_get::<_, String>(0)?)))
+        .collect()
+}
+
+// latest indexed price of a token, with the day it was sampled
+pub async fn get_latest_price_internal(
+    client: &Client,
+    token: &str,
+) -> Result<Option<(f64, String)>, ApiError> {
+    let sql = "
+        SELECT price, date
+        FROM daily_token_price_history
+        WHERE token_contract = $1
+        ORDER BY date DESC
+        LIMIT 1";
+
+    match client.query_opt(sql, &[&token]).await? {
+        Some(row) => Ok(Some((row.try_get(0)?, row.try_get(1)?))),
+        None => Ok(None),
+    }
+}
+
+// daily totals in USD between the two dates, see the SQLite version for how days carry over
+pub async fn get_portfolio_history_internal(
+    client: &Client,
+    address: String,
+    date_init: String,
+    date_end: String,
+) -> Result<Vec<PortfolioDay>, ApiError> {
+    let (start, end) = portfolio_range(&date_init, &date_end)?;
+
+    let balance_sql = "
+        SELECT date, token_contract, balance
+        FROM daily_address_balances
+        WHERE address = $1 AND date <= $2
+        ORDER BY date ASC, id ASC";
+
+    let balances = client
+        .query(balance_sql, &[&address, &date_end])
+        .await?
+        .iter()
+        .map(|row| Ok((row.try_get(0)?, row.try_get(1)?, row.try_get(2)?)))
+        .collect::<Result<Vec<(String, String, i64)>, ApiError>>()?;
+
+    let mut decimals = HashMap::new();
+    let mut prices = Vec::new();
+
+    let price_sql = "
+        SELECT date, price
+        FROM daily_token_price_history
+        WHERE token_contract = $1 AND date <= $2
+        ORDER BY date ASC";
+
+    for (_, token, _) in balances.iter() {
+        if decimals.contains_key(token) {
+            continue;
+        }
+
+        let (_, token_decimals) = contract_postgres::get_token_info(client, token).await?;
+        decimals.insert(token.clone(), token_decimals);
+
+        for row in client.query(price_sql, &[token, &date_end]).await? {
+            prices.push((row.try_get::<_, String>(0)?, token.clone(), row.try_get(1)?));
+        }
+    }
+    prices.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(walk_portfolio_days(
+        start, end, &balances, &prices, &decimals,
+    ))
+}
@@ -1,47 +1,89 @@
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{get, web, HttpResponse};
 
-use crate::error::Error;
-use crate::ConnectionPool;
+use crate::error::ApiError;
+use crate::shared::models::{PagedResp, PaginationAndFilterParams};
+use crate::shared::storage::ReadStorage;
+use crate::shared::utils::normalize_pagination;
 
-use super::internals;
+use super::models::{LatestBlocksParams, LATEST_DEFAULT, LATEST_MAX};
 
 #[get("/v1/block/{id}")]
-async fn get_block(pool: web::Data<ConnectionPool>, path: web::Path<String>) -> impl Responder {
-    let conn = &pool.connection.get().unwrap();
+async fn get_block(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
     let id = path.into_inner();
 
-    let mut block = match internals::get_block_internal(conn, id) {
-        Ok(blc) => blc,
-        Err(err) => return HttpResponse::NotFound().json(err),
-    };
-
-    let witnesses = match internals::get_witnesses(conn, block.index.clone()) {
-        Ok(witnesses) => witnesses,
-        Err(err) => return HttpResponse::InternalServerError().json(err),
-    };
-    block.witnesses = witnesses;
+    let mut block = storage.get_block(id).await?;
+    block.witnesses = storage.get_block_witnesses(block.index).await?;
 
-    HttpResponse::Ok().json(block)
+    Ok(HttpResponse::Ok().json(block))
 }
 
 #[get("/v1/block/{id}/transactions")]
 async fn get_block_transactions(
-    pool: web::Data<ConnectionPool>,
+    storage: web::Data<dyn ReadStorage>,
     path: web::Path<String>,
-) -> impl Responder {
-    let conn = &pool.connection.get().unwrap();
+) -> Result<HttpResponse, ApiError> {
     let id = path.into_inner();
 
-    let transactions = internals::get_block_transactions_internal(conn, id).unwrap();
+    // an existing block without transactions is an empty list, not a missing block
+    storage.get_block_time(id.clone()).await?;
+    let transactions = storage.get_block_transactions(id).await?;
 
-    match transactions.is_empty() {
-        false => HttpResponse::Ok().json(transactions),
-        true => HttpResponse::Ok().json(Error {
-            error: "No transactions for that block.".to_string(),
-        }),
+    Ok(HttpResponse::Ok().json(transactions))
+}
+
+#[get("/v1/blocks")]
+async fn list_blocks(
+    storage: web::Data<dyn ReadStorage>,
+    query_parameter: web::Query<PaginationAndFilterParams>,
+) -> Result<HttpResponse, ApiError> {
+    // newest first unless asked otherwise
+    let mut query_parameter = query_parameter.into_inner();
+    if query_parameter.sort_by.is_none() && query_parameter.order.is_none() {
+        query_parameter.sort_by = Some("id".to_string());
+        query_parameter.order = Some("desc".to_string());
     }
+
+    let pagination = normalize_pagination(&query_parameter)?;
+    let date_init = query_parameter.date_init.filter(|s| !s.is_empty());
+    let date_end = query_parameter.date_end.filter(|s| !s.is_empty());
+
+    let blocks = storage
+        .list_blocks(date_init.clone(), date_end.clone(), &pagination)
+        .await?;
+
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(storage.count_blocks(date_init, date_end).await?),
+    };
+
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(blocks, count)))
+}
+
+#[get("/v1/blocks/latest")]
+async fn get_latest_blocks(
+    storage: web::Data<dyn ReadStorage>,
+    query_parameter: web::Query<LatestBlocksParams>,
+) -> Result<HttpResponse, ApiError> {
+    let limit = match query_parameter.limit.unwrap_or(LATEST_DEFAULT) {
+        0 => {
+            return Err(ApiError::BadRequest(
+                "Limit must be greater than zero.".to_string(),
+            ))
+        }
+        limit => limit.min(LATEST_MAX),
+    };
+
+    let blocks = storage.get_latest_blocks(limit).await?;
+
+    Ok(HttpResponse::Ok().json(blocks))
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(get_block).service(get_block_transactions);
+    cfg.service(get_block)
+        .service(get_block_transactions)
+        .service(list_blocks)
+        .service(get_latest_blocks);
 }
@@ -1,21 +1,57 @@
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 
-use super::models::{Block, Witness};
-use crate::error::Error;
+use rusqlite::{params, Row};
+
+use super::models::{Block, BlockSummary, Witness};
+use crate::error::ApiError;
 use crate::shared::checker;
+use crate::shared::models::GAS_PRECISION;
+use crate::shared::pagination::{Page, Pagination};
 use crate::transaction::models::Transaction;
 
+const BLOCK_SUMMARY_COLUMNS: &str = "
+    b.id, b.hash, b.size, b.time, b.speaker, b.reward, b.reward_receiver,
+    (SELECT COUNT(*) FROM transactions WHERE block_index = b.id),
+    (SELECT COALESCE(SUM(CAST(sysfee AS INTEGER) + CAST(netfee AS INTEGER)), 0)
+        FROM transactions WHERE block_index = b.id)";
+
+// optional day range on the block time, both ends inclusive
+const BLOCK_TIME_FILTER: &str = "
+    (?1 IS NULL OR b.time >= CAST(strftime('%s', ?1) AS INTEGER) * 1000)
+    AND (?2 IS NULL OR b.time < CAST(strftime('%s', ?2, '+1 day') AS INTEGER) * 1000)";
+
+fn block_summary(row: &Row) -> rusqlite::Result<BlockSummary> {
+    Ok(BlockSummary {
+        index: row.get(0)?,
+        hash: row.get(1)?,
+        size: row.get(2)?,
+        time: row.get(3)?,
+        speaker: row.get(4)?,
+        reward: row.get(5)?,
+        reward_receiver: row.get(6)?,
+        transactions: row.get(7)?,
+        fees: row.get::<_, i64>(8)? as f64 / GAS_PRECISION,
+    })
+}
+
+fn block_not_found(err: rusqlite::Error) -> ApiError {
+    match err {
+        rusqlite::Error::QueryReturnedNoRows => {
+            ApiError::NotFound("Block does not exist.".to_string())
+        }
+        err => err.into(),
+    }
+}
+
 pub fn get_block_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     path: String,
-) -> Result<Block, Error> {
+) -> Result<Block, ApiError> {
     match path.trim().parse::<u64>() {
         Ok(id) => {
             let sql = "SELECT * FROM blocks WHERE id = ?";
-            let mut stmt = conn.prepare(sql).map_err(|err| Error {
-                error: format!("Failed to prepare block query: {}", err),
-            })?;
+            let mut stmt = conn.prepare(sql)?;
 
             let block_result = stmt
                 .query_row([id], |row| {
@@ -34,23 +70,17 @@ pub fn get_block_internal(
                         witnesses: Vec::new(),
                     })
                 })
-                .map_err(|err| Error {
-                    error: format!("Block does not exist: {}", err),
-                })?;
+                .map_err(block_not_found)?;
 
             Ok(block_result)
         }
         Err(_) => {
             if !checker::is_neo_txid_hash(&path) {
-                return Err(Error {
-                    error: "Invalid block hash.".to_string(),
-                });
+                return Err(ApiError::BadRequest("Invalid block hash.".to_string()));
             }
 
             let sql = "SELECT * FROM blocks WHERE hash = ?";
-            let mut stmt = conn.prepare(sql).map_err(|err| Error {
-                error: format!("Failed to prepare block query by hash: {}", err),
-            })?;
+            let mut stmt = conn.prepare(sql)?;
 
             let block_result = stmt
                 .query_row([path], |row| {
@@ -69,9 +99,7 @@ pub fn get_block_internal(
                         witnesses: Vec::new(),
                     })
                 })
-                .map_err(|err| Error {
-                    error: format!("Block does not exist: {}", err),
-                })?;
+                .map_err(block_not_found)?;
 
             Ok(block_result)
         }
@@ -81,26 +109,18 @@ pub fn get_block_internal(
 pub fn get_witnesses(
     conn: &PooledConnection<SqliteConnectionManager>,
     block_index: u64,
-) -> Result<Vec<Witness>, Error> {
+) -> Result<Vec<Witness>, ApiError> {
     let witness_sql = "SELECT invocation, verification FROM witnesses WHERE block_index = ?";
-    let mut stmt_witness = conn.prepare(witness_sql).map_err(|err| Error {
-        error: format!("Failed to prepare witness query: {}", err),
-    })?;
+    let mut stmt_witness = conn.prepare(witness_sql)?;
 
-    let witness_iter = stmt_witness
-        .query_map([block_index], |row| {
-            Ok(Witness {
-                invocation: row.get(0)?,
-                verification: row.get(1)?,
-            })
+    let witness_iter = stmt_witness.query_map([block_index], |row| {
+        Ok(Witness {
+            invocation: row.get(0)?,
+            verification: row.get(1)?,
         })
-        .map_err(|err| Error {
-            error: format!("Failed to query witnesses: {}", err),
-        })?;
+    })?;
 
-    let witnesses: Vec<Witness> = witness_iter
-        .filter_map(|witness| witness.ok()) // Ignorar erros individuais
-        .collect();
+    let witnesses = witness_iter.collect::<Result<Vec<Witness>, _>>()?;
 
     Ok(witnesses)
 }
@@ -108,33 +128,25 @@ pub fn get_witnesses(
 pub fn get_block_time(
     conn: &PooledConnection<SqliteConnectionManager>,
     path: String,
-) -> Result<u64, Error> {
+) -> Result<u64, ApiError> {
     match path.trim().parse::<u64>() {
         Ok(id) => {
             let sql = "SELECT time FROM blocks WHERE id = ?";
-            let mut stmt = conn.prepare(sql).unwrap();
-
-            let result = stmt.query_row([id], |row| row.get(0));
+            let mut stmt = conn.prepare(sql)?;
 
-            result.map_err(|_| Error {
-                error: "Block does not exist.".to_string(),
-            })
+            stmt.query_row([id], |row| row.get(0))
+                .map_err(block_not_found)
         }
         Err(_) => {
             if !checker::is_neo_txid_hash(&path) {
-                return Err(Error {
-                    error: "Invalid block hash.".to_string(),
-                });
+                return Err(ApiError::BadRequest("Invalid block hash.".to_string()));
             }
 
             let sql = "SELECT time FROM blocks WHERE hash = ?";
-            let mut stmt = conn.prepare(sql).unwrap();
-
-            let result = stmt.query_row([path], |row| row.get(0));
+            let mut stmt = conn.prepare(sql)?;
 
-            result.map_err(|_| Error {
-                error: "Block does not exist.".to_string(),
-            })
+            stmt.query_row([path], |row| row.get(0))
+                .map_err(block_not_found)
         }
     }
 }
@@ -142,31 +154,31 @@ pub fn get_block_time(
 pub fn get_block_transactions_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     path: String,
-) -> Result<Vec<Transaction>, Error> {
+) -> Result<Vec<Transaction>, ApiError> {
     match path.trim().parse::<u64>() {
         Ok(id) => {
             let sql = "SELECT * FROM transactions WHERE block_index = ?";
-            let mut stmt = conn.prepare(sql).unwrap();
+            let mut stmt = conn.prepare(sql)?;
 
-            let mut rows = stmt.query([id]).unwrap();
+            let mut rows = stmt.query([id])?;
             let mut transactions = Vec::new();
 
-            while let Some(row) = rows.next().unwrap() {
+            while let Some(row) = rows.next()? {
                 transactions.push(Transaction {
                     timestamp: 0,
-                    index: row.get(0).unwrap(),
-                    hash: row.get(1).unwrap(),
-                    block_index: row.get(2).unwrap(),
-                    vm_state: row.get(3).unwrap(),
-                    size: row.get(4).unwrap(),
-                    version: row.get(5).unwrap(),
-                    nonce: row.get(6).unwrap(),
-                    sender: row.get(7).unwrap(),
-                    sysfee: row.get(8).unwrap(),
-                    netfee: row.get(9).unwrap(),
-                    valid_until: row.get(10).unwrap(),
-                    script: row.get(11).unwrap(),
-                    stack_result: row.get(12).unwrap(),
+                    index: row.get(0)?,
+                    hash: row.get(1)?,
+                    block_index: row.get(2)?,
+                    vm_state: row.get(3)?,
+                    size: row.get(4)?,
+                    version: row.get(5)?,
+                    nonce: row.get(6)?,
+                    sender: row.get(7)?,
+                    sysfee: row.get(8)?,
+                    netfee: row.get(9)?,
+                    valid_until: row.get(10)?,
+                    script: row.get(11)?,
+                    stack_result: row.get(12)?,
                     signers: Vec::new(),
                     witnesses: Vec::new(),
                     notifications: Vec::new(),
@@ -176,33 +188,31 @@ pub fn get_block_transactions_internal(
         }
         Err(_) => {
             if !checker::is_neo_txid_hash(&path) {
-                return Err(Error {
-                    error: "Invalid block hash.".to_string(),
-                });
+                return Err(ApiError::BadRequest("Invalid block hash.".to_string()));
             }
 
             let sql = "SELECT * FROM transactions WHERE block_index = (SELECT id FROM blocks WHERE hash = ?)";
-            let mut stmt = conn.prepare(sql).unwrap();
+            let mut stmt = conn.prepare(sql)?;
 
-            let mut rows = stmt.query([path]).unwrap();
+            let mut rows = stmt.query([path])?;
             let mut transactions = Vec::new();
 
-            while let Some(row) = rows.next().unwrap() {
+            while let Some(row) = rows.next()? {
                 transactions.push(Transaction {
                     timestamp: 0,
-                    index: row.get(0).unwrap(),
-                    hash: row.get(1).unwrap(),
-                    block_index: row.get(2).unwrap(),
-                    vm_state: row.get(3).unwrap(),
-                    size: row.get(4).unwrap(),
-                    version: row.get(5).unwrap(),
-                    nonce: row.get(6).unwrap(),
-                    sender: row.get(7).unwrap(),
-                    sysfee: row.get(8).unwrap(),
-                    netfee: row.get(9).unwrap(),
-                    valid_until: row.get(10).unwrap(),
-                    script: row.get(11).unwrap(),
-                    stack_result: row.get(12).unwrap(),
+                    index: row.get(0)?,
+                    hash: row.get(1)?,
+                    block_index: row.get(2)?,
+                    vm_state: row.get(3)?,
+                    size: row.get(4)?,
+                    version: row.get(5)?,
+                    nonce: row.get(6)?,
+                    sender: row.get(7)?,
+                    sysfee: row.get(8)?,
+                    netfee: row.get(9)?,
+                    valid_until: row.get(10)?,
+                    script: row.get(11)?,
+                    stack_result: row.get(12)?,
                     signers: Vec::new(),
                     witnesses: Vec::new(),
                     notifications: Vec::new(),
@@ -212,3 +222,120 @@ pub fn get_block_transactions_internal(
         }
     }
 }
+
+pub fn list_blocks_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    date_init: Option<String>,
+    date_end: Option<String>,
+    pagination: &Pagination,
+) -> Result<Page<Vec<BlockSummary>>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("b.id", &["id", "time", "size"])?;
+
+    let sql = format!(
+        "SELECT {} FROM blocks b WHERE {} {} {}",
+        BLOCK_SUMMARY_COLUMNS, BLOCK_TIME_FILTER, keyset_clause, page_clause
+    );
+
+    let mut stmt = conn.prepare(sql.as_str())?;
+    let mut rows = stmt.query(params![date_init, date_end])?;
+
+    let mut blocks = Vec::new();
+    while let Some(row) = rows.next()? {
+        blocks.push((row.get::<_, i64>(0)?, block_summary(row)?));
+    }
+
+    if blocks.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound("No blocks in that range.".to_string()))
+    } else {
+        Ok(pagination.finish(blocks))
+    }
+}
+
+pub fn count_blocks_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    date_init: Option<String>,
+    date_end: Option<String>,
+) -> Result<usize, ApiError> {
+    let sql = format!("SELECT COUNT(*) FROM blocks b WHERE {}", BLOCK_TIME_FILTER);
+
+    let count = conn.query_row(&sql, params![date_init, date_end], |row| {
+        row.get::<_, usize>(0)
+    })?;
+
+    Ok(count)
+}
+
+pub fn get_latest_blocks_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    limit: u32,
+) -> Result<Vec<BlockSummary>, ApiError> {
+    let sql = format!(
+        "SELECT {} FROM blocks b ORDER BY b.id DESC LIMIT ?",
+        BLOCK_SUMMARY_COLUMNS
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let blocks = stmt
+        .query_map([limit], block_summary)?
+        .collect::<Result<Vec<BlockSummary>, _>>()?;
+
+    if blocks.is_empty() {
+        Err(ApiError::NotFound("No blocks indexed yet.".to_string()))
+    } else {
+        Ok(blocks)
+    }
+}
+
+#[test]
+fn test_list_blocks_internal() {
+    use crate::indexer::rpc::database::{insert_test_transaction, memory_chain};
+    use crate::shared::pagination::Cursor;
+
+    const DAY: u64 = 86_400_000;
+    const START: u64 = 1_704_067_200_000; // 2024-01-01
+
+    let conn = memory_chain(&[START, START + 1, START + DAY, START + 2 * DAY]);
+    insert_test_transaction(&conn, "0xaa", 2, "alice", "100", "10");
+    insert_test_transaction(&conn, "0xbb", 2, "alice", "200", "20");
+
+    let page = |cursor: Option<i64>| Pagination::Keyset {
+        cursor: cursor.map(|key| Cursor {
+            key,
+            descending: true,
+            backward: false,
+        }),
+        descending: true,
+        per_page: 2,
+    };
+    let indexes = |page: &Page<Vec<BlockSummary>>| -> Vec<u64> {
+        page.list.iter().map(|block| block.index).collect()
+    };
+
+    let first = list_blocks_internal(&conn, None, None, &page(None)).unwrap();
+    assert_eq!(indexes(&first), vec![4, 3]);
+    let cursor = Cursor::decode(first.next_cursor.as_deref().unwrap()).unwrap();
+    let second = list_blocks_internal(&conn, None, None, &page(Some(cursor.key))).unwrap();
+    assert_eq!(indexes(&second), vec![2, 1]);
+    assert!(second.next_cursor.is_none());
+
+    // both ends of the day range are inclusive
+    let day = |date: &str| Some(date.to_string());
+    let range = list_blocks_internal(&conn, day("2024-01-01"), day("2024-01-02"), &page(None));
+    assert_eq!(indexes(&range.unwrap()), vec![3, 2]);
+    assert_eq!(
+        count_blocks_internal(&conn, day("2024-01-01"), day("2024-01-02")).unwrap(),
+        3
+    );
+    assert_eq!(count_blocks_internal(&conn, None, None).unwrap(), 4);
+    assert!(list_blocks_internal(&conn, day("2025-01-01"), None, &page(None)).is_err());
+
+    let latest = get_latest_blocks_internal(&conn, 3).unwrap();
+    let summaries: Vec<_> = latest
+        .iter()
+        .map(|b| (b.index, b.transactions, b.fees))
+        .collect();
+    assert_eq!(
+        summaries,
+        vec![(4, 0, 0.0), (3, 0, 0.0), (2, 2, 330.0 / GAS_PRECISION)]
+    );
+}
@@ -1,4 +1,8 @@
 use serde::{Deserialize, Serialize};
+
+pub const LATEST_DEFAULT: u32 = 10;
+pub const LATEST_MAX: u32 = 100;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Block {
     pub index: u64,
@@ -20,3 +24,22 @@ pub struct Witness {
     pub invocation: String,
     pub verification: String,
 }
+
+// a block with its transaction totals, for listings
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BlockSummary {
+    pub index: u64,
+    pub hash: String,
+    pub size: u32,
+    pub time: u64,
+    pub speaker: u8,
+    pub reward: f64,
+    pub reward_receiver: String,
+    pub transactions: u64,
+    pub fees: f64, // system and network fees of its transactions, in GAS
+}
+
+#[derive(Deserialize)]
+pub struct LatestBlocksParams {
+    pub limit: Option<u32>,
+}
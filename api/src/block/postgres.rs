@@ -0,0 +1,246 @@
+use chrono::NaiveDate;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, Row};
+
+use super::models::{Block, BlockSummary, Witness};
+use crate::error::ApiError;
+use crate::shared::checker;
+use crate::shared::models::GAS_PRECISION;
+use crate::shared::pagination::{Page, Pagination};
+use crate::shared::postgres::{get_int, page_sql, to_i64};
+use crate::transaction::models::Transaction;
+use crate::transaction::postgres::transaction_row;
+
+const BLOCK_SUMMARY_COLUMNS: &str = "
+    b.id, b.hash, b.size, b.time, b.speaker, b.reward, b.reward_receiver,
+    (SELECT COUNT(*) FROM transactions WHERE block_index = b.id),
+    (SELECT COALESCE(SUM(sysfee::BIGINT + netfee::BIGINT), 0)::BIGINT
+        FROM transactions WHERE block_index = b.id)";
+
+// the day range is resolved to block times up front, so both ends stay inclusive
+const BLOCK_TIME_FILTER: &str = "
+    ($1::BIGINT IS NULL OR b.time >= $1) AND ($2::BIGINT IS NULL OR b.time < $2)";
+
+// a block path is either its index or its hash
+enum BlockKey {
+    Index(i64),
+    Hash(String),
+}
+
+impl BlockKey {
+    fn parse(path: &str) -> Result<BlockKey, ApiError> {
+        match path.trim().parse::<u64>() {
+            Ok(id) => Ok(BlockKey::Index(to_i64(id)?)),
+            Err(_) if checker::is_neo_txid_hash(path) => Ok(BlockKey::Hash(path.to_string())),
+            Err(_) => Err(ApiError::BadRequest("Invalid block hash.".to_string())),
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            BlockKey::Index(_) => "id",
+            BlockKey::Hash(_) => "hash",
+        }
+    }
+
+    fn param(&self) -> &(dyn ToSql + Sync) {
+        match self {
+            BlockKey::Index(id) => id,
+            BlockKey::Hash(hash) => hash,
+        }
+    }
+}
+
+fn block_summary(row: &Row) -> Result<BlockSummary, ApiError> {
+    Ok(BlockSummary {
+        index: get_int(row, 0)?,
+        hash: row.try_get(1)?,
+        size: get_int(row, 2)?,
+        time: get_int(row, 3)?,
+        speaker: get_int(row, 4)?,
+        reward: row.try_get(5)?,
+        reward_receiver: row.try_get(6)?,
+        transactions: get_int(row, 7)?,
+        fees: row.try_get::<_, i64>(8)? as f64 / GAS_PRECISION,
+    })
+}
+
+fn block_not_found() -> ApiError {
+    ApiError::NotFound("Block does not exist.".to_string())
+}
+
+// start of the first day and end of the last, in block time. SQLite turns a date it can't
+// read into NULL, which matches no block, so an unreadable date does the same here
+fn time_range(
+    date_init: Option<String>,
+    date_end: Option<String>,
+) -> Option<(Option<i64>, Option<i64>)> {
+    let day_start = |date: &str, days: i64| {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .map(|day| {
+                (day + chrono::Duration::days(days))
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+            })
+            .map(|start| start.and_utc().timestamp_millis())
+    };
+
+    let init = match date_init {
+        Some(date) => Some(day_start(&date, 0)?),
+        None => None,
+    };
+    let end = match date_end {
+        Some(date) => Some(day_start(&date, 1)?),
+        None => None,
+    };
+
+    Some((init, end))
+}
+
+pub async fn get_block_internal(client: &Client, path: String) -> Result<Block, ApiError> {
+    let key = BlockKey::parse(&path)?;
+    let sql = format!(
+        "SELECT id, hash, size, version, merkle_root, time, nonce, speaker, next_consensus,
+            reward, reward_receiver
+        FROM blocks WHERE {} = $1",
+        key.column()
+    );
+
+    let row = client
+        .query_opt(sql.as_str(), &[key.param()])
+        .await?
+        .ok_or_else(block_not_found)?;
+
+    Ok(Block {
+        index: get_int(&row, 0)?,
+        hash: row.try_get(1)?,
+        size: get_int(&row, 2)?,
+        version: get_int(&row, 3)?,
+        merkle_root: row.try_get(4)?,
+        time: get_int(&row, 5)?,
+        nonce: row.try_get(6)?,
+        speaker: get_int(&row, 7)?,
+        next_consensus: row.try_get(8)?,
+        reward: row.try_get(9)?,
+        reward_receiver: row.try_get(10)?,
+        witnesses: Vec::new(),
+    })
+}
+
+pub async fn get_witnesses(client: &Client, block_index: u64) -> Result<Vec<Witness>, ApiError> {
+    let sql = "SELECT invocation, verification FROM witnesses WHERE block_index = $1 ORDER BY id";
+
+    client
+        .query(sql, &[&to_i64(block_index)?])
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(Witness {
+                invocation: row.try_get(0)?,
+                verification: row.try_get(1)?,
+            })
+        })
+        .collect()
+}
+
+pub async fn get_block_time(client: &Client, path: String) -> Result<u64, ApiError> {
+    let key = BlockKey::parse(&path)?;
+    let sql = format!("SELECT time FROM blocks WHERE {} = $1", key.column());
+
+    let row = client
+        .query_opt(sql.as_str(), &[key.param()])
+        .await?
+        .ok_or_else(block_not_found)?;
+
+    get_int(&row, 0)
+}
+
+pub async fn get_block_transactions_internal(
+    client: &Client,
+    path: String,
+) -> Result<Vec<Transaction>, ApiError> {
+    let key = BlockKey::parse(&path)?;
+    let sql = format!(
+        "SELECT * FROM transactions
+        WHERE block_index = (SELECT id FROM blocks WHERE {} = $1)
+        ORDER BY id",
+        key.column()
+    );
+
+    client
+        .query(sql.as_str(), &[key.param()])
+        .await?
+        .iter()
+        .map(transaction_row)
+        .collect()
+}
+
+pub async fn list_blocks_internal(
+    client: &Client,
+    date_init: Option<String>,
+    date_end: Option<String>,
+    pagination: &Pagination,
+) -> Result<Page<Vec<BlockSummary>>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "b.id", &["id", "time", "size"])?;
+
+    let (init, end) = match time_range(date_init, date_end) {
+        Some(range) => range,
+        None => return Err(ApiError::NotFound("No blocks in that range.".to_string())),
+    };
+
+    let sql = format!(
+        "SELECT {} FROM blocks b WHERE {} {} {}",
+        BLOCK_SUMMARY_COLUMNS, BLOCK_TIME_FILTER, keyset_clause, page_clause
+    );
+
+    let mut blocks = Vec::new();
+    for row in client.query(sql.as_str(), &[&init, &end]).await? {
+        blocks.push((row.try_get::<_, i64>(0)?, block_summary(&row)?));
+    }
+
+    if blocks.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound("No blocks in that range.".to_string()))
+    } else {
+        Ok(pagination.finish(blocks))
+    }
+}
+
+pub async fn count_blocks_internal(
+    client: &Client,
+    date_init: Option<String>,
+    date_end: Option<String>,
+) -> Result<usize, ApiError> {
+    let (init, end) = match time_range(date_init, date_end) {
+        Some(range) => range,
+        None => return Ok(0),
+    };
+
+    let sql = format!("SELECT COUNT(*) FROM blocks b WHERE {}", BLOCK_TIME_FILTER);
+    let row = client.query_one(sql.as_str(), &[&init, &end]).await?;
+
+    get_int(&row, 0)
+}
+
+pub async fn get_latest_blocks_internal(
+    client: &Client,
+    limit: u32,
+) -> Result<Vec<BlockSummary>, ApiError> {
+    let sql = format!(
+        "SELECT {} FROM blocks b ORDER BY b.id DESC LIMIT $1",
+        BLOCK_SUMMARY_COLUMNS
+    );
+
+    let blocks = client
+        .query(sql.as_str(), &[&i64::from(limit)])
+        .await?
+        .iter()
+        .map(block_summary)
+        .collect::<Result<Vec<BlockSummary>, _>>()?;
+
+    if blocks.is_empty() {
+        Err(ApiError::NotFound("No blocks indexed yet.".to_string()))
+    } else {
+        Ok(blocks)
+    }
+}
@@ -0,0 +1,37 @@
+use actix_web::{get, web, HttpResponse};
+
+use crate::error::ApiError;
+use crate::shared::checker;
+use crate::shared::models::{PagedResp, PaginationAndFilterParams};
+use crate::shared::storage::ReadStorage;
+use crate::shared::utils::normalize_pagination;
+
+#[get("/v1/contracts/{contract}/events")]
+async fn list_contract_events(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<String>,
+    query_parameter: web::Query<PaginationAndFilterParams>,
+) -> Result<HttpResponse, ApiError> {
+    let contract = path.into_inner();
+
+    if !checker::is_neo_script_hash(&contract) {
+        return Err(ApiError::BadRequest("Invalid contract hash.".to_string()));
+    }
+
+    let pagination = normalize_pagination(&query_parameter)?;
+
+    let events = storage
+        .list_contract_events(contract.clone(), &pagination)
+        .await?;
+
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(storage.count_contract_events(contract.clone()).await?),
+    };
+
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(events, count)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_contract_events);
+}
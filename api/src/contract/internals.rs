@@ -0,0 +1,162 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+
+use std::collections::HashMap;
+
+use crate::contract::models::ContractEvent;
+use crate::error::ApiError;
+use crate::shared::abi::{self, ContractManifest, DecodedEvent};
+use crate::shared::models::NATIVE_TOKENS;
+use crate::shared::pagination::{Page, Pagination};
+use crate::transaction::internals::get_notification_state_values;
+use crate::transaction::models::{Notification, State};
+
+pub fn get_contract_manifest(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    contract: &str,
+) -> Option<ContractManifest> {
+    let sql = "SELECT manifest FROM contracts WHERE hash = ?";
+
+    conn.query_row(sql, params![contract], |row| {
+        row.get::<_, Option<String>>(0)
+    })
+    .ok()
+    .flatten()
+    .and_then(|manifest| abi::parse_manifest(&manifest))
+}
+
+// symbol and decimals of a token, falling back to the native tokens that have no contracts row
+pub fn get_token_info(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    contract: &str,
+) -> Result<(Option<String>, Option<u8>), ApiError> {
+    if let Some((_, symbol, decimals)) = NATIVE_TOKENS.iter().find(|(hash, _, _)| *hash == contract)
+    {
+        return Ok((Some(symbol.to_string()), Some(*decimals)));
+    }
+
+    let sql = "SELECT symbol, decimals FROM contracts WHERE hash = ?";
+
+    match conn.query_row(sql, params![contract], |row| {
+        Ok((
+            row.get::<_, Option<String>>(0)?,
+            row.get::<_, Option<u8>>(1)?,
+        ))
+    }) {
+        Ok(info) => Ok(info),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok((None, None)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// notifications from the same contract share one manifest lookup
+pub fn decode_notifications(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    notifications: Vec<Notification>,
+) -> Vec<DecodedEvent> {
+    let mut manifests: HashMap<String, Option<ContractManifest>> = HashMap::new();
+
+    notifications
+        .iter()
+        .map(|notification| {
+            let manifest = manifests
+                .entry(notification.contract.clone())
+                .or_insert_with(|| get_contract_manifest(conn, &notification.contract));
+
+            abi::decode_event(notification, manifest.as_ref())
+        })
+        .collect()
+}
+
+pub fn list_contract_events_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    contract: String,
+    pagination: &Pagination,
+) -> Result<Page<Vec<ContractEvent>>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("tn.id", &["id"])?;
+
+    let sql = format!(
+        "SELECT tn.id, tn.transaction_hash, t.block_index, tn.contract, tn.event_name, tn.state_type
+        FROM transaction_notifications tn
+        INNER JOIN transactions t ON t.hash = tn.transaction_hash
+        WHERE tn.contract = ? {}
+        {}",
+        keyset_clause, page_clause
+    );
+
+    let mut stmt = conn.prepare(sql.as_str())?;
+
+    let rows = stmt.query_map(params![contract], |row| {
+        Ok((
+            row.get::<_, String>(1)?,
+            row.get::<_, u64>(2)?,
+            Notification {
+                id: row.get(0)?,
+                contract: row.get(3)?,
+                eventname: row.get(4)?,
+                state: State {
+                    _type: row.get(5)?,
+                    value: Vec::new(),
+                },
+            },
+        ))
+    })?;
+
+    let mut keyed = Vec::new();
+    for row in rows {
+        let (txid, block_index, notification) = row?;
+        keyed.push((
+            notification.id.unwrap() as i64,
+            (txid, block_index, notification),
+        ));
+    }
+
+    let page = pagination.finish(keyed);
+
+    let mut located = Vec::new();
+    let mut notifications = Vec::new();
+    for (txid, block_index, mut notification) in page.list {
+        notification.state.value = get_notification_state_values(conn, notification.id.unwrap())?;
+
+        located.push((txid, block_index));
+        notifications.push(notification);
+    }
+
+    let events: Vec<ContractEvent> = located
+        .into_iter()
+        .zip(decode_notifications(conn, notifications))
+        .map(|((txid, block_index), event)| ContractEvent {
+            txid,
+            block_index,
+            event,
+        })
+        .collect();
+
+    if events.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No events for that contract.".to_string(),
+        ))
+    } else {
+        Ok(Page {
+            list: events,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+        })
+    }
+}
+
+pub fn count_contract_events_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    contract: String,
+) -> Result<usize, ApiError> {
+    let sql = "
+        SELECT COUNT(*)
+        FROM transaction_notifications
+        WHERE contract = ?
+    ";
+
+    let count = conn.query_row(sql, params![contract], |row| row.get::<_, usize>(0))?;
+
+    Ok(count)
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use crate::shared::abi::DecodedEvent;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ContractEvent {
+    pub txid: String,
+    pub block_index: u64,
+    #[serde(flatten)]
+    pub event: DecodedEvent,
+}
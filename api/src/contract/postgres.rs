@@ -0,0 +1,147 @@
+use tokio_postgres::Client;
+
+use std::collections::HashMap;
+
+use crate::contract::models::ContractEvent;
+use crate::error::ApiError;
+use crate::shared::abi::{self, ContractManifest, DecodedEvent};
+use crate::shared::models::NATIVE_TOKENS;
+use crate::shared::pagination::{Page, Pagination};
+use crate::shared::postgres::{get_int, get_opt_int, page_sql};
+use crate::transaction::models::{Notification, State};
+use crate::transaction::postgres::get_notification_state_values;
+
+pub async fn get_contract_manifest(
+    client: &Client,
+    contract: &str,
+) -> Result<Option<ContractManifest>, ApiError> {
+    let sql = "SELECT manifest FROM contracts WHERE hash = $1";
+
+    let manifest = match client.query_opt(sql, &[&contract]).await? {
+        Some(row) => row.try_get::<_, Option<String>>(0)?,
+        None => None,
+    };
+
+    Ok(manifest.and_then(|manifest| abi::parse_manifest(&manifest)))
+}
+
+// symbol and decimals of a token, falling back to the native tokens that have no contracts row
+pub async fn get_token_info(
+    client: &Client,
+    contract: &str,
+) -> Result<(Option<String>, Option<u8>), ApiError> {
+    if let Some((_, symbol, decimals)) = NATIVE_TOKENS.iter().find(|(hash, _, _)| *hash == contract)
+    {
+        return Ok((Some(symbol.to_string()), Some(*decimals)));
+    }
+
+    let sql = "SELECT symbol, decimals FROM contracts WHERE hash = $1";
+
+    match client.query_opt(sql, &[&contract]).await? {
+        Some(row) => Ok((row.try_get(0)?, get_opt_int(&row, 1)?)),
+        None => Ok((None, None)),
+    }
+}
+
+// notifications from the same contract share one manifest lookup
+pub async fn decode_notifications(
+    client: &Client,
+    notifications: Vec<Notification>,
+) -> Result<Vec<DecodedEvent>, ApiError> {
+    let mut manifests: HashMap<String, Option<ContractManifest>> = HashMap::new();
+    let mut events = Vec::new();
+
+    for notification in notifications.iter() {
+        if !manifests.contains_key(&notification.contract) {
+            let manifest = get_contract_manifest(client, &notification.contract).await?;
+            manifests.insert(notification.contract.clone(), manifest);
+        }
+
+        let manifest = manifests[&notification.contract].as_ref();
+        events.push(abi::decode_event(notification, manifest));
+    }
+
+    Ok(events)
+}
+
+pub async fn list_contract_events_internal(
+    client: &Client,
+    contract: String,
+    pagination: &Pagination,
+) -> Result<Page<Vec<ContractEvent>>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "tn.id", &["id"])?;
+
+    let sql = format!(
+        "SELECT tn.id, tn.transaction_hash, t.block_index, tn.contract, tn.event_name, tn.state_type
+        FROM transaction_notifications tn
+        INNER JOIN transactions t ON t.hash = tn.transaction_hash
+        WHERE tn.contract = $1 {}
+        {}",
+        keyset_clause, page_clause
+    );
+
+    let mut keyed = Vec::new();
+    for row in client.query(sql.as_str(), &[&contract]).await? {
+        let notification = Notification {
+            id: Some(get_int(&row, 0)?),
+            contract: row.try_get(3)?,
+            eventname: row.try_get(4)?,
+            state: State {
+                _type: row.try_get(5)?,
+                value: Vec::new(),
+            },
+        };
+
+        keyed.push((
+            row.try_get::<_, i64>(0)?,
+            (
+                row.try_get::<_, String>(1)?,
+                get_int::<u64>(&row, 2)?,
+                notification,
+            ),
+        ));
+    }
+
+    let page = pagination.finish(keyed);
+
+    let mut located = Vec::new();
+    let mut notifications = Vec::new();
+    for (txid, block_index, mut notification) in page.list {
+        notification.state.value =
+            get_notification_state_values(client, notification.id.unwrap()).await?;
+
+        located.push((txid, block_index));
+        notifications.push(notification);
+    }
+
+    let events: Vec<ContractEvent> = located
+        .into_iter()
+        .zip(decode_notifications(client, notifications).await?)
+        .map(|((txid, block_index), event)| ContractEvent {
+            txid,
+            block_index,
+            event,
+        })
+        .collect();
+
+    if events.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No events for that contract.".to_string(),
+        ))
+    } else {
+        Ok(Page {
+            list: events,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+        })
+    }
+}
+
+pub async fn count_contract_events_internal(
+    client: &Client,
+    contract: String,
+) -> Result<usize, ApiError> {
+    let sql = "SELECT COUNT(*) FROM transaction_notifications WHERE contract = $1";
+
+    get_int(&client.query_one(sql, &[&contract]).await?, 0)
+}
@@ -1,14 +1,139 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    pub static REQUEST_ID: String;
+}
+
+static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// unique per process run, and sortable by arrival order within it
+pub fn next_request_id() -> String {
+    static STARTED_AT: Lazy<u64> = Lazy::new(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    });
+
+    let count = REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:x}-{:08x}", *STARTED_AT, count)
+}
+
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Error {
     pub error: String,
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    Unprocessable(String),
+    #[error("{0}")]
+    Internal(String),
+    #[error("{0}")]
+    Unavailable(String),
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::Unprocessable(_) => "unprocessable_entity",
+            ApiError::Internal(_) => "internal_error",
+            ApiError::Unavailable(_) => "service_unavailable",
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let ApiError::Internal(_) = self {
+            log::error!("{}", self);
+        }
+
+        HttpResponse::build(self.status_code()).json(Error {
+            error: self.to_string(),
+            code: self.code().to_string(),
+            request_id: current_request_id(),
+        })
+    }
+}
+
+// anything the query layer didn't classify itself is a server fault
+impl From<rusqlite::Error> for ApiError {
+    fn from(err: rusqlite::Error) -> Self {
+        ApiError::Internal(format!("Database error: {}", err))
+    }
+}
+
+impl From<r2d2::Error> for ApiError {
+    fn from(err: r2d2::Error) -> Self {
+        ApiError::Unavailable(format!("Database unavailable: {}", err))
+    }
+}
+
+impl From<tokio_postgres::Error> for ApiError {
+    fn from(err: tokio_postgres::Error) -> Self {
+        ApiError::Internal(format!("Database error: {}", err))
+    }
+}
+
+impl From<deadpool_postgres::PoolError> for ApiError {
+    fn from(err: deadpool_postgres::PoolError) -> Self {
+        ApiError::Unavailable(format!("Database unavailable: {}", err))
+    }
+}
+
+#[test]
+fn test_api_error_status_and_code() {
+    let err = ApiError::NotFound("Block does not exist.".to_string());
+    assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(err.code(), "not_found");
+
+    let err = ApiError::from(rusqlite::Error::InvalidQuery);
+    assert_eq!(err.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(err.code(), "internal_error");
 }
 
-#[allow(dead_code, clippy::enum_variant_names)]
-pub enum Errors {
-    SqlError,
-    RequestError,
-    RateLimitError,
-    NotImplementedError,
+#[test]
+fn test_next_request_id_is_unique() {
+    assert_ne!(next_request_id(), next_request_id());
 }
@@ -1,155 +1,177 @@
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{get, web, HttpResponse};
 
-use crate::error::Error;
-use crate::shared::checker;
-use crate::shared::models::{PagedResp, PaginationAndFilterParams};
+use crate::error::ApiError;
+use crate::shared::export::{self, ExportFormat};
+use crate::shared::models::{AddressParam, PagedResp, PaginationAndFilterParams};
+use crate::shared::storage::ReadStorage;
 use crate::shared::utils::{normalize_filter, normalize_pagination};
-use crate::ConnectionPool;
-
-use super::internals;
 
 #[get("/v1/balance-history/{address}/{token}")]
 async fn list_balance_history(
-    pool: web::Data<ConnectionPool>,
-    path: web::Path<(String, String)>,
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<(AddressParam, String)>,
     query_parameter: web::Query<PaginationAndFilterParams>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let (address, token) = path.into_inner();
+    let address = address.into_inner();
 
-    if !checker::is_neo_address(&address) {
-        return HttpResponse::Ok().json(Error {
-            error: "Invalid address.".to_string(),
-        });
-    }
+    let pagination = normalize_pagination(&query_parameter)?;
+    let (date_init, date_end) = normalize_filter(&query_parameter)?;
 
-    let (page, per_page, sort_by, order) = match normalize_pagination(&query_parameter) {
-        Ok(result) => result,
-        Err(response) => return response,
-    };
+    if let Some(format) = ExportFormat::parse(query_parameter.format.as_deref())? {
+        let rows = storage
+            .export_history_balance(address, token, date_init, date_end)
+            .await?;
+        return Ok(export::stream(format, "balance-history", rows));
+    }
 
-    let (date_init, date_end) = match normalize_filter(&query_parameter) {
-        Ok(result) => result,
-        Err(response) => return response,
+    let balance_history = storage
+        .list_history_balance(
+            address.clone(),
+            token.clone(),
+            date_init.clone(),
+            date_end.clone(),
+            &pagination,
+        )
+        .await?;
+
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(
+            storage
+                .count_history_balance(
+                    address.clone(),
+                    token.clone(),
+                    date_init.clone(),
+                    date_end.clone(),
+                )
+                .await?,
+        ),
     };
 
-    let conn = &pool.connection.get().unwrap();
-    let balance_history = internals::list_history_balance_internal(
-        conn,
-        address.clone(),
-        token.clone(),
-        page,
-        per_page,
-        sort_by.as_deref(),
-        order.as_deref(),
-        date_init.clone(),
-        date_end.clone(),
-    );
-
-    match balance_history {
-        Ok(bh) => HttpResponse::Ok().json(PagedResp::new(
-            bh,
-            internals::count_history_balance_internal(
-                conn,
-                address.clone(),
-                token.clone(),
-                date_init.clone(),
-                date_end.clone(),
-            ),
-        )),
-        Err(err) => HttpResponse::Ok().json(err),
-    }
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(balance_history, count)))
 }
 
 #[get("/v1/tokens/{token}/price-history")]
 async fn list_token_price_history(
-    pool: web::Data<ConnectionPool>,
+    storage: web::Data<dyn ReadStorage>,
     path: web::Path<String>,
     query_parameter: web::Query<PaginationAndFilterParams>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let token = path.into_inner();
 
-    let (page, per_page, sort_by, order) = match normalize_pagination(&query_parameter) {
-        Ok(result) => result,
-        Err(response) => return response,
-    };
+    let pagination = normalize_pagination(&query_parameter)?;
+    let (date_init, date_end) = normalize_filter(&query_parameter)?;
 
-    let (date_init, date_end) = match normalize_filter(&query_parameter) {
-        Ok(result) => result,
-        Err(response) => return response,
+    if let Some(format) = ExportFormat::parse(query_parameter.format.as_deref())? {
+        let rows = storage
+            .export_history_price_token(token, date_init, date_end)
+            .await?;
+        return Ok(export::stream(format, "price-history", rows));
+    }
+
+    let price_history = storage
+        .list_history_price_token(
+            token.clone(),
+            date_init.clone(),
+            date_end.clone(),
+            &pagination,
+        )
+        .await?;
+
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(
+            storage
+                .count_history_price_token(token.clone(), date_init.clone(), date_end.clone())
+                .await?,
+        ),
     };
 
-    let conn = &pool.connection.get().unwrap();
-    let price_history = internals::list_history_price_token_internal(
-        conn,
-        token.clone(),
-        page,
-        per_page,
-        sort_by.as_deref(),
-        order.as_deref(),
-        date_init.clone(),
-        date_end.clone(),
-    );
-
-    match price_history {
-        Ok(ph) => HttpResponse::Ok().json(PagedResp::new(
-            ph,
-            internals::count_history_price_token_internal(
-                conn,
-                token.clone(),
-                date_init.clone(),
-                date_end.clone(),
-            ),
-        )),
-        Err(err) => HttpResponse::Ok().json(err),
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(price_history, count)))
+}
+
+#[get("/v1/tokens/{token}/supply-history")]
+async fn list_token_supply_history(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<String>,
+    query_parameter: web::Query<PaginationAndFilterParams>,
+) -> Result<HttpResponse, ApiError> {
+    let token = path.into_inner();
+
+    let pagination = normalize_pagination(&query_parameter)?;
+    let (date_init, date_end) = normalize_filter(&query_parameter)?;
+
+    if let Some(format) = ExportFormat::parse(query_parameter.format.as_deref())? {
+        let rows = storage
+            .export_token_supply_history(token, date_init, date_end)
+            .await?;
+        return Ok(export::stream(format, "supply-history", rows));
     }
+
+    let supply_history = storage
+        .list_token_supply_history(
+            token.clone(),
+            date_init.clone(),
+            date_end.clone(),
+            &pagination,
+        )
+        .await?;
+
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(
+            storage
+                .count_token_supply_history(token.clone(), date_init.clone(), date_end.clone())
+                .await?,
+        ),
+    };
+
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(supply_history, count)))
 }
 
 #[get("/v1/contracts/{contract}/daily-usage")]
 async fn list_daily_contract_usage(
-    pool: web::Data<ConnectionPool>,
+    storage: web::Data<dyn ReadStorage>,
     path: web::Path<String>,
     query_parameter: web::Query<PaginationAndFilterParams>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let contract = path.into_inner();
 
-    let (page, per_page, sort_by, order) = match normalize_pagination(&query_parameter) {
-        Ok(result) => result,
-        Err(response) => return response,
-    };
+    let pagination = normalize_pagination(&query_parameter)?;
+    let (date_init, date_end) = normalize_filter(&query_parameter)?;
 
-    let (date_init, date_end) = match normalize_filter(&query_parameter) {
-        Ok(result) => result,
-        Err(response) => return response,
+    if let Some(format) = ExportFormat::parse(query_parameter.format.as_deref())? {
+        let rows = storage
+            .export_daily_contract_usage(contract, date_init, date_end)
+            .await?;
+        return Ok(export::stream(format, "daily-usage", rows));
+    }
+
+    let usage_data = storage
+        .list_daily_contract_usage(
+            contract.clone(),
+            date_init.clone(),
+            date_end.clone(),
+            &pagination,
+        )
+        .await?;
+
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(
+            storage
+                .count_daily_contract_usage(contract.clone(), date_init.clone(), date_end.clone())
+                .await?,
+        ),
     };
 
-    let conn = &pool.connection.get().unwrap();
-    let usage_data = internals::list_daily_contract_usage_internal(
-        conn,
-        contract.clone(),
-        page,
-        per_page,
-        sort_by.as_deref(),
-        order.as_deref(),
-        date_init.clone(),
-        date_end.clone(),
-    );
-
-    match usage_data {
-        Ok(ud) => HttpResponse::Ok().json(PagedResp::new(
-            ud,
-            internals::count_daily_contract_usage_internal(
-                conn,
-                contract.clone(),
-                date_init.clone(),
-                date_end.clone(),
-            ),
-        )),
-        Err(err) => HttpResponse::Ok().json(err),
-    }
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(usage_data, count)))
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(list_balance_history);
     cfg.service(list_token_price_history);
+    cfg.service(list_token_supply_history);
     cfg.service(list_daily_contract_usage);
 }
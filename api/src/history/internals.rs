@@ -2,69 +2,52 @@ use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::params;
 
-use crate::error::Error;
-use crate::history::models::{DailyAddressBalance, DailyContractUsage, DailyTokenPrice};
+use crate::error::ApiError;
+use crate::history::models::{
+    DailyAddressBalance, DailyContractUsage, DailyTokenPrice, DailyTokenSupply,
+};
+use crate::shared::pagination::{Page, Pagination};
 
 pub fn list_history_balance_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     address: String,
     token: String,
-    page: u32,
-    per_page: u32,
-    sort_by: Option<&str>,
-    order: Option<&str>,
     date_init: String,
     date_end: String,
-) -> Result<Vec<DailyAddressBalance>, Error> {
-    let order_clause = if let (Some(sort_by), Some(order)) = (sort_by, order) {
-        let valid_columns = vec!["id", "date"];
-        if valid_columns.contains(&sort_by) {
-            format!("ORDER BY {} {}", sort_by, order)
-        } else {
-            return Err(Error {
-                error: format!("Invalid sort_by parameter: {}", sort_by),
-            });
-        }
-    } else {
-        String::new()
-    };
+    pagination: &Pagination,
+) -> Result<Page<Vec<DailyAddressBalance>>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("id", &["id", "date"])?;
 
     let sql = format!(
-        "SELECT * FROM daily_address_balances WHERE address = ? AND token_contract = ? AND date BETWEEN ? AND ? {} LIMIT ? OFFSET ?",
-        order_clause
+        "SELECT * FROM daily_address_balances WHERE address = ? AND token_contract = ? AND date BETWEEN ? AND ? {} {}",
+        keyset_clause, page_clause
     );
 
-    let mut stmt = conn.prepare(sql.as_str()).unwrap();
-
-    let mut rows = stmt
-        .query(params![
-            address,
-            token,
-            date_init,
-            date_end,
-            per_page,
-            page * per_page
-        ])
-        .unwrap();
+    let mut stmt = conn.prepare(sql.as_str())?;
+
+    let mut rows = stmt.query(params![address, token, date_init, date_end])?;
     let mut daily_balances = Vec::new();
 
-    while let Some(row) = rows.next().unwrap() {
-        daily_balances.push(DailyAddressBalance {
-            timestamp: 0,
-            block_index: row.get(1).unwrap(),
-            date: row.get(2).unwrap(),
-            address: row.get(3).unwrap(),
-            token_contract: row.get(4).unwrap(),
-            balance: row.get(5).unwrap(),
-        })
+    while let Some(row) = rows.next()? {
+        daily_balances.push((
+            row.get::<_, i64>(0)?,
+            DailyAddressBalance {
+                timestamp: 0,
+                block_index: row.get(1)?,
+                date: row.get(2)?,
+                address: row.get(3)?,
+                token_contract: row.get(4)?,
+                balance: row.get(5)?,
+            },
+        ))
     }
 
-    if daily_balances.is_empty() {
-        Err(Error {
-            error: "No balances for that address/token.".to_string(),
-        })
+    if daily_balances.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No balances for that address/token.".to_string(),
+        ))
     } else {
-        Ok(daily_balances)
+        Ok(pagination.finish(daily_balances))
     }
 }
 
@@ -74,74 +57,54 @@ pub fn count_history_balance_internal(
     token: String,
     date_init: String,
     date_end: String,
-) -> usize {
+) -> Result<usize, ApiError> {
     let sql = "
         SELECT COUNT(*) 
         FROM daily_address_balances 
         WHERE address = ? AND token_contract = ? AND date BETWEEN ? AND ?";
 
-    conn.query_row(sql, params![address, token, date_init, date_end], |row| {
+    let count = conn.query_row(sql, params![address, token, date_init, date_end], |row| {
         row.get::<_, usize>(0)
-    })
-    .unwrap_or(0)
+    })?;
+
+    Ok(count)
 }
 
 pub fn list_history_price_token_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     token: String,
-    page: u32,
-    per_page: u32,
-    sort_by: Option<&str>,
-    order: Option<&str>,
     date_init: String,
     date_end: String,
-) -> Result<Vec<DailyTokenPrice>, Error> {
-    let order_clause = if let (Some(sort_by), Some(order)) = (sort_by, order) {
-        let valid_columns = vec!["id", "date"];
-        if valid_columns.contains(&sort_by) {
-            format!("ORDER BY {} {}", sort_by, order)
-        } else {
-            return Err(Error {
-                error: format!("Invalid sort_by parameter: {}", sort_by),
-            });
-        }
-    } else {
-        String::new()
-    };
+    pagination: &Pagination,
+) -> Result<Page<Vec<DailyTokenPrice>>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("id", &["id", "date"])?;
 
     let sql = format!(
-        "SELECT * FROM daily_token_price_history WHERE token_contract = ? AND date BETWEEN ? AND ? {} LIMIT ? OFFSET ?",
-        order_clause
+        "SELECT * FROM daily_token_price_history WHERE token_contract = ? AND date BETWEEN ? AND ? {} {}",
+        keyset_clause, page_clause
     );
 
-    let mut stmt = conn.prepare(sql.as_str()).unwrap();
-
-    let mut rows = stmt
-        .query(params![
-            token,
-            date_init,
-            date_end,
-            per_page,
-            page * per_page
-        ])
-        .unwrap();
+    let mut stmt = conn.prepare(sql.as_str())?;
+
+    let mut rows = stmt.query(params![token, date_init, date_end])?;
     let mut daily_token_price = Vec::new();
 
-    while let Some(row) = rows.next().unwrap() {
-        daily_token_price.push(DailyTokenPrice {
-            block_index: row.get(1).unwrap(),
-            date: row.get(2).unwrap(),
-            token_contract: row.get(3).unwrap(),
-            price: row.get(4).unwrap(),
-        })
+    while let Some(row) = rows.next()? {
+        daily_token_price.push((
+            row.get::<_, i64>(0)?,
+            DailyTokenPrice {
+                block_index: row.get(1)?,
+                date: row.get(2)?,
+                token_contract: row.get(3)?,
+                price: row.get(4)?,
+            },
+        ))
     }
 
-    if daily_token_price.is_empty() {
-        Err(Error {
-            error: "No price for that token.".to_string(),
-        })
+    if daily_token_price.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound("No price for that token.".to_string()))
     } else {
-        Ok(daily_token_price)
+        Ok(pagination.finish(daily_token_price))
     }
 }
 
@@ -150,74 +113,114 @@ pub fn count_history_price_token_internal(
     token: String,
     date_init: String,
     date_end: String,
-) -> usize {
+) -> Result<usize, ApiError> {
     let sql = "
         SELECT COUNT(*)
         FROM daily_token_price_history
         WHERE token_contract = ? AND date BETWEEN ? AND ?
     ";
 
-    conn.query_row(sql, params![token, date_init, date_end], |row| {
+    let count = conn.query_row(sql, params![token, date_init, date_end], |row| {
+        row.get::<_, usize>(0)
+    })?;
+
+    Ok(count)
+}
+
+pub fn list_token_supply_history_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: String,
+    date_init: String,
+    date_end: String,
+    pagination: &Pagination,
+) -> Result<Page<Vec<DailyTokenSupply>>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("id", &["id", "date"])?;
+
+    let sql = format!(
+        "SELECT id, date, token_contract, minted, burned, supply FROM daily_token_stats WHERE token_contract = ? AND date BETWEEN ? AND ? {} {}",
+        keyset_clause, page_clause
+    );
+
+    let mut stmt = conn.prepare(sql.as_str())?;
+
+    let mut rows = stmt.query(params![token, date_init, date_end])?;
+    let mut daily_supply = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        daily_supply.push((
+            row.get::<_, i64>(0)?,
+            DailyTokenSupply {
+                date: row.get(1)?,
+                token_contract: row.get(2)?,
+                minted: row.get(3)?,
+                burned: row.get(4)?,
+                supply: row.get(5)?,
+            },
+        ))
+    }
+
+    if daily_supply.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound("No supply for that token.".to_string()))
+    } else {
+        Ok(pagination.finish(daily_supply))
+    }
+}
+
+pub fn count_token_supply_history_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: String,
+    date_init: String,
+    date_end: String,
+) -> Result<usize, ApiError> {
+    let sql = "
+        SELECT COUNT(*)
+        FROM daily_token_stats
+        WHERE token_contract = ? AND date BETWEEN ? AND ?
+    ";
+
+    let count = conn.query_row(sql, params![token, date_init, date_end], |row| {
         row.get::<_, usize>(0)
-    })
-    .unwrap_or(0)
+    })?;
+
+    Ok(count)
 }
 
 pub fn list_daily_contract_usage_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     contract: String,
-    page: u32,
-    per_page: u32,
-    sort_by: Option<&str>,
-    order: Option<&str>,
     date_init: String,
     date_end: String,
-) -> Result<Vec<DailyContractUsage>, Error> {
-    let order_clause = if let (Some(sort_by), Some(order)) = (sort_by, order) {
-        let valid_columns = vec!["id", "date"];
-        if valid_columns.contains(&sort_by) {
-            format!("ORDER BY {} {}", sort_by, order)
-        } else {
-            return Err(Error {
-                error: format!("Invalid sort_by parameter: {}", sort_by),
-            });
-        }
-    } else {
-        String::new()
-    };
+    pagination: &Pagination,
+) -> Result<Page<Vec<DailyContractUsage>>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("id", &["id", "date"])?;
 
     let sql = format!(
-        "SELECT * FROM daily_contract_usage WHERE contract = ? AND date BETWEEN ? AND ? {} LIMIT ? OFFSET ?",
-        order_clause
+        "SELECT * FROM daily_contract_usage WHERE contract = ? AND date BETWEEN ? AND ? {} {}",
+        keyset_clause, page_clause
     );
 
-    let mut stmt = conn.prepare(sql.as_str()).unwrap();
-
-    let mut rows = stmt
-        .query(params![
-            contract,
-            date_init,
-            date_end,
-            per_page,
-            page * per_page
-        ])
-        .unwrap();
+    let mut stmt = conn.prepare(sql.as_str())?;
+
+    let mut rows = stmt.query(params![contract, date_init, date_end])?;
     let mut daily_contract_usage = Vec::new();
 
-    while let Some(row) = rows.next().unwrap() {
-        daily_contract_usage.push(DailyContractUsage {
-            date: row.get(1).unwrap(),
-            contract: row.get(2).unwrap(),
-            usage: row.get(3).unwrap(),
-        })
+    while let Some(row) = rows.next()? {
+        daily_contract_usage.push((
+            row.get::<_, i64>(0)?,
+            DailyContractUsage {
+                date: row.get(1)?,
+                contract: row.get(2)?,
+                usage: row.get(3)?,
+            },
+        ))
     }
 
-    if daily_contract_usage.is_empty() {
-        Err(Error {
-            error: "No contract usage for that token.".to_string(),
-        })
+    if daily_contract_usage.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No contract usage for that token.".to_string(),
+        ))
     } else {
-        Ok(daily_contract_usage)
+        Ok(pagination.finish(daily_contract_usage))
     }
 }
 
@@ -226,15 +229,143 @@ pub fn count_daily_contract_usage_internal(
     contract: String,
     date_init: String,
     date_end: String,
-) -> usize {
+) -> Result<usize, ApiError> {
     let sql = "
         SELECT COUNT(*)
         FROM daily_contract_usage
         WHERE contract = ? AND date BETWEEN ? AND ?
     ";
 
-    conn.query_row(sql, params![contract, date_init, date_end], |row| {
+    let count = conn.query_row(sql, params![contract, date_init, date_end], |row| {
         row.get::<_, usize>(0)
-    })
-    .unwrap_or(0)
+    })?;
+
+    Ok(count)
+}
+
+pub fn export_history_balance_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    address: String,
+    token: String,
+    date_init: String,
+    date_end: String,
+    emit: &mut dyn FnMut(DailyAddressBalance) -> bool,
+) -> Result<(), ApiError> {
+    let sql = "
+        SELECT * FROM daily_address_balances
+        WHERE address = ? AND token_contract = ? AND date BETWEEN ? AND ?
+        ORDER BY id ASC";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![address, token, date_init, date_end])?;
+
+    while let Some(row) = rows.next()? {
+        let balance = DailyAddressBalance {
+            timestamp: 0,
+            block_index: row.get(1)?,
+            date: row.get(2)?,
+            address: row.get(3)?,
+            token_contract: row.get(4)?,
+            balance: row.get(5)?,
+        };
+
+        if !emit(balance) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn export_history_price_token_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: String,
+    date_init: String,
+    date_end: String,
+    emit: &mut dyn FnMut(DailyTokenPrice) -> bool,
+) -> Result<(), ApiError> {
+    let sql = "
+        SELECT * FROM daily_token_price_history
+        WHERE token_contract = ? AND date BETWEEN ? AND ?
+        ORDER BY id ASC";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![token, date_init, date_end])?;
+
+    while let Some(row) = rows.next()? {
+        let price = DailyTokenPrice {
+            block_index: row.get(1)?,
+            date: row.get(2)?,
+            token_contract: row.get(3)?,
+            price: row.get(4)?,
+        };
+
+        if !emit(price) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn export_token_supply_history_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: String,
+    date_init: String,
+    date_end: String,
+    emit: &mut dyn FnMut(DailyTokenSupply) -> bool,
+) -> Result<(), ApiError> {
+    let sql = "
+        SELECT date, token_contract, minted, burned, supply FROM daily_token_stats
+        WHERE token_contract = ? AND date BETWEEN ? AND ?
+        ORDER BY id ASC";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![token, date_init, date_end])?;
+
+    while let Some(row) = rows.next()? {
+        let supply = DailyTokenSupply {
+            date: row.get(0)?,
+            token_contract: row.get(1)?,
+            minted: row.get(2)?,
+            burned: row.get(3)?,
+            supply: row.get(4)?,
+        };
+
+        if !emit(supply) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn export_daily_contract_usage_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    contract: String,
+    date_init: String,
+    date_end: String,
+    emit: &mut dyn FnMut(DailyContractUsage) -> bool,
+) -> Result<(), ApiError> {
+    let sql = "
+        SELECT * FROM daily_contract_usage
+        WHERE contract = ? AND date BETWEEN ? AND ?
+        ORDER BY id ASC";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![contract, date_init, date_end])?;
+
+    while let Some(row) = rows.next()? {
+        let usage = DailyContractUsage {
+            date: row.get(1)?,
+            contract: row.get(2)?,
+            usage: row.get(3)?,
+        };
+
+        if !emit(usage) {
+            break;
+        }
+    }
+
+    Ok(())
 }
@@ -1,3 +1,4 @@
 pub mod controller;
-mod internals;
+pub mod internals;
 pub mod models;
+pub mod postgres;
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::shared::export::ExportRow;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DailyAddressBalance {
     pub block_index: u64,
@@ -18,9 +20,78 @@ pub struct DailyTokenPrice {
     pub price: f64,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DailyTokenSupply {
+    pub date: String,
+    pub token_contract: String,
+    pub minted: String, // raw integer amounts
+    pub burned: String,
+    pub supply: Option<String>, // total at the end of the day, None until it has been seeded
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DailyContractUsage {
     pub date: String,
     pub contract: String,
     pub usage: u32,
 }
+
+impl ExportRow for DailyAddressBalance {
+    const HEADER: &'static [&'static str] = &[
+        "date",
+        "block_index",
+        "address",
+        "token_contract",
+        "balance",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.date.clone(),
+            self.block_index.to_string(),
+            self.address.clone(),
+            self.token_contract.clone(),
+            self.balance.to_string(),
+        ]
+    }
+}
+
+impl ExportRow for DailyTokenPrice {
+    const HEADER: &'static [&'static str] = &["date", "block_index", "token_contract", "price"];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.date.clone(),
+            self.block_index.to_string(),
+            self.token_contract.clone(),
+            self.price.to_string(),
+        ]
+    }
+}
+
+impl ExportRow for DailyTokenSupply {
+    const HEADER: &'static [&'static str] =
+        &["date", "token_contract", "minted", "burned", "supply"];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.date.clone(),
+            self.token_contract.clone(),
+            self.minted.clone(),
+            self.burned.clone(),
+            self.supply.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl ExportRow for DailyContractUsage {
+    const HEADER: &'static [&'static str] = &["date", "contract", "usage"];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.date.clone(),
+            self.contract.clone(),
+            self.usage.to_string(),
+        ]
+    }
+}
@@ -0,0 +1,345 @@
+use tokio_postgres::{Client, Row};
+
+use crate::error::ApiError;
+use crate::history::models::{
+    DailyAddressBalance, DailyContractUsage, DailyTokenPrice, DailyTokenSupply,
+};
+use crate::shared::export::ExportSink;
+use crate::shared::pagination::{Page, Pagination};
+use crate::shared::postgres::{get_int, page_sql, stream_rows};
+
+const BALANCE_COLUMNS: &str = "id, block_index, date, address, token_contract, balance";
+const PRICE_COLUMNS: &str = "id, block_index, date, token_contract, price";
+const SUPPLY_COLUMNS: &str = "id, date, token_contract, minted, burned, supply";
+const USAGE_COLUMNS: &str = "id, date, contract, usage";
+
+fn balance_row(row: &Row) -> Result<DailyAddressBalance, ApiError> {
+    Ok(DailyAddressBalance {
+        timestamp: 0,
+        block_index: get_int(row, 1)?,
+        date: row.try_get(2)?,
+        address: row.try_get(3)?,
+        token_contract: row.try_get(4)?,
+        balance: row.try_get(5)?,
+    })
+}
+
+fn price_row(row: &Row) -> Result<DailyTokenPrice, ApiError> {
+    Ok(DailyTokenPrice {
+        block_index: get_int(row, 1)?,
+        date: row.try_get(2)?,
+        token_contract: row.try_get(3)?,
+        price: row.try_get(4)?,
+    })
+}
+
+fn supply_row(row: &Row) -> Result<DailyTokenSupply, ApiError> {
+    Ok(DailyTokenSupply {
+        date: row.try_get(1)?,
+        token_contract: row.try_get(2)?,
+        minted: row.try_get(3)?,
+        burned: row.try_get(4)?,
+        supply: row.try_get(5)?,
+    })
+}
+
+fn usage_row(row: &Row) -> Result<DailyContractUsage, ApiError> {
+    Ok(DailyContractUsage {
+        date: row.try_get(1)?,
+        contract: row.try_get(2)?,
+        usage: get_int(row, 3)?,
+    })
+}
+
+// rows keyed by their id, the first column of every query here
+fn keyed<T>(
+    rows: Vec<Row>,
+    map: fn(&Row) -> Result<T, ApiError>,
+) -> Result<Vec<(i64, T)>, ApiError> {
+    rows.iter()
+        .map(|row| Ok((row.try_get::<_, i64>(0)?, map(row)?)))
+        .collect()
+}
+
+pub async fn list_history_balance_internal(
+    client: &Client,
+    address: String,
+    token: String,
+    date_init: String,
+    date_end: String,
+    pagination: &Pagination,
+) -> Result<Page<Vec<DailyAddressBalance>>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "id", &["id", "date"])?;
+
+    let sql = format!(
+        "SELECT {} FROM daily_address_balances
+        WHERE address = $1 AND token_contract = $2 AND date BETWEEN $3 AND $4 {} {}",
+        BALANCE_COLUMNS, keyset_clause, page_clause
+    );
+
+    let rows = client
+        .query(sql.as_str(), &[&address, &token, &date_init, &date_end])
+        .await?;
+    let daily_balances = keyed(rows, balance_row)?;
+
+    if daily_balances.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No balances for that address/token.".to_string(),
+        ))
+    } else {
+        Ok(pagination.finish(daily_balances))
+    }
+}
+
+pub async fn count_history_balance_internal(
+    client: &Client,
+    address: String,
+    token: String,
+    date_init: String,
+    date_end: String,
+) -> Result<usize, ApiError> {
+    let sql = "
+        SELECT COUNT(*)
+        FROM daily_address_balances
+        WHERE address = $1 AND token_contract = $2 AND date BETWEEN $3 AND $4";
+
+    let row = client
+        .query_one(sql, &[&address, &token, &date_init, &date_end])
+        .await?;
+
+    get_int(&row, 0)
+}
+
+pub async fn list_history_price_token_internal(
+    client: &Client,
+    token: String,
+    date_init: String,
+    date_end: String,
+    pagination: &Pagination,
+) -> Result<Page<Vec<DailyTokenPrice>>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "id", &["id", "date"])?;
+
+    let sql = format!(
+        "SELECT {} FROM daily_token_price_history
+        WHERE token_contract = $1 AND date BETWEEN $2 AND $3 {} {}",
+        PRICE_COLUMNS, keyset_clause, page_clause
+    );
+
+    let rows = client
+        .query(sql.as_str(), &[&token, &date_init, &date_end])
+        .await?;
+    let daily_token_price = keyed(rows, price_row)?;
+
+    if daily_token_price.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound("No price for that token.".to_string()))
+    } else {
+        Ok(pagination.finish(daily_token_price))
+    }
+}
+
+pub async fn count_history_price_token_internal(
+    client: &Client,
+    token: String,
+    date_init: String,
+    date_end: String,
+) -> Result<usize, ApiError> {
+    let sql = "
+        SELECT COUNT(*)
+        FROM daily_token_price_history
+        WHERE token_contract = $1 AND date BETWEEN $2 AND $3";
+
+    let row = client
+        .query_one(sql, &[&token, &date_init, &date_end])
+        .await?;
+
+    get_int(&row, 0)
+}
+
+pub async fn list_token_supply_history_internal(
+    client: &Client,
+    token: String,
+    date_init: String,
+    date_end: String,
+    pagination: &Pagination,
+) -> Result<Page<Vec<DailyTokenSupply>>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "id", &["id", "date"])?;
+
+    let sql = format!(
+        "SELECT {} FROM daily_token_stats
+        WHERE token_contract = $1 AND date BETWEEN $2 AND $3 {} {}",
+        SUPPLY_COLUMNS, keyset_clause, page_clause
+    );
+
+    let rows = client
+        .query(sql.as_str(), &[&token, &date_init, &date_end])
+        .await?;
+    let daily_supply = keyed(rows, supply_row)?;
+
+    if daily_supply.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound("No supply for that token.".to_string()))
+    } else {
+        Ok(pagination.finish(daily_supply))
+    }
+}
+
+pub async fn count_token_supply_history_internal(
+    client: &Client,
+    token: String,
+    date_init: String,
+    date_end: String,
+) -> Result<usize, ApiError> {
+    let sql = "
+        SELECT COUNT(*)
+        FROM daily_token_stats
+        WHERE token_contract = $1 AND date BETWEEN $2 AND $3";
+
+    let row = client
+        .query_one(sql, &[&token, &date_init, &date_end])
+        .await?;
+
+    get_int(&row, 0)
+}
+
+pub async fn list_daily_contract_usage_internal(
+    client: &Client,
+    contract: String,
+    date_init: String,
+    date_end: String,
+    pagination: &Pagination,
+) -> Result<Page<Vec<DailyContractUsage>>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "id", &["id", "date"])?;
+
+    let sql = format!(
+        "SELECT {} FROM daily_contract_usage
+        WHERE contract = $1 AND date BETWEEN $2 AND $3 {} {}",
+        USAGE_COLUMNS, keyset_clause, page_clause
+    );
+
+    let rows = client
+        .query(sql.as_str(), &[&contract, &date_init, &date_end])
+        .await?;
+    let daily_contract_usage = keyed(rows, usage_row)?;
+
+    if daily_contract_usage.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No contract usage for that token.".to_string(),
+        ))
+    } else {
+        Ok(pagination.finish(daily_contract_usage))
+    }
+}
+
+pub async fn count_daily_contract_usage_internal(
+    client: &Client,
+    contract: String,
+    date_init: String,
+    date_end: String,
+) -> Result<usize, ApiError> {
+    let sql = "
+        SELECT COUNT(*)
+        FROM daily_contract_usage
+        WHERE contract = $1 AND date BETWEEN $2 AND $3";
+
+    let row = client
+        .query_one(sql, &[&contract, &date_init, &date_end])
+        .await?;
+
+    get_int(&row, 0)
+}
+
+pub async fn export_history_balance_internal(
+    client: &Client,
+    address: String,
+    token: String,
+    date_init: String,
+    date_end: String,
+    sink: &ExportSink<DailyAddressBalance>,
+) -> Result<(), ApiError> {
+    let sql = format!(
+        "SELECT {} FROM daily_address_balances
+        WHERE address = $1 AND token_contract = $2 AND date BETWEEN $3 AND $4
+        ORDER BY id ASC",
+        BALANCE_COLUMNS
+    );
+
+    stream_rows(
+        client,
+        &sql,
+        &[&address, &token, &date_init, &date_end],
+        sink,
+        balance_row,
+    )
+    .await
+}
+
+pub async fn export_history_price_token_internal(
+    client: &Client,
+    token: String,
+    date_init: String,
+    date_end: String,
+    sink: &ExportSink<DailyTokenPrice>,
+) -> Result<(), ApiError> {
+    let sql = format!(
+        "SELECT {} FROM daily_token_price_history
+        WHERE token_contract = $1 AND date BETWEEN $2 AND $3
+        ORDER BY id ASC",
+        PRICE_COLUMNS
+    );
+
+    stream_rows(
+        client,
+        &sql,
+        &[&token, &date_init, &date_end],
+        sink,
+        price_row,
+    )
+    .await
+}
+
+pub async fn export_token_supply_history_internal(
+    client: &Client,
+    token: String,
+    date_init: String,
+    date_end: String,
+    sink: &ExportSink<DailyTokenSupply>,
+) -> Result<(), ApiError> {
+    let sql = format!(
+        "SELECT {} FROM daily_token_stats
+        WHERE token_contract = $1 AND date BETWEEN $2 AND $3
+        ORDER BY id ASC",
+        SUPPLY_COLUMNS
+    );
+
+    stream_rows(
+        client,
+        &sql,
+        &[&token, &date_init, &date_end],
+        sink,
+        supply_row,
+    )
+    .await
+}
+
+pub async fn export_daily_contract_usage_internal(
+    client: &Client,
+    contract: String,
+    date_init: String,
+    date_end: String,
+    sink: &ExportSink<DailyContractUsage>,
+) -> Result<(), ApiError> {
+    let sql = format!(
+        "SELECT {} FROM daily_contract_usage
+        WHERE contract = $1 AND date BETWEEN $2 AND $3
+        ORDER BY id ASC",
+        USAGE_COLUMNS
+    );
+
+    stream_rows(
+        client,
+        &sql,
+        &[&contract, &date_init, &date_end],
+        sink,
+        usage_row,
+    )
+    .await
+}
@@ -0,0 +1,159 @@
+use crate::indexer::controller::{backfill_prices_once, rebuild_once, snapshot_once, verify_once};
+use crate::indexer::models::VerifyParams;
+use crate::indexer::snapshot;
+use crate::shared::db::DB_PATH;
+use crate::ConnectionPool;
+
+use std::path::PathBuf;
+use std::process;
+
+const USAGE: &str = "Usage: api [verify [--sample N] [--from A --to B] [--repair] | rebuild \
+                     | backfill-prices | snapshot [--out DIR] | import MANIFEST [--force]]";
+
+// one-off commands run instead of the server when the binary gets arguments
+pub async fn run(command: &str, args: &[String], pool: &ConnectionPool) -> std::io::Result<()> {
+    match command {
+        "verify" => verify(args, pool).await,
+        "rebuild" => rebuild(pool).await,
+        "backfill-prices" => backfill_prices(pool).await,
+        "snapshot" => create_snapshot(args, pool),
+        _ => {
+            eprintln!("Unknown command {command}. {USAGE}");
+            process::exit(2);
+        }
+    }
+}
+
+async fn verify(args: &[String], pool: &ConnectionPool) -> std::io::Result<()> {
+    let params = parse_verify_args(args).unwrap_or_else(|err| {
+        eprintln!("{err}. {USAGE}");
+        process::exit(2);
+    });
+
+    match verify_once(pool, &params).await {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if !report.consistent {
+                process::exit(1);
+            }
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(2);
+        }
+    }
+}
+
+async fn rebuild(pool: &ConnectionPool) -> std::io::Result<()> {
+    match rebuild_once(pool).await {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(2);
+        }
+    }
+}
+
+async fn backfill_prices(pool: &ConnectionPool) -> std::io::Result<()> {
+    match backfill_prices_once(pool).await {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(2);
+        }
+    }
+}
+
+fn create_snapshot(args: &[String], pool: &ConnectionPool) -> std::io::Result<()> {
+    let out_dir = match args {
+        [] => snapshot::snapshot_dir(),
+        [flag, dir] if flag == "--out" => PathBuf::from(dir),
+        _ => {
+            eprintln!("Invalid snapshot arguments. {USAGE}");
+            process::exit(2);
+        }
+    };
+
+    match snapshot_once(pool, &out_dir) {
+        Ok(manifest) => {
+            println!("{}", serde_json::to_string_pretty(&manifest)?);
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(2);
+        }
+    }
+}
+
+// runs before the database is opened, as the file is swapped out from under it
+pub fn import(args: &[String]) -> std::io::Result<()> {
+    let (manifest, force) = match args {
+        [manifest] => (manifest, false),
+        [manifest, flag] if flag == "--force" => (manifest, true),
+        _ => {
+            eprintln!("Invalid import arguments. {USAGE}");
+            process::exit(2);
+        }
+    };
+
+    match snapshot::import_snapshot(&PathBuf::from(manifest), &DB_PATH, force) {
+        Ok(manifest) => {
+            println!("{}", serde_json::to_string_pretty(&manifest)?);
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("Failed to import snapshot: {err}");
+            process::exit(2);
+        }
+    }
+}
+
+fn parse_verify_args(args: &[String]) -> Result<VerifyParams, String> {
+    let mut params = VerifyParams::default();
+    let mut args = args.iter();
+
+    while let Some(flag) = args.next() {
+        if flag == "--repair" {
+            params.repair = Some(true);
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {flag}"))?
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid value for {flag}"))?;
+        match flag.as_str() {
+            "--sample" => params.sample = Some(value),
+            "--from" => params.from = Some(value),
+            "--to" => params.to = Some(value),
+            _ => return Err(format!("Unknown flag {flag}")),
+        }
+    }
+
+    Ok(params)
+}
+
+#[test]
+fn test_parse_verify_args() {
+    let args: Vec<String> = ["--sample", "5", "--repair", "--from", "10", "--to", "20"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    let params = parse_verify_args(&args).unwrap();
+    assert_eq!(params.sample, Some(5));
+    assert_eq!(params.from, Some(10));
+    assert_eq!(params.to, Some(20));
+    assert_eq!(params.repair, Some(true));
+
+    assert!(parse_verify_args(&["--sample".to_string()]).is_err());
+    assert!(parse_verify_args(&["--depth".to_string(), "1".to_string()]).is_err());
+}
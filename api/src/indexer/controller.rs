@@ -1,175 +1,520 @@
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use once_cell::sync::Lazy;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
 
+use crate::error::ApiError;
+use crate::shared::auth::{authorize_admin, bearer};
+use crate::shared::config::{AdminConfig, Config, StorageBackend};
 use crate::ConnectionPool;
 
 use crate::indexer::config::AppConfig;
+use crate::indexer::models::{
+    IndexerStatus, PriceBackfill, RebuildReport, SnapshotManifest, VerifyParams, VerifyReport,
+    VERIFY_RANGE_MAX, VERIFY_SAMPLE_DEFAULT, VERIFY_SAMPLE_MAX,
+};
+use crate::indexer::prices::oracle::PriceOracle;
 use crate::indexer::rpc::client::Client as RpcClient;
 use crate::indexer::rpc::database::Database as LocalDatabase;
-use crate::indexer::spawn::indexer::Indexer;
+use crate::indexer::rpc::storage::{self, Storage};
+use crate::indexer::snapshot;
+use crate::indexer::spawn::indexer::{self as spawned, Indexer};
+use crate::indexer::spawn::sync;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::Mutex;
 
-pub async fn initilize_indexer_setup(pool: web::Data<ConnectionPool>) -> impl Responder {
-    let conn = &pool.connection.get().unwrap();
+fn setup_step<T>(result: rusqlite::Result<T>, message: &str) -> Result<T, ApiError> {
+    result.map_err(|err| ApiError::Internal(format!("{}: {}", message, err)))
+}
 
-    let db = match LocalDatabase::new(&conn) {
-        Ok(db) => db,
-        Err(_) => return HttpResponse::InternalServerError().body("Failed to initialize database"),
-    };
+pub async fn initilize_indexer_setup(pool: web::Data<ConnectionPool>) -> Result<(), ApiError> {
+    let conn = &pool.connection.get()?;
+
+    create_tables(conn)?;
+
+    storage::setup(&load_config()?.storage)
+        .await
+        .map_err(|err| ApiError::Internal(format!("Failed to set up storage: {}", err)))?;
+
+    Ok(())
+}
+
+// creates and migrates every SQLite table, leaving existing data in place
+pub fn create_tables(conn: &PooledConnection<SqliteConnectionManager>) -> Result<(), ApiError> {
+    let db = setup_step(LocalDatabase::new(conn), "Failed to initialize database")?;
 
     // make sure WAL journal mode is enabled
-    if let Err(_) = db.set_to_wal() {
-        return HttpResponse::InternalServerError().body("Failed to set to WAL");
-    }
+    setup_step(db.set_to_wal(), "Failed to set to WAL")?;
 
     // fails if it already exists
-    if let Err(_) = db.create_block_table() {
-        return HttpResponse::InternalServerError().body("Failed to create block table");
-    }
-    if let Err(_) = db.create_transaction_table() {
-        return HttpResponse::InternalServerError().body("Failed to create transaction table");
-    }
-    if let Err(_) = db.create_witnesses_table() {
-        return HttpResponse::InternalServerError().body("Failed to create witnesses table");
-    }
-    if let Err(_) = db.create_sginers_table() {
-        return HttpResponse::InternalServerError().body("Failed to create signer table");
-    }
-    if let Err(_) = db.create_allowed_contracts_table() {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create allowed contracts table");
-    }
-    if let Err(_) = db.create_transaction_notification_table() {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create transaction notification table");
-    }
-    if let Err(_) = db.create_transaction_notification_state_value_table() {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create transaction notification state value table");
-    }
-    if let Err(_) = db.create_daily_address_balances() {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create daily_address_balances table");
-    }
-    if let Err(_) = db.create_daily_token_price_history() {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create daily_token_price_history table");
-    }
-    if let Err(_) = db.create_contract_table() {
-        return HttpResponse::InternalServerError().body("Failed to create contract table");
-    }
-    if let Err(_) = db.create_daily_contract_usage() {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create daily contract usage table");
-    }
+    setup_step(db.create_block_table(), "Failed to create block table")?;
+    setup_step(
+        db.create_transaction_table(),
+        "Failed to create transaction table",
+    )?;
+    setup_step(
+        db.create_witnesses_table(),
+        "Failed to create witnesses table",
+    )?;
+    setup_step(db.create_sginers_table(), "Failed to create signer table")?;
+    setup_step(
+        db.create_allowed_contracts_table(),
+        "Failed to create allowed contracts table",
+    )?;
+    setup_step(
+        db.create_transaction_notification_table(),
+        "Failed to create transaction notification table",
+    )?;
+    setup_step(
+        db.create_transaction_notification_state_value_table(),
+        "Failed to create transaction notification state value table",
+    )?;
+    setup_step(
+        db.create_daily_address_balances(),
+        "Failed to create daily_address_balances table",
+    )?;
+    setup_step(
+        db.create_address_balances(),
+        "Failed to create address_balances table",
+    )?;
+    setup_step(
+        db.create_balance_changes(),
+        "Failed to create balance_changes table",
+    )?;
+    setup_step(
+        db.create_balance_changes_seeded(),
+        "Failed to create balance_changes_seeded table",
+    )?;
+    setup_step(
+        db.create_daily_token_price_history(),
+        "Failed to create daily_token_price_history table",
+    )?;
+    setup_step(
+        db.create_contract_table(),
+        "Failed to create contract table",
+    )?;
+    setup_step(
+        db.create_daily_contract_usage(),
+        "Failed to create daily contract usage table",
+    )?;
+    setup_step(
+        db.create_daily_network_stats(),
+        "Failed to create daily network stats table",
+    )?;
+    setup_step(
+        db.create_daily_token_stats(),
+        "Failed to create daily_token_stats table",
+    )?;
+    setup_step(db.create_webhooks(), "Failed to create webhooks table")?;
+    setup_step(
+        db.create_webhook_deliveries(),
+        "Failed to create webhook_deliveries table",
+    )?;
+    setup_step(
+        db.create_webhook_dead_letters(),
+        "Failed to create webhook_dead_letters table",
+    )?;
+    setup_step(
+        db.create_address_first_seen(),
+        "Failed to create address first seen table",
+    )?;
+
+    // bring tables created by older versions up to date
+    setup_step(
+        db.add_column_if_missing("contracts", "manifest", "TEXT NULL"),
+        "Failed to migrate contract table",
+    )?;
+    setup_step(
+        db.add_column_if_missing("contracts", "symbol", "TEXT NULL"),
+        "Failed to migrate contract table",
+    )?;
+    setup_step(
+        db.add_column_if_missing("contracts", "decimals", "INTEGER NULL"),
+        "Failed to migrate contract table",
+    )?;
+    setup_step(
+        db.clear_unseeded_token_supply(),
+        "Failed to migrate daily_token_stats table",
+    )?;
+
+    setup_step(
+        db.seed_address_balances(),
+        "Failed to seed address_balances table",
+    )?;
+    setup_step(
+        db.seed_balance_changes(),
+        "Failed to seed balance_changes table",
+    )?;
 
     // create indexes if they don't exist
-    if let Err(_) = db.create_index("idx_blocks_hash", "blocks", "hash") {
-        return HttpResponse::InternalServerError().body("Failed to create block index");
-    }
-    if let Err(_) = db.create_index("idx_tx_hash", "transactions", "hash") {
-        return HttpResponse::InternalServerError().body("Failed to create txid index");
-    }
-    if let Err(_) = db.create_index("idx_tx_senders", "transactions", "sender") {
-        return HttpResponse::InternalServerError().body("Failed to create txsender index");
-    }
-    if let Err(_) = db.create_index("idx_transaction_block_index", "transactions", "block_index") {
-        return HttpResponse::InternalServerError().body("Failed to create block index");
-    }
-    if let Err(_) = db.create_index(
-        "idx_transaction_notifications_event_name",
-        "transaction_notifications",
-        "event_name",
-    ) {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create transaction_notifications event_name index");
-    }
-    if let Err(_) = db.create_index(
-        "idx_transaction_notification_state_values_value",
-        "transaction_notification_state_values",
-        "value",
-    ) {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create transaction_notification_state_values value index");
-    }
-    if let Err(_) = db.create_index(
-        "idx_daily_address_balances_address",
-        "daily_address_balances",
-        "address",
-    ) {
-        return HttpResponse::InternalServerError().body("Failed to create address index");
-    }
-    if let Err(_) = db.create_index(
-        "idx_daily_address_balances_date",
-        "daily_address_balances",
-        "date",
-    ) {
-        return HttpResponse::InternalServerError().body("Failed to create date index");
-    }
-    if let Err(_) = db.create_index(
-        "idx_daily_token_price_history_date",
-        "daily_token_price_history",
-        "date",
-    ) {
-        return HttpResponse::InternalServerError().body("Failed to create address index");
-    }
-    if let Err(_) = db.create_index("idx_contract_hash", "contracts", "hash") {
-        return HttpResponse::InternalServerError().body("Failed to create contract index");
-    }
-    if let Err(_) = db.create_index(
-        "idx_daily_contract_usage_date",
-        "daily_contract_usage",
-        "date",
-    ) {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create daily contract usage date index");
-    }
-    if let Err(_) = db.create_index(
-        "idx_daily_contract_usage_contract",
-        "daily_contract_usage",
-        "contract",
-    ) {
-        return HttpResponse::InternalServerError()
-            .body("Failed to create daily contract usage contract index");
-    }
+    setup_step(
+        db.create_index("idx_blocks_hash", "blocks", "hash"),
+        "Failed to create block index",
+    )?;
+    setup_step(
+        db.create_index("idx_blocks_time", "blocks", "time"),
+        "Failed to create block time index",
+    )?;
+    setup_step(
+        db.create_index("idx_tx_hash", "transactions", "hash"),
+        "Failed to create txid index",
+    )?;
+    setup_step(
+        db.create_index("idx_tx_senders", "transactions", "sender"),
+        "Failed to create txsender index",
+    )?;
+    setup_step(
+        db.create_index("idx_transaction_block_index", "transactions", "block_index"),
+        "Failed to create block index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_transaction_notifications_event_name",
+            "transaction_notifications",
+            "event_name",
+        ),
+        "Failed to create transaction_notifications event_name index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_transaction_notifications_transaction_hash",
+            "transaction_notifications",
+            "transaction_hash",
+        ),
+        "Failed to create transaction_notifications transaction_hash index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_transaction_notifications_contract",
+            "transaction_notifications",
+            "contract",
+        ),
+        "Failed to create transaction_notifications contract index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_transaction_notification_state_values_notification_id",
+            "transaction_notification_state_values",
+            "transaction_notification_id",
+        ),
+        "Failed to create transaction_notification_state_values notification_id index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_transaction_notification_state_values_value",
+            "transaction_notification_state_values",
+            "value",
+        ),
+        "Failed to create transaction_notification_state_values value index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_daily_address_balances_address",
+            "daily_address_balances",
+            "address",
+        ),
+        "Failed to create address index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_daily_address_balances_date",
+            "daily_address_balances",
+            "date",
+        ),
+        "Failed to create date index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_address_balances_token_balance",
+            "address_balances",
+            "token_contract, balance",
+        ),
+        "Failed to create address balances index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_webhook_deliveries_status",
+            "webhook_deliveries",
+            "status, next_attempt_at",
+        ),
+        "Failed to create webhook deliveries index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_balance_changes_token_address_block",
+            "balance_changes",
+            "token_contract, address, block_index",
+        ),
+        "Failed to create balance changes index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_daily_token_price_history_date",
+            "daily_token_price_history",
+            "date",
+        ),
+        "Failed to create address index",
+    )?;
+    setup_step(
+        db.create_index("idx_contract_hash", "contracts", "hash"),
+        "Failed to create contract index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_daily_contract_usage_date",
+            "daily_contract_usage",
+            "date",
+        ),
+        "Failed to create daily contract usage date index",
+    )?;
+    setup_step(
+        db.create_index("idx_address_first_seen_date", "address_first_seen", "date"),
+        "Failed to create address first seen date index",
+    )?;
+    setup_step(
+        db.create_index(
+            "idx_daily_contract_usage_contract",
+            "daily_contract_usage",
+            "contract",
+        ),
+        "Failed to create daily contract usage contract index",
+    )?;
 
-    HttpResponse::Ok().json(true)
+    // snapshots carry it, so an import can tell whether this build reads the layout
+    setup_step(db.set_schema_version(), "Failed to set schema version")
+}
+
+// run on shutdown once every writer has stopped, so the database is left as a single file
+pub fn checkpoint_database(pool: web::Data<ConnectionPool>) -> Result<(), ApiError> {
+    let conn = &pool.connection.get()?;
+    let db = setup_step(LocalDatabase::new(conn), "Failed to initialize database")?;
+
+    setup_step(db.checkpoint_wal(), "Failed to checkpoint WAL")
 }
 
 static INDEXER_RUNNING: AtomicBool = AtomicBool::new(false);
+static LAST_PRICE_BACKFILL: Lazy<Mutex<Option<PriceBackfill>>> = Lazy::new(|| Mutex::new(None));
 
-#[post("/v1/indexer/run")]
-async fn run_indexer(pool: web::Data<ConnectionPool>) -> impl Responder {
-    if INDEXER_RUNNING
+// sync runs, backfills and rebuilds all write through the indexer, so only one may run at a time
+fn claim_indexer() -> Result<(), ApiError> {
+    INDEXER_RUNNING
         .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
-        .is_err()
-    {
-        return HttpResponse::Conflict().body("Indexer is already running");
-    }
+        .map(|_| ())
+        .map_err(|_| ApiError::Conflict("Indexer is already running".to_string()))
+}
 
+fn load_config() -> Result<Config, ApiError> {
+    Config::new()
+        .map_err(|err| ApiError::Internal(format!("Failed to load configuration: {}", err)))
+}
+
+async fn open_storage<'a>(
+    conn: &'a PooledConnection<SqliteConnectionManager>,
+) -> Result<Box<dyn Storage + 'a>, ApiError> {
+    storage::open(&load_config()?.storage, conn)
+        .await
+        .map_err(|err| ApiError::Internal(format!("Failed to open storage: {}", err)))
+}
+
+async fn build_indexer(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Indexer<'_>, ApiError> {
     let config = AppConfig::new();
 
     let client = RpcClient::new();
-    let conn = &pool.connection.get().unwrap();
 
-    let db = match LocalDatabase::new(&conn) {
-        Ok(db) => db,
-        Err(_) => {
-            INDEXER_RUNNING.store(false, Ordering::SeqCst);
-            return HttpResponse::InternalServerError().body("Failed to initialize database");
-        }
-    };
+    let db = open_storage(conn).await?;
 
-    let indexer = Indexer::new(client, db, config);
-    if let Err(_) = indexer.run().await {
-        INDEXER_RUNNING.store(false, Ordering::SeqCst);
-        return HttpResponse::InternalServerError().json("Failed to run indexer");
+    let prices = PriceOracle::from_config(&load_config()?.prices)
+        .map_err(|err| ApiError::Internal(format!("Failed to set up price sources: {}", err)))?;
+
+    Ok(Indexer::new(client, db, config, prices))
+}
+
+fn require_synced_node() -> Result<(), ApiError> {
+    if !sync::node_ready() {
+        return Err(ApiError::Unavailable(
+            "Managed node is still synchronizing".to_string(),
+        ));
     }
 
+    Ok(())
+}
+
+#[post("/v1/indexer/run")]
+async fn run_indexer(pool: web::Data<ConnectionPool>) -> Result<HttpResponse, ApiError> {
+    require_synced_node()?;
+    claim_indexer()?;
+
+    let result = run_indexer_once(&pool).await;
     INDEXER_RUNNING.store(false, Ordering::SeqCst);
-    HttpResponse::Ok().json(true)
+
+    result.map(|_| HttpResponse::Ok().json(true))
+}
+
+async fn run_indexer_once(pool: &ConnectionPool) -> Result<(), ApiError> {
+    let conn = &pool.connection.get()?;
+
+    let indexer = build_indexer(conn).await?;
+
+    indexer
+        .run()
+        .await
+        .map_err(|err| ApiError::Internal(format!("Failed to run indexer: {}", err)))
+}
+
+#[post("/v1/indexer/prices/backfill")]
+async fn backfill_prices(pool: web::Data<ConnectionPool>) -> Result<HttpResponse, ApiError> {
+    require_synced_node()?;
+    claim_indexer()?;
+
+    let result = backfill_prices_once(&pool).await;
+    INDEXER_RUNNING.store(false, Ordering::SeqCst);
+
+    let report = result?;
+    *LAST_PRICE_BACKFILL.lock().unwrap() = Some(report.clone());
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+pub async fn backfill_prices_once(pool: &ConnectionPool) -> Result<PriceBackfill, ApiError> {
+    let conn = &pool.connection.get()?;
+
+    let indexer = build_indexer(conn).await?;
+
+    indexer
+        .backfill_price_gaps()
+        .await
+        .map_err(|err| ApiError::Internal(format!("Failed to backfill prices: {}", err)))
+}
+
+// works off the stored chain alone, so the node may be down or still synchronizing
+#[post("/v1/indexer/rebuild")]
+async fn rebuild_derived(
+    pool: web::Data<ConnectionPool>,
+    admin: web::Data<AdminConfig>,
+    request: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    authorize_admin(&admin, bearer(&request))?;
+    claim_indexer()?;
+
+    let result = rebuild_once(&pool).await;
+    INDEXER_RUNNING.store(false, Ordering::SeqCst);
+
+    Ok(HttpResponse::Ok().json(result?))
+}
+
+pub async fn rebuild_once(pool: &ConnectionPool) -> Result<RebuildReport, ApiError> {
+    let conn = &pool.connection.get()?;
+
+    let indexer = build_indexer(conn).await?;
+
+    indexer
+        .rebuild_derived()
+        .await
+        .map_err(|err| ApiError::Internal(format!("Failed to rebuild derived tables: {}", err)))
+}
+
+// the backup API copies a consistent state, so this can run next to a sync
+#[post("/v1/indexer/snapshot")]
+async fn create_snapshot(
+    pool: web::Data<ConnectionPool>,
+    admin: web::Data<AdminConfig>,
+    request: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    authorize_admin(&admin, bearer(&request))?;
+
+    let manifest =
+        tokio::task::spawn_blocking(move || snapshot_once(&pool, &snapshot::snapshot_dir()))
+            .await
+            .map_err(|err| ApiError::Internal(format!("Snapshot task failed: {}", err)))??;
+
+    Ok(HttpResponse::Ok().json(manifest))
+}
+
+pub fn snapshot_once(
+    pool: &ConnectionPool,
+    out_dir: &std::path::Path,
+) -> Result<SnapshotManifest, ApiError> {
+    if load_config()?.storage.backend != StorageBackend::Sqlite {
+        return Err(ApiError::BadRequest(
+            "Snapshots copy the SQLite database, use pg_dump with the postgres backend".to_string(),
+        ));
+    }
+
+    let conn = &pool.connection.get()?;
+
+    snapshot::create_snapshot(conn, out_dir)
+        .map_err(|err| ApiError::Internal(format!("Failed to create snapshot: {}", err)))
+}
+
+// read only unless repairing, so it can run next to a sync; the node is asked for every
+// checked block
+#[post("/v1/indexer/verify")]
+async fn verify_database(
+    pool: web::Data<ConnectionPool>,
+    admin: web::Data<AdminConfig>,
+    request: HttpRequest,
+    query_parameter: web::Query<VerifyParams>,
+) -> Result<HttpResponse, ApiError> {
+    authorize_admin(&admin, bearer(&request))?;
+
+    let report = verify_once(&pool, &query_parameter).await?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+pub async fn verify_once(
+    pool: &ConnectionPool,
+    params: &VerifyParams,
+) -> Result<VerifyReport, ApiError> {
+    let sample = params.sample.unwrap_or(VERIFY_SAMPLE_DEFAULT);
+    if sample > VERIFY_SAMPLE_MAX {
+        return Err(ApiError::BadRequest(format!(
+            "Sample can be at most {VERIFY_SAMPLE_MAX} blocks"
+        )));
+    }
+
+    let range = match (params.from, params.to) {
+        (None, None) => None,
+        (Some(from), Some(to)) if from <= to && to - from < VERIFY_RANGE_MAX => Some((from, to)),
+        _ => {
+            return Err(ApiError::BadRequest(format!(
+                "Set both from and to, covering at most {VERIFY_RANGE_MAX} blocks"
+            )))
+        }
+    };
+
+    let conn = &pool.connection.get()?;
+
+    let indexer = build_indexer(conn).await?;
+
+    indexer
+        .verify(sample, range, params.repair.unwrap_or(false))
+        .await
+        .map_err(|err| ApiError::Internal(format!("Failed to verify database: {}", err)))
+}
+
+#[get("/v1/indexer/status")]
+async fn get_indexer_status(pool: web::Data<ConnectionPool>) -> Result<HttpResponse, ApiError> {
+    let conn = &pool.connection.get()?;
+    let db = open_storage(conn).await?;
+    let storage_error = |err: anyhow::Error| ApiError::Internal(err.to_string());
+
+    Ok(HttpResponse::Ok().json(IndexerStatus {
+        running: INDEXER_RUNNING.load(Ordering::SeqCst),
+        stored_height: db.get_last_index("blocks").await.map_err(storage_error)?,
+        price_gaps: spawned::price_gaps(),
+        last_price_backfill: LAST_PRICE_BACKFILL.lock().unwrap().clone(),
+        node: sync::node_status(),
+    }))
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(run_indexer);
+    cfg.service(run_indexer)
+        .service(backfill_prices)
+        .service(rebuild_derived)
+        .service(create_snapshot)
+        .service(verify_database)
+        .service(get_indexer_status);
 }
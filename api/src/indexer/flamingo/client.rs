@@ -39,25 +39,4 @@ impl FlamingoClient {
         let prices = response.json::<Vec<FlamingoPrice>>().await?;
         Ok(prices)
     }
-
-    pub async fn get_latest_prices(&self) -> Result<Vec<FlamingoPrice>> {
-        let url = format!("{}/flamingo/live-data/prices/latest", self.base_url);
-
-        let response = self
-            .client
-            .get(&url)
-            .header("Content-Type", "application/json")
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!(
-                "Erro ao buscar dados: {}",
-                response.status()
-            ));
-        }
-
-        let prices = response.json::<Vec<FlamingoPrice>>().await?;
-        Ok(prices)
-    }
 }
@@ -1,6 +1,10 @@
+pub mod cli;
 pub mod config;
 pub mod controller;
 pub mod flamingo;
+pub mod models;
+pub mod prices;
 pub mod rpc;
+pub mod snapshot;
 pub mod spawn;
 pub mod utils;
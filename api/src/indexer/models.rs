@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PriceBackfill {
+    pub missing: usize, // (date, token) pairs without a price before the run
+    pub filled: usize,
+    pub skipped: usize, // pairs of tokens only priced by sources that can't look back, like dex
+    pub remaining: usize,
+    pub finished_at: u64, // unix timestamp in ms
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IndexerStatus {
+    pub running: bool,
+    pub stored_height: u64,
+    pub price_gaps: Option<usize>, // as of the last catch-up, sampled batch or backfill
+    pub last_price_backfill: Option<PriceBackfill>,
+    pub node: Option<NodeStatus>, // only reported for a managed node
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct NodeStatus {
+    pub running: bool,
+    pub synchronized: bool,
+    pub header_height: u64,
+    pub restarts: u64,
+}
+
+pub const VERIFY_SAMPLE_DEFAULT: u64 = 10;
+pub const VERIFY_SAMPLE_MAX: u64 = 1000;
+pub const VERIFY_RANGE_MAX: u64 = 10000; // blocks compared against the node in one run
+
+#[derive(Deserialize, Default)]
+pub struct VerifyParams {
+    pub sample: Option<u64>, // random blocks spot checked against the node
+    pub from: Option<u64>,   // inclusive range of blocks checked against the node
+    pub to: Option<u64>,
+    pub repair: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HeightRange {
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OrphanRows {
+    pub table: String,
+    pub rows: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BlockMismatch {
+    pub index: u64,
+    pub issue: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RepairList {
+    pub reindex: Vec<HeightRange>,
+    pub delete_orphans: Vec<String>, // tables
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VerifyReport {
+    pub consistent: bool,
+    pub stored_height: u64,
+    pub missing_heights: Vec<HeightRange>,
+    pub orphans: Vec<OrphanRows>, // only tables that have some
+    pub checked_blocks: usize,
+    pub mismatches: Vec<BlockMismatch>,
+    pub repair: Option<RepairList>,
+    pub finished_at: u64, // unix timestamp in ms
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RebuiltTable {
+    pub table: String,
+    pub rows: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RebuildReport {
+    pub blocks: u64, // stored blocks the tables were recomputed from
+    pub tables: Vec<RebuiltTable>,
+    pub finished_at: u64, // unix timestamp in ms
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SnapshotManifest {
+    pub file: String, // gzipped database, next to the manifest
+    pub height: u64,
+    pub block_hash: String,
+    pub schema_version: u32,
+    pub sha256: String, // of the gzipped file
+    pub size: u64,
+    pub created_at: u64, // unix timestamp in ms
+}
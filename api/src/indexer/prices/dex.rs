@@ -0,0 +1,228 @@
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::shared::config::DexPairConfig;
+use crate::shared::models::Hash160;
+use crate::transaction::models::{Notification, Transaction};
+
+use super::{utc_date, PriceSample, PriceSource};
+
+// a swap pair quoted against a USD-pegged token, so the other side can be priced from swaps
+struct DexPair {
+    base: Hash160,
+    base_decimals: u8,
+    quote_decimals: u8,
+    base_is_token0: bool,
+}
+
+type SwapPrices = BTreeMap<u64, (u64, f64)>; // block -> (timestamp, price)
+
+// prices derived from on-chain Swap events of configured pairs. the pair contracts emit
+// Swap(sender, amount0In, amount1In, amount0Out, amount1Out, to), and the last swap at or
+// before a sample block on the same day sets the price. swaps are only seen while syncing, so
+// days without one, or before a restart, get no price from here
+pub struct DexSource {
+    pairs: HashMap<Hash160, DexPair>,
+    swaps: Mutex<HashMap<Hash160, SwapPrices>>,
+}
+
+impl DexSource {
+    pub fn new(pairs: &[DexPairConfig]) -> Result<Self> {
+        let mut configured = HashMap::new();
+
+        for pair in pairs {
+            let base_is_token0 = if pair.quote == pair.token1 {
+                true
+            } else if pair.quote == pair.token0 {
+                false
+            } else {
+                return Err(anyhow!(
+                    "DEX pair {} is not quoted in {}",
+                    pair.contract,
+                    pair.quote
+                ));
+            };
+
+            let (base, base_decimals, quote_decimals) = match base_is_token0 {
+                true => (&pair.token0, pair.decimals0, pair.decimals1),
+                false => (&pair.token1, pair.decimals1, pair.decimals0),
+            };
+
+            configured.insert(
+                pair.contract.to_lowercase(),
+                DexPair {
+                    base: base.to_lowercase(),
+                    base_decimals,
+                    quote_decimals,
+                    base_is_token0,
+                },
+            );
+        }
+
+        Ok(Self {
+            pairs: configured,
+            swaps: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn swap_price(&self, notification: &Notification) -> Option<(Hash160, f64)> {
+        if notification.eventname != "Swap" {
+            return None;
+        }
+        let pair = self.pairs.get(&notification.contract)?;
+
+        let amount = |index: usize| -> Option<f64> {
+            match &notification.state.value.get(index)?.value {
+                Some(serde_json::Value::String(s)) => s.parse::<f64>().ok(),
+                _ => None,
+            }
+        };
+
+        let (amount0, amount1) = (amount(1)? + amount(3)?, amount(2)? + amount(4)?);
+        let (base, quote) = match pair.base_is_token0 {
+            true => (amount0, amount1),
+            false => (amount1, amount0),
+        };
+
+        let base = base / 10f64.powi(pair.base_decimals as i32);
+        let quote = quote / 10f64.powi(pair.quote_decimals as i32);
+
+        if base == 0.0 || quote == 0.0 {
+            return None;
+        }
+
+        Some((pair.base.clone(), quote / base))
+    }
+}
+
+impl PriceSource for DexSource {
+    fn name(&self) -> &'static str {
+        "dex"
+    }
+
+    // swaps of past days were dropped once those days were sampled
+    fn looks_back(&self) -> bool {
+        false
+    }
+
+    fn observe(&self, transactions: &[Transaction]) {
+        let mut swaps = self.swaps.lock().unwrap();
+
+        for transaction in transactions {
+            for notification in transaction.notifications.iter() {
+                if let Some((token, price)) = self.swap_price(notification) {
+                    swaps
+                        .entry(token)
+                        .or_default()
+                        .insert(transaction.block_index, (transaction.timestamp, price));
+                }
+            }
+        }
+
+        // batches arrive in block order, so days before this one have been sampled already
+        if let Some(first) = transactions.first() {
+            let day = utc_date(first.timestamp);
+            for by_block in swaps.values_mut() {
+                by_block.retain(|_, (timestamp, _)| utc_date(*timestamp) >= day);
+            }
+            swaps.retain(|_, by_block| !by_block.is_empty());
+        }
+    }
+
+    fn prices_at<'a>(
+        &'a self,
+        sample: &'a PriceSample,
+    ) -> BoxFuture<'a, Result<Vec<(Hash160, f64)>>> {
+        let day = sample.date();
+        let prices = self
+            .swaps
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(token, by_block)| {
+                let (_, (timestamp, price)) = by_block.range(..=sample.block_index).next_back()?;
+                (utc_date(*timestamp) == day).then(|| (token.clone(), *price))
+            })
+            .collect();
+
+        async move { Ok(prices) }.boxed()
+    }
+}
+
+#[test]
+fn test_swap_price() {
+    use crate::transaction::models::{State, StateValue};
+
+    let fusdt = "0xcd48b160c1bbc9d74997b803b9a7ad50a4bef020".to_string();
+    let flm = "0xf0151f528127558851b39c2cd8aa47da7418ab28".to_string();
+    let source = DexSource::new(&[DexPairConfig {
+        contract: "0x1111111111111111111111111111111111111111".to_string(),
+        token0: flm.clone(),
+        token1: fusdt.clone(),
+        decimals0: 8,
+        decimals1: 6,
+        quote: fusdt,
+    }])
+    .unwrap();
+
+    let value = |v: &str| StateValue {
+        _type: "Integer".to_string(),
+        value: Some(serde_json::Value::String(v.to_string())),
+    };
+    // 50 FLM in, 5 fUSDT out
+    let swap = Notification {
+        id: None,
+        contract: "0x1111111111111111111111111111111111111111".to_string(),
+        eventname: "Swap".to_string(),
+        state: State {
+            _type: "Array".to_string(),
+            value: vec![
+                value(""),
+                value("5000000000"),
+                value("0"),
+                value("0"),
+                value("5000000"),
+                value(""),
+            ],
+        },
+    };
+
+    assert_eq!(source.swap_price(&swap), Some((flm.clone(), 0.1)));
+
+    let day = 1_700_000_000_000; // 2023-11-14
+    source.observe(&[Transaction {
+        index: 0,
+        hash: String::new(),
+        block_index: 10,
+        timestamp: day,
+        vm_state: "HALT".to_string(),
+        size: 0,
+        version: 0,
+        nonce: 0,
+        sender: String::new(),
+        sysfee: "0".to_string(),
+        netfee: "0".to_string(),
+        valid_until: 0,
+        signers: Vec::new(),
+        script: String::new(),
+        witnesses: Vec::new(),
+        stack_result: String::new(),
+        notifications: vec![swap],
+    }]);
+
+    let prices_at = |block_index, timestamp| {
+        futures::executor::block_on(source.prices_at(&PriceSample {
+            block_index,
+            timestamp,
+        }))
+        .unwrap()
+    };
+    assert_eq!(prices_at(11, day + 1000), vec![(flm, 0.1)]);
+    // not yet swapped at block 9, and a quiet next day does not inherit the price
+    assert!(prices_at(9, day).is_empty());
+    assert!(prices_at(20, day + 86_400_000).is_empty());
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+
+use crate::indexer::flamingo::client::FlamingoClient;
+use crate::shared::models::Hash160;
+
+use super::{PriceSample, PriceSource};
+
+pub struct FlamingoSource {
+    client: FlamingoClient,
+    min_block: u64, // the Flamingo price feed starts at this height
+}
+
+impl FlamingoSource {
+    pub fn new(base_url: &str, min_block: u64) -> Self {
+        Self {
+            client: FlamingoClient::new(Some(base_url)),
+            min_block,
+        }
+    }
+}
+
+impl PriceSource for FlamingoSource {
+    fn name(&self) -> &'static str {
+        "flamingo"
+    }
+
+    fn prices_at<'a>(
+        &'a self,
+        sample: &'a PriceSample,
+    ) -> BoxFuture<'a, Result<Vec<(Hash160, f64)>>> {
+        async move {
+            if sample.block_index <= self.min_block {
+                return Ok(Vec::new());
+            }
+
+            let prices = self
+                .client
+                .get_prices_from_block(sample.block_index)
+                .await?;
+
+            Ok(prices
+                .into_iter()
+                .map(|price| (price.hash, price.usd_price))
+                .collect())
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,58 @@
+pub mod dex;
+pub mod file;
+pub mod flamingo;
+pub mod oracle;
+
+use anyhow::Result;
+use chrono::DateTime;
+use futures::future::BoxFuture;
+
+use crate::shared::models::Hash160;
+use crate::transaction::models::Transaction;
+
+// the block a set of prices is taken at
+#[derive(Debug, Clone, Copy)]
+pub struct PriceSample {
+    pub block_index: u64,
+    pub timestamp: u64, // block time in ms
+}
+
+impl PriceSample {
+    // UTC day the sample counts towards, same format as the daily tables
+    pub fn date(&self) -> String {
+        utc_date(self.timestamp)
+    }
+}
+
+pub fn utc_date(timestamp: u64) -> String {
+    DateTime::from_timestamp_millis(timestamp as i64)
+        .map(|datetime| datetime.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenPrice {
+    pub token_contract: Hash160,
+    pub usd_price: f64,
+    pub block_index: u64,
+    pub timestamp: u64,
+}
+
+pub trait PriceSource: Send + Sync {
+    // the name used for this source in the [prices] config
+    fn name(&self) -> &'static str;
+
+    // called with every indexed batch, for sources that read prices off the chain itself
+    fn observe(&self, _transactions: &[Transaction]) {}
+
+    // whether prices_at answers for days already synced, which a backfill relies on
+    fn looks_back(&self) -> bool {
+        true
+    }
+
+    // USD prices known to this source at the sample block
+    fn prices_at<'a>(
+        &'a self,
+        sample: &'a PriceSample,
+    ) -> BoxFuture<'a, Result<Vec<(Hash160, f64)>>>;
+}
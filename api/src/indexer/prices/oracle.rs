@@ -0,0 +1,213 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveTime};
+use futures::future::join_all;
+use log::warn;
+
+use std::collections::HashMap;
+
+use crate::shared::config::PriceConfig;
+use crate::shared::models::Hash160;
+use crate::transaction::models::Transaction;
+
+use super::dex::DexSource;
+use super::file::FileSource;
+use super::flamingo::FlamingoSource;
+use super::{PriceSample, PriceSource, TokenPrice};
+
+// queries every configured source at sample blocks and keeps, per token, the price from
+// the highest priority source that has one
+pub struct PriceOracle {
+    sources: Vec<Box<dyn PriceSource>>,
+    sample_time: NaiveTime,
+    token_sources: HashMap<Hash160, Vec<String>>,
+}
+
+impl PriceOracle {
+    pub fn from_config(config: &PriceConfig) -> Result<Self> {
+        let mut sources: Vec<Box<dyn PriceSource>> = Vec::new();
+
+        for name in config.sources.iter() {
+            let source: Box<dyn PriceSource> = match name.as_str() {
+                "flamingo" => Box::new(FlamingoSource::new(
+                    &config.flamingo_base_url,
+                    config.flamingo_min_block,
+                )),
+                "dex" => Box::new(DexSource::new(&config.dex_pairs)?),
+                "file" => {
+                    let path = config
+                        .file_path
+                        .as_deref()
+                        .ok_or_else(|| anyhow!("The file price source needs prices.file_path"))?;
+                    Box::new(FileSource::load(path)?)
+                }
+                other => return Err(anyhow!("Unknown price source: {other}")),
+            };
+            sources.push(source);
+        }
+
+        for (token, names) in config.token_sources.iter() {
+            if let Some(name) = names.iter().find(|n| !config.sources.contains(n)) {
+                return Err(anyhow!("Price source {name} for {token} is not enabled"));
+            }
+        }
+
+        let sample_time = NaiveTime::parse_from_str(&config.sample_time, "%H:%M:%S")
+            .map_err(|_| anyhow!("Invalid prices.sample_time: {}", config.sample_time))?;
+
+        Ok(Self {
+            sources,
+            sample_time,
+            token_sources: config
+                .token_sources
+                .iter()
+                .map(|(token, names)| (token.to_lowercase(), names.clone()))
+                .collect(),
+        })
+    }
+
+    // blocks after the sample time of their UTC day are the ones prices get taken at
+    pub fn is_sample_block(&self, timestamp: u64) -> bool {
+        DateTime::from_timestamp_millis(timestamp as i64)
+            .map(|datetime| datetime.time() > self.sample_time)
+            .unwrap_or(false)
+    }
+
+    // false when every source the token may be priced from only knows the day being synced
+    pub fn can_backfill(&self, token: &str) -> bool {
+        let names = self.token_sources.get(&token.to_lowercase());
+
+        self.sources.iter().any(|source| {
+            source.looks_back()
+                && names.is_none_or(|names| names.iter().any(|n| n == source.name()))
+        })
+    }
+
+    pub fn observe(&self, transactions: &[Transaction]) {
+        for source in self.sources.iter() {
+            source.observe(transactions);
+        }
+    }
+
+    pub async fn sample(&self, sample: PriceSample) -> Vec<TokenPrice> {
+        let results = join_all(self.sources.iter().map(|source| source.prices_at(&sample))).await;
+
+        let mut by_source = Vec::new();
+        for (source, result) in self.sources.iter().zip(results) {
+            match result {
+                Ok(prices) => by_source.push((source.name(), prices)),
+                Err(err) => warn!(
+                    "Price source {} failed at block {}: {err}",
+                    source.name(),
+                    sample.block_index
+                ),
+            }
+        }
+
+        self.resolve(by_source)
+            .into_iter()
+            .map(|(token_contract, usd_price)| TokenPrice {
+                token_contract,
+                usd_price,
+                block_index: sample.block_index,
+                timestamp: sample.timestamp,
+            })
+            .collect()
+    }
+
+    // sources are listed in default priority order; tokens with their own list only take
+    // prices from the sources on it, in that order
+    fn resolve(&self, by_source: Vec<(&str, Vec<(Hash160, f64)>)>) -> Vec<(Hash160, f64)> {
+        let mut chosen: HashMap<Hash160, (usize, f64)> = HashMap::new();
+
+        for (default_rank, (name, prices)) in by_source.into_iter().enumerate() {
+            for (token, price) in prices {
+                let token = token.to_lowercase();
+                let rank = match self.token_sources.get(&token) {
+                    Some(names) => match names.iter().position(|n| n == name) {
+                        Some(rank) => rank,
+                        None => continue,
+                    },
+                    None => default_rank,
+                };
+
+                match chosen.get(&token) {
+                    Some((best, _)) if *best <= rank => {}
+                    _ => {
+                        chosen.insert(token, (rank, price));
+                    }
+                }
+            }
+        }
+
+        let mut prices: Vec<(Hash160, f64)> = chosen
+            .into_iter()
+            .map(|(token, (_, price))| (token, price))
+            .collect();
+        prices.sort_by(|a, b| a.0.cmp(&b.0));
+        prices
+    }
+}
+
+#[test]
+fn test_resolve_priority() {
+    let oracle = PriceOracle {
+        sources: Vec::new(),
+        sample_time: NaiveTime::from_hms_opt(23, 59, 40).unwrap(),
+        token_sources: HashMap::from([(
+            "0xbb".to_string(),
+            vec!["file".to_string(), "dex".to_string()],
+        )]),
+    };
+
+    let prices = oracle.resolve(vec![
+        (
+            "flamingo",
+            vec![("0xaa".to_string(), 1.0), ("0xbb".to_string(), 2.0)],
+        ),
+        (
+            "dex",
+            vec![
+                ("0xAA".to_string(), 1.5),
+                ("0xbb".to_string(), 2.5),
+                ("0xcc".to_string(), 3.0),
+            ],
+        ),
+        ("file", vec![("0xbb".to_string(), 2.75)]),
+    ]);
+
+    assert_eq!(
+        prices,
+        vec![
+            ("0xaa".to_string(), 1.0),
+            ("0xbb".to_string(), 2.75),
+            ("0xcc".to_string(), 3.0),
+        ]
+    );
+
+    // 23:59:50 UTC samples, 12:00 UTC doesn't
+    assert!(oracle.is_sample_block(1704153590000));
+    assert!(!oracle.is_sample_block(1704110400000));
+}
+
+#[test]
+fn test_can_backfill() {
+    let oracle = PriceOracle {
+        sources: vec![
+            Box::new(FlamingoSource::new("http://localhost", 0)),
+            Box::new(DexSource::new(&[]).unwrap()),
+        ],
+        sample_time: NaiveTime::from_hms_opt(23, 59, 40).unwrap(),
+        token_sources: HashMap::from([
+            ("0xaa".to_string(), vec!["dex".to_string()]),
+            (
+                "0xbb".to_string(),
+                vec!["dex".to_string(), "flamingo".to_string()],
+            ),
+        ]),
+    };
+
+    // swaps of past days are gone, so a token priced by dex alone can't be backfilled
+    assert!(!oracle.can_backfill("0xAA"));
+    assert!(oracle.can_backfill("0xbb"));
+    assert!(oracle.can_backfill("0xcc"));
+}
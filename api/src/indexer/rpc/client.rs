@@ -1,5 +1,7 @@
 use anyhow::Result;
 use reqwest::Client as ReqwestClient;
+use std::collections::HashMap;
+use std::sync::Mutex;
 
 use crate::shared::config::Config;
 
@@ -14,6 +16,9 @@ use super::models::{
 pub struct Client {
     client: ReqwestClient,
     base_url: String,
+    ws_url: Option<String>,
+    // executions pushed by the node subscription, keyed by block or transaction hash
+    executions: Mutex<HashMap<String, Vec<Execution>>>,
 }
 
 impl Client {
@@ -22,9 +27,28 @@ impl Client {
         Self {
             client: ReqwestClient::new(),
             base_url: config.rpc_base_url.clone(),
+            ws_url: config.rpc_ws_url.clone(),
+            executions: Mutex::new(HashMap::new()),
         }
     }
 
+    pub fn ws_url(&self) -> Option<&str> {
+        self.ws_url.as_deref()
+    }
+
+    pub fn cache_execution(&self, container: String, execution: Execution) {
+        let mut executions = self.executions.lock().unwrap();
+        executions.entry(container).or_default().push(execution);
+    }
+
+    pub fn clear_executions(&self) {
+        self.executions.lock().unwrap().clear();
+    }
+
+    fn take_executions(&self, container: &str) -> Option<Vec<Execution>> {
+        self.executions.lock().unwrap().remove(container)
+    }
+
     pub async fn send_request<T: RpcMethod, R: serde::de::DeserializeOwned>(
         &self,
         method: T,
@@ -98,7 +122,13 @@ impl Client {
 
     pub async fn fetch_full_block(&self, height: u64) -> Result<(BlockResult, BlockAppLogResult)> {
         let block = self.get_block(height).await?;
-        let block_app_log: BlockAppLogResult = self.get_application_log(&block.hash).await?;
+        let block_app_log = match self.take_executions(&block.hash) {
+            Some(executions) => BlockAppLogResult {
+                blockhash: block.hash.clone(),
+                executions,
+            },
+            None => self.get_application_log(&block.hash).await?,
+        };
 
         Ok((block, block_app_log))
     }
@@ -107,7 +137,13 @@ impl Client {
         &self,
         tx: TransactionResult,
     ) -> Result<(TransactionResult, TransactionAppLogResult)> {
-        let tx_app_log: TransactionAppLogResult = self.get_application_log(&tx.hash).await?;
+        let tx_app_log = match self.take_executions(&tx.hash) {
+            Some(executions) => TransactionAppLogResult {
+                txid: tx.hash.clone(),
+                executions,
+            },
+            None => self.get_application_log(&tx.hash).await?,
+        };
 
         Ok((tx, tx_app_log))
     }
@@ -151,6 +187,48 @@ impl Client {
         .await
     }
 
+    pub async fn get_symbol_of_historic(
+        &self,
+        state_root_or_block: u64,
+        script_hash: &str,
+    ) -> Result<Execution, ClientError> {
+        self.invoke_function_historic(
+            state_root_or_block,
+            script_hash.to_string(),
+            "symbol".to_string(),
+            vec![],
+        )
+        .await
+    }
+
+    pub async fn get_decimals_of_historic(
+        &self,
+        state_root_or_block: u64,
+        script_hash: &str,
+    ) -> Result<Execution, ClientError> {
+        self.invoke_function_historic(
+            state_root_or_block,
+            script_hash.to_string(),
+            "decimals".to_string(),
+            vec![],
+        )
+        .await
+    }
+
+    pub async fn get_total_supply_of_historic(
+        &self,
+        state_root_or_block: u64,
+        script_hash: &str,
+    ) -> Result<Execution, ClientError> {
+        self.invoke_function_historic(
+            state_root_or_block,
+            script_hash.to_string(),
+            "totalSupply".to_string(),
+            vec![],
+        )
+        .await
+    }
+
     pub async fn get_candidates_of_historic(
         &self,
         state_root_or_block: u64,
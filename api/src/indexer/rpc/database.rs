@@ -1,24 +1,168 @@
+use async_trait::async_trait;
+use chrono::DateTime;
 use log::info;
 use rusqlite::{params, Result, ToSql};
 
-use crate::indexer::flamingo::models::FlamingoPrice;
+use crate::indexer::prices::TokenPrice;
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
+use std::collections::{BTreeSet, HashMap};
 
 use crate::block::models::Block;
 use crate::history::models::DailyAddressBalance;
-use crate::indexer::rpc::models::Contract;
-use crate::transaction::models::Transaction;
+use crate::indexer::rpc::models::{Contract, TokenActivity};
+use crate::indexer::rpc::storage::Storage;
+use crate::live::models::LiveMessage;
+use crate::shared::neo;
+use crate::transaction::models::{Notification, State, StateValue, Transaction};
+use crate::webhook::models::Webhook;
+
+// bump whenever the tables created or migrated below change shape
+pub const SCHEMA_VERSION: u32 = 2;
+
+// symbol and decimals of a token contract
+pub type TokenMetadata = (Option<String>, Option<u8>);
+
+const ORPHAN_CHECKS: [(&str, &str); 6] = [
+    (
+        "transactions",
+        "SELECT COUNT(*) FROM transactions t
+        WHERE NOT EXISTS (SELECT 1 FROM blocks b WHERE b.id = t.block_index)",
+    ),
+    (
+        "witnesses",
+        "SELECT COUNT(*) FROM witnesses w
+        WHERE (w.block_index IS NULL AND w.transaction_id IS NULL)
+            OR (w.block_index IS NOT NULL
+                AND NOT EXISTS (SELECT 1 FROM blocks b WHERE b.id = w.block_index))
+            OR (w.transaction_id IS NOT NULL
+                AND NOT EXISTS (SELECT 1 FROM transactions t WHERE t.id = w.transaction_id))",
+    ),
+    (
+        "signers",
+        "SELECT COUNT(*) FROM signers s
+        WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.id = s.transaction_id)",
+    ),
+    (
+        "allowed_contracts",
+        "SELECT COUNT(*) FROM allowed_contracts a
+        WHERE NOT EXISTS (SELECT 1 FROM signers s WHERE s.id = a.signer_id)",
+    ),
+    (
+        "transaction_notifications",
+        "SELECT COUNT(*) FROM transaction_notifications n
+        WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.hash = n.transaction_hash)",
+    ),
+    (
+        "transaction_notification_state_values",
+        "SELECT COUNT(*) FROM transaction_notification_state_values v
+        WHERE NOT EXISTS (
+            SELECT 1 FROM transaction_notifications n WHERE n.id = v.transaction_notification_id
+        )",
+    ),
+];
+
+// state values are kept as text, anything other than a string or number as its JSON
+pub fn state_value_text(state_value: &StateValue) -> Option<String> {
+    match &state_value.value {
+        Some(serde_json::Value::String(s)) => Some(s.clone()),
+        Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+        Some(serde_json::Value::Null) => None,
+        None => None,
+        value => Some(serde_json::to_string(value).unwrap_or_default()),
+    }
+}
+
+// the sender and every transfer participant, which count towards address_first_seen
+pub fn transaction_addresses(transaction: &Transaction) -> Vec<String> {
+    let mut addresses = vec![transaction.sender.clone()];
+    for notification in transaction.notifications.iter() {
+        if notification.eventname != "Transfer" || notification.state.value.len() < 3 {
+            continue;
+        }
+
+        for participant in notification.state.value[..2].iter() {
+            if let Some(serde_json::Value::String(s)) = &participant.value {
+                addresses.push(neo::base64_to_address(s));
+            }
+        }
+    }
+
+    addresses
+}
+
+// token amounts are stored as text, see create_daily_token_stats
+pub fn parse_amount(text: String) -> i128 {
+    text.parse::<i128>().unwrap_or(0)
+}
+
+// the highest block and JSON payload of a delivery, None when nothing in the batch matched
+pub fn webhook_payload(webhook: &Webhook, messages: &[LiveMessage]) -> Option<(u64, String)> {
+    let matched: Vec<&LiveMessage> = messages
+        .iter()
+        .filter(|message| webhook.matches(message))
+        .collect();
+
+    let block_index = matched.iter().filter_map(|m| m.block_index()).max()?;
+
+    let payload = serde_json::json!({
+        "webhook_id": webhook.id,
+        "block_index": block_index,
+        "events": matched
+            .iter()
+            .map(|message| serde_json::json!({ "topic": message.topic(), "data": message.data() }))
+            .collect::<Vec<_>>(),
+    });
+
+    Some((block_index, payload.to_string()))
+}
 
 pub struct Database<'a> {
     conn: &'a PooledConnection<SqliteConnectionManager>,
 }
 
+// Savepoints nest where transactions can't, so every write below is atomic on its own and
+// still joins an enclosing batch. Dropping one without commit rolls its changes back
+pub struct Savepoint<'c> {
+    conn: &'c PooledConnection<SqliteConnectionManager>,
+    released: bool,
+}
+
+impl<'c> Savepoint<'c> {
+    fn new(conn: &'c PooledConnection<SqliteConnectionManager>) -> Result<Self> {
+        conn.execute_batch("SAVEPOINT shrike_write")?;
+        Ok(Savepoint {
+            conn,
+            released: false,
+        })
+    }
+
+    pub fn commit(mut self) -> Result<()> {
+        self.conn.execute_batch("RELEASE shrike_write")?;
+        self.released = true;
+        Ok(())
+    }
+}
+
+impl Drop for Savepoint<'_> {
+    fn drop(&mut self) {
+        if !self.released {
+            let _ = self
+                .conn
+                .execute_batch("ROLLBACK TO shrike_write; RELEASE shrike_write");
+        }
+    }
+}
+
 impl<'a> Database<'a> {
     pub fn new(conn: &'a PooledConnection<SqliteConnectionManager>) -> Result<Self> {
         Ok(Database { conn })
     }
 
+    pub fn savepoint(&self) -> Result<Savepoint<'a>> {
+        Savepoint::new(self.conn)
+    }
+
     pub fn set_to_wal(&self) -> Result<()> {
         let wal_active: String = self
             .conn
@@ -35,6 +179,18 @@ impl<'a> Database<'a> {
         Ok(())
     }
 
+    pub fn set_schema_version(&self) -> Result<()> {
+        self.conn
+            .pragma_update(None, "user_version", SCHEMA_VERSION)
+    }
+
+    pub fn checkpoint_wal(&self) -> Result<()> {
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+
+        Ok(())
+    }
+
     pub fn create_index(&self, name: &str, table: &str, column: &str) -> Result<usize> {
         let sql = format!("CREATE INDEX IF NOT EXISTS {name} ON {table} ({column})");
         let result = self.conn.execute(&sql, [])?;
@@ -42,6 +198,25 @@ impl<'a> Database<'a> {
         Ok(result)
     }
 
+    // CREATE TABLE IF NOT EXISTS leaves older databases untouched, so new columns are added here
+    pub fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({table})"))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|name| name.ok())
+            .any(|name| name == column);
+
+        if !exists {
+            self.conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+                [],
+            )?;
+            info!("Added column {column} to {table}.");
+        }
+
+        Ok(())
+    }
+
     pub fn create_block_table(&self) -> Result<usize> {
         let result = self.conn.execute(
             "CREATE TABLE IF NOT EXISTS blocks (
@@ -181,6 +356,95 @@ impl<'a> Database<'a> {
         Ok(result)
     }
 
+    // current balance per address and token, the latest of the daily rows
+    pub fn create_address_balances(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS address_balances (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            address             TEXT NOT NULL,
+            token_contract      TEXT NOT NULL,
+            balance             INTEGER NOT NULL,
+            block_index         INTEGER NOT NULL,
+            UNIQUE (address, token_contract),
+            FOREIGN KEY (block_index) REFERENCES blocks (id)
+        )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
+    // every balance the indexer fetched, at the block of the transfer that changed it
+    pub fn create_balance_changes(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS balance_changes (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            block_index         INTEGER NOT NULL,
+            address             TEXT NOT NULL,
+            token_contract      TEXT NOT NULL,
+            balance             INTEGER NOT NULL,
+            UNIQUE (block_index, address, token_contract),
+            FOREIGN KEY (block_index) REFERENCES blocks (id)
+        )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
+    // height up to which balance_changes was seeded from end of day rows, if it ever was
+    pub fn create_balance_changes_seeded(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS balance_changes_seeded (
+            block_index         INTEGER NOT NULL
+        )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
+    // older databases only have end of day balances, which become the change at that block.
+    // below the height they were seeded at, a balance is only right at the end of a day
+    pub fn seed_balance_changes(&self) -> Result<usize> {
+        let tx = self.savepoint()?;
+        let result = self.conn.execute(
+            "INSERT OR IGNORE INTO balance_changes (block_index, address, token_contract, balance)
+            SELECT block_index, address, token_contract, balance
+            FROM daily_address_balances
+            WHERE NOT EXISTS (SELECT 1 FROM balance_changes)",
+            [],
+        )?;
+
+        if result > 0 {
+            self.conn.execute(
+                "INSERT INTO balance_changes_seeded (block_index) SELECT MAX(id) FROM blocks",
+                [],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(result)
+    }
+
+    // fills address_balances from the daily history on databases indexed before it existed
+    pub fn seed_address_balances(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "INSERT INTO address_balances (address, token_contract, balance, block_index)
+            SELECT b.address, b.token_contract, b.balance, b.block_index
+            FROM daily_address_balances b
+            WHERE NOT EXISTS (SELECT 1 FROM address_balances)
+                AND b.date = (
+                    SELECT MAX(date)
+                    FROM daily_address_balances
+                    WHERE address = b.address AND token_contract = b.token_contract
+                )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
     pub fn create_daily_token_price_history(&self) -> Result<usize> {
         let result = self.conn.execute(
             "CREATE TABLE IF NOT EXISTS daily_token_price_history (
@@ -205,6 +469,9 @@ impl<'a> Database<'a> {
             block_index         INTEGER NOT NULL,
             hash                TEXT NOT NULL UNIQUE,
             contract_type       TEXT NOT NULL,
+            manifest            TEXT NULL,
+            symbol              TEXT NULL,
+            decimals            INTEGER NULL,
             FOREIGN KEY (block_index) REFERENCES blocks (id)
         )",
             [],
@@ -228,22 +495,178 @@ impl<'a> Database<'a> {
         Ok(result)
     }
 
+    pub fn create_daily_network_stats(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS daily_network_stats (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            date                TEXT NOT NULL UNIQUE,
+            active_senders      INTEGER NOT NULL,
+            new_addresses       INTEGER NOT NULL,
+            transactions        INTEGER NOT NULL,
+            transfers           INTEGER NOT NULL,
+            fees                INTEGER NOT NULL,
+            new_contracts       INTEGER NOT NULL
+        )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
+    // amounts are raw integers kept as text, 18 decimal tokens outgrow an i64
+    pub fn create_daily_token_stats(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS daily_token_stats (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            date                TEXT NOT NULL,
+            token_contract      TEXT NOT NULL,
+            transfers           INTEGER NOT NULL,
+            volume              TEXT NOT NULL,
+            minted              TEXT NOT NULL,
+            burned              TEXT NOT NULL,
+            supply              TEXT NULL,
+            UNIQUE (date, token_contract)
+        )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
+    // tables from before supply was seeded counted it from zero at the upgrade, so it is wrong
+    // for every token older than that. they are rebuilt with supply unknown, to be seeded again
+    pub fn clear_unseeded_token_supply(&self) -> Result<()> {
+        let not_null: bool = self.conn.query_row(
+            "SELECT \"notnull\" FROM pragma_table_info('daily_token_stats') WHERE name = 'supply'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !not_null {
+            return Ok(());
+        }
+
+        let tx = self.savepoint()?;
+        self.conn.execute(
+            "ALTER TABLE daily_token_stats RENAME TO daily_token_stats_unseeded",
+            [],
+        )?;
+        self.create_daily_token_stats()?;
+        self.conn.execute_batch(
+            "INSERT INTO daily_token_stats (id, date, token_contract, transfers, volume, minted, burned)
+            SELECT id, date, token_contract, transfers, volume, minted, burned
+            FROM daily_token_stats_unseeded;
+            DROP TABLE daily_token_stats_unseeded;",
+        )?;
+        tx.commit()?;
+        info!("Cleared unseeded supply from daily_token_stats.");
+
+        Ok(())
+    }
+
+    pub fn create_webhooks(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS webhooks (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            url                 TEXT NOT NULL,
+            secret              TEXT NOT NULL,
+            address             TEXT NULL,
+            contract            TEXT NULL,
+            event_name          TEXT NULL,
+            min_amount          TEXT NULL,
+            active              INTEGER NOT NULL DEFAULT 1,
+            created_at          INTEGER NOT NULL
+        )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
+    // status is pending, delivered, failed or cancelled
+    pub fn create_webhook_deliveries(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS webhook_deliveries (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            webhook_id          INTEGER NOT NULL,
+            block_index         INTEGER NOT NULL,
+            payload             TEXT NOT NULL,
+            status              TEXT NOT NULL,
+            attempts            INTEGER NOT NULL DEFAULT 0,
+            response_status     INTEGER NULL,
+            error               TEXT NULL,
+            created_at          INTEGER NOT NULL,
+            next_attempt_at     INTEGER NOT NULL,
+            delivered_at        INTEGER NULL,
+            FOREIGN KEY (webhook_id) REFERENCES webhooks (id)
+        )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
+    pub fn create_webhook_dead_letters(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS webhook_dead_letters (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            delivery_id         INTEGER NOT NULL UNIQUE,
+            webhook_id          INTEGER NOT NULL,
+            payload             TEXT NOT NULL,
+            error               TEXT NULL,
+            failed_at           INTEGER NOT NULL,
+            FOREIGN KEY (delivery_id) REFERENCES webhook_deliveries (id),
+            FOREIGN KEY (webhook_id) REFERENCES webhooks (id)
+        )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
+    pub fn create_address_first_seen(&self) -> Result<usize> {
+        let result = self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS address_first_seen (
+            address             TEXT PRIMARY KEY,
+            block_index         INTEGER NOT NULL,
+            date                TEXT NOT NULL,
+            FOREIGN KEY (block_index) REFERENCES blocks (id)
+        )",
+            [],
+        )?;
+
+        Ok(result)
+    }
+
     pub fn insert_contracts(&self, contracts: impl Iterator<Item = Contract>) -> Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
+        let tx = self.savepoint()?;
 
         let mut values: Vec<String> = Vec::new();
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
         for contract in contracts {
-            values.push("(?, ?, ?)".to_string());
+            values.push("(?, ?, ?, ?, ?, ?)".to_string());
             params.push(Box::new(contract.block_index));
             params.push(Box::new(contract.hash));
             params.push(Box::new(contract.contract_type));
+            params.push(Box::new(contract.manifest));
+            params.push(Box::new(contract.symbol));
+            params.push(Box::new(contract.decimals));
         }
 
         if !values.is_empty() {
+            // an Update event re-deploys an existing hash, so keep the original block_index, and
+            // the stored manifest when the new script has none that parses
             let query = format!(
-                "INSERT INTO contracts (block_index, hash, contract_type) VALUES {}",
+                "INSERT INTO contracts (
+                    block_index, hash, contract_type, manifest, symbol, decimals
+                ) VALUES {}
+                ON CONFLICT (hash)
+                DO UPDATE SET
+                    contract_type = CASE WHEN excluded.manifest IS NULL
+                        THEN contracts.contract_type ELSE excluded.contract_type END,
+                    manifest = COALESCE(excluded.manifest, contracts.manifest),
+                    symbol = COALESCE(excluded.symbol, contracts.symbol),
+                    decimals = COALESCE(excluded.decimals, contracts.decimals)",
                 values.join(", ")
             );
 
@@ -260,13 +683,17 @@ impl<'a> Database<'a> {
         &self,
         balances: impl Iterator<Item = DailyAddressBalance>,
     ) -> Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
+        let tx = self.savepoint()?;
 
         let mut values: Vec<String> = Vec::new();
         let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+        let mut current_values: Vec<String> = Vec::new();
+        let mut current_params: Vec<Box<dyn ToSql>> = Vec::new();
+        let mut change_params: Vec<Box<dyn ToSql>> = Vec::new();
 
         for balance in balances {
             values.push("(strftime('%Y-%m-%d', ? / 1000, 'unixepoch'), ?, ?, ?, ?)".to_string());
+            current_values.push("(?, ?, ?, ?)".to_string());
 
             let date_i64 = i64::try_from(balance.timestamp).map_err(|_| {
                 rusqlite::Error::ToSqlConversionFailure(Box::new(std::io::Error::new(
@@ -280,6 +707,16 @@ impl<'a> Database<'a> {
             params.push(Box::new(balance.token_contract.clone()));
             params.push(Box::new(balance.balance));
             params.push(Box::new(balance.block_index));
+
+            current_params.push(Box::new(balance.address.clone()));
+            current_params.push(Box::new(balance.token_contract.clone()));
+            current_params.push(Box::new(balance.balance));
+            current_params.push(Box::new(balance.block_index));
+
+            change_params.push(Box::new(balance.block_index));
+            change_params.push(Box::new(balance.address));
+            change_params.push(Box::new(balance.token_contract));
+            change_params.push(Box::new(balance.balance));
         }
 
         if !values.is_empty() {
@@ -295,30 +732,55 @@ impl<'a> Database<'a> {
             let params_ref: Vec<&dyn ToSql> = params.iter().map(|v| v.as_ref()).collect();
 
             self.conn.execute(&query, &params_ref[..])?;
+
+            let current_query = format!(
+                "INSERT INTO address_balances (
+                    address, token_contract, balance, block_index
+                ) VALUES {}
+                ON CONFLICT (address, token_contract)
+                DO UPDATE SET balance = excluded.balance, block_index = excluded.block_index
+                WHERE excluded.block_index >= address_balances.block_index",
+                current_values.join(", ")
+            );
+
+            let current_params_ref: Vec<&dyn ToSql> =
+                current_params.iter().map(|v| v.as_ref()).collect();
+
+            self.conn.execute(&current_query, &current_params_ref[..])?;
+
+            // current_values has the same (?, ?, ?, ?) shape
+            let change_query = format!(
+                "INSERT INTO balance_changes (
+                    block_index, address, token_contract, balance
+                ) VALUES {}
+                ON CONFLICT (block_index, address, token_contract)
+                DO UPDATE SET balance = excluded.balance",
+                current_values.join(", ")
+            );
+
+            let change_params_ref: Vec<&dyn ToSql> =
+                change_params.iter().map(|v| v.as_ref()).collect();
+
+            self.conn.execute(&change_query, &change_params_ref[..])?;
         }
 
         tx.commit()?;
         Ok(())
     }
 
-    pub fn persist_daily_token_price_history(
-        &self,
-        prices_vec: Vec<Vec<FlamingoPrice>>,
-    ) -> Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
+    pub fn persist_daily_token_price_history(&self, prices: Vec<TokenPrice>) -> Result<()> {
+        let tx = self.savepoint()?;
 
         let mut values: Vec<String> = Vec::new();
         let mut params: Vec<Box<dyn ToSql>> = Vec::new();
 
-        for prices in prices_vec {
-            for price in prices {
-                values.push("(strftime('%Y-%m-%d', ? / 1000, 'unixepoch'), ?, ?, ?)".to_string());
+        for price in prices {
+            values.push("(strftime('%Y-%m-%d', ? / 1000, 'unixepoch'), ?, ?, ?)".to_string());
 
-                params.push(Box::new(price.timestamp));
-                params.push(Box::new(price.hash));
-                params.push(Box::new(price.usd_price));
-                params.push(Box::new(price.block_index));
-            }
+            params.push(Box::new(price.timestamp));
+            params.push(Box::new(price.token_contract));
+            params.push(Box::new(price.usd_price));
+            params.push(Box::new(price.block_index));
         }
 
         if !values.is_empty() {
@@ -346,7 +808,7 @@ impl<'a> Database<'a> {
         blocks: impl Iterator<Item = Block>,
         transactions: impl Iterator<Item = Transaction>,
     ) -> Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
+        let tx = self.savepoint()?;
 
         let block_query = "
             INSERT INTO blocks (
@@ -487,14 +949,7 @@ impl<'a> Database<'a> {
                     stmt_state.execute(params![
                         notification_id,
                         state_value._type,
-                        match &state_value.value {
-                            Some(serde_json::Value::String(s)) => Some(s.clone()),
-                            Some(serde_json::Value::Number(n)) => Some(n.to_string()),
-                            Some(serde_json::Value::Null) => None,
-                            None => None,
-                            _ =>
-                                Some(serde_json::to_string(&state_value.value).unwrap_or_default()),
-                        }
+                        state_value_text(state_value),
                     ])?;
                 }
             }
@@ -504,6 +959,230 @@ impl<'a> Database<'a> {
         Ok(())
     }
 
+    // records first appearances of senders and transfer participants, then recomputes the
+    // daily_network_stats rows of every day the transactions fall on
+    pub fn update_daily_network_stats(&self, transactions: &[Transaction]) -> Result<()> {
+        let tx = self.savepoint()?;
+
+        let mut stmt_first_seen = self.conn.prepare(
+            "INSERT OR IGNORE INTO address_first_seen (address, block_index, date)
+            VALUES (?, ?, strftime('%Y-%m-%d', ? / 1000, 'unixepoch'))",
+        )?;
+
+        let mut dates = BTreeSet::new();
+        for transaction in transactions {
+            if let Some(datetime) = DateTime::from_timestamp_millis(transaction.timestamp as i64) {
+                dates.insert(datetime.format("%Y-%m-%d").to_string());
+            }
+
+            for address in transaction_addresses(transaction) {
+                stmt_first_seen.execute(params![
+                    address,
+                    transaction.block_index,
+                    transaction.timestamp
+                ])?;
+            }
+        }
+
+        let mut stmt_stats = self.conn.prepare(
+            "INSERT INTO daily_network_stats (
+                date, active_senders, new_addresses, transactions, transfers, fees, new_contracts
+            )
+            SELECT
+                ?1,
+                (SELECT COUNT(DISTINCT sender) FROM transactions
+                    WHERE block_index BETWEEN r.first AND r.last),
+                (SELECT COUNT(*) FROM address_first_seen WHERE date = ?1),
+                (SELECT COUNT(*) FROM transactions
+                    WHERE block_index BETWEEN r.first AND r.last),
+                (SELECT COUNT(*) FROM transactions t
+                    INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+                    WHERE t.block_index BETWEEN r.first AND r.last AND tn.event_name = 'Transfer'),
+                (SELECT COALESCE(SUM(CAST(sysfee AS INTEGER) + CAST(netfee AS INTEGER)), 0)
+                    FROM transactions WHERE block_index BETWEEN r.first AND r.last),
+                (SELECT COUNT(*) FROM contracts WHERE block_index BETWEEN r.first AND r.last)
+            FROM (
+                SELECT MIN(id) AS first, MAX(id) AS last
+                FROM blocks
+                WHERE time >= CAST(strftime('%s', ?1) AS INTEGER) * 1000
+                    AND time < CAST(strftime('%s', ?1, '+1 day') AS INTEGER) * 1000
+            ) r
+            WHERE true
+            ON CONFLICT (date) DO UPDATE SET
+                active_senders = excluded.active_senders,
+                new_addresses = excluded.new_addresses,
+                transactions = excluded.transactions,
+                transfers = excluded.transfers,
+                fees = excluded.fees,
+                new_contracts = excluded.new_contracts",
+        )?;
+
+        for date in dates {
+            stmt_stats.execute([date])?;
+        }
+
+        drop(stmt_first_seen);
+        drop(stmt_stats);
+        tx.commit()?;
+        Ok(())
+    }
+
+    // adds the activity onto the day, carrying the supply over from the token's previous day
+    // tokens whose latest daily row has no supply, or that have no rows yet
+    pub fn tokens_without_supply(&self, tokens: &[String]) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT supply IS NOT NULL FROM daily_token_stats
+            WHERE token_contract = ?
+            ORDER BY date DESC
+            LIMIT 1",
+        )?;
+
+        let mut unseeded = Vec::new();
+        for token in tokens {
+            let seeded = match stmt.query_row([token], |row| row.get::<_, bool>(0)) {
+                Ok(seeded) => seeded,
+                Err(rusqlite::Error::QueryReturnedNoRows) => false,
+                Err(err) => return Err(err),
+            };
+            if !seeded {
+                unseeded.push(token.clone());
+            }
+        }
+
+        Ok(unseeded)
+    }
+
+    // seeds hold the supply before this batch of tokens without a known running total;
+    // without one the supply stays unknown rather than counting from zero
+    pub fn update_daily_token_stats(
+        &self,
+        activity: &[TokenActivity],
+        seeds: &HashMap<String, i128>,
+    ) -> Result<()> {
+        let tx = self.savepoint()?;
+
+        let mut stmt_current = self.conn.prepare(
+            "SELECT transfers, volume, minted, burned, supply
+            FROM daily_token_stats
+            WHERE date = ? AND token_contract = ?",
+        )?;
+        let mut stmt_previous = self.conn.prepare(
+            "SELECT supply FROM daily_token_stats
+            WHERE date < ? AND token_contract = ?
+            ORDER BY date DESC
+            LIMIT 1",
+        )?;
+        let mut stmt_upsert = self.conn.prepare(
+            "INSERT INTO daily_token_stats (
+                date, token_contract, transfers, volume, minted, burned, supply
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (date, token_contract) DO UPDATE SET
+                transfers = excluded.transfers,
+                volume = excluded.volume,
+                minted = excluded.minted,
+                burned = excluded.burned,
+                supply = excluded.supply",
+        )?;
+
+        for day in activity {
+            let key = params![day.date, day.token_contract];
+            let current = stmt_current
+                .query_row(key, |row| {
+                    Ok((
+                        row.get::<_, u64>(0)?,
+                        parse_amount(row.get(1)?),
+                        parse_amount(row.get(2)?),
+                        parse_amount(row.get(3)?),
+                        row.get::<_, Option<String>>(4)?.map(parse_amount),
+                    ))
+                })
+                .or_else(|err| match err {
+                    rusqlite::Error::QueryReturnedNoRows => {
+                        let supply = match stmt_previous
+                            .query_row(key, |row| row.get::<_, Option<String>>(0))
+                        {
+                            Ok(supply) => supply.map(parse_amount),
+                            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                            Err(err) => return Err(err),
+                        };
+                        Ok((0, 0, 0, 0, supply))
+                    }
+                    err => Err(err),
+                })?;
+
+            let (transfers, volume, minted, burned, supply) = current;
+            let supply = supply.or_else(|| seeds.get(&day.token_contract).copied());
+            stmt_upsert.execute(params![
+                day.date,
+                day.token_contract,
+                transfers + day.transfers,
+                (volume + day.volume).to_string(),
+                (minted + day.minted).to_string(),
+                (burned + day.burned).to_string(),
+                supply.map(|supply| (supply + day.minted - day.burned).to_string()),
+            ])?;
+        }
+
+        drop(stmt_current);
+        drop(stmt_previous);
+        drop(stmt_upsert);
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn has_active_webhooks(&self) -> Result<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM webhooks WHERE active = 1)",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    // one pending delivery per webhook with everything in the batch it matched
+    pub fn queue_webhook_deliveries(&self, messages: &[LiveMessage], now: u64) -> Result<usize> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, url, address, contract, event_name, min_amount, active, created_at
+            FROM webhooks
+            WHERE active = 1",
+        )?;
+        let webhooks = stmt
+            .query_map([], |row| {
+                Ok(Webhook {
+                    id: row.get(0)?,
+                    url: row.get(1)?,
+                    secret: None,
+                    address: row.get(2)?,
+                    contract: row.get(3)?,
+                    event_name: row.get(4)?,
+                    min_amount: row.get(5)?,
+                    active: row.get(6)?,
+                    created_at: row.get(7)?,
+                })
+            })?
+            .collect::<Result<Vec<Webhook>>>()?;
+
+        let tx = self.savepoint()?;
+        let mut stmt_insert = self.conn.prepare(
+            "INSERT INTO webhook_deliveries (
+                webhook_id, block_index, payload, status, created_at, next_attempt_at
+            ) VALUES (?, ?, ?, 'pending', ?, ?)",
+        )?;
+
+        let mut queued = 0;
+        for webhook in webhooks {
+            let Some((block_index, payload)) = webhook_payload(&webhook, messages) else {
+                continue;
+            };
+
+            stmt_insert.execute(params![webhook.id, block_index, payload, now, now])?;
+            queued += 1;
+        }
+
+        drop(stmt_insert);
+        tx.commit()?;
+        Ok(queued)
+    }
+
     pub fn get_last_index(&self, table: &str) -> Result<u64> {
         let sql = format!("SELECT id FROM {table} WHERE id=(SELECT max(id) FROM {table})");
         let mut stmt = self.conn.prepare(&sql)?;
@@ -513,6 +1192,278 @@ impl<'a> Database<'a> {
         Ok(index)
     }
 
+    // (date, token) pairs with no price, between each token's first price and the last
+    // fully indexed day
+    pub fn find_price_gaps(&self) -> Result<Vec<(String, String)>> {
+        let sql = "
+            WITH RECURSIVE
+            last_day(date) AS (
+                SELECT date(MAX(time) / 1000, 'unixepoch', '-1 day') FROM blocks
+            ),
+            days(token_contract, date) AS (
+                SELECT token_contract, MIN(date)
+                FROM daily_token_price_history
+                GROUP BY token_contract
+                UNION ALL
+                SELECT token_contract, date(date, '+1 day')
+                FROM days
+                WHERE date < (SELECT date FROM last_day)
+            )
+            SELECT d.date, d.token_contract
+            FROM days d
+            LEFT JOIN daily_token_price_history p
+                ON p.token_contract = d.token_contract AND p.date = d.date
+            WHERE p.id IS NULL AND d.date <= (SELECT date FROM last_day)
+            ORDER BY d.date, d.token_contract";
+
+        let mut stmt = self.conn.prepare(sql)?;
+        let gaps = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(String, String)>>>()?;
+
+        Ok(gaps)
+    }
+
+    // index and time of the last block before the end of a UTC day
+    pub fn get_last_block_of_day(&self, date: &str) -> Result<Option<(u64, u64)>> {
+        let sql = "
+            SELECT id, time
+            FROM blocks
+            WHERE time < CAST(strftime('%s', ?, '+1 day') AS INTEGER) * 1000
+            ORDER BY time DESC
+            LIMIT 1";
+
+        match self
+            .conn
+            .query_row(sql, [date], |row| Ok((row.get(0)?, row.get(1)?)))
+        {
+            Ok(block) => Ok(Some(block)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    // (first, last) heights of every hole between stored blocks
+    pub fn find_missing_heights(&self) -> Result<Vec<(u64, u64)>> {
+        let sql = "
+            SELECT prev + 1, id - 1
+            FROM (SELECT id, LAG(id) OVER (ORDER BY id) AS prev FROM blocks)
+            WHERE prev IS NOT NULL AND id - prev > 1
+            ORDER BY id";
+
+        let mut stmt = self.conn.prepare(sql)?;
+        let gaps = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(u64, u64)>>>()?;
+
+        Ok(gaps)
+    }
+
+    // rows whose parent block, transaction, signer or notification no longer exists
+    pub fn count_orphans(&self) -> Result<Vec<(String, u64)>> {
+        let mut orphans = Vec::new();
+        for (table, sql) in ORPHAN_CHECKS {
+            let rows: u64 = self.conn.query_row(sql, [], |row| row.get(0))?;
+            orphans.push((table.to_string(), rows));
+        }
+
+        Ok(orphans)
+    }
+
+    pub fn sample_block_indexes(&self, count: u64) -> Result<Vec<u64>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id FROM blocks ORDER BY RANDOM() LIMIT ?")?;
+        let indexes = stmt
+            .query_map([count], |row| row.get(0))?
+            .collect::<Result<Vec<u64>>>()?;
+
+        Ok(indexes)
+    }
+
+    pub fn get_block_hash(&self, index: u64) -> Result<Option<String>> {
+        match self
+            .conn
+            .query_row("SELECT hash FROM blocks WHERE id = ?", [index], |row| {
+                row.get(0)
+            }) {
+            Ok(hash) => Ok(Some(hash)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn get_block_transaction_hashes(&self, index: u64) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT hash FROM transactions WHERE block_index = ? ORDER BY id")?;
+        let hashes = stmt
+            .query_map([index], |row| row.get(0))?
+            .collect::<Result<Vec<String>>>()?;
+
+        Ok(hashes)
+    }
+
+    pub fn count_rows(&self, table: &str) -> Result<u64> {
+        self.conn
+            .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+    }
+
+    pub fn clear_table(&self, table: &str) -> Result<usize> {
+        self.conn.execute(&format!("DELETE FROM {table}"), [])
+    }
+
+    // symbol and decimals came from contract calls, so a rebuild carries them over
+    pub fn get_token_metadata(&self) -> Result<HashMap<String, TokenMetadata>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT hash, symbol, decimals FROM contracts
+            WHERE symbol IS NOT NULL OR decimals IS NOT NULL",
+        )?;
+        let metadata = stmt
+            .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        Ok(metadata)
+    }
+
+    // stored transactions of a block range with their notifications, signers and witnesses
+    // are left out since no derived table reads them
+    pub fn load_transactions(&self, first: u64, last: u64) -> Result<Vec<Transaction>> {
+        let mut stmt_state = self.conn.prepare(
+            "SELECT v.transaction_notification_id, v.type, v.value
+            FROM transaction_notification_state_values v
+            INNER JOIN transaction_notifications n ON n.id = v.transaction_notification_id
+            INNER JOIN transactions t ON t.hash = n.transaction_hash
+            WHERE t.block_index BETWEEN ? AND ?
+            ORDER BY v.id",
+        )?;
+        let mut states: HashMap<u64, Vec<StateValue>> = HashMap::new();
+        let mut rows = stmt_state.query([first, last])?;
+        while let Some(row) = rows.next()? {
+            states.entry(row.get(0)?).or_default().push(StateValue {
+                _type: row.get(1)?,
+                value: row
+                    .get::<_, Option<String>>(2)?
+                    .map(serde_json::Value::String),
+            });
+        }
+
+        let mut stmt_notification = self.conn.prepare(
+            "SELECT n.id, n.transaction_hash, n.contract, n.event_name, n.state_type
+            FROM transaction_notifications n
+            INNER JOIN transactions t ON t.hash = n.transaction_hash
+            WHERE t.block_index BETWEEN ? AND ?
+            ORDER BY n.id",
+        )?;
+        let mut notifications: HashMap<String, Vec<Notification>> = HashMap::new();
+        let mut rows = stmt_notification.query([first, last])?;
+        while let Some(row) = rows.next()? {
+            let id: u64 = row.get(0)?;
+            notifications
+                .entry(row.get(1)?)
+                .or_default()
+                .push(Notification {
+                    id: Some(id),
+                    contract: row.get(2)?,
+                    eventname: row.get(3)?,
+                    state: State {
+                        _type: row.get(4)?,
+                        value: states.remove(&id).unwrap_or_default(),
+                    },
+                });
+        }
+
+        let mut stmt_transaction = self.conn.prepare(
+            "SELECT t.*, b.time
+            FROM transactions t
+            INNER JOIN blocks b ON b.id = t.block_index
+            WHERE t.block_index BETWEEN ? AND ?
+            ORDER BY t.id",
+        )?;
+        let mut transactions = Vec::new();
+        let mut rows = stmt_transaction.query([first, last])?;
+        while let Some(row) = rows.next()? {
+            let hash: String = row.get(1)?;
+            transactions.push(Transaction {
+                index: row.get(0)?,
+                notifications: notifications.remove(&hash).unwrap_or_default(),
+                hash,
+                block_index: row.get(2)?,
+                vm_state: row.get(3)?,
+                size: row.get(4)?,
+                version: row.get(5)?,
+                nonce: row.get(6)?,
+                sender: row.get(7)?,
+                sysfee: row.get(8)?,
+                netfee: row.get(9)?,
+                valid_until: row.get(10)?,
+                script: row.get(11)?,
+                stack_result: row.get::<_, Option<String>>(12)?.unwrap_or_default(),
+                timestamp: row.get(13)?,
+                signers: Vec::new(),
+                witnesses: Vec::new(),
+            });
+        }
+
+        Ok(transactions)
+    }
+
+    pub fn rebuild_daily_contract_usage(&self) -> Result<usize> {
+        self.conn.execute(
+            "INSERT INTO daily_contract_usage (date, contract, usage)
+            SELECT strftime('%Y-%m-%d', b.time / 1000, 'unixepoch'), n.contract, COUNT(*)
+            FROM transaction_notifications n
+            INNER JOIN transactions t ON t.hash = n.transaction_hash
+            INNER JOIN blocks b ON b.id = t.block_index
+            GROUP BY 1, 2",
+            [],
+        )
+    }
+
+    // balance_changes holds every balance the node reported, the other balance tables are
+    // the last of those per day and overall
+    pub fn rebuild_address_balances(&self) -> Result<usize> {
+        let daily = self.conn.execute(
+            "INSERT INTO daily_address_balances (
+                date, address, token_contract, balance, block_index
+            )
+            SELECT date, address, token_contract, balance, block_index
+            FROM (
+                SELECT
+                    strftime('%Y-%m-%d', b.time / 1000, 'unixepoch') AS date,
+                    c.address, c.token_contract, c.balance, c.block_index,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY strftime('%Y-%m-%d', b.time / 1000, 'unixepoch'),
+                            c.address, c.token_contract
+                        ORDER BY c.block_index DESC
+                    ) AS position
+                FROM balance_changes c
+                INNER JOIN blocks b ON b.id = c.block_index
+            )
+            WHERE position = 1",
+            [],
+        )?;
+
+        let current = self.conn.execute(
+            "INSERT INTO address_balances (address, token_contract, balance, block_index)
+            SELECT address, token_contract, balance, block_index
+            FROM (
+                SELECT address, token_contract, balance, block_index,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY address, token_contract
+                        ORDER BY block_index DESC
+                    ) AS position
+                FROM balance_changes
+            )
+            WHERE position = 1",
+            [],
+        )?;
+
+        Ok(daily + current)
+    }
+
     #[allow(dead_code)]
     pub fn drop_table(&self, table: &str) -> Result<usize> {
         let result = self.conn.execute(&format!("DROP TABLE {table}"), [])?;
@@ -520,3 +1471,369 @@ impl<'a> Database<'a> {
         Ok(result)
     }
 }
+
+// inherent methods take precedence over the trait's, so each of these delegates rather than
+// recursing. nothing here yields, a batch is written in full once it starts
+#[async_trait(?Send)]
+impl Storage for Database<'_> {
+    async fn begin_batch(&self) -> anyhow::Result<()> {
+        Ok(self.conn.execute_batch("SAVEPOINT shrike_batch")?)
+    }
+
+    async fn commit_batch(&self) -> anyhow::Result<()> {
+        Ok(self.conn.execute_batch("RELEASE shrike_batch")?)
+    }
+
+    async fn rollback_batch(&self) -> anyhow::Result<()> {
+        Ok(self
+            .conn
+            .execute_batch("ROLLBACK TO shrike_batch; RELEASE shrike_batch")?)
+    }
+
+    async fn insert_blocks_transactions(
+        &self,
+        blocks: Vec<Block>,
+        transactions: Vec<Transaction>,
+    ) -> anyhow::Result<()> {
+        Ok(self.insert_blocks_transactions(blocks.into_iter(), transactions.into_iter())?)
+    }
+
+    async fn insert_contracts(&self, contracts: Vec<Contract>) -> anyhow::Result<()> {
+        Ok(self.insert_contracts(contracts.into_iter())?)
+    }
+
+    async fn update_daily_network_stats(&self, transactions: &[Transaction]) -> anyhow::Result<()> {
+        Ok(self.update_daily_network_stats(transactions)?)
+    }
+
+    async fn tokens_without_supply(&self, tokens: &[String]) -> anyhow::Result<Vec<String>> {
+        Ok(self.tokens_without_supply(tokens)?)
+    }
+
+    async fn update_daily_token_stats(
+        &self,
+        activity: &[TokenActivity],
+        seeds: &HashMap<String, i128>,
+    ) -> anyhow::Result<()> {
+        Ok(self.update_daily_token_stats(activity, seeds)?)
+    }
+
+    async fn persist_daily_address_balances(
+        &self,
+        balances: Vec<DailyAddressBalance>,
+    ) -> anyhow::Result<()> {
+        Ok(self.persist_daily_address_balances(balances.into_iter())?)
+    }
+
+    async fn persist_daily_token_price_history(
+        &self,
+        prices: Vec<TokenPrice>,
+    ) -> anyhow::Result<()> {
+        Ok(self.persist_daily_token_price_history(prices)?)
+    }
+
+    async fn has_active_webhooks(&self) -> anyhow::Result<bool> {
+        Ok(self.has_active_webhooks()?)
+    }
+
+    async fn queue_webhook_deliveries(
+        &self,
+        messages: &[LiveMessage],
+        now: u64,
+    ) -> anyhow::Result<usize> {
+        Ok(self.queue_webhook_deliveries(messages, now)?)
+    }
+
+    async fn get_last_index(&self, table: &str) -> anyhow::Result<u64> {
+        Ok(self.get_last_index(table)?)
+    }
+
+    async fn find_price_gaps(&self) -> anyhow::Result<Vec<(String, String)>> {
+        Ok(self.find_price_gaps()?)
+    }
+
+    async fn get_last_block_of_day(&self, date: &str) -> anyhow::Result<Option<(u64, u64)>> {
+        Ok(self.get_last_block_of_day(date)?)
+    }
+
+    async fn find_missing_heights(&self) -> anyhow::Result<Vec<(u64, u64)>> {
+        Ok(self.find_missing_heights()?)
+    }
+
+    async fn count_orphans(&self) -> anyhow::Result<Vec<(String, u64)>> {
+        Ok(self.count_orphans()?)
+    }
+
+    async fn sample_block_indexes(&self, count: u64) -> anyhow::Result<Vec<u64>> {
+        Ok(self.sample_block_indexes(count)?)
+    }
+
+    async fn get_block_hash(&self, index: u64) -> anyhow::Result<Option<String>> {
+        Ok(self.get_block_hash(index)?)
+    }
+
+    async fn get_block_transaction_hashes(&self, index: u64) -> anyhow::Result<Vec<String>> {
+        Ok(self.get_block_transaction_hashes(index)?)
+    }
+
+    async fn count_rows(&self, table: &str) -> anyhow::Result<u64> {
+        Ok(self.count_rows(table)?)
+    }
+
+    async fn clear_table(&self, table: &str) -> anyhow::Result<usize> {
+        Ok(self.clear_table(table)?)
+    }
+
+    async fn get_token_metadata(&self) -> anyhow::Result<HashMap<String, TokenMetadata>> {
+        Ok(self.get_token_metadata()?)
+    }
+
+    async fn load_transactions(&self, first: u64, last: u64) -> anyhow::Result<Vec<Transaction>> {
+        Ok(self.load_transactions(first, last)?)
+    }
+
+    async fn rebuild_daily_contract_usage(&self) -> anyhow::Result<usize> {
+        Ok(self.rebuild_daily_contract_usage()?)
+    }
+
+    async fn rebuild_address_balances(&self) -> anyhow::Result<usize> {
+        Ok(self.rebuild_address_balances()?)
+    }
+}
+
+// every in-memory connection opens a database of its own, so the pool holds just one
+#[cfg(test)]
+pub fn memory_connection() -> PooledConnection<SqliteConnectionManager> {
+    r2d2::Pool::builder()
+        .max_size(1)
+        .build(SqliteConnectionManager::memory())
+        .unwrap()
+        .get()
+        .unwrap()
+}
+
+// the full schema with a block at each time, heights counting from 1 as the indexer stores them
+#[cfg(test)]
+pub fn memory_chain(times: &[u64]) -> PooledConnection<SqliteConnectionManager> {
+    let conn = memory_connection();
+    crate::indexer::controller::create_tables(&conn).unwrap();
+
+    for time in times {
+        insert_test_block(&conn, *time);
+    }
+
+    conn
+}
+
+// the next block on top of the stored ones
+#[cfg(test)]
+pub fn insert_test_block(conn: &PooledConnection<SqliteConnectionManager>, time: u64) {
+    conn.execute(
+        "INSERT INTO blocks (
+            hash, size, version, merkle_root, time, nonce, speaker, next_consensus, reward, reward_receiver
+        ) VALUES (printf('0x%064x', (SELECT COUNT(*) + 1 FROM blocks)), 0, 0, '', ?, '', 0, '', 0, '')",
+        params![time],
+    )
+    .unwrap();
+}
+
+// a transaction at the given height, fees as the node reports them
+#[cfg(test)]
+pub fn insert_test_transaction(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    hash: &str,
+    block_index: u64,
+    sender: &str,
+    sysfee: &str,
+    netfee: &str,
+) {
+    conn.execute(
+        "INSERT INTO transactions (
+            hash, block_index, vm_state, size, version, nonce, sender, sysfee, netfee, valid_until, script,
+            stack_result
+        ) VALUES (?, ?, 'HALT', 0, 0, 0, ?, ?, ?, 0, '', '[]')",
+        params![hash, block_index, sender, sysfee, netfee],
+    )
+    .unwrap();
+}
+
+// a notification on a stored transaction, its state values as (type, value)
+#[cfg(test)]
+pub fn insert_test_notification(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    transaction_hash: &str,
+    contract: &str,
+    event_name: &str,
+    values: &[(&str, &str)],
+) {
+    let notification_id: i64 = conn
+        .query_row(
+            "INSERT INTO transaction_notifications (transaction_hash, contract, event_name, state_type)
+            VALUES (?, ?, ?, 'Array')
+            RETURNING id",
+            params![transaction_hash, contract, event_name],
+            |row| row.get(0),
+        )
+        .unwrap();
+
+    for (value_type, value) in values {
+        conn.execute(
+            "INSERT INTO transaction_notification_state_values (transaction_notification_id, type, value)
+            VALUES (?, ?, ?)",
+            params![notification_id, value_type, value],
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_savepoint_batch_rollback() {
+    let conn = memory_connection();
+    let db = Database::new(&conn).unwrap();
+    conn.execute_batch("CREATE TABLE writes (id INTEGER)")
+        .unwrap();
+
+    // an inner write commits into the batch, which is then abandoned
+    {
+        let _batch = db.savepoint().unwrap();
+        let inner = db.savepoint().unwrap();
+        conn.execute("INSERT INTO writes VALUES (1)", []).unwrap();
+        inner.commit().unwrap();
+    }
+    let count: u64 = conn
+        .query_row("SELECT COUNT(*) FROM writes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 0);
+
+    let batch = db.savepoint().unwrap();
+    let inner = db.savepoint().unwrap();
+    conn.execute("INSERT INTO writes VALUES (1)", []).unwrap();
+    inner.commit().unwrap();
+    batch.commit().unwrap();
+    let count: u64 = conn
+        .query_row("SELECT COUNT(*) FROM writes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn test_contract_update_keeps_manifest() {
+    let conn = memory_connection();
+    let db = Database::new(&conn).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE blocks (id INTEGER PRIMARY KEY); INSERT INTO blocks VALUES (1), (2)",
+    )
+    .unwrap();
+    db.create_contract_table().unwrap();
+
+    let contract = |block_index, contract_type: &str, manifest: Option<&str>| Contract {
+        block_index,
+        hash: "0xd2a4cff31913016155e38e474a2c06d08be276cf".to_string(),
+        contract_type: contract_type.to_string(),
+        manifest: manifest.map(str::to_string),
+        symbol: None,
+        decimals: None,
+    };
+    db.insert_contracts([contract(1, "NEP-17", Some("{}"))].into_iter())
+        .unwrap();
+    // an Update whose manifest does not parse
+    db.insert_contracts([contract(2, "", None)].into_iter())
+        .unwrap();
+
+    let stored: (u64, String, Option<String>) = conn
+        .query_row(
+            "SELECT block_index, contract_type, manifest FROM contracts",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(stored, (1, "NEP-17".to_string(), Some("{}".to_string())));
+}
+
+#[test]
+fn test_token_supply_seeding() {
+    let conn = memory_connection();
+    let db = Database::new(&conn).unwrap();
+    db.create_daily_token_stats().unwrap();
+
+    let day = |date: &str, token: &str, minted| TokenActivity {
+        date: date.to_string(),
+        token_contract: token.to_string(),
+        transfers: 1,
+        minted,
+        ..Default::default()
+    };
+    let seeds = HashMap::from([("0xaa".to_string(), 1000)]);
+    db.update_daily_token_stats(
+        &[day("2024-01-01", "0xaa", 5), day("2024-01-01", "0xbb", 5)],
+        &seeds,
+    )
+    .unwrap();
+    db.update_daily_token_stats(
+        &[day("2024-01-02", "0xaa", 5), day("2024-01-02", "0xbb", 5)],
+        &HashMap::new(),
+    )
+    .unwrap();
+
+    let supply = |date: &str, token: &str| -> Option<String> {
+        conn.query_row(
+            "SELECT supply FROM daily_token_stats WHERE date = ? AND token_contract = ?",
+            [date, token],
+            |row| row.get(0),
+        )
+        .unwrap()
+    };
+    assert_eq!(supply("2024-01-02", "0xaa"), Some("1010".to_string()));
+    // never seeded, so not counted from zero either
+    assert_eq!(supply("2024-01-02", "0xbb"), None);
+    assert_eq!(
+        db.tokens_without_supply(&["0xaa".to_string(), "0xbb".to_string()])
+            .unwrap(),
+        vec!["0xbb".to_string()]
+    );
+}
+
+#[test]
+fn test_find_price_gaps() {
+    const HOUR: u64 = 3_600_000;
+    const DAY: u64 = 24 * HOUR;
+    const START: u64 = 1_704_067_200_000; // 2024-01-01
+
+    // the chain is into 2024-01-04, so 2024-01-03 is the last full day
+    let conn = memory_chain(&[
+        START + HOUR,
+        START + DAY + HOUR,
+        START + DAY + 23 * HOUR,
+        START + 2 * DAY + HOUR,
+        START + 3 * DAY + HOUR,
+    ]);
+    let db = Database::new(&conn).unwrap();
+
+    let price = |token: &str, day: u64| TokenPrice {
+        token_contract: token.to_string(),
+        usd_price: 1.0,
+        block_index: 1,
+        timestamp: START + day * DAY,
+    };
+    // 0xaa misses the middle day, 0xbb starts on the second day and then stops
+    db.persist_daily_token_price_history(vec![
+        price("0xaa", 0),
+        price("0xaa", 2),
+        price("0xbb", 1),
+    ])
+    .unwrap();
+
+    assert_eq!(
+        db.find_price_gaps().unwrap(),
+        vec![
+            ("2024-01-02".to_string(), "0xaa".to_string()),
+            ("2024-01-03".to_string(), "0xbb".to_string()),
+        ]
+    );
+
+    assert_eq!(
+        db.get_last_block_of_day("2024-01-02").unwrap(),
+        Some((3, START + DAY + 23 * HOUR))
+    );
+    assert_eq!(db.get_last_block_of_day("2023-12-31").unwrap(), None);
+}
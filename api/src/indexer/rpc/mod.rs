@@ -3,3 +3,6 @@ mod client_test;
 pub mod database;
 pub mod method;
 pub mod models;
+pub mod postgres;
+pub mod storage;
+pub mod subscription;
@@ -131,4 +131,18 @@ pub struct Contract {
     pub block_index: u64,
     pub hash: String,
     pub contract_type: String,
+    pub manifest: Option<String>,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+}
+
+// transfer totals of one token over one day, amounts as raw integers
+#[derive(Debug, Clone, Default)]
+pub struct TokenActivity {
+    pub date: String,
+    pub token_contract: String,
+    pub transfers: u64,
+    pub volume: i128,
+    pub minted: i128,
+    pub burned: i128,
 }
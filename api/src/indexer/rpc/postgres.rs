@@ -0,0 +1,1537 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::DateTime;
+use log::error;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, NoTls, Row};
+
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+
+use crate::block::models::Block;
+use crate::history::models::DailyAddressBalance;
+use crate::indexer::prices::TokenPrice;
+use crate::indexer::rpc::database::{
+    parse_amount, state_value_text, transaction_addresses, webhook_payload, TokenMetadata,
+};
+use crate::indexer::rpc::models::{Contract, TokenActivity};
+use crate::indexer::rpc::storage::Storage;
+use crate::live::models::LiveMessage;
+use crate::transaction::models::{Notification, State, StateValue, Transaction};
+use crate::webhook::postgres::{webhook_row, WEBHOOK_COLUMNS};
+
+// The SQLite schema in Postgres types. SQLite integers are 64 bit, so
+// every INTEGER column is a BIGINT here. blocks.id is the height, set on insert as sequences
+// don't roll back with a batch
+const SCHEMA: &str = "
+    CREATE OR REPLACE FUNCTION shrike_date(ms BIGINT) RETURNS TEXT AS $$
+        SELECT to_char(to_timestamp(ms / 1000) AT TIME ZONE 'UTC', 'YYYY-MM-DD')
+    $$ LANGUAGE SQL STABLE;
+
+    CREATE TABLE IF NOT EXISTS blocks (
+        id                  BIGINT PRIMARY KEY,
+        hash                TEXT NOT NULL UNIQUE,
+        size                BIGINT NOT NULL,
+        version             BIGINT NOT NULL,
+        merkle_root         TEXT NOT NULL,
+        time                BIGINT NOT NULL,
+        nonce               TEXT NOT NULL,
+        speaker             BIGINT NOT NULL,
+        next_consensus      TEXT NOT NULL,
+        reward              DOUBLE PRECISION NOT NULL,
+        reward_receiver     TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS transactions (
+        id                  BIGSERIAL PRIMARY KEY,
+        hash                TEXT NOT NULL UNIQUE,
+        block_index         BIGINT NOT NULL REFERENCES blocks (id),
+        vm_state            TEXT NOT NULL,
+        size                BIGINT NOT NULL,
+        version             BIGINT NOT NULL,
+        nonce               BIGINT NOT NULL,
+        sender              TEXT NOT NULL,
+        sysfee              TEXT NOT NULL,
+        netfee              TEXT NOT NULL,
+        valid_until         BIGINT NOT NULL,
+        script              TEXT NOT NULL,
+        stack_result        TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS witnesses (
+        id                  BIGSERIAL PRIMARY KEY,
+        block_index         BIGINT NULL REFERENCES blocks (id),
+        transaction_id      BIGINT NULL REFERENCES transactions (id),
+        invocation          TEXT NOT NULL,
+        verification        TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS signers (
+        id                  BIGSERIAL PRIMARY KEY,
+        transaction_id      BIGINT NOT NULL REFERENCES transactions (id),
+        account             TEXT NOT NULL,
+        scopes              TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS allowed_contracts (
+        id                  BIGSERIAL PRIMARY KEY,
+        signer_id           BIGINT NOT NULL REFERENCES signers (id),
+        contract            TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS transaction_notifications (
+        id                  BIGSERIAL PRIMARY KEY,
+        transaction_hash    TEXT NOT NULL REFERENCES transactions (hash),
+        contract            TEXT NOT NULL,
+        event_name          TEXT NOT NULL,
+        state_type          TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS transaction_notification_state_values (
+        id                              BIGSERIAL PRIMARY KEY,
+        transaction_notification_id     BIGINT NOT NULL REFERENCES transaction_notifications (id),
+        type                            TEXT NOT NULL,
+        value                           TEXT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS contracts (
+        id                  BIGSERIAL PRIMARY KEY,
+        block_index         BIGINT NOT NULL REFERENCES blocks (id),
+        hash                TEXT NOT NULL UNIQUE,
+        contract_type       TEXT NOT NULL,
+        manifest            TEXT NULL,
+        symbol              TEXT NULL,
+        decimals            BIGINT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS daily_address_balances (
+        id                  BIGSERIAL PRIMARY KEY,
+        block_index         BIGINT NOT NULL REFERENCES blocks (id),
+        date                TEXT NOT NULL,
+        address             TEXT NOT NULL,
+        token_contract      TEXT NOT NULL,
+        balance             BIGINT NOT NULL,
+        UNIQUE (date, address, token_contract)
+    );
+
+    CREATE TABLE IF NOT EXISTS address_balances (
+        id                  BIGSERIAL PRIMARY KEY,
+        address             TEXT NOT NULL,
+        token_contract      TEXT NOT NULL,
+        balance             BIGINT NOT NULL,
+        block_index         BIGINT NOT NULL REFERENCES blocks (id),
+        UNIQUE (address, token_contract)
+    );
+
+    CREATE TABLE IF NOT EXISTS balance_changes (
+        id                  BIGSERIAL PRIMARY KEY,
+        block_index         BIGINT NOT NULL REFERENCES blocks (id),
+        address             TEXT NOT NULL,
+        token_contract      TEXT NOT NULL,
+        balance             BIGINT NOT NULL,
+        UNIQUE (block_index, address, token_contract)
+    );
+
+    CREATE TABLE IF NOT EXISTS daily_token_price_history (
+        id                  BIGSERIAL PRIMARY KEY,
+        block_index         BIGINT NOT NULL REFERENCES blocks (id),
+        date                TEXT NOT NULL,
+        token_contract      TEXT NOT NULL,
+        price               DOUBLE PRECISION NOT NULL,
+        UNIQUE (date, token_contract)
+    );
+
+    CREATE TABLE IF NOT EXISTS daily_contract_usage (
+        id                  BIGSERIAL PRIMARY KEY,
+        date                TEXT NOT NULL,
+        contract            TEXT NOT NULL,
+        usage               BIGINT NOT NULL,
+        UNIQUE (date, contract)
+    );
+
+    CREATE TABLE IF NOT EXISTS daily_network_stats (
+        id                  BIGSERIAL PRIMARY KEY,
+        date                TEXT NOT NULL UNIQUE,
+        active_senders      BIGINT NOT NULL,
+        new_addresses       BIGINT NOT NULL,
+        transactions        BIGINT NOT NULL,
+        transfers           BIGINT NOT NULL,
+        fees                BIGINT NOT NULL,
+        new_contracts       BIGINT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS daily_token_stats (
+        id                  BIGSERIAL PRIMARY KEY,
+        date                TEXT NOT NULL,
+        token_contract      TEXT NOT NULL,
+        transfers           BIGINT NOT NULL,
+        volume              TEXT NOT NULL,
+        minted              TEXT NOT NULL,
+        burned              TEXT NOT NULL,
+        supply              TEXT NULL,
+        UNIQUE (date, token_contract)
+    );
+
+    CREATE TABLE IF NOT EXISTS address_first_seen (
+        address             TEXT PRIMARY KEY,
+        block_index         BIGINT NOT NULL REFERENCES blocks (id),
+        date                TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS webhooks (
+        id                  BIGSERIAL PRIMARY KEY,
+        url                 TEXT NOT NULL,
+        secret              TEXT NOT NULL,
+        address             TEXT NULL,
+        contract            TEXT NULL,
+        event_name          TEXT NULL,
+        min_amount          TEXT NULL,
+        active              BOOLEAN NOT NULL DEFAULT TRUE,
+        created_at          BIGINT NOT NULL
+    );
+
+    -- status is pending, delivered, failed or cancelled
+    CREATE TABLE IF NOT EXISTS webhook_deliveries (
+        id                  BIGSERIAL PRIMARY KEY,
+        webhook_id          BIGINT NOT NULL REFERENCES webhooks (id),
+        block_index         BIGINT NOT NULL,
+        payload             TEXT NOT NULL,
+        status              TEXT NOT NULL,
+        attempts            BIGINT NOT NULL DEFAULT 0,
+        response_status     BIGINT NULL,
+        error               TEXT NULL,
+        created_at          BIGINT NOT NULL,
+        next_attempt_at     BIGINT NOT NULL,
+        delivered_at        BIGINT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS webhook_dead_letters (
+        id                  BIGSERIAL PRIMARY KEY,
+        delivery_id         BIGINT NOT NULL UNIQUE REFERENCES webhook_deliveries (id),
+        webhook_id          BIGINT NOT NULL REFERENCES webhooks (id),
+        payload             TEXT NOT NULL,
+        error               TEXT NULL,
+        failed_at           BIGINT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_blocks_time ON blocks (time);
+    CREATE INDEX IF NOT EXISTS idx_tx_senders ON transactions (sender);
+    CREATE INDEX IF NOT EXISTS idx_transaction_block_index ON transactions (block_index);
+    CREATE INDEX IF NOT EXISTS idx_witnesses_block_index ON witnesses (block_index);
+    CREATE INDEX IF NOT EXISTS idx_witnesses_transaction_id ON witnesses (transaction_id);
+    CREATE INDEX IF NOT EXISTS idx_signers_transaction_id ON signers (transaction_id);
+    CREATE INDEX IF NOT EXISTS idx_allowed_contracts_signer_id ON allowed_contracts (signer_id);
+    CREATE INDEX IF NOT EXISTS idx_transaction_notifications_event_name
+        ON transaction_notifications (event_name);
+    CREATE INDEX IF NOT EXISTS idx_transaction_notifications_transaction_hash
+        ON transaction_notifications (transaction_hash);
+    CREATE INDEX IF NOT EXISTS idx_transaction_notifications_contract
+        ON transaction_notifications (contract);
+    CREATE INDEX IF NOT EXISTS idx_transaction_notification_state_values_notification_id
+        ON transaction_notification_state_values (transaction_notification_id);
+    -- values are only looked up by equality and may outgrow a btree entry
+    CREATE INDEX IF NOT EXISTS idx_transaction_notification_state_values_value
+        ON transaction_notification_state_values USING HASH (value);
+    CREATE INDEX IF NOT EXISTS idx_daily_address_balances_address
+        ON daily_address_balances (address);
+    CREATE INDEX IF NOT EXISTS idx_daily_address_balances_date ON daily_address_balances (date);
+    CREATE INDEX IF NOT EXISTS idx_address_balances_token_balance
+        ON address_balances (token_contract, balance);
+    CREATE INDEX IF NOT EXISTS idx_balance_changes_token_address_block
+        ON balance_changes (token_contract, address, block_index);
+    CREATE INDEX IF NOT EXISTS idx_daily_token_price_history_date
+        ON daily_token_price_history (date);
+    CREATE INDEX IF NOT EXISTS idx_daily_contract_usage_date ON daily_contract_usage (date);
+    CREATE INDEX IF NOT EXISTS idx_daily_contract_usage_contract ON daily_contract_usage (contract);
+    CREATE INDEX IF NOT EXISTS idx_address_first_seen_date ON address_first_seen (date);
+";
+
+// foreign keys are enforced here, so these only find rows written around them by hand
+const ORPHAN_CHECKS: [(&str, &str); 6] = [
+    (
+        "transactions",
+        "SELECT COUNT(*) FROM transactions t
+        WHERE NOT EXISTS (SELECT 1 FROM blocks b WHERE b.id = t.block_index)",
+    ),
+    (
+        "witnesses",
+        "SELECT COUNT(*) FROM witnesses w
+        WHERE w.block_index IS NULL AND w.transaction_id IS NULL",
+    ),
+    (
+        "signers",
+        "SELECT COUNT(*) FROM signers s
+        WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.id = s.transaction_id)",
+    ),
+    (
+        "allowed_contracts",
+        "SELECT COUNT(*) FROM allowed_contracts a
+        WHERE NOT EXISTS (SELECT 1 FROM signers s WHERE s.id = a.signer_id)",
+    ),
+    (
+        "transaction_notifications",
+        "SELECT COUNT(*) FROM transaction_notifications n
+        WHERE NOT EXISTS (SELECT 1 FROM transactions t WHERE t.hash = n.transaction_hash)",
+    ),
+    (
+        "transaction_notification_state_values",
+        "SELECT COUNT(*) FROM transaction_notification_state_values v
+        WHERE NOT EXISTS (
+            SELECT 1 FROM transaction_notifications n WHERE n.id = v.transaction_notification_id
+        )",
+    ),
+];
+
+fn to_i64(value: u64) -> Result<i64> {
+    Ok(i64::try_from(value)?)
+}
+
+fn get_u64(row: &Row, index: usize) -> Result<u64> {
+    Ok(u64::try_from(row.try_get::<_, i64>(index)?)?)
+}
+
+pub struct PostgresDatabase {
+    client: Client,
+    in_batch: Cell<bool>,
+}
+
+impl PostgresDatabase {
+    pub async fn connect(url: &str) -> Result<Self> {
+        let (client, connection) = tokio_postgres::connect(url, NoTls).await?;
+        // the connection drives the socket; an open transaction is rolled back once it closes
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                error!("Postgres connection closed: {}", err);
+            }
+        });
+
+        Ok(PostgresDatabase {
+            client,
+            in_batch: Cell::new(false),
+        })
+    }
+
+    async fn reserve_ids(&self, table: &str, count: usize) -> Result<Vec<i64>> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let rows = self
+            .client
+            .query(
+                "SELECT nextval(pg_get_serial_sequence($1, 'id')) FROM generate_series(1, $2::BIGINT)",
+                &[&table, &to_i64(count as u64)?],
+            )
+            .await?;
+
+        rows.iter().map(|row| Ok(row.try_get(0)?)).collect()
+    }
+
+    pub async fn setup(&self) -> Result<()> {
+        self.client.batch_execute(SCHEMA).await?;
+
+        Ok(())
+    }
+
+    // Postgres has no savepoints outside a transaction, so a write opens its own unless it
+    // is part of a batch, whose transaction it then joins
+    async fn atomic<T>(&self, write: impl Future<Output = Result<T>>) -> Result<T> {
+        if self.in_batch.get() {
+            return write.await;
+        }
+
+        self.client.batch_execute("BEGIN").await?;
+        match write.await {
+            Ok(value) => {
+                self.client.batch_execute("COMMIT").await?;
+                Ok(value)
+            }
+            Err(err) => {
+                let _ = self.client.batch_execute("ROLLBACK").await;
+                Err(err)
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl Storage for PostgresDatabase {
+    async fn begin_batch(&self) -> Result<()> {
+        self.client.batch_execute("BEGIN").await?;
+        self.in_batch.set(true);
+
+        Ok(())
+    }
+
+    async fn commit_batch(&self) -> Result<()> {
+        self.in_batch.set(false);
+        self.client.batch_execute("COMMIT").await?;
+
+        Ok(())
+    }
+
+    async fn rollback_batch(&self) -> Result<()> {
+        self.in_batch.set(false);
+        self.client.batch_execute("ROLLBACK").await?;
+
+        Ok(())
+    }
+
+    // every table is written with one UNNEST insert per batch. rows referencing others in the
+    // same batch get their ids reserved up front, as RETURNING gives no row order to match on
+    async fn insert_blocks_transactions(
+        &self,
+        blocks: Vec<Block>,
+        transactions: Vec<Transaction>,
+    ) -> Result<()> {
+        self.atomic(async {
+            let signer_count = transactions.iter().map(|tx| tx.signers.len()).sum();
+            let notification_count = transactions.iter().map(|tx| tx.notifications.len()).sum();
+            let mut transaction_ids = self
+                .reserve_ids("transactions", transactions.len())
+                .await?
+                .into_iter();
+            let mut signer_ids = self.reserve_ids("signers", signer_count).await?.into_iter();
+            let mut notification_ids = self
+                .reserve_ids("transaction_notifications", notification_count)
+                .await?
+                .into_iter();
+
+            let mut block_ids = Vec::new();
+            let mut block_hashes = Vec::new();
+            let mut block_sizes = Vec::new();
+            let mut block_versions = Vec::new();
+            let mut merkle_roots = Vec::new();
+            let mut block_times = Vec::new();
+            let mut block_nonces = Vec::new();
+            let mut speakers = Vec::new();
+            let mut next_consensus = Vec::new();
+            let mut rewards = Vec::new();
+            let mut reward_receivers = Vec::new();
+
+            let mut witness_blocks: Vec<Option<i64>> = Vec::new();
+            let mut witness_transactions: Vec<Option<i64>> = Vec::new();
+            let mut invocations = Vec::new();
+            let mut verifications = Vec::new();
+
+            for block in blocks {
+                let block_id = to_i64(block.index)?;
+                for witness in block.witnesses {
+                    witness_blocks.push(Some(block_id));
+                    witness_transactions.push(None);
+                    invocations.push(witness.invocation);
+                    verifications.push(witness.verification);
+                }
+
+                block_ids.push(block_id);
+                block_hashes.push(block.hash);
+                block_sizes.push(i64::from(block.size));
+                block_versions.push(i64::from(block.version));
+                merkle_roots.push(block.merkle_root);
+                block_times.push(to_i64(block.time)?);
+                block_nonces.push(block.nonce);
+                speakers.push(i64::from(block.speaker));
+                next_consensus.push(block.next_consensus);
+                rewards.push(block.reward);
+                reward_receivers.push(block.reward_receiver);
+            }
+
+            let mut tx_ids = Vec::new();
+            let mut tx_hashes = Vec::new();
+            let mut tx_blocks = Vec::new();
+            let mut vm_states = Vec::new();
+            let mut tx_sizes = Vec::new();
+            let mut tx_versions = Vec::new();
+            let mut tx_nonces = Vec::new();
+            let mut senders = Vec::new();
+            let mut sysfees = Vec::new();
+            let mut netfees = Vec::new();
+            let mut valid_untils = Vec::new();
+            let mut scripts = Vec::new();
+            let mut stack_results = Vec::new();
+
+            let mut signer_rows = Vec::new();
+            let mut signer_transactions = Vec::new();
+            let mut accounts = Vec::new();
+            let mut scopes = Vec::new();
+            let mut allowed_signers = Vec::new();
+            let mut allowed_contracts = Vec::new();
+
+            let mut notification_rows = Vec::new();
+            let mut notification_hashes = Vec::new();
+            let mut notification_contracts = Vec::new();
+            let mut event_names = Vec::new();
+            let mut state_types = Vec::new();
+            let mut usage_times = Vec::new();
+            let mut state_notifications = Vec::new();
+            let mut state_value_types = Vec::new();
+            let mut state_values = Vec::new();
+
+            for transaction in transactions {
+                let transaction_id = transaction_ids.next().context("Reserved too few ids")?;
+                let timestamp = to_i64(transaction.timestamp)?;
+
+                for signer in transaction.signers {
+                    let signer_id = signer_ids.next().context("Reserved too few ids")?;
+                    for contract in signer.allowedcontracts.unwrap_or_default() {
+                        allowed_signers.push(signer_id);
+                        allowed_contracts.push(contract);
+                    }
+                    signer_rows.push(signer_id);
+                    signer_transactions.push(transaction_id);
+                    accounts.push(signer.account);
+                    scopes.push(signer.scopes);
+                }
+
+                for witness in transaction.witnesses {
+                    witness_blocks.push(None);
+                    witness_transactions.push(Some(transaction_id));
+                    invocations.push(witness.invocation);
+                    verifications.push(witness.verification);
+                }
+
+                for notification in transaction.notifications.iter() {
+                    let notification_id =
+                        notification_ids.next().context("Reserved too few ids")?;
+                    for state_value in notification.state.value.iter() {
+                        state_notifications.push(notification_id);
+                        state_value_types.push(state_value._type.clone());
+                        state_values.push(state_value_text(state_value));
+                    }
+                    notification_rows.push(notification_id);
+                    notification_hashes.push(transaction.hash.clone());
+                    notification_contracts.push(notification.contract.clone());
+                    event_names.push(notification.eventname.clone());
+                    state_types.push(notification.state._type.clone());
+                    usage_times.push(timestamp);
+                }
+
+                tx_ids.push(transaction_id);
+                tx_hashes.push(transaction.hash);
+                tx_blocks.push(to_i64(transaction.block_index)?);
+                vm_states.push(transaction.vm_state);
+                tx_sizes.push(i64::from(transaction.size));
+                tx_versions.push(i64::from(transaction.version));
+                tx_nonces.push(to_i64(transaction.nonce)?);
+                senders.push(transaction.sender);
+                sysfees.push(transaction.sysfee);
+                netfees.push(transaction.netfee);
+                valid_untils.push(to_i64(transaction.valid_until)?);
+                scripts.push(transaction.script);
+                stack_results.push(transaction.stack_result);
+            }
+
+            self.client
+                .execute(
+                    "INSERT INTO blocks (
+                        id, hash, size, version, merkle_root, time, nonce, speaker,
+                        next_consensus, reward, reward_receiver
+                    )
+                    SELECT * FROM UNNEST(
+                        $1::BIGINT[], $2::TEXT[], $3::BIGINT[], $4::BIGINT[], $5::TEXT[],
+                        $6::BIGINT[], $7::TEXT[], $8::BIGINT[], $9::TEXT[],
+                        $10::DOUBLE PRECISION[], $11::TEXT[]
+                    )",
+                    &[
+                        &block_ids,
+                        &block_hashes,
+                        &block_sizes,
+                        &block_versions,
+                        &merkle_roots,
+                        &block_times,
+                        &block_nonces,
+                        &speakers,
+                        &next_consensus,
+                        &rewards,
+                        &reward_receivers,
+                    ],
+                )
+                .await?;
+
+            self.client
+                .execute(
+                    "INSERT INTO transactions (
+                        id, hash, block_index, vm_state, size, version, nonce, sender, sysfee,
+                        netfee, valid_until, script, stack_result
+                    )
+                    SELECT * FROM UNNEST(
+                        $1::BIGINT[], $2::TEXT[], $3::BIGINT[], $4::TEXT[], $5::BIGINT[],
+                        $6::BIGINT[], $7::BIGINT[], $8::TEXT[], $9::TEXT[], $10::TEXT[],
+                        $11::BIGINT[], $12::TEXT[], $13::TEXT[]
+                    )",
+                    &[
+                        &tx_ids,
+                        &tx_hashes,
+                        &tx_blocks,
+                        &vm_states,
+                        &tx_sizes,
+                        &tx_versions,
+                        &tx_nonces,
+                        &senders,
+                        &sysfees,
+                        &netfees,
+                        &valid_untils,
+                        &scripts,
+                        &stack_results,
+                    ],
+                )
+                .await?;
+
+            self.client
+                .execute(
+                    "INSERT INTO witnesses (block_index, transaction_id, invocation, verification)
+                    SELECT * FROM UNNEST($1::BIGINT[], $2::BIGINT[], $3::TEXT[], $4::TEXT[])",
+                    &[
+                        &witness_blocks,
+                        &witness_transactions,
+                        &invocations,
+                        &verifications,
+                    ],
+                )
+                .await?;
+
+            self.client
+                .execute(
+                    "INSERT INTO signers (id, transaction_id, account, scopes)
+                    SELECT * FROM UNNEST($1::BIGINT[], $2::BIGINT[], $3::TEXT[], $4::TEXT[])",
+                    &[&signer_rows, &signer_transactions, &accounts, &scopes],
+                )
+                .await?;
+
+            self.client
+                .execute(
+                    "INSERT INTO allowed_contracts (signer_id, contract)
+                    SELECT * FROM UNNEST($1::BIGINT[], $2::TEXT[])",
+                    &[&allowed_signers, &allowed_contracts],
+                )
+                .await?;
+
+            self.client
+                .execute(
+                    "INSERT INTO transaction_notifications (
+                        id, transaction_hash, contract, event_name, state_type
+                    )
+                    SELECT * FROM UNNEST(
+                        $1::BIGINT[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::TEXT[]
+                    )",
+                    &[
+                        &notification_rows,
+                        &notification_hashes,
+                        &notification_contracts,
+                        &event_names,
+                        &state_types,
+                    ],
+                )
+                .await?;
+
+            self.client
+                .execute(
+                    "INSERT INTO transaction_notification_state_values (
+                        transaction_notification_id, type, value
+                    )
+                    SELECT * FROM UNNEST($1::BIGINT[], $2::TEXT[], $3::TEXT[])",
+                    &[&state_notifications, &state_value_types, &state_values],
+                )
+                .await?;
+
+            // grouped first, an upsert may not touch the same row twice
+            self.client
+                .execute(
+                    "INSERT INTO daily_contract_usage (date, contract, usage)
+                    SELECT shrike_date(time), contract, COUNT(*)
+                    FROM UNNEST($1::BIGINT[], $2::TEXT[]) AS batch (time, contract)
+                    GROUP BY 1, 2
+                    ON CONFLICT (date, contract)
+                    DO UPDATE SET usage = daily_contract_usage.usage + excluded.usage",
+                    &[&usage_times, &notification_contracts],
+                )
+                .await?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn insert_contracts(&self, contracts: Vec<Contract>) -> Result<()> {
+        self.atomic(async {
+            // an Update event re-deploys an existing hash, so keep the original block_index, and
+            // the stored manifest when the new script has none that parses
+            let stmt = self
+                .client
+                .prepare(
+                    "INSERT INTO contracts (
+                        block_index, hash, contract_type, manifest, symbol, decimals
+                    ) VALUES ($1, $2, $3, $4, $5, $6)
+                    ON CONFLICT (hash)
+                    DO UPDATE SET
+                        contract_type = CASE WHEN excluded.manifest IS NULL
+                            THEN contracts.contract_type ELSE excluded.contract_type END,
+                        manifest = COALESCE(excluded.manifest, contracts.manifest),
+                        symbol = COALESCE(excluded.symbol, contracts.symbol),
+                        decimals = COALESCE(excluded.decimals, contracts.decimals)",
+                )
+                .await?;
+
+            for contract in contracts {
+                self.client
+                    .execute(
+                        &stmt,
+                        &[
+                            &to_i64(contract.block_index)?,
+                            &contract.hash,
+                            &contract.contract_type,
+                            &contract.manifest,
+                            &contract.symbol,
+                            &contract.decimals.map(i64::from),
+                        ],
+                    )
+                    .await?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    // records first appearances of senders and transfer participants, then recomputes the
+    // daily_network_stats rows of every day the transactions fall on
+    async fn update_daily_network_stats(&self, transactions: &[Transaction]) -> Result<()> {
+        self.atomic(async {
+            let mut dates = BTreeSet::new();
+            let mut addresses = Vec::new();
+            let mut blocks = Vec::new();
+            let mut times = Vec::new();
+            for transaction in transactions {
+                if let Some(datetime) =
+                    DateTime::from_timestamp_millis(transaction.timestamp as i64)
+                {
+                    dates.insert(datetime.format("%Y-%m-%d").to_string());
+                }
+
+                let block_index = to_i64(transaction.block_index)?;
+                let timestamp = to_i64(transaction.timestamp)?;
+                for address in transaction_addresses(transaction) {
+                    addresses.push(address);
+                    blocks.push(block_index);
+                    times.push(timestamp);
+                }
+            }
+
+            self.client
+                .execute(
+                    "INSERT INTO address_first_seen (address, block_index, date)
+                    SELECT DISTINCT ON (address) address, block_index, shrike_date(time)
+                    FROM UNNEST($1::TEXT[], $2::BIGINT[], $3::BIGINT[])
+                        AS batch (address, block_index, time)
+                    ORDER BY address, block_index
+                    ON CONFLICT DO NOTHING",
+                    &[&addresses, &blocks, &times],
+                )
+                .await?;
+
+            let stmt_stats = self
+                .client
+                .prepare(
+                    "INSERT INTO daily_network_stats (
+                        date, active_senders, new_addresses, transactions, transfers, fees,
+                        new_contracts
+                    )
+                    SELECT
+                        $1::TEXT,
+                        (SELECT COUNT(DISTINCT sender) FROM transactions
+                            WHERE block_index BETWEEN r.first_block AND r.last_block),
+                        (SELECT COUNT(*) FROM address_first_seen WHERE date = $1::TEXT),
+                        (SELECT COUNT(*) FROM transactions
+                            WHERE block_index BETWEEN r.first_block AND r.last_block),
+                        (SELECT COUNT(*) FROM transactions t
+                            INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+                            WHERE t.block_index BETWEEN r.first_block AND r.last_block
+                                AND tn.event_name = 'Transfer'),
+                        (SELECT COALESCE(SUM(CAST(sysfee AS BIGINT) + CAST(netfee AS BIGINT)), 0)
+                            FROM transactions
+                            WHERE block_index BETWEEN r.first_block AND r.last_block)::BIGINT,
+                        (SELECT COUNT(*) FROM contracts
+                            WHERE block_index BETWEEN r.first_block AND r.last_block)
+                    FROM (
+                        SELECT MIN(id) AS first_block, MAX(id) AS last_block
+                        FROM blocks
+                        WHERE time >= EXTRACT(EPOCH FROM $1::TEXT::DATE)::BIGINT * 1000
+                            AND time < EXTRACT(EPOCH FROM $1::TEXT::DATE + 1)::BIGINT * 1000
+                    ) r
+                    ON CONFLICT (date) DO UPDATE SET
+                        active_senders = excluded.active_senders,
+                        new_addresses = excluded.new_addresses,
+                        transactions = excluded.transactions,
+                        transfers = excluded.transfers,
+                        fees = excluded.fees,
+                        new_contracts = excluded.new_contracts",
+                )
+                .await?;
+
+            for date in dates {
+                self.client.execute(&stmt_stats, &[&date]).await?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    // adds the activity onto the day, carrying the supply over from the token's previous day
+    async fn tokens_without_supply(&self, tokens: &[String]) -> Result<Vec<String>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT token FROM UNNEST($1::TEXT[]) AS token
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM (
+                        SELECT supply FROM daily_token_stats
+                        WHERE token_contract = token
+                        ORDER BY date DESC
+                        LIMIT 1
+                    ) latest
+                    WHERE supply IS NOT NULL
+                )",
+                &[&tokens],
+            )
+            .await?;
+
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    async fn update_daily_token_stats(
+        &self,
+        activity: &[TokenActivity],
+        seeds: &HashMap<String, i128>,
+    ) -> Result<()> {
+        self.atomic(async {
+            let stmt_current = self
+                .client
+                .prepare(
+                    "SELECT transfers, volume, minted, burned, supply
+                    FROM daily_token_stats
+                    WHERE date = $1 AND token_contract = $2",
+                )
+                .await?;
+            let stmt_previous = self
+                .client
+                .prepare(
+                    "SELECT supply FROM daily_token_stats
+                    WHERE date < $1 AND token_contract = $2
+                    ORDER BY date DESC
+                    LIMIT 1",
+                )
+                .await?;
+            let stmt_upsert = self
+                .client
+                .prepare(
+                    "INSERT INTO daily_token_stats (
+                        date, token_contract, transfers, volume, minted, burned, supply
+                    ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    ON CONFLICT (date, token_contract) DO UPDATE SET
+                        transfers = excluded.transfers,
+                        volume = excluded.volume,
+                        minted = excluded.minted,
+                        burned = excluded.burned,
+                        supply = excluded.supply",
+                )
+                .await?;
+
+            for day in activity {
+                let key: [&(dyn ToSql + Sync); 2] = [&day.date, &day.token_contract];
+                let (transfers, volume, minted, burned, supply) =
+                    match self.client.query_opt(&stmt_current, &key).await? {
+                        Some(row) => (
+                            get_u64(&row, 0)?,
+                            parse_amount(row.try_get(1)?),
+                            parse_amount(row.try_get(2)?),
+                            parse_amount(row.try_get(3)?),
+                            row.try_get::<_, Option<String>>(4)?.map(parse_amount),
+                        ),
+                        None => {
+                            let supply = match self.client.query_opt(&stmt_previous, &key).await? {
+                                Some(row) => row.try_get::<_, Option<String>>(0)?.map(parse_amount),
+                                None => None,
+                            };
+                            (0, 0, 0, 0, supply)
+                        }
+                    };
+                let supply = supply.or_else(|| seeds.get(&day.token_contract).copied());
+
+                self.client
+                    .execute(
+                        &stmt_upsert,
+                        &[
+                            &day.date,
+                            &day.token_contract,
+                            &to_i64(transfers + day.transfers)?,
+                            &(volume + day.volume).to_string(),
+                            &(minted + day.minted).to_string(),
+                            &(burned + day.burned).to_string(),
+                            &supply.map(|supply| (supply + day.minted - day.burned).to_string()),
+                        ],
+                    )
+                    .await?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn persist_daily_address_balances(
+        &self,
+        balances: Vec<DailyAddressBalance>,
+    ) -> Result<()> {
+        self.atomic(async {
+            let mut blocks = Vec::new();
+            let mut times = Vec::new();
+            let mut addresses = Vec::new();
+            let mut tokens = Vec::new();
+            let mut amounts = Vec::new();
+            for balance in balances {
+                blocks.push(to_i64(balance.block_index)?);
+                times.push(to_i64(balance.timestamp)?);
+                addresses.push(balance.address);
+                tokens.push(balance.token_contract);
+                amounts.push(balance.balance);
+            }
+            let params: [&(dyn ToSql + Sync); 5] = [&blocks, &times, &addresses, &tokens, &amounts];
+
+            // an upsert may not touch the same row twice, so only the last balance of each key
+            // in the batch is written, as writing them one by one would have left it
+            self.client
+                .execute(
+                    "INSERT INTO daily_address_balances (
+                        date, address, token_contract, balance, block_index
+                    )
+                    SELECT DISTINCT ON (shrike_date(time), address, token_contract)
+                        shrike_date(time), address, token_contract, balance, block_index
+                    FROM UNNEST($1::BIGINT[], $2::BIGINT[], $3::TEXT[], $4::TEXT[], $5::BIGINT[])
+                        WITH ORDINALITY
+                        AS batch (block_index, time, address, token_contract, balance, position)
+                    ORDER BY shrike_date(time), address, token_contract, position DESC
+                    ON CONFLICT (date, address, token_contract)
+                    DO UPDATE SET balance = excluded.balance, block_index = excluded.block_index",
+                    &params,
+                )
+                .await?;
+            self.client
+                .execute(
+                    "INSERT INTO address_balances (address, token_contract, balance, block_index)
+                    SELECT DISTINCT ON (address, token_contract)
+                        address, token_contract, balance, block_index
+                    FROM UNNEST($1::BIGINT[], $2::BIGINT[], $3::TEXT[], $4::TEXT[], $5::BIGINT[])
+                        WITH ORDINALITY
+                        AS batch (block_index, time, address, token_contract, balance, position)
+                    ORDER BY address, token_contract, block_index DESC, position DESC
+                    ON CONFLICT (address, token_contract)
+                    DO UPDATE SET balance = excluded.balance, block_index = excluded.block_index
+                    WHERE excluded.block_index >= address_balances.block_index",
+                    &params,
+                )
+                .await?;
+            self.client
+                .execute(
+                    "INSERT INTO balance_changes (block_index, address, token_contract, balance)
+                    SELECT DISTINCT ON (block_index, address, token_contract)
+                        block_index, address, token_contract, balance
+                    FROM UNNEST($1::BIGINT[], $2::BIGINT[], $3::TEXT[], $4::TEXT[], $5::BIGINT[])
+                        WITH ORDINALITY
+                        AS batch (block_index, time, address, token_contract, balance, position)
+                    ORDER BY block_index, address, token_contract, position DESC
+                    ON CONFLICT (block_index, address, token_contract)
+                    DO UPDATE SET balance = excluded.balance",
+                    &params,
+                )
+                .await?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn persist_daily_token_price_history(&self, prices: Vec<TokenPrice>) -> Result<()> {
+        self.atomic(async {
+            let mut times = Vec::new();
+            let mut tokens = Vec::new();
+            let mut usd_prices = Vec::new();
+            let mut blocks = Vec::new();
+            for price in prices {
+                times.push(to_i64(price.timestamp)?);
+                tokens.push(price.token_contract);
+                usd_prices.push(price.usd_price);
+                blocks.push(to_i64(price.block_index)?);
+            }
+
+            self.client
+                .execute(
+                    "INSERT INTO daily_token_price_history (
+                        date, token_contract, price, block_index
+                    )
+                    SELECT DISTINCT ON (shrike_date(time), token_contract)
+                        shrike_date(time), token_contract, price, block_index
+                    FROM UNNEST($1::BIGINT[], $2::TEXT[], $3::DOUBLE PRECISION[], $4::BIGINT[])
+                        WITH ORDINALITY AS batch (time, token_contract, price, block_index, position)
+                    ORDER BY shrike_date(time), token_contract, position DESC
+                    ON CONFLICT (date, token_contract)
+                    DO UPDATE SET price = excluded.price, block_index = excluded.block_index",
+                    &[&times, &tokens, &usd_prices, &blocks],
+                )
+                .await?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn has_active_webhooks(&self) -> Result<bool> {
+        let row = self
+            .client
+            .query_one("SELECT EXISTS (SELECT 1 FROM webhooks WHERE active)", &[])
+            .await?;
+
+        Ok(row.try_get(0)?)
+    }
+
+    // one pending delivery per webhook with everything in the batch it matched
+    async fn queue_webhook_deliveries(&self, messages: &[LiveMessage], now: u64) -> Result<usize> {
+        self.atomic(async {
+            let sql = format!("SELECT {} FROM webhooks WHERE active", WEBHOOK_COLUMNS);
+            let webhooks = self
+                .client
+                .query(sql.as_str(), &[])
+                .await?
+                .iter()
+                .map(webhook_row)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+
+            let now = to_i64(now)?;
+            let mut queued = 0;
+            for webhook in webhooks {
+                let Some((block_index, payload)) = webhook_payload(&webhook, messages) else {
+                    continue;
+                };
+
+                self.client
+                    .execute(
+                        "INSERT INTO webhook_deliveries (
+                            webhook_id, block_index, payload, status, created_at, next_attempt_at
+                        ) VALUES ($1, $2, $3, 'pending', $4, $4)",
+                        &[&to_i64(webhook.id)?, &to_i64(block_index)?, &payload, &now],
+                    )
+                    .await?;
+                queued += 1;
+            }
+
+            Ok(queued)
+        })
+        .await
+    }
+
+    async fn get_last_index(&self, table: &str) -> Result<u64> {
+        let row = self
+            .client
+            .query_one(&format!("SELECT MAX(id) FROM {table}"), &[])
+            .await?;
+
+        Ok(row
+            .try_get::<_, Option<i64>>(0)?
+            .map_or(Ok(0), u64::try_from)?)
+    }
+
+    // (date, token) pairs with no price, between each token's first price and the last
+    // fully indexed day
+    async fn find_price_gaps(&self) -> Result<Vec<(String, String)>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT d.date, d.token_contract
+                FROM (
+                    SELECT f.token_contract, to_char(day, 'YYYY-MM-DD') AS date
+                    FROM (
+                        SELECT token_contract, MIN(date) AS first_date
+                        FROM daily_token_price_history
+                        GROUP BY token_contract
+                    ) f,
+                    generate_series(
+                        f.first_date::DATE,
+                        (SELECT shrike_date(MAX(time) - 86400000) FROM blocks)::DATE,
+                        INTERVAL '1 day'
+                    ) day
+                ) d
+                LEFT JOIN daily_token_price_history p
+                    ON p.token_contract = d.token_contract AND p.date = d.date
+                WHERE p.id IS NULL
+                ORDER BY d.date, d.token_contract",
+                &[],
+            )
+            .await?;
+
+        rows.iter()
+            .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+            .collect()
+    }
+
+    // index and time of the last block before the end of a UTC day
+    async fn get_last_block_of_day(&self, date: &str) -> Result<Option<(u64, u64)>> {
+        let row = self
+            .client
+            .query_opt(
+                "SELECT id, time
+                FROM blocks
+                WHERE time < EXTRACT(EPOCH FROM $1::TEXT::DATE + 1)::BIGINT * 1000
+                ORDER BY time DESC
+                LIMIT 1",
+                &[&date],
+            )
+            .await?;
+
+        row.map(|row| Ok((get_u64(&row, 0)?, get_u64(&row, 1)?)))
+            .transpose()
+    }
+
+    // (first, last) heights of every hole between stored blocks
+    async fn find_missing_heights(&self) -> Result<Vec<(u64, u64)>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT prev + 1, id - 1
+                FROM (SELECT id, LAG(id) OVER (ORDER BY id) AS prev FROM blocks) ordered
+                WHERE prev IS NOT NULL AND id - prev > 1
+                ORDER BY id",
+                &[],
+            )
+            .await?;
+
+        rows.iter()
+            .map(|row| Ok((get_u64(row, 0)?, get_u64(row, 1)?)))
+            .collect()
+    }
+
+    async fn count_orphans(&self) -> Result<Vec<(String, u64)>> {
+        let mut orphans = Vec::new();
+        for (table, sql) in ORPHAN_CHECKS {
+            let row = self.client.query_one(sql, &[]).await?;
+            orphans.push((table.to_string(), get_u64(&row, 0)?));
+        }
+
+        Ok(orphans)
+    }
+
+    async fn sample_block_indexes(&self, count: u64) -> Result<Vec<u64>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT id FROM blocks ORDER BY random() LIMIT $1",
+                &[&to_i64(count)?],
+            )
+            .await?;
+
+        rows.iter().map(|row| get_u64(row, 0)).collect()
+    }
+
+    async fn get_block_hash(&self, index: u64) -> Result<Option<String>> {
+        let row = self
+            .client
+            .query_opt("SELECT hash FROM blocks WHERE id = $1", &[&to_i64(index)?])
+            .await?;
+
+        Ok(row.map(|row| row.try_get(0)).transpose()?)
+    }
+
+    async fn get_block_transaction_hashes(&self, index: u64) -> Result<Vec<String>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT hash FROM transactions WHERE block_index = $1 ORDER BY id",
+                &[&to_i64(index)?],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| row.try_get(0))
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn count_rows(&self, table: &str) -> Result<u64> {
+        let row = self
+            .client
+            .query_one(&format!("SELECT COUNT(*) FROM {table}"), &[])
+            .await?;
+
+        get_u64(&row, 0)
+    }
+
+    async fn clear_table(&self, table: &str) -> Result<usize> {
+        let deleted = self
+            .client
+            .execute(&format!("DELETE FROM {table}"), &[])
+            .await?;
+
+        Ok(deleted as usize)
+    }
+
+    // symbol and decimals came from contract calls, so a rebuild carries them over
+    async fn get_token_metadata(&self) -> Result<HashMap<String, TokenMetadata>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT hash, symbol, decimals FROM contracts
+                WHERE symbol IS NOT NULL OR decimals IS NOT NULL",
+                &[],
+            )
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                let decimals = row
+                    .try_get::<_, Option<i64>>(2)?
+                    .map(u8::try_from)
+                    .transpose()?;
+                Ok((row.try_get(0)?, (row.try_get(1)?, decimals)))
+            })
+            .collect()
+    }
+
+    // stored transactions of a block range with their notifications, signers and witnesses
+    // are left out since no derived table reads them
+    async fn load_transactions(&self, first: u64, last: u64) -> Result<Vec<Transaction>> {
+        let (first, last) = (to_i64(first)?, to_i64(last)?);
+        let range: [&(dyn ToSql + Sync); 2] = [&first, &last];
+
+        let mut states: HashMap<u64, Vec<StateValue>> = HashMap::new();
+        let rows = self
+            .client
+            .query(
+                "SELECT v.transaction_notification_id, v.type, v.value
+                FROM transaction_notification_state_values v
+                INNER JOIN transaction_notifications n ON n.id = v.transaction_notification_id
+                INNER JOIN transactions t ON t.hash = n.transaction_hash
+                WHERE t.block_index BETWEEN $1 AND $2
+                ORDER BY v.id",
+                &range,
+            )
+            .await?;
+        for row in rows.iter() {
+            states
+                .entry(get_u64(row, 0)?)
+                .or_default()
+                .push(StateValue {
+                    _type: row.try_get(1)?,
+                    value: row
+                        .try_get::<_, Option<String>>(2)?
+                        .map(serde_json::Value::String),
+                });
+        }
+
+        let mut notifications: HashMap<String, Vec<Notification>> = HashMap::new();
+        let rows = self
+            .client
+            .query(
+                "SELECT n.id, n.transaction_hash, n.contract, n.event_name, n.state_type
+                FROM transaction_notifications n
+                INNER JOIN transactions t ON t.hash = n.transaction_hash
+                WHERE t.block_index BETWEEN $1 AND $2
+                ORDER BY n.id",
+                &range,
+            )
+            .await?;
+        for row in rows.iter() {
+            let id = get_u64(row, 0)?;
+            notifications
+                .entry(row.try_get(1)?)
+                .or_default()
+                .push(Notification {
+                    id: Some(id),
+                    contract: row.try_get(2)?,
+                    eventname: row.try_get(3)?,
+                    state: State {
+                        _type: row.try_get(4)?,
+                        value: states.remove(&id).unwrap_or_default(),
+                    },
+                });
+        }
+
+        let rows = self
+            .client
+            .query(
+                "SELECT t.id, t.hash, t.block_index, t.vm_state, t.size, t.version, t.nonce,
+                    t.sender, t.sysfee, t.netfee, t.valid_until, t.script, t.stack_result, b.time
+                FROM transactions t
+                INNER JOIN blocks b ON b.id = t.block_index
+                WHERE t.block_index BETWEEN $1 AND $2
+                ORDER BY t.id",
+                &range,
+            )
+            .await?;
+        let mut transactions = Vec::new();
+        for row in rows.iter() {
+            let hash: String = row.try_get(1)?;
+            transactions.push(Transaction {
+                index: get_u64(row, 0)?,
+                notifications: notifications.remove(&hash).unwrap_or_default(),
+                hash,
+                block_index: get_u64(row, 2)?,
+                vm_state: row.try_get(3)?,
+                size: u32::try_from(row.try_get::<_, i64>(4)?)?,
+                version: u8::try_from(row.try_get::<_, i64>(5)?)?,
+                nonce: get_u64(row, 6)?,
+                sender: row.try_get(7)?,
+                sysfee: row.try_get(8)?,
+                netfee: row.try_get(9)?,
+                valid_until: get_u64(row, 10)?,
+                script: row.try_get(11)?,
+                stack_result: row.try_get::<_, Option<String>>(12)?.unwrap_or_default(),
+                timestamp: get_u64(row, 13)?,
+                signers: Vec::new(),
+                witnesses: Vec::new(),
+            });
+        }
+
+        Ok(transactions)
+    }
+
+    async fn rebuild_daily_contract_usage(&self) -> Result<usize> {
+        let inserted = self
+            .client
+            .execute(
+                "INSERT INTO daily_contract_usage (date, contract, usage)
+                SELECT shrike_date(b.time), n.contract, COUNT(*)
+                FROM transaction_notifications n
+                INNER JOIN transactions t ON t.hash = n.transaction_hash
+                INNER JOIN blocks b ON b.id = t.block_index
+                GROUP BY 1, 2",
+                &[],
+            )
+            .await?;
+
+        Ok(inserted as usize)
+    }
+
+    // balance_changes holds every balance the node reported, the other balance tables are
+    // the last of those per day and overall
+    async fn rebuild_address_balances(&self) -> Result<usize> {
+        self.atomic(async {
+            let daily = self
+                .client
+                .execute(
+                    "INSERT INTO daily_address_balances (
+                        date, address, token_contract, balance, block_index
+                    )
+                    SELECT date, address, token_contract, balance, block_index
+                    FROM (
+                        SELECT
+                            shrike_date(b.time) AS date,
+                            c.address, c.token_contract, c.balance, c.block_index,
+                            ROW_NUMBER() OVER (
+                                PARTITION BY shrike_date(b.time), c.address, c.token_contract
+                                ORDER BY c.block_index DESC
+                            ) AS position
+                        FROM balance_changes c
+                        INNER JOIN blocks b ON b.id = c.block_index
+                    ) ranked
+                    WHERE position = 1",
+                    &[],
+                )
+                .await?;
+
+            let current = self
+                .client
+                .execute(
+                    "INSERT INTO address_balances (address, token_contract, balance, block_index)
+                    SELECT address, token_contract, balance, block_index
+                    FROM (
+                        SELECT address, token_contract, balance, block_index,
+                            ROW_NUMBER() OVER (
+                                PARTITION BY address, token_contract
+                                ORDER BY block_index DESC
+                            ) AS position
+                        FROM balance_changes
+                    ) ranked
+                    WHERE position = 1",
+                    &[],
+                )
+                .await?;
+
+            Ok((daily + current) as usize)
+        })
+        .await
+    }
+}
+
+// needs a server, so it is skipped unless SHRIKE_TEST_POSTGRES_URL is set, for example to
+// postgres://postgres@localhost/shrike
+#[test]
+fn test_postgres_batch() {
+    let Ok(url) = std::env::var("SHRIKE_TEST_POSTGRES_URL") else {
+        eprintln!("SHRIKE_TEST_POSTGRES_URL is unset, skipping the Postgres batch test");
+        return;
+    };
+    let block = |index: u64| Block {
+        index,
+        hash: format!("0x{index:02x}"),
+        size: 0,
+        version: 0,
+        merkle_root: String::new(),
+        time: 1_700_000_000_000 + index,
+        nonce: String::new(),
+        speaker: 0,
+        next_consensus: String::new(),
+        reward: 0.5,
+        reward_receiver: String::new(),
+        witnesses: Vec::new(),
+    };
+    let transaction = |hash: &str| Transaction {
+        index: 0,
+        hash: hash.to_string(),
+        block_index: 1,
+        timestamp: 1_700_000_000_001,
+        vm_state: "HALT".to_string(),
+        size: 0,
+        version: 0,
+        nonce: 0,
+        sender: "sender".to_string(),
+        sysfee: "1".to_string(),
+        netfee: "1".to_string(),
+        valid_until: 0,
+        signers: vec![crate::transaction::models::Signer {
+            account: "account".to_string(),
+            scopes: "CalledByEntry".to_string(),
+            allowedcontracts: Some(vec!["0xcontract".to_string()]),
+        }],
+        script: String::new(),
+        witnesses: vec![crate::block::models::Witness {
+            invocation: "invocation".to_string(),
+            verification: "verification".to_string(),
+        }],
+        stack_result: "[]".to_string(),
+        notifications: vec![Notification {
+            id: None,
+            contract: "0xcontract".to_string(),
+            eventname: "Transfer".to_string(),
+            state: State {
+                _type: "Array".to_string(),
+                value: vec![StateValue {
+                    _type: "Integer".to_string(),
+                    value: Some(serde_json::json!(hash)),
+                }],
+            },
+        }],
+    };
+    let balance = |amount: i64| DailyAddressBalance {
+        block_index: 1,
+        date: String::new(),
+        timestamp: 1_700_000_000_001,
+        address: "address".to_string(),
+        token_contract: "0xcontract".to_string(),
+        balance: amount,
+    };
+
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let db = PostgresDatabase::connect(&url).await.unwrap();
+        let schema = format!("shrike_test_{}", std::process::id());
+        db.client
+            .batch_execute(&format!(
+                "CREATE SCHEMA {schema}; SET search_path TO {schema}"
+            ))
+            .await
+            .unwrap();
+        db.setup().await.unwrap();
+
+        db.begin_batch().await.unwrap();
+        db.insert_blocks_transactions(
+            vec![block(0), block(1)],
+            vec![transaction("0xaa"), transaction("0xbb")],
+        )
+        .await
+        .unwrap();
+        db.persist_daily_address_balances(vec![balance(5), balance(7)])
+            .await
+            .unwrap();
+        db.update_daily_network_stats(&[transaction("0xaa")])
+            .await
+            .unwrap();
+        db.persist_daily_token_price_history(vec![TokenPrice {
+            token_contract: "0xcontract".to_string(),
+            usd_price: 1.5,
+            block_index: 1,
+            timestamp: 1_700_000_000_001,
+        }])
+        .await
+        .unwrap();
+        db.commit_batch().await.unwrap();
+
+        // an abandoned batch leaves nothing behind
+        db.begin_batch().await.unwrap();
+        db.insert_blocks_transactions(vec![block(2)], Vec::new())
+            .await
+            .unwrap();
+        db.rollback_batch().await.unwrap();
+
+        assert_eq!(db.get_last_index("blocks").await.unwrap(), 1);
+        assert_eq!(
+            db.get_block_hash(1).await.unwrap(),
+            Some("0x01".to_string())
+        );
+        assert_eq!(db.get_block_hash(2).await.unwrap(), None);
+        assert!(db.find_missing_heights().await.unwrap().is_empty());
+        assert!(db
+            .count_orphans()
+            .await
+            .unwrap()
+            .iter()
+            .all(|(_, count)| *count == 0));
+
+        let loaded = db.load_transactions(1, 1).await.unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].hash, "0xbb");
+        assert_eq!(
+            loaded[1].notifications[0].state.value[0].value,
+            Some(serde_json::json!("0xbb"))
+        );
+
+        // signers and witnesses hang off the ids reserved for their transactions
+        let row = db
+            .client
+            .query_one(
+                "SELECT COUNT(DISTINCT s.transaction_id), COUNT(a.id), COUNT(DISTINCT w.id)
+                FROM signers s
+                INNER JOIN allowed_contracts a ON a.signer_id = s.id
+                INNER JOIN witnesses w ON w.transaction_id = s.transaction_id",
+                &[],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            (
+                row.get::<_, i64>(0),
+                row.get::<_, i64>(1),
+                row.get::<_, i64>(2)
+            ),
+            (2, 2, 2)
+        );
+
+        // the last balance of the batch wins
+        let row = db
+            .client
+            .query_one("SELECT balance FROM address_balances", &[])
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, i64>(0), 7);
+        assert!(db.find_price_gaps().await.unwrap().is_empty());
+        let row = db
+            .client
+            .query_one("SELECT usage FROM daily_contract_usage", &[])
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, i64>(0), 2);
+
+        db.client
+            .batch_execute(&format!("DROP SCHEMA {schema} CASCADE"))
+            .await
+            .unwrap();
+    });
+}
@@ -0,0 +1,95 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use std::collections::HashMap;
+
+use crate::block::models::Block;
+use crate::history::models::DailyAddressBalance;
+use crate::indexer::prices::TokenPrice;
+use crate::indexer::rpc::database::{Database, TokenMetadata};
+use crate::indexer::rpc::models::{Contract, TokenActivity};
+use crate::indexer::rpc::postgres::PostgresDatabase;
+use crate::live::models::LiveMessage;
+use crate::shared::config::{StorageBackend, StorageConfig};
+use crate::transaction::models::Transaction;
+
+// Everything the indexer reads and writes. Writes are atomic on their own, and between
+// begin_batch and commit_batch they only become visible together
+#[async_trait(?Send)]
+pub trait Storage {
+    async fn begin_batch(&self) -> Result<()>;
+    async fn commit_batch(&self) -> Result<()>;
+    async fn rollback_batch(&self) -> Result<()>;
+
+    async fn insert_blocks_transactions(
+        &self,
+        blocks: Vec<Block>,
+        transactions: Vec<Transaction>,
+    ) -> Result<()>;
+    async fn insert_contracts(&self, contracts: Vec<Contract>) -> Result<()>;
+    async fn update_daily_network_stats(&self, transactions: &[Transaction]) -> Result<()>;
+    async fn tokens_without_supply(&self, tokens: &[String]) -> Result<Vec<String>>;
+    async fn update_daily_token_stats(
+        &self,
+        activity: &[TokenActivity],
+        seeds: &HashMap<String, i128>,
+    ) -> Result<()>;
+    async fn persist_daily_address_balances(
+        &self,
+        balances: Vec<DailyAddressBalance>,
+    ) -> Result<()>;
+    async fn persist_daily_token_price_history(&self, prices: Vec<TokenPrice>) -> Result<()>;
+    async fn has_active_webhooks(&self) -> Result<bool>;
+    async fn queue_webhook_deliveries(&self, messages: &[LiveMessage], now: u64) -> Result<usize>;
+
+    async fn get_last_index(&self, table: &str) -> Result<u64>;
+    async fn find_price_gaps(&self) -> Result<Vec<(String, String)>>;
+    async fn get_last_block_of_day(&self, date: &str) -> Result<Option<(u64, u64)>>;
+    async fn find_missing_heights(&self) -> Result<Vec<(u64, u64)>>;
+    async fn count_orphans(&self) -> Result<Vec<(String, u64)>>;
+    async fn sample_block_indexes(&self, count: u64) -> Result<Vec<u64>>;
+    async fn get_block_hash(&self, index: u64) -> Result<Option<String>>;
+    async fn get_block_transaction_hashes(&self, index: u64) -> Result<Vec<String>>;
+
+    async fn count_rows(&self, table: &str) -> Result<u64>;
+    async fn clear_table(&self, table: &str) -> Result<usize>;
+    async fn get_token_metadata(&self) -> Result<HashMap<String, TokenMetadata>>;
+    async fn load_transactions(&self, first: u64, last: u64) -> Result<Vec<Transaction>>;
+    async fn rebuild_daily_contract_usage(&self) -> Result<usize>;
+    async fn rebuild_address_balances(&self) -> Result<usize>;
+}
+
+fn postgres_url(config: &StorageConfig) -> Result<&str> {
+    config
+        .url
+        .as_deref()
+        .ok_or_else(|| anyhow!("storage.url is required by the postgres backend"))
+}
+
+// the configured backend; the SQLite connection is only used when that is the one selected
+pub async fn open<'a>(
+    config: &StorageConfig,
+    conn: &'a PooledConnection<SqliteConnectionManager>,
+) -> Result<Box<dyn Storage + 'a>> {
+    match config.backend {
+        StorageBackend::Sqlite => Ok(Box::new(Database::new(conn)?)),
+        StorageBackend::Postgres => Ok(Box::new(
+            PostgresDatabase::connect(postgres_url(config)?).await?,
+        )),
+    }
+}
+
+// SQLite tables are created by the indexer setup either way, this adds the Postgres schema
+pub async fn setup(config: &StorageConfig) -> Result<()> {
+    match config.backend {
+        StorageBackend::Sqlite => Ok(()),
+        StorageBackend::Postgres => {
+            PostgresDatabase::connect(postgres_url(config)?)
+                .await?
+                .setup()
+                .await
+        }
+    }
+}
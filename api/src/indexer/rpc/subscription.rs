@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Result};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use super::models::Execution;
+
+// notification_from_execution repeats the notifications already carried by
+// transaction_executed, so its events are skipped when parsing
+const SUBSCRIBED_EVENTS: [&str; 3] = [
+    "block_added",
+    "transaction_executed",
+    "notification_from_execution",
+];
+
+pub enum SubscriptionEvent {
+    BlockAdded(u64),
+    Executed(String, Execution), // container hash, block or transaction
+}
+
+#[derive(Deserialize)]
+struct BlockAddedEvent {
+    index: u64,
+}
+
+#[derive(Deserialize)]
+struct ExecutedEvent {
+    container: String,
+    #[serde(flatten)]
+    execution: Execution,
+}
+
+pub struct Subscription {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl Subscription {
+    pub async fn connect(ws_url: &str) -> Result<Self> {
+        let (mut stream, _) = connect_async(ws_url).await?;
+        for (id, event) in SUBSCRIBED_EVENTS.iter().enumerate() {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": id + 1,
+                "method": "subscribe",
+                "params": [event],
+            });
+            stream.send(Message::Text(request.to_string())).await?;
+        }
+
+        Ok(Self { stream })
+    }
+
+    // Returns None once the node closes the connection
+    pub async fn next(&mut self) -> Result<Option<SubscriptionEvent>> {
+        while let Some(message) = self.stream.next().await {
+            match message? {
+                Message::Text(text) => {
+                    if let Some(event) = parse_event(&text)? {
+                        return Ok(Some(event));
+                    }
+                }
+                Message::Ping(payload) => self.stream.send(Message::Pong(payload)).await?,
+                Message::Close(_) => return Ok(None),
+                _ => {}
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+fn parse_event(text: &str) -> Result<Option<SubscriptionEvent>> {
+    let mut message: Value = serde_json::from_str(text)?;
+    if let Some(error) = message.get("error") {
+        return Err(anyhow!("Subscription error: {error}"));
+    }
+
+    let payload = message
+        .pointer_mut("/params/0")
+        .map(Value::take)
+        .unwrap_or_default();
+    match message["method"].as_str() {
+        Some("block_added") => {
+            let block: BlockAddedEvent = serde_json::from_value(payload)?;
+            Ok(Some(SubscriptionEvent::BlockAdded(block.index)))
+        }
+        Some("transaction_executed") => {
+            let executed: ExecutedEvent = serde_json::from_value(payload)?;
+            Ok(Some(SubscriptionEvent::Executed(
+                executed.container,
+                executed.execution,
+            )))
+        }
+        // subscribe acknowledgements, notification_from_execution and event_missed
+        _ => Ok(None),
+    }
+}
+
+#[test]
+fn test_parse_event() {
+    let block =
+        r#"{"jsonrpc":"2.0","method":"block_added","params":[{"hash":"0xab","index":42,"tx":[]}]}"#;
+    assert!(matches!(
+        parse_event(block).unwrap(),
+        Some(SubscriptionEvent::BlockAdded(42))
+    ));
+
+    let executed = r#"{"jsonrpc":"2.0","method":"transaction_executed","params":[{"container":"0xcd","trigger":"Application","vmstate":"HALT","gasconsumed":"9977780","stack":[],"notifications":[]}]}"#;
+    match parse_event(executed).unwrap() {
+        Some(SubscriptionEvent::Executed(container, execution)) => {
+            assert_eq!(container, "0xcd");
+            assert_eq!(execution.vmstate, "HALT");
+        }
+        _ => panic!("expected an execution event"),
+    }
+
+    let ack = r#"{"jsonrpc":"2.0","id":1,"result":"0"}"#;
+    assert!(parse_event(ack).unwrap().is_none());
+
+    let error = r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32602,"message":"Invalid params"}}"#;
+    assert!(parse_event(error).is_err());
+}
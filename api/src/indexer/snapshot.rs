@@ -0,0 +1,318 @@
+use anyhow::{anyhow, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::info;
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+use sha2::{Digest, Sha256};
+
+use crate::indexer::models::SnapshotManifest;
+use crate::indexer::rpc::database::SCHEMA_VERSION;
+use crate::shared::db::DB_PATH;
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const BACKUP_RETRY: Duration = Duration::from_millis(250); // wait while the source is locked
+const KEEP_SNAPSHOTS: usize = 3; // older archives are removed once a new one is written
+
+// receiver urls and signing secrets stay with the instance they were registered on
+const PRIVATE_TABLES: [&str; 3] = ["webhook_dead_letters", "webhook_deliveries", "webhooks"];
+
+// one snapshot at a time, concurrent ones at the same height would write the same archive
+static SNAPSHOT_LOCK: Mutex<()> = Mutex::new(());
+
+// snapshots are written next to the database unless told otherwise
+pub fn snapshot_dir() -> PathBuf {
+    DB_PATH
+        .parent()
+        .expect("Failed to get db parent directory")
+        .join("snapshots")
+}
+
+// hashes the compressed bytes on their way to disk, so the archive is read once
+struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// (height, hash) of the highest stored block, None for an empty database
+fn chain_tip(conn: &Connection) -> Result<Option<(u64, String)>> {
+    match conn.query_row(
+        "SELECT id, hash FROM blocks ORDER BY id DESC LIMIT 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ) {
+        Ok(tip) => Ok(Some(tip)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn file_sha256(path: &Path) -> Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut BufReader::new(File::open(path)?), &mut hasher)?;
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+// Copies the live database with the online backup API, which sees a single committed state
+// even while the indexer keeps writing, then compresses the copy and writes its manifest
+pub fn create_snapshot(conn: &Connection, out_dir: &Path) -> Result<SnapshotManifest> {
+    let _guard = SNAPSHOT_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    fs::create_dir_all(out_dir)?;
+    // named per process, the CLI can snapshot next to a running server
+    let partial = out_dir.join(format!("shrike-{}.db3.partial", std::process::id()));
+    let _ = fs::remove_file(&partial);
+
+    {
+        let mut copy = Connection::open(&partial)?;
+        let backup = Backup::new(conn, &mut copy)?;
+        // all pages in one step, a stepped backup restarts whenever the source is written to.
+        // under WAL this is a plain read, so the indexer keeps writing meanwhile
+        loop {
+            match backup.step(-1)? {
+                StepResult::Done => break,
+                _ => thread::sleep(BACKUP_RETRY),
+            }
+        }
+        drop(backup);
+        // the copy inherits WAL mode, leave it a single self-contained file; setup turns WAL
+        // back on after an import
+        let _: String = copy.query_row("PRAGMA journal_mode=DELETE", [], |row| row.get(0))?;
+
+        for table in PRIVATE_TABLES {
+            let exists: bool = copy.query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+                [table],
+                |row| row.get(0),
+            )?;
+            if exists {
+                copy.execute(&format!("DELETE FROM {table}"), [])?;
+            }
+        }
+        // deleted rows would otherwise linger in free pages
+        copy.execute("VACUUM", [])?;
+    }
+
+    let copy = Connection::open_with_flags(&partial, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let (height, block_hash) =
+        chain_tip(&copy)?.ok_or_else(|| anyhow!("There are no blocks to snapshot"))?;
+    let schema_version: u32 = copy.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    drop(copy);
+
+    let file = format!("shrike-{height}.db3.gz");
+    let mut writer = HashingWriter {
+        inner: BufWriter::new(File::create(out_dir.join(&file))?),
+        hasher: Sha256::new(),
+        size: 0,
+    };
+    let mut encoder = GzEncoder::new(&mut writer, Compression::default());
+    io::copy(&mut BufReader::new(File::open(&partial)?), &mut encoder)?;
+    encoder.finish()?;
+    writer.flush()?;
+    fs::remove_file(&partial)?;
+
+    let manifest = SnapshotManifest {
+        file,
+        height,
+        block_hash,
+        schema_version,
+        sha256: hex::encode(writer.hasher.finalize()),
+        size: writer.size,
+        created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
+    };
+    let manifest_path = out_dir.join(format!("shrike-{height}.json"));
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    info!("Wrote snapshot {}.", manifest_path.display());
+    prune_snapshots(out_dir, KEEP_SNAPSHOTS)?;
+
+    Ok(manifest)
+}
+
+// removes all but the newest keep snapshots, each an archive and its manifest
+fn prune_snapshots(out_dir: &Path, keep: usize) -> Result<()> {
+    let mut heights: Vec<u64> = fs::read_dir(out_dir)?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            name.strip_prefix("shrike-")?
+                .strip_suffix(".json")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    heights.sort_unstable_by(|a, b| b.cmp(a));
+
+    for height in heights.into_iter().skip(keep) {
+        // either half may already be gone, the other should still go
+        let _ = fs::remove_file(out_dir.join(format!("shrike-{height}.db3.gz")));
+        fs::remove_file(out_dir.join(format!("shrike-{height}.json")))?;
+        info!("Removed snapshot at height {height}.");
+    }
+
+    Ok(())
+}
+
+// Checks a snapshot against its manifest and moves it into place at db_path. Must run
+// before anything opens db_path, as the old database and its WAL are replaced underneath.
+pub fn import_snapshot(
+    manifest_path: &Path,
+    db_path: &Path,
+    force: bool,
+) -> Result<SnapshotManifest> {
+    let manifest: SnapshotManifest = serde_json::from_str(&fs::read_to_string(manifest_path)?)?;
+    if manifest.schema_version != SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Snapshot has schema version {}, this build reads version {}",
+            manifest.schema_version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    let archive = manifest_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(&manifest.file);
+    let (sha256, size) = file_sha256(&archive)?;
+    if sha256 != manifest.sha256 || size != manifest.size {
+        return Err(anyhow!("{} does not match its manifest", archive.display()));
+    }
+
+    if db_path.exists() && !force {
+        let existing = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        // a fresh install has an empty schema from setup, which is fine to replace
+        if let Ok(Some((height, _))) = chain_tip(&existing) {
+            return Err(anyhow!(
+                "Database already holds blocks up to {height}, use --force to replace it"
+            ));
+        }
+    }
+
+    let staged = db_path.with_extension("db3.import");
+    let mut decoder = GzDecoder::new(BufReader::new(File::open(&archive)?));
+    io::copy(&mut decoder, &mut BufWriter::new(File::create(&staged)?))?;
+
+    if let Err(err) = check_staged(&staged, &manifest) {
+        let _ = fs::remove_file(&staged);
+        return Err(err);
+    }
+
+    // stale WAL frames would be replayed on top of the new file
+    for suffix in ["-wal", "-shm"] {
+        let mut path = db_path.as_os_str().to_owned();
+        path.push(suffix);
+        let _ = fs::remove_file(path);
+    }
+    fs::rename(&staged, db_path)?;
+    info!("Imported snapshot at height {}.", manifest.height);
+
+    Ok(manifest)
+}
+
+fn check_staged(staged: &Path, manifest: &SnapshotManifest) -> Result<()> {
+    let conn = Connection::open_with_flags(staged, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if integrity != "ok" {
+        return Err(anyhow!("Snapshot failed the integrity check: {integrity}"));
+    }
+
+    match chain_tip(&conn)? {
+        Some((height, hash)) if height == manifest.height && hash == manifest.block_hash => Ok(()),
+        _ => Err(anyhow!(
+            "Snapshot does not end at block {} ({})",
+            manifest.height,
+            manifest.block_hash
+        )),
+    }
+}
+
+#[test]
+fn test_snapshot_roundtrip() {
+    let dir = std::env::temp_dir().join(format!("shrike-snapshot-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let source = Connection::open(dir.join("source.db3")).unwrap();
+    source
+        .execute_batch(&format!(
+            "CREATE TABLE blocks (id INTEGER PRIMARY KEY, hash TEXT);
+             INSERT INTO blocks VALUES (0, '0xaa'), (1, '0xbb');
+             CREATE TABLE webhooks (id INTEGER PRIMARY KEY, url TEXT, secret TEXT);
+             INSERT INTO webhooks VALUES (1, 'https://example.com/hook', 'not-for-export');
+             PRAGMA user_version = {SCHEMA_VERSION};"
+        ))
+        .unwrap();
+
+    let manifest = create_snapshot(&source, &dir.join("out")).unwrap();
+    assert_eq!(manifest.height, 1);
+    assert_eq!(manifest.block_hash, "0xbb");
+    let manifest_path = dir.join("out").join("shrike-1.json");
+
+    let target = dir.join("shrike.db3");
+    import_snapshot(&manifest_path, &target, false).unwrap();
+    let imported = Connection::open(&target).unwrap();
+    assert_eq!(chain_tip(&imported).unwrap(), Some((1, "0xbb".to_string())));
+    let webhooks: u64 = imported
+        .query_row("SELECT COUNT(*) FROM webhooks", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(webhooks, 0);
+
+    // the target now holds blocks, and a tampered archive never gets that far
+    assert!(import_snapshot(&manifest_path, &target, false).is_err());
+    fs::write(dir.join("out").join(&manifest.file), b"corrupt").unwrap();
+    assert!(import_snapshot(&manifest_path, &target, true).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_prune_snapshots() {
+    let dir = std::env::temp_dir().join(format!("shrike-prune-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    for height in [5, 40, 300, 7] {
+        fs::write(dir.join(format!("shrike-{height}.db3.gz")), b"").unwrap();
+        fs::write(dir.join(format!("shrike-{height}.json")), b"{}").unwrap();
+    }
+    fs::write(dir.join("notes.json"), b"{}").unwrap();
+
+    prune_snapshots(&dir, 2).unwrap();
+    let mut left: Vec<String> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    left.sort();
+    // heights compare as numbers, 300 is newer than 40
+    assert_eq!(
+        left,
+        [
+            "notes.json",
+            "shrike-300.db3.gz",
+            "shrike-300.json",
+            "shrike-40.db3.gz",
+            "shrike-40.json"
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
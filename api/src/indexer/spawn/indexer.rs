@@ -1,34 +1,86 @@
 use anyhow::Context;
 use futures::future::join_all;
 use futures::future::try_join_all;
-use log::{error, info};
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
 use tokio::time::sleep;
 
-use chrono::{DateTime, NaiveTime};
-use std::time::{Duration, SystemTime};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::block::models::Block;
+use crate::history::models::DailyAddressBalance;
 use crate::indexer::config::AppConfig;
-use crate::indexer::flamingo::client::FlamingoClient;
-use crate::indexer::flamingo::models::FlamingoPrice;
+use crate::indexer::models::{
+    BlockMismatch, HeightRange, OrphanRows, PriceBackfill, RebuildReport, RebuiltTable, RepairList,
+    VerifyReport,
+};
+use crate::indexer::prices::oracle::PriceOracle;
+use crate::indexer::prices::{PriceSample, TokenPrice};
 use crate::indexer::rpc::client::Client;
-use crate::indexer::rpc::database::Database;
-use crate::indexer::rpc::models::{BlockResult, TransactionResult};
+use crate::indexer::rpc::database::TokenMetadata;
+use crate::indexer::rpc::models::{BlockResult, Contract, TokenActivity, TransactionResult};
+use crate::indexer::rpc::storage::Storage;
+use crate::indexer::rpc::subscription::{Subscription, SubscriptionEvent};
 use crate::indexer::utils::{conversion, logger};
+use crate::live::internals as live_internals;
+use crate::live::models::LiveMessage;
+use crate::shared::shutdown;
+use crate::transaction::models::{Notification, Transaction};
+
+// blocks whose transactions are loaded at once while rebuilding derived tables
+const REBUILD_CHUNK: u64 = 10000;
+
+// tables computed from the stored chain data alone, in the order they are rebuilt
+const DERIVED_TABLES: [&str; 6] = [
+    "contracts",
+    "address_first_seen",
+    "daily_network_stats",
+    "daily_contract_usage",
+    "daily_address_balances",
+    "address_balances",
+];
+
+// polling rounds between attempts to restore the node subscription
+const SUBSCRIPTION_RETRY_POLLS: u64 = 12;
+
+// missing daily prices as of the last catch-up, sampled batch or backfill. counting them walks
+// every day of the chain, too slow to repeat on each status request
+static PRICE_GAPS: Lazy<Mutex<Option<usize>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn price_gaps() -> Option<usize> {
+    *PRICE_GAPS.lock().unwrap()
+}
 
 pub struct Indexer<'a> {
     client: Client,
-    db: Database<'a>,
+    db: Box<dyn Storage + 'a>,
     config: AppConfig,
+    prices: PriceOracle,
+    // tokens whose totalSupply failed or had no value, not asked again until the next run
+    unseedable: Mutex<HashSet<String>>,
 }
 
 impl<'a> Indexer<'a> {
-    pub fn new(client: Client, db: Database<'a>, config: AppConfig) -> Self {
-        Self { client, db, config }
+    pub fn new(
+        client: Client,
+        db: Box<dyn Storage + 'a>,
+        config: AppConfig,
+        prices: PriceOracle,
+    ) -> Self {
+        Self {
+            client,
+            db,
+            config,
+            prices,
+            unseedable: Mutex::new(HashSet::new()),
+        }
     }
 
     pub async fn run(&self) -> Result<(), anyhow::Error> {
         let current_height = self.client.get_current_height().await?;
-        let stored_height = self.db.get_last_index("blocks")?;
+        let stored_height = self.db.get_last_index("blocks").await?;
         info!("Chain height is {}.", current_height);
 
         // Ensure chain height isn't lower than stored height
@@ -54,11 +106,13 @@ impl<'a> Indexer<'a> {
             let new_stored_height = self
                 .db
                 .get_last_index("blocks")
+                .await
                 .context("Failed to get latest stored index")?;
             info!("Indexing completed in {} ms.", index_duration.as_millis());
             info!("New stored height is {}.", new_stored_height);
+            self.refresh_price_gaps().await;
 
-            if self.config.keep_alive {
+            if self.config.keep_alive && !shutdown::requested() {
                 self.continuous_sync(new_stored_height + 1, self.config.keep_alive_interval)
                     .await?;
             }
@@ -67,6 +121,332 @@ impl<'a> Indexer<'a> {
         }
     }
 
+    // batches only sample prices when they contain a block past the sample time, and a failing
+    // source leaves the day empty. this re-samples every missing day at its last block
+    pub async fn backfill_price_gaps(&self) -> Result<PriceBackfill, anyhow::Error> {
+        let gaps = self
+            .db
+            .find_price_gaps()
+            .await
+            .context("Failed to find price gaps")?;
+        info!("Found {} missing daily prices.", gaps.len());
+
+        let mut by_date: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+        let mut skipped_tokens = BTreeSet::new();
+        let mut skipped = 0;
+        for (date, token) in gaps.iter() {
+            if !self.prices.can_backfill(token) {
+                skipped_tokens.insert(token.as_str());
+                skipped += 1;
+                continue;
+            }
+            by_date
+                .entry(date.clone())
+                .or_default()
+                .insert(token.clone());
+        }
+
+        if !skipped_tokens.is_empty() {
+            warn!(
+                "Skipping {} missing prices of {} token(s) whose price sources can't look back: {}",
+                skipped,
+                skipped_tokens.len(),
+                skipped_tokens.into_iter().collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        let mut filled = 0;
+        for (date, tokens) in by_date {
+            let Some((block_index, timestamp)) = self.db.get_last_block_of_day(&date).await? else {
+                continue;
+            };
+
+            let prices: Vec<_> = self
+                .prices
+                .sample(PriceSample {
+                    block_index,
+                    timestamp,
+                })
+                .await
+                .into_iter()
+                .filter(|price| tokens.contains(&price.token_contract))
+                .collect();
+
+            filled += prices.len();
+            self.db
+                .persist_daily_token_price_history(prices)
+                .await
+                .context("Failed to insert daily token price history")?;
+
+            logger::inline_print(&format!("\rBackfilled prices up to {date}."));
+        }
+        println!();
+
+        let remaining = gaps.len() - filled;
+        *PRICE_GAPS.lock().unwrap() = Some(remaining);
+
+        Ok(PriceBackfill {
+            missing: gaps.len(),
+            filled,
+            skipped,
+            remaining,
+            finished_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
+        })
+    }
+
+    async fn refresh_price_gaps(&self) {
+        match self.db.find_price_gaps().await {
+            Ok(gaps) => *PRICE_GAPS.lock().unwrap() = Some(gaps.len()),
+            Err(err) => warn!("Failed to count price gaps: {err}"),
+        }
+    }
+
+    // recomputes the derived tables from the stored blocks, transactions and notifications,
+    // without asking the node. daily_token_stats is left alone, its mints and burns come from
+    // block executions that are never stored
+    pub async fn rebuild_derived(&self) -> Result<RebuildReport, anyhow::Error> {
+        let stored_height = self.db.get_last_index("blocks").await?;
+        let metadata = self.db.get_token_metadata().await?;
+
+        // readers keep seeing the old tables until the rebuild commits
+        self.db.begin_batch().await?;
+        let rebuilt = self.rebuild_tables(stored_height, &metadata).await;
+        let tables = self
+            .finish_batch(rebuilt)
+            .await
+            .context("Failed to commit rebuild")?;
+
+        Ok(RebuildReport {
+            blocks: self.db.count_rows("blocks").await?,
+            tables,
+            finished_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
+        })
+    }
+
+    async fn rebuild_tables(
+        &self,
+        stored_height: u64,
+        metadata: &HashMap<String, TokenMetadata>,
+    ) -> Result<Vec<RebuiltTable>, anyhow::Error> {
+        for table in DERIVED_TABLES {
+            self.db
+                .clear_table(table)
+                .await
+                .with_context(|| format!("Failed to clear {table}"))?;
+        }
+
+        let mut first = 0;
+        while first <= stored_height {
+            let last = std::cmp::min(first + REBUILD_CHUNK - 1, stored_height);
+            let transactions = self.db.load_transactions(first, last).await?;
+
+            let contracts = transactions
+                .iter()
+                .flat_map(|transaction| {
+                    conversion::convert_contract_result(
+                        transaction.script.clone(),
+                        transaction.notifications.clone(),
+                        transaction.block_index,
+                    )
+                })
+                .map(|mut contract| {
+                    if let Some((symbol, decimals)) = metadata.get(&contract.hash) {
+                        contract.symbol = symbol.clone();
+                        contract.decimals = *decimals;
+                    }
+                    contract
+                })
+                .collect();
+            self.db
+                .insert_contracts(contracts)
+                .await
+                .context("Failed to rebuild contracts")?;
+
+            // also fills address_first_seen, contracts of the range are already in
+            self.db
+                .update_daily_network_stats(&transactions)
+                .await
+                .context("Failed to rebuild daily network stats")?;
+
+            logger::inline_print(&format!("\rRebuilt up to block {last}."));
+            first = last + 1;
+        }
+        println!();
+
+        self.db
+            .rebuild_daily_contract_usage()
+            .await
+            .context("Failed to rebuild daily contract usage")?;
+        self.db
+            .rebuild_address_balances()
+            .await
+            .context("Failed to rebuild address balances")?;
+
+        let mut tables = Vec::new();
+        for table in DERIVED_TABLES {
+            tables.push(RebuiltTable {
+                table: table.to_string(),
+                rows: self.db.count_rows(table).await?,
+            });
+        }
+
+        Ok(tables)
+    }
+
+    // commits a batch opened with begin_batch, or rolls it back when one of its writes failed
+    async fn finish_batch<T>(&self, written: Result<T, anyhow::Error>) -> Result<T, anyhow::Error> {
+        match written {
+            Ok(value) => {
+                self.db.commit_batch().await?;
+                Ok(value)
+            }
+            Err(err) => {
+                self.db.rollback_batch().await?;
+                Err(err)
+            }
+        }
+    }
+
+    // checks the stored chain for holes and orphaned rows, then compares the given range
+    // and a random sample of blocks with the node
+    pub async fn verify(
+        &self,
+        sample: u64,
+        range: Option<(u64, u64)>,
+        repair: bool,
+    ) -> Result<VerifyReport, anyhow::Error> {
+        let stored_height = self.db.get_last_index("blocks").await?;
+        let missing_heights: Vec<HeightRange> = self
+            .db
+            .find_missing_heights()
+            .await
+            .context("Failed to find missing heights")?
+            .into_iter()
+            .map(|(from, to)| HeightRange { from, to })
+            .collect();
+        let orphans: Vec<OrphanRows> = self
+            .db
+            .count_orphans()
+            .await
+            .context("Failed to count orphaned rows")?
+            .into_iter()
+            .filter(|(_, rows)| *rows > 0)
+            .map(|(table, rows)| OrphanRows { table, rows })
+            .collect();
+
+        let mut indexes: BTreeSet<u64> = self
+            .db
+            .sample_block_indexes(sample)
+            .await?
+            .into_iter()
+            .collect();
+        if let Some((from, to)) = range {
+            indexes.extend(from..=to.min(stored_height));
+        }
+        let indexes: Vec<u64> = indexes.into_iter().collect();
+
+        let mut mismatches = Vec::new();
+        let mut checked = 0;
+        for chunk in indexes.chunks(self.config.batch_size as usize) {
+            let blocks = try_join_all(chunk.iter().map(|index| self.client.get_block(*index)))
+                .await
+                .context("Failed to fetch blocks from the node")?;
+
+            for block in blocks {
+                mismatches.extend(self.verify_block(&block).await?);
+            }
+            checked += chunk.len();
+            logger::inline_print(&format!("\rVerified {checked} block(s)."));
+        }
+        if checked > 0 {
+            println!();
+        }
+
+        let repair = repair.then(|| {
+            let mut reindex = missing_heights.clone();
+            reindex.extend(mismatches.iter().map(|mismatch| HeightRange {
+                from: mismatch.index,
+                to: mismatch.index,
+            }));
+            reindex.sort_by_key(|range| range.from);
+
+            // a mismatch inside or next to a hole joins it, so every height is listed once
+            let mut merged: Vec<HeightRange> = Vec::new();
+            for range in reindex {
+                match merged.last_mut() {
+                    Some(last) if range.from <= last.to + 1 => last.to = last.to.max(range.to),
+                    _ => merged.push(range),
+                }
+            }
+
+            RepairList {
+                reindex: merged,
+                delete_orphans: orphans.iter().map(|orphan| orphan.table.clone()).collect(),
+            }
+        });
+
+        Ok(VerifyReport {
+            consistent: missing_heights.is_empty() && orphans.is_empty() && mismatches.is_empty(),
+            stored_height,
+            missing_heights,
+            orphans,
+            checked_blocks: checked,
+            mismatches,
+            repair,
+            finished_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
+        })
+    }
+
+    async fn verify_block(&self, block: &BlockResult) -> Result<Vec<BlockMismatch>, anyhow::Error> {
+        let mut issues = Vec::new();
+
+        match self.db.get_block_hash(block.index).await? {
+            // holes are already reported as missing heights
+            None => return Ok(Vec::new()),
+            Some(hash) if hash != block.hash => issues.push(format!(
+                "stored hash {hash} differs from node hash {}",
+                block.hash
+            )),
+            Some(_) => {
+                let stored: Vec<String> = self.db.get_block_transaction_hashes(block.index).await?;
+                if stored.len() != block.tx.len() {
+                    issues.push(format!(
+                        "{} transactions stored, node has {}",
+                        stored.len(),
+                        block.tx.len()
+                    ));
+                } else if stored
+                    .iter()
+                    .zip(block.tx.iter())
+                    .any(|(hash, tx)| *hash != tx.hash)
+                {
+                    issues.push("stored transaction hashes differ from the node".to_string());
+                }
+            }
+        }
+
+        // the hash chain link to the previous stored block
+        if block.index > 0 {
+            if let Some(previous) = self.db.get_block_hash(block.index - 1).await? {
+                if previous != block.previousblockhash {
+                    issues.push(format!(
+                        "previous block hash {} does not match stored block {}",
+                        block.previousblockhash,
+                        block.index - 1
+                    ));
+                }
+            }
+        }
+
+        Ok(issues
+            .into_iter()
+            .map(|issue| BlockMismatch {
+                index: block.index,
+                issue,
+            })
+            .collect())
+    }
+
     async fn initial_sync(
         &self,
         mut start_height: u64,
@@ -76,6 +456,13 @@ impl<'a> Indexer<'a> {
         let mut count = 0;
         info!("Updating tables:");
         while start_height < current_height {
+            // batches are never interrupted, a shutdown takes effect between them
+            if shutdown::requested() {
+                println!();
+                info!("Shutdown requested, stopping before block {start_height}.");
+                return Ok(());
+            }
+
             let end_height = std::cmp::min(start_height + batch_size, current_height);
 
             self.sync_between(start_height, end_height)
@@ -91,7 +478,12 @@ impl<'a> Indexer<'a> {
         Ok(())
     }
 
-    async fn sync_between(&self, start_height: u64, end_height: u64) -> Result<(), anyhow::Error> {
+    // returns whether the batch sampled prices
+    async fn sync_between(
+        &self,
+        start_height: u64,
+        end_height: u64,
+    ) -> Result<bool, anyhow::Error> {
         let future_blocks = (start_height..end_height).map(|i| self.client.fetch_full_block(i));
         let all_blocks = join_all(future_blocks).await;
         let all_blocks_ref = &all_blocks;
@@ -145,44 +537,41 @@ impl<'a> Indexer<'a> {
         let all_transactions_with_index =
             all_transactions.into_iter().zip(block_indexes.into_iter());
 
-        let flamingo_min_block_number = 664000;
-        let time_threshold = NaiveTime::from_hms_opt(23, 59, 40).unwrap();
+        let sample_blocks: Vec<PriceSample> = all_blocks_ref
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|(block_result, _)| PriceSample {
+                block_index: block_result.index,
+                timestamp: block_result.time,
+            })
+            .filter(|sample| self.prices.is_sample_block(sample.timestamp))
+            .collect();
 
-        let filtered_flamingo_blocks: Vec<&BlockResult> = all_blocks_ref
+        // mints and burns outside of transactions, taken before the blocks are consumed
+        let block_notifications: Vec<(u64, Vec<Notification>)> = all_blocks_ref
             .iter()
             .filter_map(|result| result.as_ref().ok())
-            .map(|(block_result, _)| block_result)
-            .filter(|block| {
-                if let Some(datetime) = DateTime::from_timestamp_millis(block.time as i64) {
-                    let block_time: NaiveTime = datetime.time();
-                    block.index > flamingo_min_block_number && block_time > time_threshold
-                } else {
-                    false
-                }
+            .map(|(block_result, app_log)| {
+                let notifications = app_log
+                    .executions
+                    .iter()
+                    .flat_map(|execution| execution.notifications.iter().cloned())
+                    .collect();
+                (block_result.time, notifications)
             })
             .collect();
 
-        let fclient = FlamingoClient::new(None);
-        let flamingo_prices: Vec<Vec<FlamingoPrice>> =
-            join_all(filtered_flamingo_blocks.iter().map(|block| {
-                let fclient_ref = &fclient;
-
-                async move {
-                    fclient_ref
-                        .get_prices_from_block(block.index)
-                        // .get_prices_from_block(664000)
-                        .await
-                        .unwrap_or_else(|_| vec![])
-                        .into_iter()
-                        .map(|mut price| {
-                            price.block_index = Some(block.index);
-                            price.timestamp = Some(block.time as i64);
-                            price
-                        })
-                        .collect()
-                }
-            }))
-            .await;
+        let live_blocks: Vec<(u64, String, u64)> = all_blocks_ref
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|(block_result, _)| {
+                (
+                    block_result.index,
+                    block_result.hash.clone(),
+                    block_result.time,
+                )
+            })
+            .collect();
 
         let prepped_blocks = all_blocks.into_iter().filter_map(|result| match result {
             Ok((b, a)) => Some(conversion::convert_block_result(b, &a)),
@@ -201,13 +590,41 @@ impl<'a> Indexer<'a> {
             })
             .collect();
 
-        let prepped_contracts = prepped_tx.iter().flat_map(|transaction| {
-            conversion::convert_contract_result(
-                transaction.script.clone(),
-                transaction.notifications.clone(),
-                transaction.block_index,
-            )
-        });
+        let prepped_contracts = join_all(
+            prepped_tx
+                .iter()
+                .flat_map(|transaction| {
+                    conversion::convert_contract_result(
+                        transaction.script.clone(),
+                        transaction.notifications.clone(),
+                        transaction.block_index,
+                    )
+                })
+                .map(|contract| conversion::convert_token_metadata(contract, &self.client)),
+        )
+        .await;
+
+        // sources that read prices off the chain only see transactions up to each sample block
+        let sampled = !sample_blocks.is_empty();
+        let mut token_prices = Vec::new();
+        let mut observed = 0;
+        for sample in sample_blocks {
+            let upto = prepped_tx[observed..]
+                .iter()
+                .take_while(|tx| tx.block_index <= sample.block_index)
+                .count();
+            self.prices.observe(&prepped_tx[observed..observed + upto]);
+            observed += upto;
+
+            token_prices.extend(self.prices.sample(sample).await);
+        }
+        self.prices.observe(&prepped_tx[observed..]);
+
+        let token_activity = conversion::convert_token_activity(&prepped_tx, &block_notifications);
+        let supply_seeds = self
+            .seed_token_supply(&token_activity, end_height - 1)
+            .await
+            .context("Failed to seed token supply")?;
 
         let prepped_daily_balances = try_join_all(prepped_tx.iter().map(|transaction| async {
             conversion::convert_address_result(
@@ -222,24 +639,148 @@ impl<'a> Indexer<'a> {
         .into_iter()
         .flatten();
 
-        // synced rollback point
+        // synced rollback point. the whole batch commits at once, so get_last_index("blocks")
+        // never reports a batch whose derived tables are missing. SQLite writes without
+        // yielding, so a batch cancelled on shutdown is dropped before its first write; Postgres
+        // rolls the open transaction back when the connection closes with the indexer
+        self.db.begin_batch().await?;
+        let written = self
+            .write_batch(
+                prepped_blocks.collect(),
+                &prepped_tx,
+                prepped_contracts,
+                &token_activity,
+                &supply_seeds,
+                prepped_daily_balances.collect(),
+                token_prices,
+                &live_blocks,
+            )
+            .await;
+        let messages = self
+            .finish_batch(written)
+            .await
+            .context("Failed to commit batch")?;
+        live_internals::publish(&messages);
+
+        Ok(sampled)
+    }
+
+    // the writes of one synced batch, returning the live messages to publish once it commits
+    #[allow(clippy::too_many_arguments)]
+    async fn write_batch(
+        &self,
+        blocks: Vec<Block>,
+        transactions: &[Transaction],
+        contracts: Vec<Contract>,
+        token_activity: &[TokenActivity],
+        supply_seeds: &HashMap<String, i128>,
+        daily_balances: Vec<DailyAddressBalance>,
+        token_prices: Vec<TokenPrice>,
+        live_blocks: &[(u64, String, u64)],
+    ) -> Result<Vec<LiveMessage>, anyhow::Error> {
         self.db
-            .insert_blocks_transactions(prepped_blocks, prepped_tx.iter().cloned())
+            .insert_blocks_transactions(blocks, transactions.to_vec())
+            .await
             .context("Failed to insert data")?;
 
         self.db
-            .insert_contracts(prepped_contracts)
+            .insert_contracts(contracts)
+            .await
             .context("Failed to insert contracts")?;
 
         self.db
-            .persist_daily_address_balances(prepped_daily_balances)
+            .update_daily_network_stats(transactions)
+            .await
+            .context("Failed to update daily network stats")?;
+
+        self.db
+            .update_daily_token_stats(token_activity, supply_seeds)
+            .await
+            .context("Failed to update daily token stats")?;
+
+        self.db
+            .persist_daily_address_balances(daily_balances)
+            .await
             .context("Failed to insert daily balances")?;
 
         self.db
-            .persist_daily_token_price_history(flamingo_prices)
+            .persist_daily_token_price_history(token_prices)
+            .await
             .context("Failed to insert daily token price history")?;
 
-        Ok(())
+        // messages are only built when someone is listening or has a webhook
+        let hooked = self.db.has_active_webhooks().await?;
+        let messages = if hooked || live_internals::has_listeners() {
+            LiveMessage::from_batch(live_blocks, transactions)
+        } else {
+            Vec::new()
+        };
+
+        if hooked {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+            self.db
+                .queue_webhook_deliveries(&messages, now)
+                .await
+                .context("Failed to queue webhook deliveries")?;
+        }
+
+        Ok(messages)
+    }
+
+    // supply before the batch of tokens without a running total yet: totalSupply at the last
+    // block of the batch, less the batch's own mints and burns. tokens that don't answer keep
+    // an unknown supply, and are not asked again while this indexer runs
+    async fn seed_token_supply(
+        &self,
+        activity: &[TokenActivity],
+        last_block: u64,
+    ) -> Result<HashMap<String, i128>, anyhow::Error> {
+        let tokens: BTreeSet<String> = activity
+            .iter()
+            .map(|day| day.token_contract.clone())
+            .collect();
+        let unseeded = self
+            .db
+            .tokens_without_supply(&tokens.into_iter().collect::<Vec<_>>())
+            .await?;
+
+        let mut seeds = HashMap::new();
+        for token in unseeded {
+            if self.unseedable.lock().unwrap().contains(&token) {
+                continue;
+            }
+
+            let total = match self
+                .client
+                .get_total_supply_of_historic(last_block, &token)
+                .await
+            {
+                Ok(execution) => execution
+                    .stack
+                    .first()
+                    .and_then(|entry| entry.value.as_ref())
+                    .and_then(|value| value.as_str())
+                    .and_then(|value| value.parse::<i128>().ok()),
+                Err(err) => {
+                    warn!("Failed to get the total supply of {token}: {err:?}");
+                    None
+                }
+            };
+            let Some(total) = total else {
+                info!("Leaving the supply of {token} unknown for this run.");
+                self.unseedable.lock().unwrap().insert(token);
+                continue;
+            };
+
+            let change: i128 = activity
+                .iter()
+                .filter(|day| day.token_contract == token)
+                .map(|day| day.minted - day.burned)
+                .sum();
+            seeds.insert(token, total - change);
+        }
+
+        Ok(seeds)
     }
 
     async fn continuous_sync(&self, start_height: u64, interval: u64) -> Result<(), anyhow::Error> {
@@ -247,14 +788,284 @@ impl<'a> Indexer<'a> {
 
         info!("Listening for new blocks:");
         loop {
-            let new_height = self.client.get_current_height().await?;
-            if new_height > current_height {
-                self.sync_between(current_height, new_height).await?;
+            if let Some(ws_url) = self.client.ws_url() {
+                // returns between batches once shutdown is requested, never midway through one
+                let followed = self.follow_subscription(ws_url, &mut current_height).await;
+                if shutdown::requested() {
+                    return Ok(());
+                }
+                match followed {
+                    Ok(()) => info!("Node subscription closed, polling until it reconnects"),
+                    Err(err) => {
+                        error!("Node subscription failed, polling until it reconnects: {err}")
+                    }
+                }
+                self.client.clear_executions();
+            }
 
-                logger::inline_print(&format!("\rCurrent synced height: {new_height}"));
-                current_height = new_height;
+            // without a subscription endpoint polling never gives way
+            let polls = match self.client.ws_url() {
+                Some(_) => SUBSCRIPTION_RETRY_POLLS,
+                None => u64::MAX,
+            };
+            for _ in 0..polls {
+                self.poll_height(&mut current_height).await?;
+                tokio::select! {
+                    _ = sleep(Duration::from_secs(interval)) => {}
+                    _ = shutdown::wait() => return Ok(()),
+                }
             }
-            sleep(Duration::from_secs(interval)).await;
         }
     }
+
+    async fn poll_height(&self, current_height: &mut u64) -> Result<(), anyhow::Error> {
+        let new_height = self.client.get_current_height().await?;
+        if new_height > *current_height {
+            if self.sync_between(*current_height, new_height).await? {
+                self.refresh_price_gaps().await;
+            }
+
+            logger::inline_print(&format!("\rCurrent synced height: {new_height}"));
+            *current_height = new_height;
+        }
+        Ok(())
+    }
+
+    // Syncs on every block_added, reusing the executions pushed ahead of it so only the
+    // blocks themselves are fetched over RPC
+    async fn follow_subscription(
+        &self,
+        ws_url: &str,
+        current_height: &mut u64,
+    ) -> Result<(), anyhow::Error> {
+        let mut subscription = tokio::select! {
+            subscription = Subscription::connect(ws_url) => subscription?,
+            _ = shutdown::wait() => return Ok(()),
+        };
+        info!("Subscribed to node events at {ws_url}");
+
+        // blocks persisted while the subscription was being set up
+        self.poll_height(current_height).await?;
+
+        // executions received before the first block_added may belong to a block
+        // whose earlier executions were missed, so they are not cached
+        let mut caching = false;
+        loop {
+            // giving up the wait for an event loses nothing, unlike a batch being synced
+            let event = tokio::select! {
+                event = subscription.next() => event?,
+                _ = shutdown::wait() => return Ok(()),
+            };
+            let Some(event) = event else {
+                break;
+            };
+            match event {
+                SubscriptionEvent::Executed(container, execution) => {
+                    if caching {
+                        self.client.cache_execution(container, execution);
+                    }
+                }
+                SubscriptionEvent::BlockAdded(index) => {
+                    if index >= *current_height {
+                        let new_height = index + 1;
+                        if self.sync_between(*current_height, new_height).await? {
+                            self.refresh_price_gaps().await;
+                        }
+
+                        logger::inline_print(&format!("\rCurrent synced height: {new_height}"));
+                        *current_height = new_height;
+                    }
+                    self.client.clear_executions();
+                    caching = true;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_rebuild_matches_live_tables() {
+    use crate::indexer::controller::create_tables;
+    use crate::indexer::rpc::database::{memory_connection, Database};
+    use crate::shared::config::PriceConfig;
+    use crate::shared::neo;
+    use crate::transaction::models::{State, StateValue};
+
+    const DAY: u64 = 86_400_000;
+    const START: u64 = 1_704_067_200_000; // 2024-01-01
+    const TOKEN: &str = "0xd2a4cff31913016155e38e474a2c06d08be276cf";
+    // base64 of 20 byte script hashes, as the node reports them in notifications
+    const ALICE: &str = "AQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+    const BOB: &str = "AgICAgICAgICAgICAgICAgICAgI=";
+
+    let conn = memory_connection();
+    create_tables(&conn).unwrap();
+
+    let block = |index: u64, time: u64| Block {
+        index,
+        hash: format!("0x{index:064x}"),
+        size: 0,
+        version: 0,
+        merkle_root: String::new(),
+        time,
+        nonce: String::new(),
+        speaker: 0,
+        next_consensus: String::new(),
+        reward: 0.5,
+        reward_receiver: String::new(),
+        witnesses: Vec::new(),
+    };
+    let byte_string = |value: &str| StateValue {
+        _type: "ByteString".to_string(),
+        value: Some(serde_json::json!(value)),
+    };
+    let transfer = |from: &str, to: &str, amount: u64| Notification {
+        id: None,
+        contract: TOKEN.to_string(),
+        eventname: "Transfer".to_string(),
+        state: State {
+            _type: "Array".to_string(),
+            value: vec![
+                byte_string(from),
+                byte_string(to),
+                StateValue {
+                    _type: "Integer".to_string(),
+                    value: Some(serde_json::json!(amount.to_string())),
+                },
+            ],
+        },
+    };
+    let deploy = Notification {
+        id: None,
+        contract: "0xfffdc93764dbaddd97c48f252a53ea4643faa3fd".to_string(),
+        eventname: "Deploy".to_string(),
+        state: State {
+            _type: "Array".to_string(),
+            value: vec![byte_string(BOB)],
+        },
+    };
+    let transaction = |hash: &str, block: &Block, notifications: Vec<Notification>| Transaction {
+        index: 0,
+        hash: hash.to_string(),
+        block_index: block.index,
+        timestamp: block.time,
+        vm_state: "HALT".to_string(),
+        size: 0,
+        version: 0,
+        nonce: 0,
+        sender: neo::base64_to_address(ALICE),
+        sysfee: "100".to_string(),
+        netfee: "10".to_string(),
+        valid_until: 0,
+        signers: Vec::new(),
+        script: String::new(),
+        witnesses: Vec::new(),
+        stack_result: "[]".to_string(),
+        notifications,
+    };
+    let balance = |block: &Block, holder: &str, amount: i64| DailyAddressBalance {
+        block_index: block.index,
+        date: String::new(),
+        timestamp: block.time,
+        address: neo::base64_to_address(holder),
+        token_contract: TOKEN.to_string(),
+        balance: amount,
+    };
+
+    // the indexer starts after genesis, so blocks.id is the height from 1
+    let blocks = vec![block(1, START), block(2, START + 10), block(3, START + DAY)];
+    let transactions = vec![
+        transaction("0xaa", &blocks[1], vec![deploy, transfer(ALICE, BOB, 100)]),
+        transaction("0xbb", &blocks[1], vec![transfer(BOB, ALICE, 30)]),
+        transaction("0xcc", &blocks[2], vec![transfer(ALICE, BOB, 5)]),
+    ];
+    let balances = vec![
+        balance(&blocks[1], ALICE, 900),
+        balance(&blocks[1], BOB, 100),
+        balance(&blocks[1], ALICE, 930),
+        balance(&blocks[1], BOB, 70),
+        balance(&blocks[2], ALICE, 925),
+        balance(&blocks[2], BOB, 75),
+    ];
+
+    // (table, columns) of every derived table, rows in a stable order
+    let dump =
+        || -> Vec<Vec<String>> {
+            [
+            ("contracts", "block_index, hash, contract_type, manifest, symbol, decimals"),
+            ("address_first_seen", "address, block_index, date"),
+            (
+                "daily_network_stats",
+                "date, active_senders, new_addresses, transactions, transfers, fees, new_contracts",
+            ),
+            ("daily_contract_usage", "date, contract, usage"),
+            (
+                "daily_address_balances",
+                "date, address, token_contract, balance, block_index",
+            ),
+            ("address_balances", "address, token_contract, balance, block_index"),
+        ]
+        .iter()
+        .map(|(table, columns)| {
+            let mut stmt = conn
+                .prepare(&format!("SELECT {columns} FROM {table} ORDER BY {columns}"))
+                .unwrap();
+            let width = stmt.column_count();
+            stmt.query_map([], |row| {
+                (0..width)
+                    .map(|i| Ok(format!("{:?}", row.get::<_, rusqlite::types::Value>(i)?)))
+                    .collect::<rusqlite::Result<String>>()
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .unwrap()
+        })
+        .collect()
+        };
+
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        let indexer = Indexer::new(
+            Client::new(),
+            Box::new(Database::new(&conn).unwrap()),
+            AppConfig::new(),
+            PriceOracle::from_config(&PriceConfig::default()).unwrap(),
+        );
+
+        // the writes of a synced batch
+        let contracts = transactions
+            .iter()
+            .flat_map(|tx| {
+                conversion::convert_contract_result(
+                    tx.script.clone(),
+                    tx.notifications.clone(),
+                    tx.block_index,
+                )
+            })
+            .collect();
+        indexer
+            .db
+            .insert_blocks_transactions(blocks, transactions.clone())
+            .await
+            .unwrap();
+        indexer.db.insert_contracts(contracts).await.unwrap();
+        indexer
+            .db
+            .update_daily_network_stats(&transactions)
+            .await
+            .unwrap();
+        indexer
+            .db
+            .persist_daily_address_balances(balances)
+            .await
+            .unwrap();
+        let live = dump();
+        assert!(live.iter().all(|rows| !rows.is_empty()));
+        // one daily_network_stats row per day of the fixture
+        assert_eq!(live[2].len(), 2);
+
+        let report = indexer.rebuild_derived().await.unwrap();
+        assert_eq!(report.blocks, 3);
+        assert_eq!(dump(), live);
+    });
 }
@@ -1,66 +1,198 @@
-use log::warn;
-use tokio::io::{AsyncBufReadExt, BufReader, Lines};
-use tokio::process::{ChildStderr, Command};
-use tokio::sync::oneshot;
-use tokio::task::JoinHandle;
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::time::{sleep, timeout};
 
-use crate::indexer::utils::{logger, node};
+use crate::indexer::config::AppConfig;
+use crate::indexer::models::NodeStatus;
+use crate::indexer::utils::node;
+use crate::shared::config::NodeConfig;
+use crate::shared::shutdown;
 use regex::Regex;
 use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
 
-pub async fn run_node(
-    max_height: u64,
-) -> Result<
-    (
-        Lines<BufReader<ChildStderr>>,
-        JoinHandle<()>,
-        oneshot::Sender<()>,
-    ),
-    anyhow::Error,
-> {
+const STOP_TIMEOUT: u64 = 30; // seconds NeoGo gets to close its chain store before being killed
+
+// None unless the node is managed by this process
+static NODE_STATUS: Lazy<Mutex<Option<NodeStatus>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn node_status() -> Option<NodeStatus> {
+    NODE_STATUS.lock().unwrap().clone()
+}
+
+// An unmanaged node is the operator's concern, so it always counts as ready
+pub fn node_ready() -> bool {
+    match node_status() {
+        Some(status) => status.synchronized,
+        None => true,
+    }
+}
+
+fn update_status(update: impl FnOnce(&mut NodeStatus)) {
+    if let Some(status) = NODE_STATUS.lock().unwrap().as_mut() {
+        update(status);
+    }
+}
+
+pub fn run_node(config: &NodeConfig) -> Result<Child, anyhow::Error> {
     let re = Regex::new(r#""headerHeight": (\d+),"#).unwrap();
-    let mut cmd = Command::new(node::NEOGO_PATH);
-    cmd.stderr(Stdio::piped());
 
-    let mut node = cmd
-        .args(["node", "-m"])
-        .spawn()
-        .expect("Failed to run node");
+    let mut node = Command::new(&config.path)
+        .args(&config.args)
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
 
     let stderr = node.stderr.take().expect("No stderr for node");
-    let mut stderr_reader = BufReader::new(stderr).lines();
+    tokio::spawn(async move {
+        let mut stderr_reader = BufReader::new(stderr).lines();
 
-    while let Some(line) = stderr_reader.next_line().await.unwrap_or_default() {
-        if line.contains("headerHeight") {
-            if let Some(caps) = re.captures(&line) {
-                let height = caps.get(1).unwrap().as_str().parse::<u64>().unwrap();
-                logger::inline_print(&format!("\rCurrent height: {height}"));
-
-                if max_height != 0 && height >= max_height {
-                    warn!("Exceeded target height.");
-                    break;
+        while let Some(line) = stderr_reader.next_line().await.unwrap_or_default() {
+            if line.contains("headerHeight") {
+                if let Some(caps) = re.captures(&line) {
+                    let height = caps.get(1).unwrap().as_str().parse::<u64>().unwrap();
+                    update_status(|status| status.header_height = height);
                 }
             }
-        } else {
-            // println!("{}", line); // for debugging
+
+            if line.contains("synchronized") {
+                info!("NeoGo synchronized.");
+                update_status(|status| status.synchronized = true);
+            }
         }
+    });
+
+    Ok(node)
+}
 
-        if line.contains("synchronized") {
-            println!();
-            break;
+// Keeps a NeoGo node running until shutdown is signalled, restarting it whenever it exits
+pub async fn supervise_node(config: NodeConfig) -> Result<(), anyhow::Error> {
+    // reported as not synchronized from the start so nothing indexes during the download
+    *NODE_STATUS.lock().unwrap() = Some(NodeStatus::default());
+    node::check_neogo(&AppConfig::new(), &config).await?;
+
+    loop {
+        let mut node = run_node(&config)?;
+        update_status(|status| status.running = true);
+        info!("NeoGo started.");
+
+        tokio::select! {
+            exit = node.wait() => {
+                update_status(|status| {
+                    status.running = false;
+                    status.synchronized = false;
+                    status.restarts += 1;
+                });
+                error!(
+                    "NeoGo exited ({}), restarting in {}s.",
+                    exit.map(|code| code.to_string()).unwrap_or_else(|err| err.to_string()),
+                    config.restart_delay
+                );
+
+                tokio::select! {
+                    _ = sleep(Duration::from_secs(config.restart_delay)) => {}
+                    _ = shutdown::wait() => return Ok(()),
+                }
+            }
+            _ = shutdown::wait() => {
+                stop_node(&mut node).await?;
+                update_status(|status| {
+                    status.running = false;
+                    status.synchronized = false;
+                });
+                return Ok(());
+            }
         }
     }
+}
 
-    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+async fn stop_node(node: &mut Child) -> Result<(), anyhow::Error> {
+    warn!("Shutdown signal received.");
 
-    let handle = {
-        tokio::spawn(async move {
-            let _ = shutdown_rx.await;
-            warn!("Shutdown signal received.");
-            let _ = node.kill().await;
-            warn!("Node killed.");
-        })
+    // ask for a clean exit first so the chain store is flushed
+    #[cfg(unix)]
+    if let Some(pid) = node.id() {
+        let _ = std::process::Command::new("kill")
+            .arg("-TERM")
+            .arg(pid.to_string())
+            .status();
+    }
+
+    if timeout(Duration::from_secs(STOP_TIMEOUT), node.wait())
+        .await
+        .is_err()
+    {
+        node.kill().await?;
+    }
+    warn!("Node stopped.");
+
+    Ok(())
+}
+
+// a stand-in for NeoGo that reports its version, then logs progress and crashes on every run
+#[cfg(unix)]
+#[test]
+fn test_supervise_node_restarts() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("shrike-node-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("neogo");
+    std::fs::write(
+        &path,
+        "#!/bin/sh
+if [ \"$1\" = \"-v\" ]; then echo 'Version: 0.106.3 (linux/amd64)'; exit 0; fi
+echo '{\"headerHeight\": 42, \"height\": 7}' >&2
+exit 1
+",
+    )
+    .unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let config = NodeConfig {
+        managed: true,
+        version: Some("v0.106.3".to_string()),
+        path: path.to_string_lossy().to_string(),
+        sha256: None,
+        args: Vec::new(),
+        restart_delay: 0,
     };
 
-    Ok((stderr_reader, handle, shutdown_tx))
+    tokio::runtime::Runtime::new().unwrap().block_on(async {
+        // an installed binary of another version is refused rather than run
+        let other = NodeConfig {
+            version: Some("v0.99.0".to_string()),
+            ..config.clone()
+        };
+        assert!(node::check_neogo(&AppConfig::new(), &other).await.is_err());
+
+        let supervisor = tokio::spawn(supervise_node(config));
+        let status = timeout(Duration::from_secs(10), async {
+            loop {
+                match node_status() {
+                    Some(status) if status.restarts >= 2 && status.header_height == 42 => {
+                        return status
+                    }
+                    _ => sleep(Duration::from_millis(20)).await,
+                }
+            }
+        })
+        .await
+        .expect("the node was not restarted");
+        supervisor.abort();
+
+        // never synchronized, so nothing may index against it
+        assert!(!status.synchronized);
+        assert!(!node_ready());
+    });
+
+    // an unmanaged node is always ready
+    *NODE_STATUS.lock().unwrap() = None;
+    assert!(node_ready());
+
+    let _ = std::fs::remove_dir_all(&dir);
 }
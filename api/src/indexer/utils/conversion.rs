@@ -1,5 +1,12 @@
 use crate::indexer::rpc::client::Client;
 
+use chrono::DateTime;
+use log::warn;
+use std::collections::BTreeMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
 use crate::shared::neo::{
     address_to_hash160, base64_to_address, base64_to_hex, base64_to_script_hash, hex_decode,
     hex_to_base64, neo3_disassemble,
@@ -9,9 +16,10 @@ use serde_json::to_string;
 use crate::block::models::Block;
 use crate::history::models::DailyAddressBalance;
 use crate::indexer::rpc::models::{
-    BlockAppLogResult, BlockResult, ClientError, Contract, TransactionAppLogResult,
+    BlockAppLogResult, BlockResult, ClientError, Contract, TokenActivity, TransactionAppLogResult,
     TransactionResult,
 };
+use crate::shared::events::parse_transfer;
 use crate::transaction::models::{Notification, Transaction};
 
 pub fn convert_block_result(r: BlockResult, a: &BlockAppLogResult) -> Block {
@@ -80,28 +88,29 @@ pub fn convert_contract_result(
     let mut contracts = Vec::new();
 
     for notification in notifications {
-        if notification.eventname == "Deploy"
+        if (notification.eventname == "Deploy" || notification.eventname == "Update")
             && notification.contract == "0xfffdc93764dbaddd97c48f252a53ea4643faa3fd"
         {
             let full_disassembled_script = neo3_disassemble(&hex_to_base64(&script));
             let disassembled_script: Vec<&str> = full_disassembled_script.split("\n").collect();
 
             let mut contract_supported_standard: String = "[]".to_string();
+            let mut manifest: Option<String> = None;
 
-            if let Some(data) = disassembled_script
+            // the manifest is the first pushed data item that decodes to a JSON object
+            if let Some(metadata_json) = disassembled_script
                 .iter()
-                .find(|&s| s.contains("PUSHDATA2"))
+                .filter(|&s| s.starts_with("PUSHDATA"))
+                .filter_map(|data| {
+                    let parts: Vec<&str> = data.split_whitespace().collect();
+                    let metadata_hex_decoded = hex_decode(parts.get(1).unwrap_or(&""));
+                    String::from_utf8(metadata_hex_decoded).ok()
+                })
+                .filter(|metadata| metadata.starts_with('{'))
+                .find_map(|metadata| serde_json::from_str::<serde_json::Value>(&metadata).ok())
             {
-                let parts: Vec<&str> = data.split_whitespace().collect();
-                let metadata_hex = parts.get(1).unwrap_or(&"");
-                let metadata_hex_decoded = hex_decode(metadata_hex);
-                let metadata = String::from_utf8(metadata_hex_decoded).unwrap();
-
-                if metadata.starts_with("{") {
-                    let metadata_json: serde_json::Value = serde_json::from_str(&metadata).unwrap();
-
-                    contract_supported_standard = metadata_json["supportedstandards"].to_string();
-                }
+                contract_supported_standard = metadata_json["supportedstandards"].to_string();
+                manifest = Some(metadata_json.to_string());
             }
 
             let contract_hash_base64 = notification.state.value[0]
@@ -117,6 +126,9 @@ pub fn convert_contract_result(
                 block_index: block_height,
                 hash: contract_script_hash,
                 contract_type: contract_supported_standard,
+                manifest,
+                symbol: None,
+                decimals: None,
             });
         }
     }
@@ -124,6 +136,48 @@ pub fn convert_contract_result(
     return contracts;
 }
 
+// symbol and decimals are only exposed through contract calls, so NEP-17 tokens are asked once at deploy.
+// a call that fails leaves its field empty rather than failing the batch the deploy is in
+pub async fn convert_token_metadata(mut contract: Contract, client: &Client) -> Contract {
+    if !contract.contract_type.contains("NEP-17") {
+        return contract;
+    }
+
+    contract.symbol = match client
+        .get_symbol_of_historic(contract.block_index, &contract.hash)
+        .await
+    {
+        Ok(symbol_response) => symbol_response
+            .stack
+            .first()
+            .and_then(|entry| entry.value.as_ref())
+            .and_then(|val| val.as_str())
+            .and_then(|s| STANDARD.decode(s).ok())
+            .and_then(|bytes| String::from_utf8(bytes).ok()),
+        Err(err) => {
+            warn!("Failed to get the symbol of {}: {:?}", contract.hash, err);
+            None
+        }
+    };
+
+    contract.decimals = match client
+        .get_decimals_of_historic(contract.block_index, &contract.hash)
+        .await
+    {
+        Ok(decimals_response) => decimals_response
+            .stack
+            .first()
+            .and_then(|entry| entry.value.as_ref())
+            .and_then(|val| val.as_str().and_then(|s| s.parse::<u8>().ok())),
+        Err(err) => {
+            warn!("Failed to get the decimals of {}: {:?}", contract.hash, err);
+            None
+        }
+    };
+
+    contract
+}
+
 pub async fn convert_address_result(
     notifications: Vec<Notification>,
     block_height: u64,
@@ -211,3 +265,60 @@ pub async fn convert_address_result(
 
     Ok(addresses)
 }
+
+// groups transfers by day and token. block executions only add to the mints and burns,
+// that is where GAS is minted to the validators and where fees are burned
+pub fn convert_token_activity(
+    transactions: &[Transaction],
+    block_notifications: &[(u64, Vec<Notification>)],
+) -> Vec<TokenActivity> {
+    let mut activity: BTreeMap<(String, String), TokenActivity> = BTreeMap::new();
+
+    let from_transactions = transactions.iter().flat_map(|transaction| {
+        transaction
+            .notifications
+            .iter()
+            .map(move |notification| (transaction.timestamp, notification, true))
+    });
+    let from_blocks = block_notifications
+        .iter()
+        .flat_map(|(timestamp, notifications)| {
+            notifications
+                .iter()
+                .map(move |notification| (*timestamp, notification, false))
+        });
+
+    for (timestamp, notification, counted) in from_transactions.chain(from_blocks) {
+        let Some((token, from, to, qty)) = parse_transfer(notification) else {
+            continue;
+        };
+        let Ok(amount) = qty.parse::<i128>() else {
+            continue;
+        };
+        let Some(datetime) = DateTime::from_timestamp_millis(timestamp as i64) else {
+            continue;
+        };
+        let date = datetime.format("%Y-%m-%d").to_string();
+
+        let entry = activity
+            .entry((date.clone(), token.clone()))
+            .or_insert_with(|| TokenActivity {
+                date,
+                token_contract: token,
+                ..Default::default()
+            });
+
+        if counted {
+            entry.transfers += 1;
+            entry.volume += amount;
+        }
+        if from == "null" {
+            entry.minted += amount;
+        }
+        if to == "null" {
+            entry.burned += amount;
+        }
+    }
+
+    activity.into_values().collect()
+}
@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod tests {
-    use crate::indexer::utils::conversion::{convert_address_result, convert_contract_result};
+    use crate::indexer::utils::conversion::{
+        convert_address_result, convert_contract_result, convert_token_activity,
+    };
     use crate::transaction::models::{Notification, State, StateValue};
     use serde_json::json;
 
@@ -40,6 +42,10 @@ mod tests {
         assert_eq!(contract.block_index, block_height);
         assert_eq!(contract.hash, "0xb776afb6ad0c11565e70f8ee1dd898da43e51be1");
         assert_eq!(contract.contract_type, "[]");
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(contract.manifest.as_ref().unwrap()).unwrap();
+        assert_eq!(manifest["name"], "CommitteeInfoContract");
     }
 
     #[test]
@@ -110,4 +116,62 @@ mod tests {
         // assert_eq!(recipient.address, "NWcHZ95TNzfVCfvK2AvY5xyEw6ur3oD3wL");
         // assert_eq!(recipient.balances, "{}");
     }
+
+    #[test]
+    fn test_convert_token_activity() {
+        let gas = "0xd2a4cff31913016155e38e474a2c06d08be276cf";
+        let transfer = |from: Option<&str>, to: Option<&str>, amount: &str| Notification {
+            id: None,
+            contract: gas.to_string(),
+            eventname: "Transfer".to_string(),
+            state: State {
+                _type: "Array".to_string(),
+                value: vec![
+                    match from {
+                        Some(from) => StateValue {
+                            _type: "ByteString".to_string(),
+                            value: Some(json!(from)),
+                        },
+                        None => StateValue {
+                            _type: "Any".to_string(),
+                            value: None,
+                        },
+                    },
+                    match to {
+                        Some(to) => StateValue {
+                            _type: "ByteString".to_string(),
+                            value: Some(json!(to)),
+                        },
+                        None => StateValue {
+                            _type: "Any".to_string(),
+                            value: None,
+                        },
+                    },
+                    StateValue {
+                        _type: "Integer".to_string(),
+                        value: Some(json!(amount)),
+                    },
+                ],
+            },
+        };
+
+        let account = "4RvlQ9qY2B3u+HBeVhEMrbavdrc=";
+        let day_one = 1704153600000; // 2024-01-02
+        let day_two = day_one + 86400000;
+        let blocks = vec![
+            (day_one, vec![transfer(Some(account), None, "30")]),
+            (day_one, vec![transfer(None, Some(account), "50000000")]),
+            (day_two, vec![transfer(None, Some(account), "50000000")]),
+        ];
+
+        let activity = convert_token_activity(&[], &blocks);
+
+        assert_eq!(activity.len(), 2);
+        assert_eq!(activity[0].date, "2024-01-02");
+        assert_eq!(activity[0].minted, 50000000);
+        assert_eq!(activity[0].burned, 30);
+        assert_eq!(activity[0].transfers, 0); // block executions are not transfers
+        assert_eq!(activity[1].date, "2024-01-03");
+        assert_eq!(activity[1].burned, 0);
+    }
 }
@@ -1,11 +1,12 @@
-use log::{error, info, warn};
-use text_io::read;
-use tokio::{fs::File, io::AsyncWriteExt};
+use anyhow::{anyhow, Context};
+use log::{error, info};
+use sha2::{Digest, Sha256};
 
 use crate::indexer::config::AppConfig;
-use std::{env, path::Path, process::Command};
+use crate::shared::config::NodeConfig;
+use std::{fs, path::Path, process::Command};
 
-// move these to config in future
+// default binary location, overridden by node.path
 #[cfg(target_os = "linux")]
 pub static NEOGO_PATH: &str = "./neogo";
 #[cfg(target_os = "macos")]
@@ -13,84 +14,79 @@ pub static NEOGO_PATH: &str = "./neogo";
 #[cfg(target_os = "windows")]
 pub static NEOGO_PATH: &str = "./neogo.exe";
 
-fn get_neogo_release_notes(config: &AppConfig) -> String {
+fn get_neogo_release_notes(version: &str) -> String {
     #[cfg(target_os = "linux")]
     {
         format!(
             "https://github.com/nspcc-dev/neo-go/releases/tag/{}",
-            config.node_version
+            version
         )
     }
     #[cfg(target_os = "macos")]
     {
         format!(
             "https://github.com/nspcc-dev/neo-go/releases/tag/{}",
-            config.node_version
+            version
         )
     }
     #[cfg(target_os = "windows")]
     {
         format!(
             "https://github.com/nspcc-dev/neo-go/releases/tag/{}",
-            config.node_version
+            version
         )
     }
 }
 
-fn get_neogo_dl(config: &AppConfig) -> String {
+fn get_neogo_dl(version: &str) -> String {
     #[cfg(target_os = "linux")]
     {
         format!(
             "https://github.com/nspcc-dev/neo-go/releases/download/{}/neo-go-linux-amd64",
-            config.node_version
+            version
         )
     }
     #[cfg(target_os = "macos")]
     {
         format!(
             "https://github.com/nspcc-dev/neo-go/releases/download/{}/neo-go-darwin-arm64",
-            config.node_version
+            version
         )
     }
     #[cfg(target_os = "windows")]
     {
         format!(
             "https://github.com/nspcc-dev/neo-go/releases/download/{}/neo-go-windows-amd64.exe",
-            config.node_version
+            version
         )
     }
 }
 
-pub async fn check_neogo(config: &AppConfig) -> Result<(), anyhow::Error> {
-    let path = Path::new(NEOGO_PATH);
-    if !path.exists() {
-        warn!("NeoGo not found in directory. Install? (y/n)");
-        let answer: char = read!();
-        assert!((answer == 'y'), "User declined to install NeoGo.");
-
-        let mut file = File::create(path).await?;
-        let mut response = reqwest::get(&get_neogo_dl(config)).await.unwrap();
-        while let Some(chunk) = response.chunk().await.unwrap() {
-            file.write_all(&chunk).await?;
-        }
+fn neogo_version(config: &AppConfig, node: &NodeConfig) -> String {
+    node.version
+        .clone()
+        .unwrap_or_else(|| config.node_version.clone())
+}
 
-        if env::consts::OS != "windows" {
-            info!("Updating permissions..");
-            Command::new("chmod")
-                .arg("+x")
-                .arg(NEOGO_PATH)
-                .output()
-                .expect("failed to update permissions");
-        }
+pub async fn check_neogo(config: &AppConfig, node: &NodeConfig) -> Result<(), anyhow::Error> {
+    let path = Path::new(&node.path);
+    let expected_version = neogo_version(config, node);
+
+    if !path.exists() {
+        info!(
+            "NeoGo not found at {}, downloading {}..",
+            node.path, expected_version
+        );
+        download_neogo(&expected_version, node).await?;
+        info!("NeoGo {} installed.", expected_version);
     } else {
         info!("NeoGo already installed.");
-        let installed_version = check_neogo_version()?;
-        let expected_version = config.node_version.to_string();
+        let installed_version = check_neogo_version(path)?;
 
         if installed_version != expected_version {
-            error!("Incorrect NeoGo version detected. Remove {} and re-run to install the correct version.", NEOGO_PATH);
-            error!("Check the NeoGo version release notes at {} to see if chain state data is compatible.", get_neogo_release_notes(config));
-            return Err(anyhow::anyhow!(
+            error!("Incorrect NeoGo version detected. Remove {} and re-run to install the correct version.", node.path);
+            error!("Check the NeoGo version release notes at {} to see if chain state data is compatible.", get_neogo_release_notes(&expected_version));
+            return Err(anyhow!(
                 "NeoGo version mismatch. Expected {}, got {}.",
                 expected_version,
                 installed_version
@@ -100,14 +96,62 @@ pub async fn check_neogo(config: &AppConfig) -> Result<(), anyhow::Error> {
     Ok(())
 }
 
-pub fn check_neogo_version() -> Result<String, anyhow::Error> {
-    let command_output = Command::new(NEOGO_PATH)
+async fn download_neogo(version: &str, node: &NodeConfig) -> Result<(), anyhow::Error> {
+    let url = get_neogo_dl(version);
+    let binary = reqwest::get(&url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    let expected = match &node.sha256 {
+        Some(checksum) => checksum.clone(),
+        None => fetch_checksum(&url).await?,
+    };
+    let actual = hex::encode(Sha256::digest(&binary));
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(anyhow!(
+            "NeoGo checksum mismatch. Expected {}, got {}.",
+            expected,
+            actual
+        ));
+    }
+
+    // only a verified binary ever lands on the configured path
+    let partial = format!("{}.download", node.path);
+    fs::write(&partial, &binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&partial, fs::Permissions::from_mode(0o755))?;
+    }
+    fs::rename(&partial, &node.path)?;
+
+    Ok(())
+}
+
+// releases publish a "<checksum>  <file>" line next to every binary
+async fn fetch_checksum(binary_url: &str) -> Result<String, anyhow::Error> {
+    let body = reqwest::get(format!("{binary_url}.sha256"))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    body.split_whitespace()
+        .next()
+        .map(|checksum| checksum.to_string())
+        .ok_or_else(|| anyhow!("Empty NeoGo checksum file."))
+}
+
+pub fn check_neogo_version(path: &Path) -> Result<String, anyhow::Error> {
+    let command_output = Command::new(path)
         .arg("-v")
         .output()
-        .expect("Failed to execute version check");
+        .context("Failed to execute version check")?;
 
     if !command_output.status.success() {
-        return Err(anyhow::anyhow!("NeoGo version check failed."));
+        return Err(anyhow!("NeoGo version check failed."));
     }
 
     let mut lines = command_output.stdout.split(|b| *b == b'\n');
@@ -118,6 +162,6 @@ pub fn check_neogo_version() -> Result<String, anyhow::Error> {
         let version = format!("v{}", version.trim().split(' ').nth(1).unwrap());
         Ok(version)
     } else {
-        Err(anyhow::anyhow!("NeoGo version check failed."))
+        Err(anyhow!("NeoGo version check failed."))
     }
 }
@@ -0,0 +1,85 @@
+use actix_web::{get, web, HttpResponse};
+
+use crate::error::ApiError;
+use crate::shared::checker;
+use crate::shared::storage::ReadStorage;
+
+use super::models::{
+    HolderBoard, Leaderboard, LeaderboardParams, Window, LIMIT_DEFAULT, LIMIT_MAX,
+};
+
+fn normalize_limit(query_parameter: &LeaderboardParams) -> Result<u32, ApiError> {
+    match query_parameter.limit.unwrap_or(LIMIT_DEFAULT) {
+        0 => Err(ApiError::BadRequest(
+            "Limit must be greater than zero.".to_string(),
+        )),
+        limit => Ok(limit.min(LIMIT_MAX)),
+    }
+}
+
+#[get("/v1/leaderboard/holders/{token}")]
+async fn get_top_holders(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<String>,
+    query_parameter: web::Query<LeaderboardParams>,
+) -> Result<HttpResponse, ApiError> {
+    let token = path.into_inner();
+
+    if !checker::is_neo_script_hash(&token) {
+        return Err(ApiError::BadRequest("Invalid token hash.".to_string()));
+    }
+
+    let limit = normalize_limit(&query_parameter)?;
+
+    let holders = storage.get_top_holders(token.clone(), limit).await?;
+    let (symbol, decimals) = storage.get_token_info(&token).await?;
+
+    Ok(HttpResponse::Ok().json(HolderBoard {
+        token_contract: token,
+        symbol,
+        decimals,
+        list: holders,
+    }))
+}
+
+#[get("/v1/leaderboard/senders")]
+async fn get_top_senders(
+    storage: web::Data<dyn ReadStorage>,
+    query_parameter: web::Query<LeaderboardParams>,
+) -> Result<HttpResponse, ApiError> {
+    let window = Window::parse(query_parameter.window.as_deref())?;
+    let limit = normalize_limit(&query_parameter)?;
+
+    let since = storage.get_window_start(window).await?;
+    let senders = storage.get_top_senders(since, limit).await?;
+
+    Ok(HttpResponse::Ok().json(Leaderboard {
+        window: window.name().to_string(),
+        since,
+        list: senders,
+    }))
+}
+
+#[get("/v1/leaderboard/contracts")]
+async fn get_top_contracts(
+    storage: web::Data<dyn ReadStorage>,
+    query_parameter: web::Query<LeaderboardParams>,
+) -> Result<HttpResponse, ApiError> {
+    let window = Window::parse(query_parameter.window.as_deref())?;
+    let limit = normalize_limit(&query_parameter)?;
+
+    let since = storage.get_window_start(window).await?;
+    let contracts = storage.get_top_contracts(since, limit).await?;
+
+    Ok(HttpResponse::Ok().json(Leaderboard {
+        window: window.name().to_string(),
+        since,
+        list: contracts,
+    }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_top_holders)
+        .service(get_top_senders)
+        .service(get_top_contracts);
+}
@@ -0,0 +1,206 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+
+use crate::error::ApiError;
+use crate::leaderboard::models::{ContractEntry, Holder, SenderEntry, Window};
+use crate::shared::models::{GAS_PRECISION, NATIVE_TOKENS};
+
+// windows are measured back from the last indexed block, so a node still catching up
+// gets meaningful boards too
+pub fn get_window_start_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    window: Window,
+) -> Result<Option<u64>, ApiError> {
+    let Some(millis) = window.millis() else {
+        return Ok(None);
+    };
+
+    let sql = "SELECT MAX(time) FROM blocks";
+    let latest = conn.query_row(sql, [], |row| row.get::<_, Option<u64>>(0))?;
+
+    Ok(Some(latest.unwrap_or(0).saturating_sub(millis)))
+}
+
+pub fn get_top_holders_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: String,
+    limit: u32,
+) -> Result<Vec<Holder>, ApiError> {
+    let sql = "
+        SELECT address, balance, block_index
+        FROM address_balances
+        WHERE token_contract = ? AND balance > 0
+        ORDER BY balance DESC
+        LIMIT ?";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![token, limit])?;
+
+    let mut holders = Vec::new();
+    while let Some(row) = rows.next()? {
+        holders.push(Holder {
+            rank: holders.len() + 1,
+            address: row.get(0)?,
+            balance: row.get(1)?,
+            block_index: row.get(2)?,
+        });
+    }
+
+    if holders.is_empty() {
+        Err(ApiError::NotFound("No holders for that token.".to_string()))
+    } else {
+        Ok(holders)
+    }
+}
+
+pub fn get_top_senders_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    since: Option<u64>,
+    limit: u32,
+) -> Result<Vec<SenderEntry>, ApiError> {
+    let sql = "
+        SELECT sender, COUNT(*), SUM(CAST(sysfee AS INTEGER) + CAST(netfee AS INTEGER))
+        FROM transactions
+        WHERE block_index >= COALESCE((SELECT MIN(id) FROM blocks WHERE time >= ?), 0)
+        GROUP BY sender
+        ORDER BY COUNT(*) DESC
+        LIMIT ?";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![since.unwrap_or(0), limit])?;
+
+    let mut senders = Vec::new();
+    while let Some(row) = rows.next()? {
+        senders.push(SenderEntry {
+            rank: senders.len() + 1,
+            address: row.get(0)?,
+            transactions: row.get(1)?,
+            fees: row.get::<_, i64>(2)? as f64 / GAS_PRECISION,
+        });
+    }
+
+    if senders.is_empty() {
+        Err(ApiError::NotFound("No senders in that window.".to_string()))
+    } else {
+        Ok(senders)
+    }
+}
+
+// contract usage is only kept per day, so windows round down to whole days
+pub fn get_top_contracts_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    since: Option<u64>,
+    limit: u32,
+) -> Result<Vec<ContractEntry>, ApiError> {
+    let sql = "
+        SELECT u.contract, SUM(u.usage), c.symbol
+        FROM daily_contract_usage u
+        LEFT JOIN contracts c ON c.hash = u.contract
+        WHERE u.date >= strftime('%Y-%m-%d', ? / 1000, 'unixepoch')
+        GROUP BY u.contract
+        ORDER BY SUM(u.usage) DESC
+        LIMIT ?";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![since.unwrap_or(0), limit])?;
+
+    let mut contracts = Vec::new();
+    while let Some(row) = rows.next()? {
+        let contract: String = row.get(0)?;
+        let symbol = match NATIVE_TOKENS.iter().find(|(hash, _, _)| *hash == contract) {
+            Some((_, symbol, _)) => Some(symbol.to_string()),
+            None => row.get(2)?,
+        };
+
+        contracts.push(ContractEntry {
+            rank: contracts.len() + 1,
+            contract,
+            symbol,
+            usage: row.get(1)?,
+        });
+    }
+
+    if contracts.is_empty() {
+        Err(ApiError::NotFound(
+            "No contract usage in that window.".to_string(),
+        ))
+    } else {
+        Ok(contracts)
+    }
+}
+
+#[test]
+fn test_leaderboards() {
+    use crate::indexer::rpc::database::{insert_test_transaction, memory_chain};
+
+    const DAY: u64 = 86_400_000;
+    const START: u64 = 1_704_067_200_000; // 2024-01-01
+    const GAS: &str = "0xd2a4cff31913016155e38e474a2c06d08be276cf";
+    const TOKEN: &str = "0x0000000000000000000000000000000000000001";
+
+    // three days of blocks, the last 24h only hold block 3
+    let conn = memory_chain(&[START, START + DAY, START + 2 * DAY + 1]);
+    insert_test_transaction(&conn, "0xaa", 1, "alice", "100", "0");
+    insert_test_transaction(&conn, "0xbb", 2, "alice", "100", "0");
+    insert_test_transaction(&conn, "0xcc", 3, "bob", "50", "5");
+    conn.execute_batch(&format!(
+        "INSERT INTO address_balances (address, token_contract, balance, block_index)
+        VALUES ('alice', '{GAS}', 10, 1), ('bob', '{GAS}', 30, 3), ('carol', '{GAS}', 0, 3);
+        INSERT INTO contracts (block_index, hash, contract_type, symbol)
+        VALUES (1, '{TOKEN}', '[]', 'TKN');
+        INSERT INTO daily_contract_usage (date, contract, usage)
+        VALUES ('2024-01-01', '{TOKEN}', 5), ('2024-01-03', '{GAS}', 2);"
+    ))
+    .unwrap();
+
+    let holders = get_top_holders_internal(&conn, GAS.to_string(), 10).unwrap();
+    let holders: Vec<_> = holders
+        .iter()
+        .map(|h| (h.rank, h.address.as_str(), h.balance))
+        .collect();
+    // emptied balances are no holders
+    assert_eq!(holders, vec![(1, "bob", 30), (2, "alice", 10)]);
+    assert!(get_top_holders_internal(&conn, TOKEN.to_string(), 10).is_err());
+
+    assert_eq!(get_window_start_internal(&conn, Window::All).unwrap(), None);
+    let day = get_window_start_internal(&conn, Window::Day).unwrap();
+    assert_eq!(day, Some(START + DAY + 1));
+
+    let senders = |since| -> Vec<(String, u64)> {
+        get_top_senders_internal(&conn, since, 10)
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.address, s.transactions))
+            .collect()
+    };
+    assert_eq!(
+        senders(None),
+        vec![("alice".to_string(), 2), ("bob".to_string(), 1)]
+    );
+    assert_eq!(senders(day), vec![("bob".to_string(), 1)]);
+    assert_eq!(
+        get_top_senders_internal(&conn, day, 10).unwrap()[0].fees,
+        55.0 / GAS_PRECISION
+    );
+
+    let contracts = |since| -> Vec<(String, Option<String>, u64)> {
+        get_top_contracts_internal(&conn, since, 10)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.contract, c.symbol, c.usage))
+            .collect()
+    };
+    assert_eq!(
+        contracts(None),
+        vec![
+            (TOKEN.to_string(), Some("TKN".to_string()), 5),
+            (GAS.to_string(), Some("GAS".to_string()), 2)
+        ]
+    );
+    // the day window starts on 2024-01-02, so only usage from then on counts
+    assert_eq!(
+        contracts(day),
+        vec![(GAS.to_string(), Some("GAS".to_string()), 2)]
+    );
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::shared::models::{Address, Hash160};
+
+pub const LIMIT_DEFAULT: u32 = 10;
+pub const LIMIT_MAX: u32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Window {
+    Day,
+    Week,
+    Month,
+    All,
+}
+
+impl Window {
+    pub fn parse(window: Option<&str>) -> Result<Window, ApiError> {
+        match window {
+            Some("24h") => Ok(Window::Day),
+            Some("7d") => Ok(Window::Week),
+            Some("30d") => Ok(Window::Month),
+            None | Some("all") => Ok(Window::All),
+            Some(other) => Err(ApiError::BadRequest(format!(
+                "Invalid window parameter: {}",
+                other
+            ))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Window::Day => "24h",
+            Window::Week => "7d",
+            Window::Month => "30d",
+            Window::All => "all",
+        }
+    }
+
+    pub fn millis(&self) -> Option<u64> {
+        let hours = match self {
+            Window::Day => 24,
+            Window::Week => 7 * 24,
+            Window::Month => 30 * 24,
+            Window::All => return None,
+        };
+
+        Some(hours * 60 * 60 * 1000)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LeaderboardParams {
+    pub window: Option<String>, // "24h", "7d", "30d" or "all"
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Leaderboard<T> {
+    pub window: String,
+    pub since: Option<u64>, // window start as a block timestamp, measured back from the last block
+    pub list: Vec<T>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HolderBoard {
+    pub token_contract: Hash160,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub list: Vec<Holder>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Holder {
+    pub rank: usize,
+    pub address: Address,
+    pub balance: i64, // raw integer balance
+    pub block_index: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SenderEntry {
+    pub rank: usize,
+    pub address: Address,
+    pub transactions: u64,
+    pub fees: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ContractEntry {
+    pub rank: usize,
+    pub contract: Hash160,
+    pub symbol: Option<String>,
+    pub usage: u64, // summed daily_contract_usage over the window
+}
@@ -0,0 +1,125 @@
+use tokio_postgres::Client;
+
+use crate::error::ApiError;
+use crate::leaderboard::models::{ContractEntry, Holder, SenderEntry, Window};
+use crate::shared::models::{GAS_PRECISION, NATIVE_TOKENS};
+use crate::shared::postgres::{get_int, get_opt_int, to_i64};
+
+// windows are measured back from the last indexed block, so a node still catching up
+// gets meaningful boards too
+pub async fn get_window_start_internal(
+    client: &Client,
+    window: Window,
+) -> Result<Option<u64>, ApiError> {
+    let Some(millis) = window.millis() else {
+        return Ok(None);
+    };
+
+    let sql = "SELECT MAX(time) FROM blocks";
+    let latest: Option<u64> = get_opt_int(&client.query_one(sql, &[]).await?, 0)?;
+
+    Ok(Some(latest.unwrap_or(0).saturating_sub(millis)))
+}
+
+pub async fn get_top_holders_internal(
+    client: &Client,
+    token: String,
+    limit: u32,
+) -> Result<Vec<Holder>, ApiError> {
+    let sql = "
+        SELECT address, balance, block_index
+        FROM address_balances
+        WHERE token_contract = $1 AND balance > 0
+        ORDER BY balance DESC, id ASC
+        LIMIT $2";
+
+    let mut holders = Vec::new();
+    for row in client.query(sql, &[&token, &i64::from(limit)]).await? {
+        holders.push(Holder {
+            rank: holders.len() + 1,
+            address: row.try_get(0)?,
+            balance: row.try_get(1)?,
+            block_index: get_int(&row, 2)?,
+        });
+    }
+
+    if holders.is_empty() {
+        Err(ApiError::NotFound("No holders for that token.".to_string()))
+    } else {
+        Ok(holders)
+    }
+}
+
+pub async fn get_top_senders_internal(
+    client: &Client,
+    since: Option<u64>,
+    limit: u32,
+) -> Result<Vec<SenderEntry>, ApiError> {
+    let sql = "
+        SELECT sender, COUNT(*), SUM(sysfee::BIGINT + netfee::BIGINT)::BIGINT
+        FROM transactions
+        WHERE block_index >= COALESCE((SELECT MIN(id) FROM blocks WHERE time >= $1), 0)
+        GROUP BY sender
+        ORDER BY COUNT(*) DESC, sender ASC
+        LIMIT $2";
+
+    let since = to_i64(since.unwrap_or(0))?;
+
+    let mut senders = Vec::new();
+    for row in client.query(sql, &[&since, &i64::from(limit)]).await? {
+        senders.push(SenderEntry {
+            rank: senders.len() + 1,
+            address: row.try_get(0)?,
+            transactions: get_int(&row, 1)?,
+            fees: row.try_get::<_, i64>(2)? as f64 / GAS_PRECISION,
+        });
+    }
+
+    if senders.is_empty() {
+        Err(ApiError::NotFound("No senders in that window.".to_string()))
+    } else {
+        Ok(senders)
+    }
+}
+
+// contract usage is only kept per day, so windows round down to whole days
+pub async fn get_top_contracts_internal(
+    client: &Client,
+    since: Option<u64>,
+    limit: u32,
+) -> Result<Vec<ContractEntry>, ApiError> {
+    let sql = "
+        SELECT u.contract, SUM(u.usage)::BIGINT, MAX(c.symbol)
+        FROM daily_contract_usage u
+        LEFT JOIN contracts c ON c.hash = u.contract
+        WHERE u.date >= shrike_date($1)
+        GROUP BY u.contract
+        ORDER BY SUM(u.usage) DESC, u.contract ASC
+        LIMIT $2";
+
+    let since = to_i64(since.unwrap_or(0))?;
+
+    let mut contracts = Vec::new();
+    for row in client.query(sql, &[&since, &i64::from(limit)]).await? {
+        let contract: String = row.try_get(0)?;
+        let symbol = match NATIVE_TOKENS.iter().find(|(hash, _, _)| *hash == contract) {
+            Some((_, symbol, _)) => Some(symbol.to_string()),
+            None => row.try_get(2)?,
+        };
+
+        contracts.push(ContractEntry {
+            rank: contracts.len() + 1,
+            contract,
+            symbol,
+            usage: get_int(&row, 1)?,
+        });
+    }
+
+    if contracts.is_empty() {
+        Err(ApiError::NotFound(
+            "No contract usage in that window.".to_string(),
+        ))
+    } else {
+        Ok(contracts)
+    }
+}
@@ -0,0 +1,117 @@
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::{get, rt, web, HttpRequest, HttpResponse};
+use actix_ws::Message;
+use futures::StreamExt;
+use tokio::time;
+
+use std::time::Duration;
+
+use crate::error::ApiError;
+
+use super::internals;
+use super::models::{SubscribeParams, Subscription};
+
+// idle connections get a comment or ping this often, so proxies keep them open
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[get("/v1/live/sse")]
+async fn live_sse(query_parameter: web::Query<SubscribeParams>) -> Result<HttpResponse, ApiError> {
+    let subscription = Subscription::parse(&query_parameter)?;
+    let receiver = internals::subscribe();
+    let keep_alive = time::interval(KEEP_ALIVE);
+
+    let body = futures::stream::unfold(
+        (receiver, subscription, keep_alive),
+        |(mut receiver, subscription, mut keep_alive)| async move {
+            let chunk = tokio::select! {
+                message = internals::next_message(&mut receiver, &subscription) => {
+                    let message = message?;
+                    format!("event: {}\ndata: {}\n\n", message.topic(), message.data())
+                }
+                _ = keep_alive.tick() => ": keep-alive\n\n".to_string(),
+            };
+
+            Some((
+                Ok::<_, ApiError>(Bytes::from(chunk)),
+                (receiver, subscription, keep_alive),
+            ))
+        },
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(body))
+}
+
+// the query string sets the first subscription, a text frame shaped like it replaces it
+#[get("/v1/live/ws")]
+async fn live_ws(
+    req: HttpRequest,
+    body: web::Payload,
+    query_parameter: web::Query<SubscribeParams>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut subscription = Subscription::parse(&query_parameter)?;
+    let (response, mut session, mut stream) = actix_ws::handle(&req, body)?;
+    let mut receiver = internals::subscribe();
+    let mut keep_alive = time::interval(KEEP_ALIVE);
+
+    rt::spawn(async move {
+        loop {
+            tokio::select! {
+                message = internals::next_message(&mut receiver, &subscription) => {
+                    let Some(message) = message else { break };
+                    if session.text(message.to_json()).await.is_err() {
+                        return;
+                    }
+                }
+                frame = stream.next() => match frame {
+                    Some(Ok(Message::Text(text))) => {
+                        let parsed = serde_json::from_str::<SubscribeParams>(&text)
+                            .map_err(|err| ApiError::BadRequest(err.to_string()))
+                            .and_then(|params| Subscription::parse(&params));
+
+                        let reply = match parsed {
+                            Ok(parsed) => {
+                                subscription = parsed;
+                                serde_json::json!({ "topic": "subscribed" })
+                            }
+                            Err(err) => serde_json::json!({
+                                "topic": "error",
+                                "data": { "error": err.to_string(), "code": err.code() },
+                            }),
+                        };
+                        if session.text(reply.to_string()).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Close(reason))) => {
+                        let _ = session.close(reason).await;
+                        return;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) | None => break,
+                },
+                _ = keep_alive.tick() => {
+                    if session.ping(b"").await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(live_sse).service(live_ws);
+}
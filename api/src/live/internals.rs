@@ -0,0 +1,54 @@
+use once_cell::sync::Lazy;
+use tokio::sync::broadcast;
+
+use std::sync::Arc;
+
+use crate::live::models::{LiveMessage, Subscription};
+use crate::shared::shutdown;
+
+// messages kept for slow clients before they start missing some
+const LIVE_BUFFER: usize = 4096;
+
+pub static LIVE_FEED: Lazy<broadcast::Sender<Arc<LiveMessage>>> =
+    Lazy::new(|| broadcast::channel(LIVE_BUFFER).0);
+
+pub fn subscribe() -> broadcast::Receiver<Arc<LiveMessage>> {
+    LIVE_FEED.subscribe()
+}
+
+// lets the indexer skip building messages while nobody listens, which keeps the initial sync cheap
+pub fn has_listeners() -> bool {
+    LIVE_FEED.receiver_count() > 0
+}
+
+// called by the indexer once a batch is committed, so clients never see rolled back data
+pub fn publish(messages: &[LiveMessage]) {
+    if !has_listeners() {
+        return;
+    }
+
+    for message in messages {
+        let _ = LIVE_FEED.send(Arc::new(message.clone()));
+    }
+}
+
+// the next message for the subscription, or None once the feed is gone or the API stops
+pub async fn next_message(
+    receiver: &mut broadcast::Receiver<Arc<LiveMessage>>,
+    subscription: &Subscription,
+) -> Option<Arc<LiveMessage>> {
+    loop {
+        let received = tokio::select! {
+            received = receiver.recv() => received,
+            _ = shutdown::wait() => return None,
+        };
+        match received {
+            Ok(message) if subscription.matches(&message) => return Some(message),
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                return Some(Arc::new(LiveMessage::Lagged(skipped)))
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashSet;
+
+use crate::error::ApiError;
+use crate::shared::checker;
+use crate::shared::events::parse_transfer;
+use crate::shared::models::{Address, Hash160};
+use crate::transaction::models::Transaction;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LiveBlock {
+    pub index: u64,
+    pub hash: String,
+    pub time: u64,
+    pub transactions: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LiveTransaction {
+    pub hash: String,
+    pub block_index: u64,
+    pub sender: Address,
+    pub addresses: Vec<Address>, // sender and every transfer participant
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LiveTransfer {
+    pub txid: String,
+    pub block_index: u64,
+    pub token_contract: Hash160,
+    pub from: Address,
+    pub to: Address,
+    pub amount: String, // raw integer amount
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct LiveNotification {
+    pub txid: String,
+    pub block_index: u64,
+    pub contract: Hash160,
+    pub eventname: String,
+    pub state: serde_json::Value,
+}
+
+#[derive(Clone, Debug)]
+pub enum LiveMessage {
+    Block(LiveBlock),
+    Transaction(LiveTransaction),
+    Transfer(LiveTransfer),
+    Event(LiveNotification),
+    Lagged(u64), // messages a slow client missed
+}
+
+impl LiveMessage {
+    pub fn topic(&self) -> &'static str {
+        match self {
+            LiveMessage::Block(_) => "block",
+            LiveMessage::Transaction(_) => "transaction",
+            LiveMessage::Transfer(_) => "transfer",
+            LiveMessage::Event(_) => "event",
+            LiveMessage::Lagged(_) => "lagged",
+        }
+    }
+
+    pub fn data(&self) -> serde_json::Value {
+        let data = match self {
+            LiveMessage::Block(block) => serde_json::to_value(block),
+            LiveMessage::Transaction(transaction) => serde_json::to_value(transaction),
+            LiveMessage::Transfer(transfer) => serde_json::to_value(transfer),
+            LiveMessage::Event(event) => serde_json::to_value(event),
+            LiveMessage::Lagged(skipped) => Ok(serde_json::json!({ "skipped": skipped })),
+        };
+
+        data.unwrap_or(serde_json::Value::Null)
+    }
+
+    pub fn block_index(&self) -> Option<u64> {
+        match self {
+            LiveMessage::Block(block) => Some(block.index),
+            LiveMessage::Transaction(transaction) => Some(transaction.block_index),
+            LiveMessage::Transfer(transfer) => Some(transfer.block_index),
+            LiveMessage::Event(event) => Some(event.block_index),
+            LiveMessage::Lagged(_) => None,
+        }
+    }
+
+    // the WebSocket frame, SSE sends the topic as the event name instead
+    pub fn to_json(&self) -> String {
+        serde_json::json!({ "topic": self.topic(), "data": self.data() }).to_string()
+    }
+
+    // blocks are (index, hash, time), transactions are in block order
+    pub fn from_batch(blocks: &[(u64, String, u64)], transactions: &[Transaction]) -> Vec<Self> {
+        let mut messages = Vec::new();
+        let mut transactions = transactions.iter().peekable();
+
+        for (index, hash, time) in blocks {
+            let mut in_block = Vec::new();
+            while let Some(transaction) = transactions.next_if(|t| t.block_index <= *index) {
+                in_block.push(transaction);
+            }
+
+            messages.push(LiveMessage::Block(LiveBlock {
+                index: *index,
+                hash: hash.clone(),
+                time: *time,
+                transactions: in_block.len(),
+            }));
+
+            for transaction in in_block {
+                let mut addresses = vec![transaction.sender.clone()];
+
+                for notification in transaction.notifications.iter() {
+                    if let Some((token, from, to, amount)) = parse_transfer(notification) {
+                        addresses.extend([from.clone(), to.clone()]);
+                        messages.push(LiveMessage::Transfer(LiveTransfer {
+                            txid: transaction.hash.clone(),
+                            block_index: transaction.block_index,
+                            token_contract: token,
+                            from,
+                            to,
+                            amount,
+                        }));
+                    }
+
+                    messages.push(LiveMessage::Event(LiveNotification {
+                        txid: transaction.hash.clone(),
+                        block_index: transaction.block_index,
+                        contract: notification.contract.clone(),
+                        eventname: notification.eventname.clone(),
+                        state: serde_json::to_value(&notification.state)
+                            .unwrap_or(serde_json::Value::Null),
+                    }));
+                }
+
+                addresses.retain(|address| address != "null");
+                addresses.sort();
+                addresses.dedup();
+
+                messages.push(LiveMessage::Transaction(LiveTransaction {
+                    hash: transaction.hash.clone(),
+                    block_index: transaction.block_index,
+                    sender: transaction.sender.clone(),
+                    addresses,
+                }));
+            }
+        }
+
+        messages
+    }
+}
+
+// comma separated lists, e.g. ?blocks=true&address=N...,N...&token=0x...
+#[derive(Deserialize, Default)]
+pub struct SubscribeParams {
+    pub blocks: Option<bool>,
+    pub address: Option<String>,
+    pub token: Option<String>,
+    pub contract: Option<String>,
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Subscription {
+    pub blocks: bool,
+    pub addresses: HashSet<Address>,
+    pub tokens: HashSet<Hash160>,
+    pub contracts: HashSet<Hash160>,
+}
+
+fn split_list(list: &Option<String>) -> impl Iterator<Item = &str> {
+    list.as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+fn parse_hashes(list: &Option<String>, what: &str) -> Result<HashSet<Hash160>, ApiError> {
+    split_list(list)
+        .map(|hash| match checker::is_neo_script_hash(hash) {
+            true => Ok(hash.to_lowercase()),
+            false => Err(ApiError::BadRequest(format!(
+                "Invalid {} hash: {}",
+                what, hash
+            ))),
+        })
+        .collect()
+}
+
+impl Subscription {
+    pub fn parse(params: &SubscribeParams) -> Result<Subscription, ApiError> {
+        let addresses = split_list(&params.address)
+            .map(|address| checker::normalize_neo_address(address).map_err(ApiError::BadRequest))
+            .collect::<Result<_, _>>()?;
+
+        let subscription = Subscription {
+            blocks: params.blocks.unwrap_or(false),
+            addresses,
+            tokens: parse_hashes(&params.token, "token")?,
+            contracts: parse_hashes(&params.contract, "contract")?,
+        };
+
+        if subscription == Subscription::default() {
+            return Err(ApiError::BadRequest(
+                "Subscribe to blocks, an address, a token or a contract.".to_string(),
+            ));
+        }
+
+        Ok(subscription)
+    }
+
+    pub fn matches(&self, message: &LiveMessage) -> bool {
+        match message {
+            LiveMessage::Block(_) => self.blocks,
+            LiveMessage::Transaction(transaction) => transaction
+                .addresses
+                .iter()
+                .any(|address| self.addresses.contains(address)),
+            LiveMessage::Transfer(transfer) => self.tokens.contains(&transfer.token_contract),
+            LiveMessage::Event(event) => self.contracts.contains(&event.contract),
+            LiveMessage::Lagged(_) => true,
+        }
+    }
+}
+
+#[test]
+fn test_subscription() {
+    let params = SubscribeParams {
+        token: Some("0xd2a4cff31913016155e38e474a2c06d08be276cf, ".to_string()),
+        ..Default::default()
+    };
+    let subscription = Subscription::parse(&params).unwrap();
+
+    let transfer = |token: &str| {
+        LiveMessage::Transfer(LiveTransfer {
+            txid: "0x01".to_string(),
+            block_index: 1,
+            token_contract: token.to_string(),
+            from: "null".to_string(),
+            to: "null".to_string(),
+            amount: "1".to_string(),
+        })
+    };
+    assert!(subscription.matches(&transfer("0xd2a4cff31913016155e38e474a2c06d08be276cf")));
+    assert!(!subscription.matches(&transfer("0xef4073a0f2b305a38ec4050e4d3d28bc40ea63f5")));
+
+    assert!(Subscription::parse(&SubscribeParams::default()).is_err());
+    assert!(Subscription::parse(&SubscribeParams {
+        contract: Some("0x1234".to_string()),
+        ..Default::default()
+    })
+    .is_err());
+}
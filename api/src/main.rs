@@ -1,16 +1,29 @@
+mod address;
 mod block;
+mod contract;
 mod error;
 mod history;
 mod indexer;
+mod leaderboard;
+mod live;
+mod search;
 mod shared;
 mod stat;
+mod token;
 mod transaction;
+mod webhook;
 
-use crate::indexer::controller::initilize_indexer_setup;
+use crate::error::{next_request_id, REQUEST_ID, REQUEST_ID_HEADER};
+use crate::indexer::controller::{checkpoint_database, initilize_indexer_setup};
+use crate::indexer::spawn::sync::supervise_node;
 use crate::shared::config::Config;
 use crate::shared::db::DB_PATH;
+use crate::shared::shutdown;
+use crate::shared::utils::{json_error_handler, path_error_handler, query_error_handler};
 use actix_cors::Cors;
-use actix_web::{http::header, web, App, HttpServer};
+use actix_web::dev::Service;
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::{web, App, HttpServer};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::Connection;
@@ -20,6 +33,8 @@ use tokio::{task, time};
 use std::time::Duration;
 
 const REFRESH_INTERVAL: u64 = 3; // how often we check for a new block and refresh stats in seconds
+const SHUTDOWN_TIMEOUT: u64 = 60; // seconds in-flight requests, an indexer batch included, get to finish
+const LOOP_SHUTDOWN_TIMEOUT: u64 = 5; // seconds the background loops get once the server stopped
 
 pub struct ConnectionPool {
     connection: Pool<SqliteConnectionManager>,
@@ -33,37 +48,85 @@ async fn main() -> std::io::Result<()> {
         .to_str()
         .expect("Failed to convert database path to str");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("import") {
+        return indexer::cli::import(&args[1..]);
+    }
+
     let _ = Connection::open(db_path);
 
     let manager_ro =
         SqliteConnectionManager::file(db_path).with_flags(OpenFlags::SQLITE_OPEN_READ_ONLY);
     let pool_ro = Pool::new(manager_ro).unwrap();
-    let connection_pool_ro = web::Data::new(ConnectionPool {
-        connection: pool_ro,
-    });
-    let internal_connection_ro = connection_pool_ro.clone();
 
     let manager_rw =
         SqliteConnectionManager::file(db_path).with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE);
     let pool_rw = Pool::new(manager_rw).unwrap();
+    let storage = shared::storage::open(&config.storage, pool_ro, pool_rw.clone())
+        .map_err(|err| std::io::Error::other(err.to_string()))?;
     let connection_pool_rw = web::Data::new(ConnectionPool {
         connection: pool_rw,
     });
 
-    initilize_indexer_setup(connection_pool_rw.clone()).await;
+    if let Err(err) = initilize_indexer_setup(connection_pool_rw.clone()).await {
+        eprintln!("Failed to initialize indexer setup: {}", err);
+    }
 
-    task::spawn(async move {
+    if let Some(command) = args.first() {
+        return indexer::cli::run(command, &args[1..], &connection_pool_rw).await;
+    }
+
+    // the managed node lives exactly as long as the API
+    let node_supervisor = config.node.managed.then(|| {
+        let node_config = config.node.clone();
+        task::spawn(async move {
+            if let Err(err) = supervise_node(node_config).await {
+                eprintln!("Failed to supervise node: {}", err);
+            }
+        })
+    });
+
+    let stats_storage = storage.read.clone();
+    let stats_loop = task::spawn(async move {
+        let mut interval = time::interval(Duration::from_secs(REFRESH_INTERVAL));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown::wait() => break,
+            }
+            if let Err(err) = stat::internals::set_stats_internal(stats_storage.as_ref()).await {
+                eprintln!("Failed to refresh stats: {}", err);
+            }
+        }
+    });
+
+    let webhook_storage = storage.webhooks.clone();
+    let allow_private_urls = config.webhooks.allow_private_urls;
+    let webhook_loop = task::spawn(async move {
+        let client = webhook::internals::delivery_client(allow_private_urls);
         let mut interval = time::interval(Duration::from_secs(REFRESH_INTERVAL));
         loop {
-            let c = internal_connection_ro.clone();
-            interval.tick().await;
-            stat::internals::set_stats_internal(c).await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown::wait() => break,
+            }
+            if let Err(err) =
+                webhook::internals::deliver_pending_internal(webhook_storage.as_ref(), &client)
+                    .await
+            {
+                eprintln!("Failed to deliver webhooks: {}", err);
+            }
         }
     });
 
     println!("Opening to requests on http://0.0.0.0:{}.", config.api_port);
 
-    HttpServer::new(move || {
+    let checkpoint_pool = connection_pool_rw.clone();
+    let read_storage = web::Data::from(storage.read);
+    let webhook_storage = web::Data::from(storage.webhooks);
+    let webhook_settings = web::Data::new(config.webhooks.clone());
+    let admin_settings = web::Data::new(config.admin.clone());
+    let server = HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
             .allowed_methods(vec!["GET"])
@@ -71,15 +134,84 @@ async fn main() -> std::io::Result<()> {
             .max_age(3600);
         App::new()
             .wrap(cors)
-            .app_data(connection_pool_ro.clone())
+            .wrap_fn(|req, srv| {
+                // reuse the caller's id so logs can be correlated across services
+                let request_id = req
+                    .headers()
+                    .get(REQUEST_ID_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string())
+                    .unwrap_or_else(next_request_id);
+
+                let response = srv.call(req);
+                REQUEST_ID.scope(request_id.clone(), async move {
+                    let mut response = response.await?;
+                    if let Ok(value) = HeaderValue::from_str(&request_id) {
+                        response
+                            .headers_mut()
+                            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                    }
+                    Ok(response)
+                })
+            })
+            .app_data(web::PathConfig::default().error_handler(path_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(query_error_handler))
+            .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+            .app_data(read_storage.clone())
+            .app_data(webhook_storage.clone())
+            .app_data(connection_pool_rw.clone())
+            .app_data(webhook_settings.clone())
+            .app_data(admin_settings.clone())
             .configure(block::controller::config)
             .configure(transaction::controller::config)
             .configure(stat::controller::config)
             .configure(history::controller::config)
-            .app_data(connection_pool_rw.clone())
+            .configure(contract::controller::config)
+            .configure(address::controller::config)
+            .configure(leaderboard::controller::config)
+            .configure(token::controller::config)
+            .configure(search::controller::config)
+            .configure(webhook::controller::config)
+            .configure(live::controller::config)
             .configure(indexer::controller::config)
     })
     .bind(("0.0.0.0", config.api_port))?
-    .run()
-    .await
+    .disable_signals()
+    .shutdown_timeout(SHUTDOWN_TIMEOUT)
+    .run();
+
+    // the flag goes up before the server stops, so the indexer and live streams wind down
+    // while actix drains the requests they belong to
+    let server_handle = server.handle();
+    task::spawn(async move {
+        shutdown::wait_for_signal().await;
+        println!("Shutdown requested, finishing in-flight work..");
+        shutdown::request();
+        server_handle.stop(true).await;
+    });
+
+    server.await?;
+
+    // both loops stop at their next await, the cap only guards against one that doesn't
+    let loops_stopped = async {
+        let _ = stats_loop.await;
+        let _ = webhook_loop.await;
+    };
+    if time::timeout(Duration::from_secs(LOOP_SHUTDOWN_TIMEOUT), loops_stopped)
+        .await
+        .is_err()
+    {
+        println!("Background work did not stop in time, leaving it behind.");
+    }
+    if let Some(supervisor) = node_supervisor {
+        let _ = supervisor.await;
+    }
+
+    match checkpoint_database(checkpoint_pool) {
+        Ok(()) => println!("WAL checkpointed."),
+        Err(err) => eprintln!("{}", err),
+    }
+    println!("Shutdown complete.");
+
+    Ok(())
 }
@@ -0,0 +1,57 @@
+use actix_web::{get, web, HttpResponse};
+
+use crate::error::ApiError;
+use crate::shared::checker;
+use crate::shared::storage::ReadStorage;
+
+use super::models::{SearchParams, SearchResponse};
+
+#[get("/v1/search")]
+async fn search(
+    storage: web::Data<dyn ReadStorage>,
+    query_parameter: web::Query<SearchParams>,
+) -> Result<HttpResponse, ApiError> {
+    let query = query_parameter
+        .q
+        .as_deref()
+        .unwrap_or("")
+        .trim()
+        .to_string();
+
+    if query.is_empty() {
+        return Err(ApiError::BadRequest(
+            "The 'q' parameter is required.".to_string(),
+        ));
+    }
+
+    // hashes pasted without the 0x prefix are accepted too
+    let prefixed = match query.starts_with("0x") {
+        true => query.to_lowercase(),
+        false => format!("0x{}", query.to_lowercase()),
+    };
+
+    let results = if let Ok(index) = query.parse::<u64>() {
+        storage.search_block_index(index).await?
+    } else if checker::is_neo_txid_hash(&prefixed) {
+        storage.search_hash(&prefixed).await?
+    } else if checker::is_neo_script_hash(&prefixed) {
+        storage.search_script_hash(&prefixed).await?
+    } else if checker::is_neo_address(&query) {
+        match checker::normalize_neo_address(&query) {
+            Ok(address) => storage.search_address(&address).await?,
+            Err(_) => Vec::new(),
+        }
+    } else {
+        storage.search_name(&query).await?
+    };
+
+    if results.is_empty() {
+        return Err(ApiError::NotFound(format!("No results for {}.", query)));
+    }
+
+    Ok(HttpResponse::Ok().json(SearchResponse { query, results }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(search);
+}
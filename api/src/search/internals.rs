@@ -0,0 +1,228 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, OptionalExtension};
+
+use crate::error::ApiError;
+use crate::search::models::{SearchResult, SEARCH_LIMIT};
+use crate::shared::checker;
+use crate::shared::models::NATIVE_TOKENS;
+
+pub fn search_block_index_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    index: u64,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let sql = "SELECT id FROM blocks WHERE id = ?";
+    let found = conn.query_row(sql, [index], |row| row.get(0)).optional()?;
+
+    Ok(found.map(SearchResult::block).into_iter().collect())
+}
+
+// blocks and transactions share the hash format, so both tables are checked
+pub fn search_hash_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    hash: &str,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let mut results = Vec::new();
+
+    let sql = "SELECT id FROM blocks WHERE hash = ?";
+    if let Some(index) = conn.query_row(sql, [hash], |row| row.get(0)).optional()? {
+        results.push(SearchResult::block(index));
+    }
+
+    let sql = "SELECT hash FROM transactions WHERE hash = ?";
+    if let Some(hash) = conn.query_row(sql, [hash], |row| row.get(0)).optional()? {
+        results.push(SearchResult::transaction(hash));
+    }
+
+    Ok(results)
+}
+
+// a 0x script hash may be a contract, a token or an account
+pub fn search_script_hash_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    hash: &str,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let mut results = Vec::new();
+
+    if let Some((hash, symbol, _)) = NATIVE_TOKENS.iter().find(|(h, _, _)| *h == hash) {
+        results.push(SearchResult::token(
+            hash.to_string(),
+            Some(symbol.to_string()),
+        ));
+    }
+
+    let sql = "
+        SELECT hash, json_extract(manifest, '$.name'), symbol, contract_type LIKE '%NEP-17%'
+        FROM contracts
+        WHERE hash = ?";
+
+    let contract = conn
+        .query_row(sql, [hash], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, bool>(3)?,
+            ))
+        })
+        .optional()?;
+
+    if let Some((hash, name, symbol, is_token)) = contract {
+        if is_token {
+            results.push(SearchResult::token(hash.clone(), symbol));
+        }
+        results.push(SearchResult::contract(hash, name));
+    }
+
+    if let Ok(address) = checker::normalize_neo_address(hash) {
+        results.extend(search_address_internal(conn, &address)?);
+    }
+
+    Ok(results)
+}
+
+// only addresses the indexer has seen, so a typo does not look like an empty account
+pub fn search_address_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    address: &str,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let sql = "SELECT address FROM address_first_seen WHERE address = ?";
+    let found = conn
+        .query_row(sql, [address], |row| row.get(0))
+        .optional()?;
+
+    Ok(found.map(SearchResult::address).into_iter().collect())
+}
+
+pub fn search_name_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    query: &str,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let mut results: Vec<SearchResult> = NATIVE_TOKENS
+        .iter()
+        .filter(|(_, symbol, _)| symbol.eq_ignore_ascii_case(query))
+        .map(|(hash, symbol, _)| SearchResult::token(hash.to_string(), Some(symbol.to_string())))
+        .collect();
+
+    // exact symbols first, then names containing the query
+    let sql = "
+        SELECT hash, symbol FROM contracts
+        WHERE contract_type LIKE '%NEP-17%' AND symbol = ?1 COLLATE NOCASE
+        ORDER BY id ASC
+        LIMIT ?2";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![query, SEARCH_LIMIT])?;
+    while let Some(row) = rows.next()? {
+        results.push(SearchResult::token(row.get(0)?, row.get(1)?));
+    }
+
+    let sql = "
+        SELECT hash, json_extract(manifest, '$.name') AS name FROM contracts
+        WHERE name LIKE '%' || ?1 || '%' ESCAPE '\\'
+        ORDER BY length(name) ASC, id ASC
+        LIMIT ?2";
+
+    let pattern = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![pattern, SEARCH_LIMIT])?;
+    while let Some(row) = rows.next()? {
+        results.push(SearchResult::contract(row.get(0)?, row.get(1)?));
+    }
+
+    Ok(results)
+}
+
+#[test]
+fn test_search_internals() {
+    use crate::indexer::rpc::database::{insert_test_transaction, memory_chain};
+
+    const GAS: &str = "0xd2a4cff31913016155e38e474a2c06d08be276cf";
+    const TOKEN: &str = "0x0000000000000000000000000000000000000001";
+    const DAPP: &str = "0x0000000000000000000000000000000000000002";
+    const ACCOUNT: &str = "0x0101010101010101010101010101010101010101";
+    let block_hash = format!("0x{:064x}", 1);
+    let tx_hash = format!("0x{:064x}", 0xaa);
+
+    let conn = memory_chain(&[1000, 2000]);
+    insert_test_transaction(&conn, &tx_hash, 2, "alice", "0", "0");
+    conn.execute(
+        "INSERT INTO contracts (block_index, hash, contract_type, manifest, symbol)
+        VALUES (1, ?1, '[\"NEP-17\"]', '{\"name\":\"Token 50%\"}', 'tkn'),
+            (2, ?2, '[]', '{\"name\":\"Token Swap\"}', NULL)",
+        [TOKEN, DAPP],
+    )
+    .unwrap();
+    let account = checker::normalize_neo_address(ACCOUNT).unwrap();
+    conn.execute(
+        "INSERT INTO address_first_seen (address, block_index, date) VALUES (?, 1, '1970-01-01')",
+        [&account],
+    )
+    .unwrap();
+
+    let found = |results: Result<Vec<SearchResult>, ApiError>| -> Vec<(String, String)> {
+        results
+            .unwrap()
+            .into_iter()
+            .map(|result| (result.kind, result.id))
+            .collect()
+    };
+    let pair = |kind: &str, id: &str| (kind.to_string(), id.to_string());
+
+    assert_eq!(
+        found(search_block_index_internal(&conn, 2)),
+        vec![pair("block", "2")]
+    );
+    assert!(found(search_block_index_internal(&conn, 3)).is_empty());
+
+    assert_eq!(
+        found(search_hash_internal(&conn, &block_hash)),
+        vec![pair("block", "1")]
+    );
+    assert_eq!(
+        found(search_hash_internal(&conn, &tx_hash)),
+        vec![pair("transaction", &tx_hash)]
+    );
+
+    // a NEP-17 contract is both a token and a contract, a seen account hash is an address
+    assert_eq!(
+        found(search_script_hash_internal(&conn, TOKEN)),
+        vec![pair("token", TOKEN), pair("contract", TOKEN)]
+    );
+    assert_eq!(
+        found(search_script_hash_internal(&conn, GAS)),
+        vec![pair("token", GAS)]
+    );
+    assert_eq!(
+        found(search_script_hash_internal(&conn, ACCOUNT)),
+        vec![pair("address", &account)]
+    );
+    assert_eq!(
+        found(search_address_internal(&conn, &account)),
+        vec![pair("address", &account)]
+    );
+
+    // symbols match whole and in any case, names by substring with the shortest first
+    assert_eq!(
+        found(search_name_internal(&conn, "TKN")),
+        vec![pair("token", TOKEN)]
+    );
+    assert_eq!(
+        found(search_name_internal(&conn, "token")),
+        vec![pair("contract", TOKEN), pair("contract", DAPP)]
+    );
+    assert_eq!(
+        found(search_name_internal(&conn, "gas")),
+        vec![pair("token", GAS)]
+    );
+    // LIKE wildcards in the query are taken literally
+    assert_eq!(
+        found(search_name_internal(&conn, "50%")),
+        vec![pair("contract", TOKEN)]
+    );
+    assert!(found(search_name_internal(&conn, "_")).is_empty());
+}
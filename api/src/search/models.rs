@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+pub const SEARCH_LIMIT: u32 = 10; // name and symbol matches returned at most
+
+#[derive(Deserialize)]
+pub struct SearchParams {
+    pub q: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SearchResponse {
+    pub query: String,
+    pub results: Vec<SearchResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SearchResult {
+    pub kind: String,         // "block", "transaction", "address", "contract" or "token"
+    pub id: String,           // block index, hash or address to pass to the endpoint
+    pub name: Option<String>, // contract name or token symbol
+    pub path: String,         // endpoint holding the full record
+}
+
+impl SearchResult {
+    pub fn block(index: u64) -> Self {
+        SearchResult {
+            kind: "block".to_string(),
+            id: index.to_string(),
+            name: None,
+            path: format!("/v1/block/{}", index),
+        }
+    }
+
+    pub fn transaction(hash: String) -> Self {
+        SearchResult {
+            kind: "transaction".to_string(),
+            path: format!("/v1/transaction/{}", hash),
+            id: hash,
+            name: None,
+        }
+    }
+
+    pub fn address(address: String) -> Self {
+        SearchResult {
+            kind: "address".to_string(),
+            path: format!("/v1/address/{}", address),
+            id: address,
+            name: None,
+        }
+    }
+
+    pub fn contract(hash: String, name: Option<String>) -> Self {
+        SearchResult {
+            kind: "contract".to_string(),
+            path: format!("/v1/contracts/{}/events", hash),
+            id: hash,
+            name,
+        }
+    }
+
+    pub fn token(hash: String, symbol: Option<String>) -> Self {
+        SearchResult {
+            kind: "token".to_string(),
+            path: format!("/v1/tokens/{}", hash),
+            id: hash,
+            name: symbol,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use tokio_postgres::Client;
+
+use crate::error::ApiError;
+use crate::search::models::{SearchResult, SEARCH_LIMIT};
+use crate::shared::checker;
+use crate::shared::models::NATIVE_TOKENS;
+use crate::shared::postgres::{get_int, to_i64};
+
+pub async fn search_block_index_internal(
+    client: &Client,
+    index: u64,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let sql = "SELECT id FROM blocks WHERE id = $1";
+
+    match client.query_opt(sql, &[&to_i64(index)?]).await? {
+        Some(row) => Ok(vec![SearchResult::block(get_int(&row, 0)?)]),
+        None => Ok(Vec::new()),
+    }
+}
+
+// blocks and transactions share the hash format, so both tables are checked
+pub async fn search_hash_internal(
+    client: &Client,
+    hash: &str,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let mut results = Vec::new();
+
+    let sql = "SELECT id FROM blocks WHERE hash = $1";
+    if let Some(row) = client.query_opt(sql, &[&hash]).await? {
+        results.push(SearchResult::block(get_int(&row, 0)?));
+    }
+
+    let sql = "SELECT hash FROM transactions WHERE hash = $1";
+    if let Some(row) = client.query_opt(sql, &[&hash]).await? {
+        results.push(SearchResult::transaction(row.try_get(0)?));
+    }
+
+    Ok(results)
+}
+
+// a 0x script hash may be a contract, a token or an account
+pub async fn search_script_hash_internal(
+    client: &Client,
+    hash: &str,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let mut results = Vec::new();
+
+    if let Some((hash, symbol, _)) = NATIVE_TOKENS.iter().find(|(h, _, _)| *h == hash) {
+        results.push(SearchResult::token(
+            hash.to_string(),
+            Some(symbol.to_string()),
+        ));
+    }
+
+    let sql = "
+        SELECT hash, manifest::json ->> 'name', symbol, contract_type LIKE '%NEP-17%'
+        FROM contracts
+        WHERE hash = $1";
+
+    if let Some(row) = client.query_opt(sql, &[&hash]).await? {
+        let hash: String = row.try_get(0)?;
+
+        if row.try_get::<_, bool>(3)? {
+            results.push(SearchResult::token(hash.clone(), row.try_get(2)?));
+        }
+        results.push(SearchResult::contract(hash, row.try_get(1)?));
+    }
+
+    if let Ok(address) = checker::normalize_neo_address(hash) {
+        results.extend(search_address_internal(client, &address).await?);
+    }
+
+    Ok(results)
+}
+
+// only addresses the indexer has seen, so a typo does not look like an empty account
+pub async fn search_address_internal(
+    client: &Client,
+    address: &str,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let sql = "SELECT address FROM address_first_seen WHERE address = $1";
+
+    match client.query_opt(sql, &[&address]).await? {
+        Some(row) => Ok(vec![SearchResult::address(row.try_get(0)?)]),
+        None => Ok(Vec::new()),
+    }
+}
+
+// SQLite compares symbols without case and its LIKE ignores case, so both do here too
+pub async fn search_name_internal(
+    client: &Client,
+    query: &str,
+) -> Result<Vec<SearchResult>, ApiError> {
+    let mut results: Vec<SearchResult> = NATIVE_TOKENS
+        .iter()
+        .filter(|(_, symbol, _)| symbol.eq_ignore_ascii_case(query))
+        .map(|(hash, symbol, _)| SearchResult::token(hash.to_string(), Some(symbol.to_string())))
+        .collect();
+
+    let limit = i64::from(SEARCH_LIMIT);
+
+    // exact symbols first, then names containing the query
+    let sql = "
+        SELECT hash, symbol FROM contracts
+        WHERE contract_type LIKE '%NEP-17%' AND lower(symbol) = lower($1)
+        ORDER BY id ASC
+        LIMIT $2";
+
+    for row in client.query(sql, &[&query, &limit]).await? {
+        results.push(SearchResult::token(row.try_get(0)?, row.try_get(1)?));
+    }
+
+    let sql = "
+        SELECT hash, name FROM (
+            SELECT id, hash, manifest::json ->> 'name' AS name FROM contracts
+        ) named
+        WHERE name ILIKE '%' || $1::TEXT || '%' ESCAPE '\\'
+        ORDER BY length(name) ASC, id ASC
+        LIMIT $2";
+
+    let pattern = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    for row in client.query(sql, &[&pattern, &limit]).await? {
+        results.push(SearchResult::contract(row.try_get(0)?, row.try_get(1)?));
+    }
+
+    Ok(results)
+}
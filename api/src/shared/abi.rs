@@ -0,0 +1,256 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::shared::models::Hash160;
+use crate::shared::neo;
+use crate::transaction::models::{Notification, StateValue};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ContractManifest {
+    pub name: String,
+    pub abi: ContractAbi,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ContractAbi {
+    #[serde(default)]
+    pub events: Vec<AbiEvent>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AbiEvent {
+    pub name: String,
+    pub parameters: Vec<AbiParameter>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AbiParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub _type: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DecodedEvent {
+    pub contract: Hash160,
+    pub eventname: String,
+    pub params: Option<Map<String, Value>>, // None when no matching ABI event is known
+    pub state: Vec<StateValue>,
+}
+
+pub fn parse_manifest(manifest: &str) -> Option<ContractManifest> {
+    serde_json::from_str(manifest).ok()
+}
+
+// native contracts and contracts indexed before manifests were stored have no ABI,
+// so fall back to the standard NEP-17/NEP-11 Transfer signatures
+fn standard_event(eventname: &str, param_count: usize) -> Option<AbiEvent> {
+    let param = |name: &str, _type: &str| AbiParameter {
+        name: name.to_string(),
+        _type: _type.to_string(),
+    };
+
+    match (eventname, param_count) {
+        ("Transfer", 3) => Some(AbiEvent {
+            name: eventname.to_string(),
+            parameters: vec![
+                param("from", "Hash160"),
+                param("to", "Hash160"),
+                param("amount", "Integer"),
+            ],
+        }),
+        ("Transfer", 4) => Some(AbiEvent {
+            name: eventname.to_string(),
+            parameters: vec![
+                param("from", "Hash160"),
+                param("to", "Hash160"),
+                param("amount", "Integer"),
+                param("tokenId", "ByteArray"),
+            ],
+        }),
+        _ => None,
+    }
+}
+
+pub fn decode_event(
+    notification: &Notification,
+    manifest: Option<&ContractManifest>,
+) -> DecodedEvent {
+    let values = &notification.state.value;
+
+    let abi_event = manifest
+        .and_then(|m| {
+            m.abi
+                .events
+                .iter()
+                .find(|e| e.name == notification.eventname && e.parameters.len() == values.len())
+                .cloned()
+        })
+        .or_else(|| standard_event(&notification.eventname, values.len()));
+
+    let params = abi_event.map(|event| {
+        event
+            .parameters
+            .iter()
+            .zip(values.iter())
+            .map(|(param, value)| (param.name.clone(), convert_value(&param._type, value)))
+            .collect()
+    });
+
+    DecodedEvent {
+        contract: notification.contract.clone(),
+        eventname: notification.eventname.clone(),
+        params,
+        state: values.clone(),
+    }
+}
+
+// values are stored as text, so everything arrives as a string or null
+pub fn convert_value(param_type: &str, state_value: &StateValue) -> Value {
+    let raw = match &state_value.value {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => return Value::Null,
+        Some(other) => return other.clone(),
+    };
+
+    // ByteString and Buffer values are base64, anything else is already readable
+    let bytes = match state_value._type.as_str() {
+        "ByteString" | "Buffer" => STANDARD.decode(&raw).ok(),
+        _ => None,
+    };
+
+    let converted = match (param_type, bytes) {
+        ("Hash160", Some(bytes)) if bytes.len() == 20 => Some(Value::String(
+            neo::scripthash_to_address(&hex::encode(bytes)),
+        )),
+        ("Hash256", Some(mut bytes)) if bytes.len() == 32 => {
+            bytes.reverse();
+            Some(Value::String(format!("0x{}", hex::encode(bytes))))
+        }
+        ("String", Some(bytes)) => String::from_utf8(bytes).ok().map(Value::String),
+        ("ByteArray" | "PublicKey" | "Signature", Some(bytes)) => {
+            Some(Value::String(hex::encode(bytes)))
+        }
+        ("Integer", Some(bytes)) => Some(Value::String(le_bytes_to_decimal(&bytes))),
+        ("Integer", None) => Some(Value::String(raw.clone())),
+        ("Boolean", _) => match raw.as_str() {
+            "true" | "1" => Some(Value::Bool(true)),
+            "false" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("Array" | "Map" | "Any", None) => serde_json::from_str(&raw).ok(),
+        _ => None,
+    };
+
+    converted.unwrap_or(Value::String(raw))
+}
+
+// integers pushed as ByteString are little-endian two's complement
+fn le_bytes_to_decimal(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "0".to_string();
+    }
+    if bytes.len() > 16 {
+        return format!("0x{}", hex::encode(bytes));
+    }
+
+    let negative = bytes[bytes.len() - 1] & 0x80 != 0;
+    let mut buffer = [if negative { 0xff } else { 0x00 }; 16];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+
+    i128::from_le_bytes(buffer).to_string()
+}
+
+#[test]
+fn test_decode_event_with_manifest() {
+    use crate::transaction::models::State;
+    use serde_json::json;
+
+    let manifest = parse_manifest(
+        r#"{"name":"Test","abi":{"methods":[],"events":[{"name":"Swap","parameters":[{"name":"account","type":"Hash160"},{"name":"amount","type":"Integer"},{"name":"memo","type":"String"}]}]}}"#,
+    )
+    .unwrap();
+
+    let notification = Notification {
+        id: None,
+        contract: "0xb776afb6ad0c11565e70f8ee1dd898da43e51be1".to_string(),
+        eventname: "Swap".to_string(),
+        state: State {
+            _type: "Array".to_string(),
+            value: vec![
+                StateValue {
+                    _type: "ByteString".to_string(),
+                    value: Some(json!("axI92L7HGGSIUrvHhZXjU2oFj58=")),
+                },
+                StateValue {
+                    _type: "Integer".to_string(),
+                    value: Some(json!("100000")),
+                },
+                StateValue {
+                    _type: "ByteString".to_string(),
+                    value: Some(json!("aGVsbG8=")),
+                },
+            ],
+        },
+    };
+
+    let decoded = decode_event(&notification, Some(&manifest));
+    let params = decoded.params.unwrap();
+
+    assert_eq!(
+        params["account"],
+        json!("NVg7LjGcUSrgxgjX3zEgqaksfMaiS8Z6e1")
+    );
+    assert_eq!(params["amount"], json!("100000"));
+    assert_eq!(params["memo"], json!("hello"));
+}
+
+#[test]
+fn test_decode_event_standard_transfer() {
+    use crate::transaction::models::State;
+    use serde_json::json;
+
+    let notification = Notification {
+        id: None,
+        contract: "0xd2a4cff31913016155e38e474a2c06d08be276cf".to_string(),
+        eventname: "Transfer".to_string(),
+        state: State {
+            _type: "Array".to_string(),
+            value: vec![
+                StateValue {
+                    _type: "Any".to_string(),
+                    value: None,
+                },
+                StateValue {
+                    _type: "ByteString".to_string(),
+                    value: Some(json!("axI92L7HGGSIUrvHhZXjU2oFj58=")),
+                },
+                StateValue {
+                    _type: "Integer".to_string(),
+                    value: Some(json!("40300000")),
+                },
+            ],
+        },
+    };
+
+    let decoded = decode_event(&notification, None);
+    let params = decoded.params.unwrap();
+
+    assert_eq!(params["from"], Value::Null);
+    assert_eq!(params["to"], json!("NVg7LjGcUSrgxgjX3zEgqaksfMaiS8Z6e1"));
+    assert_eq!(params["amount"], json!("40300000"));
+
+    let mut unknown = notification.clone();
+    unknown.eventname = "Unknown".to_string();
+    assert!(decode_event(&unknown, None).params.is_none());
+}
+
+#[test]
+fn test_le_bytes_to_decimal() {
+    assert_eq!(le_bytes_to_decimal(&[]), "0");
+    assert_eq!(le_bytes_to_decimal(&[0xe8, 0x03]), "1000");
+    assert_eq!(le_bytes_to_decimal(&[0xff]), "-1");
+    assert_eq!(le_bytes_to_decimal(&[0x80, 0x00]), "128");
+}
@@ -0,0 +1,46 @@
+use actix_web::http::header;
+use actix_web::HttpRequest;
+use sha2::{Digest, Sha256};
+
+use crate::error::ApiError;
+use crate::shared::config::AdminConfig;
+
+// credentials come as "Authorization: Bearer <token>"
+pub fn bearer(request: &HttpRequest) -> Option<&str> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+// compares digests, so the time taken says nothing about how much of the token matched
+pub fn same_secret(expected: &str, given: &str) -> bool {
+    Sha256::digest(expected.as_bytes()) == Sha256::digest(given.as_bytes())
+}
+
+pub fn authorize_admin(settings: &AdminConfig, token: Option<&str>) -> Result<(), ApiError> {
+    match (&settings.token, token) {
+        (Some(admin), Some(token)) if same_secret(admin, token) => Ok(()),
+        (None, _) => Err(ApiError::Unauthorized(
+            "Admin endpoints are disabled on this instance.".to_string(),
+        )),
+        _ => Err(ApiError::Unauthorized(
+            "A valid admin token is required.".to_string(),
+        )),
+    }
+}
+
+#[test]
+fn test_authorize_admin() {
+    let settings = AdminConfig {
+        token: Some("secret".to_string()),
+    };
+
+    assert!(authorize_admin(&settings, Some("secret")).is_ok());
+    assert!(authorize_admin(&settings, Some("guess")).is_err());
+    assert!(authorize_admin(&settings, None).is_err());
+    // without a configured token nothing opens
+    assert!(authorize_admin(&AdminConfig::default(), Some("")).is_err());
+}
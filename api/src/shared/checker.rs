@@ -1,4 +1,12 @@
-use crate::shared::neo::ALPHABET;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use std::net::IpAddr;
+
+use crate::shared::models::Address;
+use crate::shared::neo::{self, ALPHABET};
+
+const ADDRESS_VERSION: u8 = 0x35;
 
 pub fn is_neo_address(string: &str) -> bool {
     string.chars().count() == 34
@@ -6,7 +14,6 @@ pub fn is_neo_address(string: &str) -> bool {
         && string.chars().all(|c| ALPHABET.contains(&(c as u8)))
 }
 
-#[allow(dead_code)]
 pub fn is_neo_script_hash(string: &str) -> bool {
     string.chars().count() == 42
         && string.starts_with("0x")
@@ -27,6 +34,80 @@ pub fn is_neo_txid_hash(string: &str) -> bool {
             .all(|c| c.is_ascii_hexdigit())
 }
 
+pub fn is_valid_address_checksum(address: &str) -> bool {
+    if !is_neo_address(address) {
+        return false;
+    }
+
+    let decoded = neo::base58_to_bytes(address);
+
+    decoded.len() == 25
+        && decoded[0] == ADDRESS_VERSION
+        && neo::checksum(&decoded[0..21])[0..4] == decoded[21..25]
+}
+
+// false for loopback, private, link-local and other addresses that don't route on the internet
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))) // carrier-grade NAT
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || (first & 0xfe00) == 0xfc00 // unique local
+                    || (first & 0xffc0) == 0xfe80) // link-local
+            }
+        },
+    }
+}
+
+// accepts an address, a 0x big-endian script hash, or a little-endian script hash
+// as hex or base64, and returns the address form the database stores
+pub fn normalize_neo_address(string: &str) -> Result<Address, String> {
+    let string = string.trim();
+
+    // a base64 script hash may start with N too, only the address shape is checksummed
+    if is_neo_address(string) {
+        return if is_valid_address_checksum(string) {
+            Ok(string.to_string())
+        } else {
+            Err(format!("Invalid address checksum: {}", string))
+        };
+    }
+
+    let little_endian = if is_neo_script_hash(string) {
+        neo::reverse_hex(&string[2..])
+    } else if string.len() == 40 && string.chars().all(|c| c.is_ascii_hexdigit()) {
+        string.to_lowercase()
+    } else {
+        match STANDARD.decode(string) {
+            Ok(bytes) if bytes.len() == 20 => hex::encode(bytes),
+            _ => {
+                return Err(format!(
+                    "Invalid address: {} is not an address or script hash",
+                    string
+                ))
+            }
+        }
+    };
+
+    Ok(neo::scripthash_to_address(&little_endian))
+}
+
 #[test]
 fn test_is_neo_address() {
     assert!(is_neo_address("NSTSntFPK36QXsjEK6oAhnPzSyfgfVA2GQ"));
@@ -68,3 +149,45 @@ fn test_is_neo_txid_hash() {
         "0x6250481ec87ae2052f90ec7cb46d757b8db1c447"
     ));
 }
+
+#[test]
+fn test_normalize_neo_address() {
+    let address = "NVg7LjGcUSrgxgjX3zEgqaksfMaiS8Z6e1";
+
+    assert_eq!(normalize_neo_address(address).unwrap(), address);
+    assert_eq!(
+        normalize_neo_address("0x9f8f056a53e39585c7bb52886418c7bed83d126b").unwrap(),
+        address
+    );
+    assert_eq!(
+        normalize_neo_address("6b123dd8bec718648852bbc78595e3536a058f9f").unwrap(),
+        address
+    );
+    assert_eq!(
+        normalize_neo_address("axI92L7HGGSIUrvHhZXjU2oFj58=").unwrap(),
+        address
+    );
+
+    assert_eq!(
+        normalize_neo_address("NAECAwQFBgcICQoLDA0ODxAREhM=").unwrap(),
+        normalize_neo_address("340102030405060708090a0b0c0d0e0f10111213").unwrap()
+    );
+
+    assert!(normalize_neo_address("NVg7LjGcUSrgxgjX3zEgqaksfMaiS8Z6e2").is_err());
+    assert!(normalize_neo_address("NSTSntFPK36QXsjEK6OAhnPzSyfgfVA2GQ").is_err());
+    assert!(normalize_neo_address("0x9f8f056a53e39585c7bb52886418c7bed83d12").is_err());
+    assert!(normalize_neo_address("").is_err());
+}
+
+#[test]
+fn test_is_public_ip() {
+    assert!(is_public_ip("1.1.1.1".parse().unwrap()));
+    assert!(is_public_ip("2606:4700::1111".parse().unwrap()));
+    assert!(!is_public_ip("127.0.0.1".parse().unwrap()));
+    assert!(!is_public_ip("10.0.0.8".parse().unwrap()));
+    assert!(!is_public_ip("169.254.169.254".parse().unwrap()));
+    assert!(!is_public_ip("100.100.1.1".parse().unwrap()));
+    assert!(!is_public_ip("::1".parse().unwrap()));
+    assert!(!is_public_ip("fd00::1".parse().unwrap()));
+    assert!(!is_public_ip("::ffff:192.168.1.1".parse().unwrap()));
+}
@@ -1,10 +1,107 @@
 use config::{Config as ConfigFile, ConfigError, File};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::Path;
 use url::Url;
 
+use crate::indexer::utils::node::NEOGO_PATH;
+
 pub struct Config {
     pub api_port: u16,
     pub rpc_base_url: String,
+    pub rpc_ws_url: Option<String>, // NeoGo WebSocket endpoint, follows the tip by subscription
+    pub prices: PriceConfig,
+    pub node: NodeConfig,
+    pub storage: StorageConfig,
+    pub webhooks: WebhookConfig,
+    pub admin: AdminConfig,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PriceConfig {
+    pub sources: Vec<String>, // enabled sources, in default priority order
+    pub sample_time: String,  // UTC time of day after which blocks are sampled
+    pub flamingo_base_url: String,
+    pub flamingo_min_block: u64,
+    pub file_path: Option<String>,
+    pub dex_pairs: Vec<DexPairConfig>,
+    pub token_sources: HashMap<String, Vec<String>>, // per-token priority overrides
+}
+
+impl Default for PriceConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![String::from("flamingo")],
+            sample_time: String::from("23:59:40"),
+            flamingo_base_url: String::from("https://neo-api.b-cdn.net"),
+            flamingo_min_block: 664000,
+            file_path: None,
+            dex_pairs: Vec::new(),
+            token_sources: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NodeConfig {
+    pub managed: bool,           // download, run and supervise a local NeoGo node
+    pub version: Option<String>, // defaults to the version the indexer is tested against
+    pub path: String,
+    pub sha256: Option<String>, // expected binary checksum, otherwise the release's .sha256 is used
+    pub args: Vec<String>,
+    pub restart_delay: u64, // seconds to wait before restarting a crashed node
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            managed: false,
+            version: None,
+            path: String::from(NEOGO_PATH),
+            sha256: None,
+            args: vec![String::from("node"), String::from("-m")],
+            restart_delay: 5,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Sqlite,
+    Postgres,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: StorageBackend, // where the chain, the stats and the webhooks are kept
+    pub url: Option<String>,     // postgres connection string, required by that backend
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub allow_private_urls: bool, // lets receivers sit on loopback or private networks
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct AdminConfig {
+    pub token: Option<String>, // opens webhook management and the indexer's maintenance endpoints
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct DexPairConfig {
+    pub contract: String,
+    pub token0: String,
+    pub token1: String,
+    pub decimals0: u8,
+    pub decimals1: u8,
+    pub quote: String, // the USD-pegged side of the pair
 }
 
 impl Config {
@@ -20,6 +117,27 @@ impl Config {
         Ok(Config {
             api_port: settings.get_int("server.port")? as u16,
             rpc_base_url: settings.get_string("rpc.base_url")?,
+            rpc_ws_url: settings.get_string("rpc.ws_url").ok(),
+            prices: match settings.get::<PriceConfig>("prices") {
+                Err(ConfigError::NotFound(_)) => PriceConfig::default(),
+                result => result?,
+            },
+            node: match settings.get::<NodeConfig>("node") {
+                Err(ConfigError::NotFound(_)) => NodeConfig::default(),
+                result => result?,
+            },
+            storage: match settings.get::<StorageConfig>("storage") {
+                Err(ConfigError::NotFound(_)) => StorageConfig::default(),
+                result => result?,
+            },
+            webhooks: match settings.get::<WebhookConfig>("webhooks") {
+                Err(ConfigError::NotFound(_)) => WebhookConfig::default(),
+                result => result?,
+            },
+            admin: match settings.get::<AdminConfig>("admin") {
+                Err(ConfigError::NotFound(_)) => AdminConfig::default(),
+                result => result?,
+            },
         })
     }
 
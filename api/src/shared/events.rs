@@ -1,4 +1,5 @@
-use crate::transaction::models::{Transaction, Transfer, TxData};
+use crate::shared::models::{Address, Hash160};
+use crate::transaction::models::{Notification, StateValue, Transaction, Transfer, TxData};
 
 use crate::shared::neo;
 
@@ -13,56 +14,32 @@ pub fn get_transfer_events(tx: Transaction) -> TxData {
     let mut transfers = Vec::new();
 
     for notification in tx.notifications {
-        let state = notification.state.clone();
+        let (contract, from, to, qty) = match parse_transfer(&notification) {
+            Some(parts) => parts,
+            None => continue,
+        };
 
-        if notification.eventname == "Transfer"
-            && state._type == "Array"
-            && state.value[0]._type == "ByteString"
-            || state.value[0]._type == "Any" && state.value[1]._type == "ByteString"
-            || state.value[1]._type == "Any" && state.value[2]._type == "Integer"
-        {
-            let contract = notification.contract.clone();
-
-            let from = if let Some(serde_json::Value::String(s)) = &state.value[0].value {
-                neo::base64_to_address(s)
-            } else {
-                "null".to_string()
-            };
-
-            let to = if let Some(serde_json::Value::String(s)) = &state.value[1].value {
-                neo::base64_to_address(s)
-            } else {
-                "null".to_string()
-            };
-
-            let qty = if let Some(serde_json::Value::String(s)) = &state.value[2].value {
-                s.clone()
-            } else {
-                "0".to_string()
-            };
-
-            let amount = match qty.parse::<f64>() {
-                Ok(v) => {
-                    if contract == "0xef4073a0f2b305a38ec4050e4d3d28bc40ea63f5" {
-                        v
-                    } else if contract == "0xcd48b160c1bbc9d74997b803b9a7ad50a4bef020" {
-                        v / FUSDT_PRECISION
-                    } else {
-                        v / GAS_PRECISION
-                    }
+        let amount = match qty.parse::<f64>() {
+            Ok(v) => {
+                if contract == "0xef4073a0f2b305a38ec4050e4d3d28bc40ea63f5" {
+                    v
+                } else if contract == "0xcd48b160c1bbc9d74997b803b9a7ad50a4bef020" {
+                    v / FUSDT_PRECISION
+                } else {
+                    v / GAS_PRECISION
                 }
-                Err(_) => continue,
-            };
+            }
+            Err(_) => continue,
+        };
 
-            let transfer = Transfer {
-                contract,
-                from,
-                to,
-                amount, // this will break on non-8 decimal contracts, will need contract table
-            };
+        let transfer = Transfer {
+            contract,
+            from,
+            to,
+            amount, // this will break on non-8 decimal contracts, will need contract table
+        };
 
-            transfers.push(transfer);
-        }
+        transfers.push(transfer);
     }
 
     TxData {
@@ -74,3 +51,63 @@ pub fn get_transfer_events(tx: Transaction) -> TxData {
         nep11_transfers: Vec::new(),
     }
 }
+
+// contract, from, to and the raw integer amount of a Transfer notification
+pub fn parse_transfer(notification: &Notification) -> Option<(Hash160, Address, Address, String)> {
+    let state = &notification.state;
+    if notification.eventname != "Transfer" || state._type != "Array" || state.value.len() < 3 {
+        return None;
+    }
+
+    // from and to are Any when tokens are minted or burned
+    let is_account = |value: &StateValue| value._type == "ByteString" || value._type == "Any";
+    if is_account(&state.value[0])
+        && is_account(&state.value[1])
+        && state.value[2]._type == "Integer"
+    {
+        let from = if let Some(serde_json::Value::String(s)) = &state.value[0].value {
+            neo::base64_to_address(s)
+        } else {
+            "null".to_string()
+        };
+
+        let to = if let Some(serde_json::Value::String(s)) = &state.value[1].value {
+            neo::base64_to_address(s)
+        } else {
+            "null".to_string()
+        };
+
+        let qty = if let Some(serde_json::Value::String(s)) = &state.value[2].value {
+            s.clone()
+        } else {
+            "0".to_string()
+        };
+
+        Some((notification.contract.clone(), from, to, qty))
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_parse_transfer_shapes() {
+    let notification = |eventname: &str, state: serde_json::Value| Notification {
+        id: None,
+        contract: "0xd2a4cff31913016155e38e474a2c06d08be276cf".to_string(),
+        eventname: eventname.to_string(),
+        state: serde_json::from_value(state).unwrap(),
+    };
+    let mint = serde_json::json!({"type": "Array", "value": [
+        {"type": "Any", "value": null},
+        {"type": "ByteString", "value": "z6LDQN4w2GM3HBPAkWIJVqpVmn0="},
+        {"type": "Integer", "value": "100"}
+    ]});
+
+    assert!(parse_transfer(&notification("Transfer", mint.clone())).is_some());
+    assert!(parse_transfer(&notification("Mint", mint)).is_none());
+    assert!(parse_transfer(&notification(
+        "Transfer",
+        serde_json::json!({"type": "Array", "value": []})
+    ))
+    .is_none());
+}
@@ -0,0 +1,229 @@
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::HttpResponse;
+use futures::StreamExt;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use std::future::Future;
+
+use crate::error::ApiError;
+
+// rows buffered between the database thread and the response before it backs off
+const EXPORT_BUFFER: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+impl ExportFormat {
+    // no format (or "json") keeps the regular paged JSON response
+    pub fn parse(format: Option<&str>) -> Result<Option<ExportFormat>, ApiError> {
+        match format {
+            None | Some("") | Some("json") => Ok(None),
+            Some("csv") => Ok(Some(ExportFormat::Csv)),
+            Some("ndjson") => Ok(Some(ExportFormat::Ndjson)),
+            Some(other) => Err(ApiError::BadRequest(format!(
+                "Invalid format parameter: {}",
+                other
+            ))),
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+pub trait ExportRow: Serialize {
+    const HEADER: &'static [&'static str];
+
+    fn record(&self) -> Vec<String>;
+}
+
+// spreadsheets run a cell starting with one of these as a formula
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+// a leading ' keeps spreadsheets from evaluating the field, negative amounts included
+fn csv_field(field: &str) -> String {
+    let field = match field.starts_with(FORMULA_PREFIXES) {
+        true => format!("'{}", field),
+        false => field.to_string(),
+    };
+
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+fn csv_line<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|f| csv_field(f.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+fn encode_row<T: ExportRow>(format: ExportFormat, row: &T) -> Result<String, ApiError> {
+    match format {
+        ExportFormat::Csv => Ok(csv_line(&row.record())),
+        ExportFormat::Ndjson => {
+            let mut line = serde_json::to_string(row)
+                .map_err(|err| ApiError::Internal(format!("Failed to encode row: {}", err)))?;
+            line.push('\n');
+            Ok(line)
+        }
+    }
+}
+
+// integer token amounts shifted by the token decimals without going through floats
+pub fn format_amount(raw: &str, decimals: u8) -> String {
+    let (negative, digits) = match raw.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, raw),
+    };
+    let digits = digits.trim_start_matches('0');
+    let decimals = decimals as usize;
+
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+
+    let sign = if negative && !digits.is_empty() {
+        "-"
+    } else {
+        ""
+    };
+    match fraction.is_empty() {
+        true => format!("{}{}", sign, whole),
+        false => format!("{}{}.{}", sign, whole, fraction),
+    }
+}
+
+// rows of an export in the order the storage produced them, an error ends them
+pub type ExportRows<T> = mpsc::Receiver<Result<T, ApiError>>;
+
+// runs a blocking producer, like a SQLite query, on its own thread. the producer gets a
+// callback per row, which returns false once the client has gone away
+pub fn produce_blocking<T, F>(producer: F) -> ExportRows<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut dyn FnMut(T) -> bool) -> Result<(), ApiError> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(EXPORT_BUFFER);
+
+    tokio::task::spawn_blocking(move || {
+        let mut emit = |row: T| tx.blocking_send(Ok(row)).is_ok();
+        if let Err(err) = producer(&mut emit) {
+            let _ = tx.blocking_send(Err(err));
+        }
+    });
+
+    rx
+}
+
+pub struct ExportSink<T>(mpsc::Sender<Result<T, ApiError>>);
+
+impl<T> ExportSink<T> {
+    // false once the client has gone away
+    pub async fn emit(&self, row: T) -> bool {
+        self.0.send(Ok(row)).await.is_ok()
+    }
+}
+
+// runs an async producer, like a Postgres query, as its own task
+pub fn produce<T, F, Fut>(producer: F) -> ExportRows<T>
+where
+    T: Send + 'static,
+    F: FnOnce(ExportSink<T>) -> Fut,
+    Fut: Future<Output = Result<(), ApiError>> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(EXPORT_BUFFER);
+    let rows = producer(ExportSink(tx.clone()));
+
+    tokio::spawn(async move {
+        if let Err(err) = rows.await {
+            let _ = tx.send(Err(err)).await;
+        }
+    });
+
+    rx
+}
+
+// streams every row the storage produces, with no page limit
+pub fn stream<T>(format: ExportFormat, name: &str, rows: ExportRows<T>) -> HttpResponse
+where
+    T: ExportRow + 'static,
+{
+    let header = (format == ExportFormat::Csv).then(|| Ok(Bytes::from(csv_line(T::HEADER))));
+
+    let lines = futures::stream::unfold(rows, move |mut rows| async move {
+        let line = rows
+            .recv()
+            .await?
+            .and_then(|row| encode_row(format, &row))
+            .map(Bytes::from);
+        if let Err(err) = &line {
+            log::error!("Export failed: {}", err);
+        }
+
+        Some((line, rows))
+    });
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.{}\"", name, format.extension()),
+        ))
+        .streaming(futures::stream::iter(header).chain(lines))
+}
+
+#[test]
+fn test_csv_line() {
+    assert_eq!(csv_line(&["a", "b c", ""]), "a,b c,\r\n");
+    assert_eq!(
+        csv_line(&["1,5", "say \"hi\"", "x\ny"]),
+        "\"1,5\",\"say \"\"hi\"\"\",\"x\ny\"\r\n"
+    );
+}
+
+#[test]
+fn test_csv_formula_fields() {
+    assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+    assert_eq!(csv_field("+1"), "'+1");
+    assert_eq!(csv_field("-1.5"), "'-1.5");
+    assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+    assert_eq!(csv_field("\tx"), "'\tx");
+    assert_eq!(csv_field("1-2"), "1-2");
+    assert_eq!(
+        csv_field("NXV7ZhHiyM1aHXwpVsRZC6BwNFP2jghXAq"),
+        "NXV7ZhHiyM1aHXwpVsRZC6BwNFP2jghXAq"
+    );
+}
+
+#[test]
+fn test_format_amount() {
+    assert_eq!(format_amount("123456789", 8), "1.23456789");
+    assert_eq!(format_amount("100000000", 8), "1");
+    assert_eq!(format_amount("5", 8), "0.00000005");
+    assert_eq!(format_amount("-2500", 2), "-25");
+    assert_eq!(format_amount("42", 0), "42");
+    assert_eq!(format_amount("0", 8), "0");
+}
@@ -1,7 +1,14 @@
+pub mod abi;
+pub mod auth;
 pub mod checker;
 pub mod config;
 pub mod db;
 pub mod events;
+pub mod export;
 pub mod models;
 pub mod neo;
+pub mod pagination;
+pub mod postgres;
+pub mod shutdown;
+pub mod storage;
 pub mod utils;
@@ -1,11 +1,42 @@
 use serde::{Deserialize, Serialize};
 
+use crate::shared::checker;
+use crate::shared::pagination::Page;
+
 pub const GAS_PRECISION: f64 = 100000000.0;
+pub const GAS_DECIMALS: u8 = 8;
 pub const FUSDT_PRECISION: f64 = 1000000.0;
 
+pub const CONTRACT_MANAGEMENT_HASH: &str = "0xfffdc93764dbaddd97c48f252a53ea4643faa3fd";
+
+// native tokens are never deployed, so their metadata isn't in the contracts table
+pub const NATIVE_TOKENS: [(&str, &str, u8); 2] = [
+    ("0xef4073a0f2b305a38ec4050e4d3d28bc40ea63f5", "NEO", 0),
+    ("0xd2a4cff31913016155e38e474a2c06d08be276cf", "GAS", 8),
+];
+
 pub type Hash160 = String;
 pub type Address = String;
 
+// path segment accepting any address or script hash form, normalized to an address
+#[derive(Deserialize, Clone)]
+#[serde(try_from = "String")]
+pub struct AddressParam(pub Address);
+
+impl TryFrom<String> for AddressParam {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        checker::normalize_neo_address(&value).map(AddressParam)
+    }
+}
+
+impl AddressParam {
+    pub fn into_inner(self) -> Address {
+        self.0
+    }
+}
+
 pub const PAGE_DEFAULT: u32 = 0;
 pub const PER_PAGE_DEFAULT: u32 = 100;
 pub const PER_PAGE_LIMIT: u32 = 1000;
@@ -20,6 +51,9 @@ pub struct PaginationAndFilterParams {
     pub date_init: Option<String>,    // Filter date init
     pub date_end: Option<String>,     // Filter date end
     pub with_transfers: Option<bool>, // Filter with transfers
+
+    pub cursor: Option<String>, // Keyset cursor, empty for the first page
+    pub format: Option<String>, // "csv" or "ndjson" streams the full result set
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -32,11 +66,21 @@ pub struct Event {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PagedResp<T> {
     pub list: T,
-    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>, // not computed for cursor pages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
 }
 
 impl<T> PagedResp<T> {
-    pub fn new(list: T, count: usize) -> Self {
-        Self { list, count }
+    pub fn from_page(page: Page<T>, count: Option<usize>) -> Self {
+        Self {
+            list: page.list,
+            count,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+        }
     }
 }
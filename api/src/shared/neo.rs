@@ -627,7 +627,6 @@ pub fn checksum(data: &[u8]) -> Vec<u8> {
     Sha256::digest(Sha256::digest(data)).to_vec()
 }
 
-#[allow(dead_code)]
 pub fn reverse_hex(hex: &str) -> String {
     let mut value = hex::decode(hex).unwrap();
     value.reverse();
@@ -0,0 +1,275 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::error::ApiError;
+
+// cursors carry the sort direction with them, so a client only has to echo them back
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cursor {
+    pub key: i64,
+    pub descending: bool,
+    pub backward: bool,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}:{}:{}",
+            if self.descending { "d" } else { "a" },
+            if self.backward { "p" } else { "n" },
+            self.key
+        );
+
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(encoded: &str) -> Result<Cursor, ApiError> {
+        let invalid = || ApiError::BadRequest("Invalid cursor.".to_string());
+
+        let bytes = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| invalid())?;
+        let raw = String::from_utf8(bytes).map_err(|_| invalid())?;
+
+        let mut parts = raw.splitn(3, ':');
+        let descending = match parts.next() {
+            Some("a") => false,
+            Some("d") => true,
+            _ => return Err(invalid()),
+        };
+        let backward = match parts.next() {
+            Some("n") => false,
+            Some("p") => true,
+            _ => return Err(invalid()),
+        };
+        let key = parts
+            .next()
+            .and_then(|k| k.parse::<i64>().ok())
+            .ok_or_else(invalid)?;
+
+        Ok(Cursor {
+            key,
+            descending,
+            backward,
+        })
+    }
+}
+
+pub enum Pagination {
+    Offset {
+        page: u32,
+        per_page: u32,
+        sort_by: Option<String>,
+        order: Option<String>,
+    },
+    // keyset pagination on an indexed integer key, no OFFSET scan and no COUNT(*)
+    Keyset {
+        cursor: Option<Cursor>,
+        descending: bool,
+        per_page: u32,
+    },
+}
+
+pub struct Page<L> {
+    pub list: L,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+impl Pagination {
+    pub fn is_keyset(&self) -> bool {
+        matches!(self, Pagination::Keyset { .. })
+    }
+
+    // a keyset page after the first. running past the last row gives an empty page, not a 404
+    pub fn has_cursor(&self) -> bool {
+        matches!(
+            self,
+            Pagination::Keyset {
+                cursor: Some(_),
+                ..
+            }
+        )
+    }
+
+    // returns the extra WHERE condition and the ORDER BY/LIMIT tail for a query
+    pub fn sql(
+        &self,
+        key_column: &str,
+        valid_columns: &[&str],
+    ) -> Result<(String, String), ApiError> {
+        match self {
+            Pagination::Offset {
+                page,
+                per_page,
+                sort_by,
+                order,
+            } => {
+                // sort columns are qualified like the key column, so joins stay unambiguous
+                let qualifier = key_column
+                    .rsplit_once('.')
+                    .map(|(table, _)| format!("{}.", table))
+                    .unwrap_or_default();
+
+                let order_clause = if let (Some(sort_by), Some(order)) = (sort_by, order) {
+                    if valid_columns.contains(&sort_by.as_str()) {
+                        format!("ORDER BY {}{} {}", qualifier, sort_by, order)
+                    } else {
+                        return Err(ApiError::Unprocessable(format!(
+                            "Invalid sort_by parameter: {}",
+                            sort_by
+                        )));
+                    }
+                } else {
+                    String::new()
+                };
+
+                Ok((
+                    String::new(),
+                    format!(
+                        "{} LIMIT {} OFFSET {}",
+                        order_clause,
+                        per_page,
+                        page * per_page
+                    ),
+                ))
+            }
+            Pagination::Keyset {
+                cursor,
+                descending,
+                per_page,
+            } => {
+                let backward = cursor.map(|c| c.backward).unwrap_or(false);
+                let (comparison, direction) = if *descending != backward {
+                    ("<", "DESC")
+                } else {
+                    (">", "ASC")
+                };
+
+                let condition = cursor
+                    .map(|c| format!("AND {} {} {}", key_column, comparison, c.key))
+                    .unwrap_or_default();
+
+                // one extra row tells us whether another page exists
+                Ok((
+                    condition,
+                    format!(
+                        "ORDER BY {} {} LIMIT {}",
+                        key_column,
+                        direction,
+                        per_page + 1
+                    ),
+                ))
+            }
+        }
+    }
+
+    // takes the rows fetched with `sql` as (key, item) pairs and trims them into a page
+    pub fn finish<T>(&self, mut rows: Vec<(i64, T)>) -> Page<Vec<T>> {
+        let (cursor, descending, per_page) = match self {
+            Pagination::Offset { .. } => {
+                return Page {
+                    list: rows.into_iter().map(|(_, item)| item).collect(),
+                    next_cursor: None,
+                    prev_cursor: None,
+                }
+            }
+            Pagination::Keyset {
+                cursor,
+                descending,
+                per_page,
+            } => (cursor, *descending, *per_page as usize),
+        };
+
+        let backward = cursor.map(|c| c.backward).unwrap_or(false);
+        let has_more = rows.len() > per_page;
+        rows.truncate(per_page);
+
+        if backward {
+            rows.reverse();
+        }
+
+        let make = |key: i64, backward: bool| {
+            Cursor {
+                key,
+                descending,
+                backward,
+            }
+            .encode()
+        };
+
+        let more_after = if backward { cursor.is_some() } else { has_more };
+        let more_before = if backward { has_more } else { cursor.is_some() };
+
+        let next_cursor = rows
+            .last()
+            .filter(|_| more_after)
+            .map(|(key, _)| make(*key, false));
+        let prev_cursor = rows
+            .first()
+            .filter(|_| more_before)
+            .map(|(key, _)| make(*key, true));
+
+        Page {
+            list: rows.into_iter().map(|(_, item)| item).collect(),
+            next_cursor,
+            prev_cursor,
+        }
+    }
+}
+
+#[test]
+fn test_cursor_roundtrip() {
+    let cursor = Cursor {
+        key: 1234567,
+        descending: true,
+        backward: false,
+    };
+
+    assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+    assert!(Cursor::decode("not a cursor").is_err());
+    assert!(Cursor::decode(&URL_SAFE_NO_PAD.encode("a:n:x")).is_err());
+}
+
+#[test]
+fn test_keyset_pages() {
+    let rows = |keys: &[i64]| keys.iter().map(|k| (*k, *k)).collect::<Vec<_>>();
+
+    let first = Pagination::Keyset {
+        cursor: None,
+        descending: false,
+        per_page: 2,
+    };
+    let (condition, tail) = first.sql("t.id", &[]).unwrap();
+    assert_eq!(condition, "");
+    assert_eq!(tail, "ORDER BY t.id ASC LIMIT 3");
+
+    let page = first.finish(rows(&[1, 2, 3]));
+    assert_eq!(page.list, vec![1, 2]);
+    assert!(page.prev_cursor.is_none());
+
+    let next = Pagination::Keyset {
+        cursor: Some(Cursor::decode(&page.next_cursor.unwrap()).unwrap()),
+        descending: false,
+        per_page: 2,
+    };
+    let (condition, _) = next.sql("t.id", &[]).unwrap();
+    assert_eq!(condition, "AND t.id > 2");
+
+    let page = next.finish(rows(&[3]));
+    assert_eq!(page.list, vec![3]);
+    assert!(page.next_cursor.is_none());
+
+    // walking back from the last page returns rows in reverse key order
+    let prev = Pagination::Keyset {
+        cursor: Some(Cursor::decode(&page.prev_cursor.unwrap()).unwrap()),
+        descending: false,
+        per_page: 2,
+    };
+    let (condition, tail) = prev.sql("t.id", &[]).unwrap();
+    assert_eq!(condition, "AND t.id < 3");
+    assert_eq!(tail, "ORDER BY t.id DESC LIMIT 3");
+
+    let page = prev.finish(rows(&[2, 1]));
+    assert_eq!(page.list, vec![1, 2]);
+    assert!(page.prev_cursor.is_none());
+    assert!(page.next_cursor.is_some());
+}
@@ -0,0 +1,1109 @@
+use async_trait::async_trait;
+use deadpool_postgres::{Manager, Object, Pool};
+use futures::{pin_mut, TryStreamExt};
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, NoTls, Row};
+
+use std::collections::HashMap;
+
+use crate::address::models::{PortfolioDay, SeenAt, TokenBalance};
+use crate::address::postgres as address_postgres;
+use crate::block::models::{Block, BlockSummary, Witness};
+use crate::block::postgres as block_postgres;
+use crate::contract::models::ContractEvent;
+use crate::contract::postgres as contract_postgres;
+use crate::error::ApiError;
+use crate::history::models::{
+    DailyAddressBalance, DailyContractUsage, DailyTokenPrice, DailyTokenSupply,
+};
+use crate::history::postgres as history_postgres;
+use crate::leaderboard::models::{ContractEntry, Holder, SenderEntry, Window};
+use crate::leaderboard::postgres as leaderboard_postgres;
+use crate::search::models::SearchResult;
+use crate::search::postgres as search_postgres;
+use crate::shared::abi::DecodedEvent;
+use crate::shared::export::{produce, ExportRows, ExportSink};
+use crate::shared::pagination::{Page, Pagination};
+use crate::shared::storage::{ReadStorage, WebhookStorage};
+use crate::stat::models::{DailyNetworkStats, NetworkStatistics, ShrikeStats};
+use crate::stat::postgres as stat_postgres;
+use crate::token::models::{HolderBalance, TokenInfo, TokenOverview};
+use crate::token::postgres as token_postgres;
+use crate::transaction::models::{
+    Notification, Signer, StateValue, Transaction, TransferExport, TxDataList,
+};
+use crate::transaction::postgres as transaction_postgres;
+use crate::webhook::models::{
+    PendingDelivery, Webhook, WebhookDeadLetter, WebhookDelivery, WebhookRequest,
+};
+use crate::webhook::postgres as webhook_postgres;
+
+// integer columns are BIGINT, narrowed here to the width the models use
+pub fn get_int<T: TryFrom<i64>>(row: &Row, index: usize) -> Result<T, ApiError> {
+    let value: i64 = row.try_get(index)?;
+
+    T::try_from(value).map_err(|_| out_of_range(value))
+}
+
+pub fn get_opt_int<T: TryFrom<i64>>(row: &Row, index: usize) -> Result<Option<T>, ApiError> {
+    row.try_get::<_, Option<i64>>(index)?
+        .map(|value| T::try_from(value).map_err(|_| out_of_range(value)))
+        .transpose()
+}
+
+fn out_of_range(value: i64) -> ApiError {
+    ApiError::Internal(format!("Database value {} is out of range.", value))
+}
+
+pub fn to_i64(value: u64) -> Result<i64, ApiError> {
+    i64::try_from(value).map_err(|_| ApiError::BadRequest(format!("{} is out of range.", value)))
+}
+
+// SQLite hands rows back in key order when no sort is asked for, Postgres in whatever
+// order it finds them, so pages here always fall back to the key
+pub fn page_sql(
+    pagination: &Pagination,
+    key_column: &str,
+    valid_columns: &[&str],
+) -> Result<(String, String), ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql(key_column, valid_columns)?;
+
+    match page_clause.trim_start().starts_with("ORDER BY") {
+        true => Ok((keyset_clause, page_clause)),
+        false => Ok((
+            keyset_clause,
+            format!("ORDER BY {} {}", key_column, page_clause),
+        )),
+    }
+}
+
+// hands the rows of a query to an export as they arrive, stopping once the client has
+// gone away. nothing else can run on the connection until the stream is done
+pub async fn stream_rows<T>(
+    client: &Client,
+    sql: &str,
+    params: &[&(dyn ToSql + Sync)],
+    sink: &ExportSink<T>,
+    map: impl Fn(&Row) -> Result<T, ApiError>,
+) -> Result<(), ApiError> {
+    let rows = client.query_raw(sql, params.iter().copied()).await?;
+    pin_mut!(rows);
+
+    while let Some(row) = rows.try_next().await? {
+        if !sink.emit(map(&row)?).await {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+// the tables the Postgres indexer writes, webhooks included
+pub struct PostgresStorage {
+    pool: Pool,
+}
+
+impl PostgresStorage {
+    pub fn new(url: &str) -> Result<Self, ApiError> {
+        let config = url
+            .parse::<tokio_postgres::Config>()
+            .map_err(|err| ApiError::Internal(format!("Invalid storage.url: {}", err)))?;
+
+        let pool = Pool::builder(Manager::new(config, NoTls))
+            .build()
+            .map_err(|err| ApiError::Internal(format!("Failed to build Postgres pool: {}", err)))?;
+
+        Ok(PostgresStorage { pool })
+    }
+
+    async fn client(&self) -> Result<Object, ApiError> {
+        Ok(self.pool.get().await?)
+    }
+}
+
+#[async_trait]
+impl ReadStorage for PostgresStorage {
+    async fn get_block(&self, path: String) -> Result<Block, ApiError> {
+        let client = self.client().await?;
+        block_postgres::get_block_internal(&client, path).await
+    }
+
+    async fn get_block_witnesses(&self, block_index: u64) -> Result<Vec<Witness>, ApiError> {
+        let client = self.client().await?;
+        block_postgres::get_witnesses(&client, block_index).await
+    }
+
+    async fn get_block_time(&self, path: String) -> Result<u64, ApiError> {
+        let client = self.client().await?;
+        block_postgres::get_block_time(&client, path).await
+    }
+
+    async fn get_block_transactions(&self, path: String) -> Result<Vec<Transaction>, ApiError> {
+        let client = self.client().await?;
+        block_postgres::get_block_transactions_internal(&client, path).await
+    }
+
+    async fn list_blocks(
+        &self,
+        date_init: Option<String>,
+        date_end: Option<String>,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<BlockSummary>>, ApiError> {
+        let client = self.client().await?;
+        block_postgres::list_blocks_internal(&client, date_init, date_end, pagination).await
+    }
+
+    async fn count_blocks(
+        &self,
+        date_init: Option<String>,
+        date_end: Option<String>,
+    ) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        block_postgres::count_blocks_internal(&client, date_init, date_end).await
+    }
+
+    async fn get_latest_blocks(&self, limit: u32) -> Result<Vec<BlockSummary>, ApiError> {
+        let client = self.client().await?;
+        block_postgres::get_latest_blocks_internal(&client, limit).await
+    }
+
+    async fn get_transaction(&self, hash: String) -> Result<Transaction, ApiError> {
+        let client = self.client().await?;
+        transaction_postgres::get_transaction_internal(&client, hash).await
+    }
+
+    async fn get_transaction_witnesses(&self, hash: String) -> Result<Vec<Witness>, ApiError> {
+        let client = self.client().await?;
+        transaction_postgres::get_witnesses(&client, hash).await
+    }
+
+    async fn get_signers(&self, hash: String) -> Result<Vec<Signer>, ApiError> {
+        let client = self.client().await?;
+        transaction_postgres::get_signers(&client, hash).await
+    }
+
+    async fn get_transaction_notifications(
+        &self,
+        hash: String,
+    ) -> Result<Vec<Notification>, ApiError> {
+        let client = self.client().await?;
+        transaction_postgres::get_transaction_notifications(&client, hash).await
+    }
+
+    async fn get_notification_state_values(
+        &self,
+        notification_id: u64,
+    ) -> Result<Vec<StateValue>, ApiError> {
+        let client = self.client().await?;
+        transaction_postgres::get_notification_state_values(&client, notification_id).await
+    }
+
+    async fn get_sender_transactions(
+        &self,
+        address: String,
+        with_transfers: bool,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<Transaction>>, ApiError> {
+        let client = self.client().await?;
+        transaction_postgres::get_sender_transactions_internal(
+            &client,
+            address,
+            with_transfers,
+            pagination,
+        )
+        .await
+    }
+
+    async fn count_sender_transactions(&self, address: String) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        transaction_postgres::count_sender_transactions_internal(&client, address).await
+    }
+
+    async fn get_address_transfers(
+        &self,
+        address: String,
+        pagination: &Pagination,
+    ) -> Result<Page<TxDataList>, ApiError> {
+        let client = self.client().await?;
+        transaction_postgres::get_address_transfers_internal(&client, address, pagination).await
+    }
+
+    async fn count_address_transfers(&self, address: String) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        transaction_postgres::count_address_transfers_internal(&client, address).await
+    }
+
+    async fn export_address_transfers(
+        &self,
+        address: String,
+    ) -> Result<ExportRows<TransferExport>, ApiError> {
+        let client = self.client().await?;
+        Ok(produce(move |sink| async move {
+            transaction_postgres::export_address_transfers_internal(&client, address, &sink).await
+        }))
+    }
+
+    async fn get_token_info(
+        &self,
+        contract: &str,
+    ) -> Result<(Option<String>, Option<u8>), ApiError> {
+        let client = self.client().await?;
+        contract_postgres::get_token_info(&client, contract).await
+    }
+
+    async fn decode_notifications(
+        &self,
+        notifications: Vec<Notification>,
+    ) -> Result<Vec<DecodedEvent>, ApiError> {
+        let client = self.client().await?;
+        contract_postgres::decode_notifications(&client, notifications).await
+    }
+
+    async fn list_contract_events(
+        &self,
+        contract: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<ContractEvent>>, ApiError> {
+        let client = self.client().await?;
+        contract_postgres::list_contract_events_internal(&client, contract, pagination).await
+    }
+
+    async fn count_contract_events(&self, contract: String) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        contract_postgres::count_contract_events_internal(&client, contract).await
+    }
+
+    async fn list_history_balance(
+        &self,
+        address: String,
+        token: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyAddressBalance>>, ApiError> {
+        let client = self.client().await?;
+        history_postgres::list_history_balance_internal(
+            &client, address, token, date_init, date_end, pagination,
+        )
+        .await
+    }
+
+    async fn count_history_balance(
+        &self,
+        address: String,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        history_postgres::count_history_balance_internal(
+            &client, address, token, date_init, date_end,
+        )
+        .await
+    }
+
+    async fn export_history_balance(
+        &self,
+        address: String,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyAddressBalance>, ApiError> {
+        let client = self.client().await?;
+        Ok(produce(move |sink| async move {
+            history_postgres::export_history_balance_internal(
+                &client, address, token, date_init, date_end, &sink,
+            )
+            .await
+        }))
+    }
+
+    async fn list_history_price_token(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyTokenPrice>>, ApiError> {
+        let client = self.client().await?;
+        history_postgres::list_history_price_token_internal(
+            &client, token, date_init, date_end, pagination,
+        )
+        .await
+    }
+
+    async fn count_history_price_token(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        history_postgres::count_history_price_token_internal(&client, token, date_init, date_end)
+            .await
+    }
+
+    async fn export_history_price_token(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyTokenPrice>, ApiError> {
+        let client = self.client().await?;
+        Ok(produce(move |sink| async move {
+            history_postgres::export_history_price_token_internal(
+                &client, token, date_init, date_end, &sink,
+            )
+            .await
+        }))
+    }
+
+    async fn list_token_supply_history(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyTokenSupply>>, ApiError> {
+        let client = self.client().await?;
+        history_postgres::list_token_supply_history_internal(
+            &client, token, date_init, date_end, pagination,
+        )
+        .await
+    }
+
+    async fn count_token_supply_history(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        history_postgres::count_token_supply_history_internal(&client, token, date_init, date_end)
+            .await
+    }
+
+    async fn export_token_supply_history(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyTokenSupply>, ApiError> {
+        let client = self.client().await?;
+        Ok(produce(move |sink| async move {
+            history_postgres::export_token_supply_history_internal(
+                &client, token, date_init, date_end, &sink,
+            )
+            .await
+        }))
+    }
+
+    async fn list_daily_contract_usage(
+        &self,
+        contract: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyContractUsage>>, ApiError> {
+        let client = self.client().await?;
+        history_postgres::list_daily_contract_usage_internal(
+            &client, contract, date_init, date_end, pagination,
+        )
+        .await
+    }
+
+    async fn count_daily_contract_usage(
+        &self,
+        contract: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        history_postgres::count_daily_contract_usage_internal(
+            &client, contract, date_init, date_end,
+        )
+        .await
+    }
+
+    async fn export_daily_contract_usage(
+        &self,
+        contract: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyContractUsage>, ApiError> {
+        let client = self.client().await?;
+        Ok(produce(move |sink| async move {
+            history_postgres::export_daily_contract_usage_internal(
+                &client, contract, date_init, date_end, &sink,
+            )
+            .await
+        }))
+    }
+
+    async fn get_seen_range(&self, address: String) -> Result<Option<(SeenAt, SeenAt)>, ApiError> {
+        let client = self.client().await?;
+        address_postgres::get_seen_range_internal(&client, address).await
+    }
+
+    async fn get_total_fees(&self, address: String) -> Result<f64, ApiError> {
+        let client = self.client().await?;
+        address_postgres::get_total_fees_internal(&client, address).await
+    }
+
+    async fn get_current_balances(&self, address: String) -> Result<Vec<TokenBalance>, ApiError> {
+        let client = self.client().await?;
+        address_postgres::get_current_balances_internal(&client, address).await
+    }
+
+    async fn get_deployed_contracts(&self, address: String) -> Result<Vec<String>, ApiError> {
+        let client = self.client().await?;
+        address_postgres::get_deployed_contracts_internal(&client, address).await
+    }
+
+    async fn get_latest_price(&self, token: &str) -> Result<Option<(f64, String)>, ApiError> {
+        let client = self.client().await?;
+        address_postgres::get_latest_price_internal(&client, token).await
+    }
+
+    async fn get_portfolio_history(
+        &self,
+        address: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<Vec<PortfolioDay>, ApiError> {
+        let client = self.client().await?;
+        address_postgres::get_portfolio_history_internal(&client, address, date_init, date_end)
+            .await
+    }
+
+    async fn get_window_start(&self, window: Window) -> Result<Option<u64>, ApiError> {
+        let client = self.client().await?;
+        leaderboard_postgres::get_window_start_internal(&client, window).await
+    }
+
+    async fn get_top_holders(&self, token: String, limit: u32) -> Result<Vec<Holder>, ApiError> {
+        let client = self.client().await?;
+        leaderboard_postgres::get_top_holders_internal(&client, token, limit).await
+    }
+
+    async fn get_top_senders(
+        &self,
+        since: Option<u64>,
+        limit: u32,
+    ) -> Result<Vec<SenderEntry>, ApiError> {
+        let client = self.client().await?;
+        leaderboard_postgres::get_top_senders_internal(&client, since, limit).await
+    }
+
+    async fn get_top_contracts(
+        &self,
+        since: Option<u64>,
+        limit: u32,
+    ) -> Result<Vec<ContractEntry>, ApiError> {
+        let client = self.client().await?;
+        leaderboard_postgres::get_top_contracts_internal(&client, since, limit).await
+    }
+
+    async fn list_token_contracts(&self, token: Option<&str>) -> Result<Vec<TokenInfo>, ApiError> {
+        let client = self.client().await?;
+        token_postgres::list_token_contracts_internal(&client, token).await
+    }
+
+    async fn get_volume_24h(&self, token: Option<&str>) -> Result<HashMap<String, i128>, ApiError> {
+        let client = self.client().await?;
+        token_postgres::get_volume_24h_internal(&client, token).await
+    }
+
+    async fn get_token_overview(
+        &self,
+        token: TokenInfo,
+        volume_24h: i128,
+    ) -> Result<TokenOverview, ApiError> {
+        let client = self.client().await?;
+        token_postgres::get_token_overview_internal(&client, token, volume_24h).await
+    }
+
+    async fn get_snapshot_block(&self, block: Option<u64>) -> Result<u64, ApiError> {
+        let client = self.client().await?;
+        token_postgres::get_snapshot_block_internal(&client, block).await
+    }
+
+    // Postgres databases keep every balance change from the start, none were seeded from
+    // end of day rows
+    async fn get_seeded_height(&self) -> Result<Option<u64>, ApiError> {
+        Ok(None)
+    }
+
+    async fn list_holders_at(
+        &self,
+        token: String,
+        block: u64,
+        decimals: Option<u8>,
+    ) -> Result<Vec<HolderBalance>, ApiError> {
+        let client = self.client().await?;
+        token_postgres::list_holders_at_internal(&client, token, block, decimals).await
+    }
+
+    async fn export_holders_at(
+        &self,
+        token: String,
+        block: u64,
+        decimals: Option<u8>,
+    ) -> Result<ExportRows<HolderBalance>, ApiError> {
+        let client = self.client().await?;
+        Ok(produce(move |sink| async move {
+            token_postgres::export_holders_at_internal(&client, token, block, decimals, &sink).await
+        }))
+    }
+
+    async fn search_block_index(&self, index: u64) -> Result<Vec<SearchResult>, ApiError> {
+        let client = self.client().await?;
+        search_postgres::search_block_index_internal(&client, index).await
+    }
+
+    async fn search_hash(&self, hash: &str) -> Result<Vec<SearchResult>, ApiError> {
+        let client = self.client().await?;
+        search_postgres::search_hash_internal(&client, hash).await
+    }
+
+    async fn search_script_hash(&self, hash: &str) -> Result<Vec<SearchResult>, ApiError> {
+        let client = self.client().await?;
+        search_postgres::search_script_hash_internal(&client, hash).await
+    }
+
+    async fn search_address(&self, address: &str) -> Result<Vec<SearchResult>, ApiError> {
+        let client = self.client().await?;
+        search_postgres::search_address_internal(&client, address).await
+    }
+
+    async fn search_name(&self, query: &str) -> Result<Vec<SearchResult>, ApiError> {
+        let client = self.client().await?;
+        search_postgres::search_name_internal(&client, query).await
+    }
+
+    async fn list_daily_stats(
+        &self,
+        date_init: String,
+        date_end: String,
+    ) -> Result<Vec<DailyNetworkStats>, ApiError> {
+        let client = self.client().await?;
+        stat_postgres::list_daily_stats_internal(&client, date_init, date_end).await
+    }
+
+    async fn get_total_blocks(&self) -> Result<u64, ApiError> {
+        let client = self.client().await?;
+        Ok(stat_postgres::get_blocks_internal(&client).await)
+    }
+
+    async fn get_stats(&self, blocks: u64) -> Result<(ShrikeStats, NetworkStatistics), ApiError> {
+        let client = self.client().await?;
+        stat_postgres::get_stats_internal(&client, blocks).await
+    }
+}
+
+#[async_trait]
+impl WebhookStorage for PostgresStorage {
+    async fn get_webhook_secret(&self, id: u64) -> Result<Option<String>, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::get_webhook_secret_internal(&client, id).await
+    }
+
+    async fn create_webhook(
+        &self,
+        request: WebhookRequest,
+        allow_private_urls: bool,
+    ) -> Result<Webhook, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::create_webhook_internal(&client, request, allow_private_urls).await
+    }
+
+    async fn list_webhooks(&self) -> Result<Vec<Webhook>, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::list_webhooks_internal(&client).await
+    }
+
+    async fn get_webhook(&self, id: u64) -> Result<Webhook, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::get_webhook_internal(&client, id).await
+    }
+
+    async fn deactivate_webhook(&self, id: u64) -> Result<(), ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::deactivate_webhook_internal(&client, id).await
+    }
+
+    async fn queue_test_delivery(&self, id: u64) -> Result<u64, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::queue_test_delivery_internal(&client, id).await
+    }
+
+    async fn list_deliveries(
+        &self,
+        id: u64,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<WebhookDelivery>>, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::list_deliveries_internal(&client, id, pagination).await
+    }
+
+    async fn count_deliveries(&self, id: u64) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::count_deliveries_internal(&client, id).await
+    }
+
+    async fn list_dead_letters(
+        &self,
+        id: u64,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<WebhookDeadLetter>>, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::list_dead_letters_internal(&client, id, pagination).await
+    }
+
+    async fn count_dead_letters(&self, id: u64) -> Result<usize, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::count_dead_letters_internal(&client, id).await
+    }
+
+    async fn get_due_deliveries(
+        &self,
+        now: u64,
+        limit: u32,
+    ) -> Result<Vec<PendingDelivery>, ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::get_due_deliveries_internal(&client, now, limit).await
+    }
+
+    async fn record_attempt(
+        &self,
+        delivery_id: u64,
+        attempts: u32,
+        result: Result<u16, (Option<u16>, String)>,
+        now: u64,
+    ) -> Result<(), ApiError> {
+        let client = self.client().await?;
+        webhook_postgres::record_attempt(&client, delivery_id, attempts, result, now).await
+    }
+}
+
+// the same batch indexed into SQLite and Postgres reads back the same through either
+// storage. needs a server, so it is skipped unless SHRIKE_TEST_POSTGRES_URL is set
+#[tokio::test]
+async fn test_postgres_storage_parity() {
+    use crate::indexer::prices::TokenPrice;
+    use crate::indexer::rpc::database::Database;
+    use crate::indexer::rpc::postgres::PostgresDatabase;
+    use crate::indexer::rpc::storage::Storage;
+    use crate::live::models::{LiveMessage, LiveTransfer};
+    use crate::shared::models::NATIVE_TOKENS;
+    use crate::shared::neo::base64_to_address;
+    use crate::shared::storage::SqliteStorage;
+    use crate::transaction::models::State;
+    use crate::webhook::models::WEBHOOK_MAX_ATTEMPTS;
+
+    let Ok(url) = std::env::var("SHRIKE_TEST_POSTGRES_URL") else {
+        eprintln!("SHRIKE_TEST_POSTGRES_URL is unset, skipping the Postgres parity test");
+        return;
+    };
+
+    const START: u64 = 1_704_067_200_000; // 2024-01-01
+    const FROM: &str = "z6LDQN4w2GM3HBPAkWIJVqpVmn0=";
+    const TO: &str = "AAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+    let token = NATIVE_TOKENS[0].0.to_string();
+    let (from, to) = (base64_to_address(FROM), base64_to_address(TO));
+
+    let block = |index: u64| Block {
+        index,
+        hash: format!("0x{index:064x}"),
+        size: 100,
+        version: 0,
+        merkle_root: String::new(),
+        time: START + index * 1000,
+        nonce: String::new(),
+        speaker: 0,
+        next_consensus: String::new(),
+        reward: 0.5,
+        reward_receiver: from.clone(),
+        witnesses: vec![Witness {
+            invocation: "invocation".to_string(),
+            verification: "verification".to_string(),
+        }],
+    };
+    let transaction = |hash: u64, block_index: u64| Transaction {
+        index: 0,
+        hash: format!("0x{hash:064x}"),
+        block_index,
+        timestamp: START + block_index * 1000,
+        vm_state: "HALT".to_string(),
+        size: 0,
+        version: 0,
+        nonce: hash,
+        sender: from.clone(),
+        sysfee: "100".to_string(),
+        netfee: "10".to_string(),
+        valid_until: 0,
+        signers: vec![Signer {
+            account: from.clone(),
+            scopes: "CalledByEntry".to_string(),
+            allowedcontracts: None,
+        }],
+        script: String::new(),
+        witnesses: Vec::new(),
+        stack_result: "[]".to_string(),
+        notifications: vec![Notification {
+            id: None,
+            contract: token.clone(),
+            eventname: "Transfer".to_string(),
+            state: State {
+                _type: "Array".to_string(),
+                value: vec![
+                    StateValue {
+                        _type: "ByteString".to_string(),
+                        value: Some(serde_json::json!(FROM)),
+                    },
+                    StateValue {
+                        _type: "ByteString".to_string(),
+                        value: Some(serde_json::json!(TO)),
+                    },
+                    StateValue {
+                        _type: "Integer".to_string(),
+                        value: Some(serde_json::json!((hash * 5).to_string())),
+                    },
+                ],
+            },
+        }],
+    };
+    let transactions = vec![transaction(1, 1), transaction(2, 2), transaction(3, 2)];
+    let balances = vec![
+        DailyAddressBalance {
+            block_index: 2,
+            date: "2024-01-01".to_string(),
+            timestamp: START + 2000,
+            address: from.clone(),
+            token_contract: token.clone(),
+            balance: 70,
+        },
+        DailyAddressBalance {
+            block_index: 2,
+            date: "2024-01-01".to_string(),
+            timestamp: START + 2000,
+            address: to.clone(),
+            token_contract: token.clone(),
+            balance: 30,
+        },
+    ];
+    let price = TokenPrice {
+        token_contract: token.clone(),
+        usd_price: 1.5,
+        block_index: 2,
+        timestamp: START + 2000,
+    };
+    let message = LiveMessage::Transfer(LiveTransfer {
+        txid: format!("0x{:064x}", 3),
+        block_index: 2,
+        token_contract: token.clone(),
+        from: from.clone(),
+        to: to.clone(),
+        amount: "15".to_string(),
+    });
+
+    async fn index(
+        writer: &dyn Storage,
+        blocks: Vec<Block>,
+        transactions: Vec<Transaction>,
+        balances: Vec<DailyAddressBalance>,
+        price: TokenPrice,
+    ) {
+        writer.begin_batch().await.unwrap();
+        writer
+            .insert_blocks_transactions(blocks, transactions.clone())
+            .await
+            .unwrap();
+        writer
+            .update_daily_network_stats(&transactions)
+            .await
+            .unwrap();
+        writer
+            .persist_daily_address_balances(balances)
+            .await
+            .unwrap();
+        writer
+            .persist_daily_token_price_history(vec![price])
+            .await
+            .unwrap();
+        writer.commit_batch().await.unwrap();
+    }
+
+    let path = std::env::temp_dir().join(format!("shrike-parity-{}.db3", std::process::id()));
+    let pool = r2d2::Pool::new(r2d2_sqlite::SqliteConnectionManager::file(&path)).unwrap();
+    let conn = pool.get().unwrap();
+    crate::indexer::controller::create_tables(&conn).unwrap();
+    index(
+        &Database::new(&conn).unwrap(),
+        vec![block(1), block(2)],
+        transactions.clone(),
+        balances.clone(),
+        price.clone(),
+    )
+    .await;
+    let sqlite = SqliteStorage::new(pool.clone(), pool.clone());
+
+    // a schema of its own, selected for every connection through the url
+    let schema = format!("shrike_parity_{}", std::process::id());
+    let (admin, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+    tokio::spawn(connection);
+    admin
+        .batch_execute(&format!(
+            "DROP SCHEMA IF EXISTS {schema} CASCADE; CREATE SCHEMA {schema}"
+        ))
+        .await
+        .unwrap();
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let scoped = format!("{url}{separator}options=-csearch_path%3D{schema}");
+    let writer = PostgresDatabase::connect(&scoped).await.unwrap();
+    writer.setup().await.unwrap();
+    index(
+        &writer,
+        vec![block(1), block(2)],
+        transactions,
+        balances,
+        price,
+    )
+    .await;
+    let postgres = PostgresStorage::new(&scoped).unwrap();
+
+    fn json<T: serde::Serialize>(result: Result<T, ApiError>) -> Result<serde_json::Value, String> {
+        result
+            .map(|value| serde_json::to_value(value).unwrap())
+            .map_err(|err| err.to_string())
+    }
+    fn page<T: serde::Serialize>(
+        result: Result<Page<T>, ApiError>,
+    ) -> Result<serde_json::Value, String> {
+        json(result.map(|page| (page.list, page.next_cursor, page.prev_cursor)))
+    }
+    async fn rows<T: serde::Serialize>(
+        result: Result<ExportRows<T>, ApiError>,
+    ) -> Result<serde_json::Value, String> {
+        let mut rows = result.map_err(|err| err.to_string())?;
+        let mut collected = Vec::new();
+        while let Some(row) = rows.recv().await {
+            collected.push(json(row)?);
+        }
+        Ok(serde_json::Value::Array(collected))
+    }
+
+    let keyset = Pagination::Keyset {
+        cursor: None,
+        descending: true,
+        per_page: 2,
+    };
+    let offset = Pagination::Offset {
+        page: 0,
+        per_page: 10,
+        sort_by: None,
+        order: None,
+    };
+    let (day, next_day) = ("2024-01-01".to_string(), "2024-01-02".to_string());
+    let tx_hash = format!("0x{:064x}", 2);
+
+    let mut reads = Vec::new();
+    for storage in [&sqlite as &dyn ReadStorage, &postgres] {
+        let range = || (day.clone(), next_day.clone());
+        let overview_info = (token.clone(), Some("NEO".to_string()), Some(0));
+        reads.push(vec![
+            json(storage.get_block("1".to_string()).await),
+            json(storage.get_block(format!("0x{:064x}", 2)).await),
+            json(storage.get_block_witnesses(1).await),
+            json(storage.get_block_time("2".to_string()).await),
+            json(storage.get_block_transactions("2".to_string()).await),
+            page(storage.list_blocks(None, None, &keyset).await),
+            page(storage.list_blocks(Some(day.clone()), None, &offset).await),
+            json(
+                storage
+                    .count_blocks(Some(day.clone()), Some(next_day.clone()))
+                    .await,
+            ),
+            json(storage.get_latest_blocks(5).await),
+            json(storage.get_transaction(tx_hash.clone()).await),
+            json(storage.get_transaction("0x00".to_string()).await),
+            json(storage.get_signers(tx_hash.clone()).await),
+            json(storage.get_transaction_notifications(tx_hash.clone()).await),
+            json(storage.get_notification_state_values(2).await),
+            page(
+                storage
+                    .get_sender_transactions(from.clone(), true, &keyset)
+                    .await,
+            ),
+            page(
+                storage
+                    .get_sender_transactions(from.clone(), false, &offset)
+                    .await,
+            ),
+            json(storage.count_sender_transactions(from.clone()).await),
+            page(storage.get_address_transfers(to.clone(), &offset).await),
+            json(storage.count_address_transfers(to.clone()).await),
+            rows(storage.export_address_transfers(to.clone()).await).await,
+            json(
+                storage
+                    .list_contract_events(token.clone(), &keyset)
+                    .await
+                    .map(|p| p.list),
+            ),
+            json(storage.count_contract_events(token.clone()).await),
+            {
+                let (start, end) = range();
+                page(
+                    storage
+                        .list_history_balance(to.clone(), token.clone(), start, end, &offset)
+                        .await,
+                )
+            },
+            {
+                let (start, end) = range();
+                rows(
+                    storage
+                        .export_history_balance(from.clone(), token.clone(), start, end)
+                        .await,
+                )
+                .await
+            },
+            {
+                let (start, end) = range();
+                page(
+                    storage
+                        .list_history_price_token(token.clone(), start, end, &offset)
+                        .await,
+                )
+            },
+            {
+                let (start, end) = range();
+                page(
+                    storage
+                        .list_daily_contract_usage(token.clone(), start, end, &offset)
+                        .await,
+                )
+            },
+            {
+                let (start, end) = range();
+                json(
+                    storage
+                        .count_daily_contract_usage(token.clone(), start, end)
+                        .await,
+                )
+            },
+            json(storage.get_seen_range(to.clone()).await),
+            json(storage.get_total_fees(from.clone()).await),
+            json(storage.get_current_balances(from.clone()).await),
+            json(storage.get_latest_price(&token).await),
+            json(
+                storage
+                    .get_portfolio_history(to.clone(), day.clone(), next_day.clone())
+                    .await,
+            ),
+            json(storage.get_window_start(Window::Day).await),
+            json(storage.get_top_holders(token.clone(), 10).await),
+            json(storage.get_top_senders(None, 10).await),
+            json(storage.get_top_contracts(None, 10).await),
+            json(storage.list_token_contracts(None).await),
+            json(storage.get_token_overview(overview_info, 15).await),
+            json(storage.get_snapshot_block(None).await),
+            json(storage.list_holders_at(token.clone(), 2, Some(0)).await),
+            rows(storage.export_holders_at(token.clone(), 2, Some(0)).await).await,
+            json(storage.search_block_index(2).await),
+            json(storage.search_hash(&tx_hash).await),
+            json(storage.search_script_hash(&token).await),
+            json(storage.search_address(&to).await),
+            json(storage.search_name("neo").await),
+            json(
+                storage
+                    .list_daily_stats(day.clone(), next_day.clone())
+                    .await,
+            ),
+            json(storage.get_total_blocks().await),
+            json(storage.get_stats(2).await),
+        ]);
+
+        let volumes = storage.get_volume_24h(None).await.unwrap();
+        assert_eq!(volumes.get(&token), Some(&30));
+    }
+    for (position, (sqlite, postgres)) in reads[0].iter().zip(reads[1].iter()).enumerate() {
+        assert_eq!(sqlite, postgres, "read {} differs", position);
+    }
+    assert!(reads[1][10].is_err());
+
+    // a webhook through its whole life, the timestamps and secret left out as they differ
+    let mut lifecycles = Vec::new();
+    for (storage, writer) in [
+        (
+            &sqlite as &dyn WebhookStorage,
+            Box::new(Database::new(&conn).unwrap()) as Box<dyn Storage>,
+        ),
+        (
+            &postgres,
+            Box::new(PostgresDatabase::connect(&scoped).await.unwrap()),
+        ),
+    ] {
+        let request = WebhookRequest {
+            url: "http://127.0.0.1:1/hook".to_string(),
+            address: Some(to.clone()),
+            contract: Some(token.clone()),
+            event_name: None,
+            min_amount: Some("10".to_string()),
+        };
+        let id = storage.create_webhook(request, true).await.unwrap().id;
+        assert!(storage.get_webhook_secret(id).await.unwrap().is_some());
+        assert!(writer.has_active_webhooks().await.unwrap());
+        assert_eq!(
+            writer
+                .queue_webhook_deliveries(std::slice::from_ref(&message), 0)
+                .await
+                .unwrap(),
+            1
+        );
+
+        let due = storage.get_due_deliveries(0, 10).await.unwrap();
+        assert_eq!(due.len(), 1);
+        for attempts in 0..WEBHOOK_MAX_ATTEMPTS {
+            let failure = Err((Some(500), "server error".to_string()));
+            storage
+                .record_attempt(due[0].id, attempts, failure, 0)
+                .await
+                .unwrap();
+        }
+        let ping = storage.queue_test_delivery(id).await.unwrap();
+        storage.record_attempt(ping, 0, Ok(200), 0).await.unwrap();
+
+        let strip = |mut value: serde_json::Value| {
+            for entry in value.as_array_mut().into_iter().flatten() {
+                if let Some(entry) = entry.as_object_mut() {
+                    for field in ["created_at", "next_attempt_at", "delivered_at", "failed_at"] {
+                        entry.remove(field);
+                    }
+                }
+            }
+            value
+        };
+        let lifecycle = vec![
+            json(storage.list_webhooks().await).map(strip),
+            json(storage.list_deliveries(id, &offset).await.map(|p| p.list)).map(strip),
+            json(storage.count_deliveries(id).await),
+            json(storage.list_dead_letters(id, &offset).await.map(|p| p.list)).map(strip),
+            json(storage.count_dead_letters(id).await),
+            json(storage.deactivate_webhook(id).await),
+            json(storage.list_webhooks().await),
+            json(storage.queue_test_delivery(id).await),
+        ];
+        assert!(!writer.has_active_webhooks().await.unwrap());
+        lifecycles.push(lifecycle);
+    }
+    assert_eq!(lifecycles[0], lifecycles[1]);
+    assert_eq!(lifecycles[1][4], Ok(serde_json::json!(1)));
+
+    drop(conn);
+    admin
+        .batch_execute(&format!("DROP SCHEMA {schema} CASCADE"))
+        .await
+        .unwrap();
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,40 @@
+use once_cell::sync::Lazy;
+use tokio::signal;
+use tokio::sync::watch;
+
+// flipped once on SIGINT or SIGTERM, long running work checks it between units of work
+static SHUTDOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+pub fn request() {
+    SHUTDOWN.send_replace(true);
+}
+
+pub fn requested() -> bool {
+    *SHUTDOWN.borrow()
+}
+
+pub fn subscribe() -> watch::Receiver<bool> {
+    SHUTDOWN.subscribe()
+}
+
+// resolves once shutdown has been requested, immediately if it already was
+pub async fn wait() {
+    let mut receiver = subscribe();
+    let _ = receiver.wait_for(|requested| *requested).await;
+}
+
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = signal::ctrl_c().await;
+    }
+}
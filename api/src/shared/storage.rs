@@ -0,0 +1,866 @@
+use async_trait::async_trait;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::address::internals as address_internals;
+use crate::address::models::{PortfolioDay, SeenAt, TokenBalance};
+use crate::block::internals as block_internals;
+use crate::block::models::{Block, BlockSummary, Witness};
+use crate::contract::internals as contract_internals;
+use crate::contract::models::ContractEvent;
+use crate::error::ApiError;
+use crate::history::internals as history_internals;
+use crate::history::models::{
+    DailyAddressBalance, DailyContractUsage, DailyTokenPrice, DailyTokenSupply,
+};
+use crate::leaderboard::internals as leaderboard_internals;
+use crate::leaderboard::models::{ContractEntry, Holder, SenderEntry, Window};
+use crate::search::internals as search_internals;
+use crate::search::models::SearchResult;
+use crate::shared::abi::DecodedEvent;
+use crate::shared::config::{StorageBackend, StorageConfig};
+use crate::shared::export::{produce_blocking, ExportRows};
+use crate::shared::pagination::{Page, Pagination};
+use crate::shared::postgres::PostgresStorage;
+use crate::stat::internals as stat_internals;
+use crate::stat::models::{DailyNetworkStats, NetworkStatistics, ShrikeStats};
+use crate::token::internals as token_internals;
+use crate::token::models::{HolderBalance, TokenInfo, TokenOverview};
+use crate::transaction::internals as transaction_internals;
+use crate::transaction::models::{
+    Notification, Signer, StateValue, Transaction, TransferExport, TxDataList,
+};
+use crate::webhook::internals as webhook_internals;
+use crate::webhook::models::{
+    PendingDelivery, Webhook, WebhookDeadLetter, WebhookDelivery, WebhookRequest,
+};
+
+// Everything the read endpoints and the stats loop query, served from the backend the
+// indexer writes to. Methods mirror the internals of each module
+#[async_trait]
+pub trait ReadStorage: Send + Sync {
+    async fn get_block(&self, path: String) -> Result<Block, ApiError>;
+    async fn get_block_witnesses(&self, block_index: u64) -> Result<Vec<Witness>, ApiError>;
+    async fn get_block_time(&self, path: String) -> Result<u64, ApiError>;
+    async fn get_block_transactions(&self, path: String) -> Result<Vec<Transaction>, ApiError>;
+    async fn list_blocks(
+        &self,
+        date_init: Option<String>,
+        date_end: Option<String>,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<BlockSummary>>, ApiError>;
+    async fn count_blocks(
+        &self,
+        date_init: Option<String>,
+        date_end: Option<String>,
+    ) -> Result<usize, ApiError>;
+    async fn get_latest_blocks(&self, limit: u32) -> Result<Vec<BlockSummary>, ApiError>;
+
+    async fn get_transaction(&self, hash: String) -> Result<Transaction, ApiError>;
+    async fn get_transaction_witnesses(&self, hash: String) -> Result<Vec<Witness>, ApiError>;
+    async fn get_signers(&self, hash: String) -> Result<Vec<Signer>, ApiError>;
+    async fn get_transaction_notifications(
+        &self,
+        hash: String,
+    ) -> Result<Vec<Notification>, ApiError>;
+    async fn get_notification_state_values(
+        &self,
+        notification_id: u64,
+    ) -> Result<Vec<StateValue>, ApiError>;
+    async fn get_sender_transactions(
+        &self,
+        address: String,
+        with_transfers: bool,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<Transaction>>, ApiError>;
+    async fn count_sender_transactions(&self, address: String) -> Result<usize, ApiError>;
+    async fn get_address_transfers(
+        &self,
+        address: String,
+        pagination: &Pagination,
+    ) -> Result<Page<TxDataList>, ApiError>;
+    async fn count_address_transfers(&self, address: String) -> Result<usize, ApiError>;
+    async fn export_address_transfers(
+        &self,
+        address: String,
+    ) -> Result<ExportRows<TransferExport>, ApiError>;
+
+    async fn get_token_info(
+        &self,
+        contract: &str,
+    ) -> Result<(Option<String>, Option<u8>), ApiError>;
+    async fn decode_notifications(
+        &self,
+        notifications: Vec<Notification>,
+    ) -> Result<Vec<DecodedEvent>, ApiError>;
+    async fn list_contract_events(
+        &self,
+        contract: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<ContractEvent>>, ApiError>;
+    async fn count_contract_events(&self, contract: String) -> Result<usize, ApiError>;
+
+    async fn list_history_balance(
+        &self,
+        address: String,
+        token: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyAddressBalance>>, ApiError>;
+    async fn count_history_balance(
+        &self,
+        address: String,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError>;
+    async fn export_history_balance(
+        &self,
+        address: String,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyAddressBalance>, ApiError>;
+    async fn list_history_price_token(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyTokenPrice>>, ApiError>;
+    async fn count_history_price_token(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError>;
+    async fn export_history_price_token(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyTokenPrice>, ApiError>;
+    async fn list_token_supply_history(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyTokenSupply>>, ApiError>;
+    async fn count_token_supply_history(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError>;
+    async fn export_token_supply_history(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyTokenSupply>, ApiError>;
+    async fn list_daily_contract_usage(
+        &self,
+        contract: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyContractUsage>>, ApiError>;
+    async fn count_daily_contract_usage(
+        &self,
+        contract: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError>;
+    async fn export_daily_contract_usage(
+        &self,
+        contract: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyContractUsage>, ApiError>;
+
+    async fn get_seen_range(&self, address: String) -> Result<Option<(SeenAt, SeenAt)>, ApiError>;
+    async fn get_total_fees(&self, address: String) -> Result<f64, ApiError>;
+    async fn get_current_balances(&self, address: String) -> Result<Vec<TokenBalance>, ApiError>;
+    async fn get_deployed_contracts(&self, address: String) -> Result<Vec<String>, ApiError>;
+    async fn get_latest_price(&self, token: &str) -> Result<Option<(f64, String)>, ApiError>;
+    async fn get_portfolio_history(
+        &self,
+        address: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<Vec<PortfolioDay>, ApiError>;
+
+    async fn get_window_start(&self, window: Window) -> Result<Option<u64>, ApiError>;
+    async fn get_top_holders(&self, token: String, limit: u32) -> Result<Vec<Holder>, ApiError>;
+    async fn get_top_senders(
+        &self,
+        since: Option<u64>,
+        limit: u32,
+    ) -> Result<Vec<SenderEntry>, ApiError>;
+    async fn get_top_contracts(
+        &self,
+        since: Option<u64>,
+        limit: u32,
+    ) -> Result<Vec<ContractEntry>, ApiError>;
+
+    async fn list_token_contracts(&self, token: Option<&str>) -> Result<Vec<TokenInfo>, ApiError>;
+    async fn get_volume_24h(&self, token: Option<&str>) -> Result<HashMap<String, i128>, ApiError>;
+    async fn get_token_overview(
+        &self,
+        token: TokenInfo,
+        volume_24h: i128,
+    ) -> Result<TokenOverview, ApiError>;
+    async fn get_snapshot_block(&self, block: Option<u64>) -> Result<u64, ApiError>;
+    async fn get_seeded_height(&self) -> Result<Option<u64>, ApiError>;
+    async fn list_holders_at(
+        &self,
+        token: String,
+        block: u64,
+        decimals: Option<u8>,
+    ) -> Result<Vec<HolderBalance>, ApiError>;
+    async fn export_holders_at(
+        &self,
+        token: String,
+        block: u64,
+        decimals: Option<u8>,
+    ) -> Result<ExportRows<HolderBalance>, ApiError>;
+
+    async fn search_block_index(&self, index: u64) -> Result<Vec<SearchResult>, ApiError>;
+    async fn search_hash(&self, hash: &str) -> Result<Vec<SearchResult>, ApiError>;
+    async fn search_script_hash(&self, hash: &str) -> Result<Vec<SearchResult>, ApiError>;
+    async fn search_address(&self, address: &str) -> Result<Vec<SearchResult>, ApiError>;
+    async fn search_name(&self, query: &str) -> Result<Vec<SearchResult>, ApiError>;
+
+    async fn list_daily_stats(
+        &self,
+        date_init: String,
+        date_end: String,
+    ) -> Result<Vec<DailyNetworkStats>, ApiError>;
+    async fn get_total_blocks(&self) -> Result<u64, ApiError>;
+    async fn get_stats(&self, blocks: u64) -> Result<(ShrikeStats, NetworkStatistics), ApiError>;
+}
+
+// Webhook registrations and their delivery queue, which the API writes itself
+#[async_trait]
+pub trait WebhookStorage: Send + Sync {
+    async fn get_webhook_secret(&self, id: u64) -> Result<Option<String>, ApiError>;
+    async fn create_webhook(
+        &self,
+        request: WebhookRequest,
+        allow_private_urls: bool,
+    ) -> Result<Webhook, ApiError>;
+    async fn list_webhooks(&self) -> Result<Vec<Webhook>, ApiError>;
+    async fn get_webhook(&self, id: u64) -> Result<Webhook, ApiError>;
+    async fn deactivate_webhook(&self, id: u64) -> Result<(), ApiError>;
+    async fn queue_test_delivery(&self, id: u64) -> Result<u64, ApiError>;
+    async fn list_deliveries(
+        &self,
+        id: u64,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<WebhookDelivery>>, ApiError>;
+    async fn count_deliveries(&self, id: u64) -> Result<usize, ApiError>;
+    async fn list_dead_letters(
+        &self,
+        id: u64,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<WebhookDeadLetter>>, ApiError>;
+    async fn count_dead_letters(&self, id: u64) -> Result<usize, ApiError>;
+    async fn get_due_deliveries(
+        &self,
+        now: u64,
+        limit: u32,
+    ) -> Result<Vec<PendingDelivery>, ApiError>;
+    async fn record_attempt(
+        &self,
+        delivery_id: u64,
+        attempts: u32,
+        result: Result<u16, (Option<u16>, String)>,
+        now: u64,
+    ) -> Result<(), ApiError>;
+}
+
+pub struct ApiStorage {
+    pub read: Arc<dyn ReadStorage>,
+    pub webhooks: Arc<dyn WebhookStorage>,
+}
+
+impl ApiStorage {
+    fn new<S: ReadStorage + WebhookStorage + 'static>(storage: S) -> Self {
+        let storage = Arc::new(storage);
+
+        ApiStorage {
+            read: storage.clone(),
+            webhooks: storage,
+        }
+    }
+}
+
+// the configured backend; the SQLite pools are only used when that is the one selected
+pub fn open(
+    config: &StorageConfig,
+    read: Pool<SqliteConnectionManager>,
+    write: Pool<SqliteConnectionManager>,
+) -> Result<ApiStorage, ApiError> {
+    match config.backend {
+        StorageBackend::Sqlite => Ok(ApiStorage::new(SqliteStorage::new(read, write))),
+        StorageBackend::Postgres => {
+            let url = config.url.as_deref().ok_or_else(|| {
+                ApiError::Internal("storage.url is required by the postgres backend".to_string())
+            })?;
+
+            Ok(ApiStorage::new(PostgresStorage::new(url)?))
+        }
+    }
+}
+
+// reads go through the read only pool, webhooks write to the database they read from
+pub struct SqliteStorage {
+    read: Pool<SqliteConnectionManager>,
+    write: Pool<SqliteConnectionManager>,
+}
+
+impl SqliteStorage {
+    pub fn new(read: Pool<SqliteConnectionManager>, write: Pool<SqliteConnectionManager>) -> Self {
+        SqliteStorage { read, write }
+    }
+}
+
+#[async_trait]
+impl ReadStorage for SqliteStorage {
+    async fn get_block(&self, path: String) -> Result<Block, ApiError> {
+        block_internals::get_block_internal(&self.read.get()?, path)
+    }
+
+    async fn get_block_witnesses(&self, block_index: u64) -> Result<Vec<Witness>, ApiError> {
+        block_internals::get_witnesses(&self.read.get()?, block_index)
+    }
+
+    async fn get_block_time(&self, path: String) -> Result<u64, ApiError> {
+        block_internals::get_block_time(&self.read.get()?, path)
+    }
+
+    async fn get_block_transactions(&self, path: String) -> Result<Vec<Transaction>, ApiError> {
+        block_internals::get_block_transactions_internal(&self.read.get()?, path)
+    }
+
+    async fn list_blocks(
+        &self,
+        date_init: Option<String>,
+        date_end: Option<String>,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<BlockSummary>>, ApiError> {
+        block_internals::list_blocks_internal(&self.read.get()?, date_init, date_end, pagination)
+    }
+
+    async fn count_blocks(
+        &self,
+        date_init: Option<String>,
+        date_end: Option<String>,
+    ) -> Result<usize, ApiError> {
+        block_internals::count_blocks_internal(&self.read.get()?, date_init, date_end)
+    }
+
+    async fn get_latest_blocks(&self, limit: u32) -> Result<Vec<BlockSummary>, ApiError> {
+        block_internals::get_latest_blocks_internal(&self.read.get()?, limit)
+    }
+
+    async fn get_transaction(&self, hash: String) -> Result<Transaction, ApiError> {
+        transaction_internals::get_transaction_internal(&self.read.get()?, hash)
+    }
+
+    async fn get_transaction_witnesses(&self, hash: String) -> Result<Vec<Witness>, ApiError> {
+        transaction_internals::get_witnesses(&self.read.get()?, hash)
+    }
+
+    async fn get_signers(&self, hash: String) -> Result<Vec<Signer>, ApiError> {
+        transaction_internals::get_signers(&self.read.get()?, hash)
+    }
+
+    async fn get_transaction_notifications(
+        &self,
+        hash: String,
+    ) -> Result<Vec<Notification>, ApiError> {
+        transaction_internals::get_transaction_notifications(&self.read.get()?, hash)
+    }
+
+    async fn get_notification_state_values(
+        &self,
+        notification_id: u64,
+    ) -> Result<Vec<StateValue>, ApiError> {
+        transaction_internals::get_notification_state_values(&self.read.get()?, notification_id)
+    }
+
+    async fn get_sender_transactions(
+        &self,
+        address: String,
+        with_transfers: bool,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<Transaction>>, ApiError> {
+        transaction_internals::get_sender_transactions_internal(
+            &self.read.get()?,
+            address,
+            with_transfers,
+            pagination,
+        )
+    }
+
+    async fn count_sender_transactions(&self, address: String) -> Result<usize, ApiError> {
+        transaction_internals::count_sender_transactions_internal(&self.read.get()?, address)
+    }
+
+    async fn get_address_transfers(
+        &self,
+        address: String,
+        pagination: &Pagination,
+    ) -> Result<Page<TxDataList>, ApiError> {
+        transaction_internals::get_address_transfers_internal(
+            &self.read.get()?,
+            address,
+            pagination,
+        )
+    }
+
+    async fn count_address_transfers(&self, address: String) -> Result<usize, ApiError> {
+        transaction_internals::count_address_transfers_internal(&self.read.get()?, address)
+    }
+
+    async fn export_address_transfers(
+        &self,
+        address: String,
+    ) -> Result<ExportRows<TransferExport>, ApiError> {
+        let conn = self.read.get()?;
+        Ok(produce_blocking(move |emit| {
+            transaction_internals::export_address_transfers_internal(&conn, address, emit)
+        }))
+    }
+
+    async fn get_token_info(
+        &self,
+        contract: &str,
+    ) -> Result<(Option<String>, Option<u8>), ApiError> {
+        contract_internals::get_token_info(&self.read.get()?, contract)
+    }
+
+    async fn decode_notifications(
+        &self,
+        notifications: Vec<Notification>,
+    ) -> Result<Vec<DecodedEvent>, ApiError> {
+        Ok(contract_internals::decode_notifications(
+            &self.read.get()?,
+            notifications,
+        ))
+    }
+
+    async fn list_contract_events(
+        &self,
+        contract: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<ContractEvent>>, ApiError> {
+        contract_internals::list_contract_events_internal(&self.read.get()?, contract, pagination)
+    }
+
+    async fn count_contract_events(&self, contract: String) -> Result<usize, ApiError> {
+        contract_internals::count_contract_events_internal(&self.read.get()?, contract)
+    }
+
+    async fn list_history_balance(
+        &self,
+        address: String,
+        token: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyAddressBalance>>, ApiError> {
+        history_internals::list_history_balance_internal(
+            &self.read.get()?,
+            address,
+            token,
+            date_init,
+            date_end,
+            pagination,
+        )
+    }
+
+    async fn count_history_balance(
+        &self,
+        address: String,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError> {
+        history_internals::count_history_balance_internal(
+            &self.read.get()?,
+            address,
+            token,
+            date_init,
+            date_end,
+        )
+    }
+
+    async fn export_history_balance(
+        &self,
+        address: String,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyAddressBalance>, ApiError> {
+        let conn = self.read.get()?;
+        Ok(produce_blocking(move |emit| {
+            history_internals::export_history_balance_internal(
+                &conn, address, token, date_init, date_end, emit,
+            )
+        }))
+    }
+
+    async fn list_history_price_token(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyTokenPrice>>, ApiError> {
+        history_internals::list_history_price_token_internal(
+            &self.read.get()?,
+            token,
+            date_init,
+            date_end,
+            pagination,
+        )
+    }
+
+    async fn count_history_price_token(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError> {
+        history_internals::count_history_price_token_internal(
+            &self.read.get()?,
+            token,
+            date_init,
+            date_end,
+        )
+    }
+
+    async fn export_history_price_token(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyTokenPrice>, ApiError> {
+        let conn = self.read.get()?;
+        Ok(produce_blocking(move |emit| {
+            history_internals::export_history_price_token_internal(
+                &conn, token, date_init, date_end, emit,
+            )
+        }))
+    }
+
+    async fn list_token_supply_history(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyTokenSupply>>, ApiError> {
+        history_internals::list_token_supply_history_internal(
+            &self.read.get()?,
+            token,
+            date_init,
+            date_end,
+            pagination,
+        )
+    }
+
+    async fn count_token_supply_history(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError> {
+        history_internals::count_token_supply_history_internal(
+            &self.read.get()?,
+            token,
+            date_init,
+            date_end,
+        )
+    }
+
+    async fn export_token_supply_history(
+        &self,
+        token: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyTokenSupply>, ApiError> {
+        let conn = self.read.get()?;
+        Ok(produce_blocking(move |emit| {
+            history_internals::export_token_supply_history_internal(
+                &conn, token, date_init, date_end, emit,
+            )
+        }))
+    }
+
+    async fn list_daily_contract_usage(
+        &self,
+        contract: String,
+        date_init: String,
+        date_end: String,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<DailyContractUsage>>, ApiError> {
+        history_internals::list_daily_contract_usage_internal(
+            &self.read.get()?,
+            contract,
+            date_init,
+            date_end,
+            pagination,
+        )
+    }
+
+    async fn count_daily_contract_usage(
+        &self,
+        contract: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<usize, ApiError> {
+        history_internals::count_daily_contract_usage_internal(
+            &self.read.get()?,
+            contract,
+            date_init,
+            date_end,
+        )
+    }
+
+    async fn export_daily_contract_usage(
+        &self,
+        contract: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<ExportRows<DailyContractUsage>, ApiError> {
+        let conn = self.read.get()?;
+        Ok(produce_blocking(move |emit| {
+            history_internals::export_daily_contract_usage_internal(
+                &conn, contract, date_init, date_end, emit,
+            )
+        }))
+    }
+
+    async fn get_seen_range(&self, address: String) -> Result<Option<(SeenAt, SeenAt)>, ApiError> {
+        address_internals::get_seen_range_internal(&self.read.get()?, address)
+    }
+
+    async fn get_total_fees(&self, address: String) -> Result<f64, ApiError> {
+        address_internals::get_total_fees_internal(&self.read.get()?, address)
+    }
+
+    async fn get_current_balances(&self, address: String) -> Result<Vec<TokenBalance>, ApiError> {
+        address_internals::get_current_balances_internal(&self.read.get()?, address)
+    }
+
+    async fn get_deployed_contracts(&self, address: String) -> Result<Vec<String>, ApiError> {
+        address_internals::get_deployed_contracts_internal(&self.read.get()?, address)
+    }
+
+    async fn get_latest_price(&self, token: &str) -> Result<Option<(f64, String)>, ApiError> {
+        address_internals::get_latest_price_internal(&self.read.get()?, token)
+    }
+
+    async fn get_portfolio_history(
+        &self,
+        address: String,
+        date_init: String,
+        date_end: String,
+    ) -> Result<Vec<PortfolioDay>, ApiError> {
+        address_internals::get_portfolio_history_internal(
+            &self.read.get()?,
+            address,
+            date_init,
+            date_end,
+        )
+    }
+
+    async fn get_window_start(&self, window: Window) -> Result<Option<u64>, ApiError> {
+        leaderboard_internals::get_window_start_internal(&self.read.get()?, window)
+    }
+
+    async fn get_top_holders(&self, token: String, limit: u32) -> Result<Vec<Holder>, ApiError> {
+        leaderboard_internals::get_top_holders_internal(&self.read.get()?, token, limit)
+    }
+
+    async fn get_top_senders(
+        &self,
+        since: Option<u64>,
+        limit: u32,
+    ) -> Result<Vec<SenderEntry>, ApiError> {
+        leaderboard_internals::get_top_senders_internal(&self.read.get()?, since, limit)
+    }
+
+    async fn get_top_contracts(
+        &self,
+        since: Option<u64>,
+        limit: u32,
+    ) -> Result<Vec<ContractEntry>, ApiError> {
+        leaderboard_internals::get_top_contracts_internal(&self.read.get()?, since, limit)
+    }
+
+    async fn list_token_contracts(&self, token: Option<&str>) -> Result<Vec<TokenInfo>, ApiError> {
+        token_internals::list_token_contracts_internal(&self.read.get()?, token)
+    }
+
+    async fn get_volume_24h(&self, token: Option<&str>) -> Result<HashMap<String, i128>, ApiError> {
+        token_internals::get_volume_24h_internal(&self.read.get()?, token)
+    }
+
+    async fn get_token_overview(
+        &self,
+        token: TokenInfo,
+        volume_24h: i128,
+    ) -> Result<TokenOverview, ApiError> {
+        token_internals::get_token_overview_internal(&self.read.get()?, token, volume_24h)
+    }
+
+    async fn get_snapshot_block(&self, block: Option<u64>) -> Result<u64, ApiError> {
+        token_internals::get_snapshot_block_internal(&self.read.get()?, block)
+    }
+
+    async fn get_seeded_height(&self) -> Result<Option<u64>, ApiError> {
+        token_internals::get_seeded_height_internal(&self.read.get()?)
+    }
+
+    async fn list_holders_at(
+        &self,
+        token: String,
+        block: u64,
+        decimals: Option<u8>,
+    ) -> Result<Vec<HolderBalance>, ApiError> {
+        token_internals::list_holders_at_internal(&self.read.get()?, token, block, decimals)
+    }
+
+    async fn export_holders_at(
+        &self,
+        token: String,
+        block: u64,
+        decimals: Option<u8>,
+    ) -> Result<ExportRows<HolderBalance>, ApiError> {
+        let conn = self.read.get()?;
+        Ok(produce_blocking(move |emit| {
+            token_internals::export_holders_at_internal(&conn, token, block, decimals, emit)
+        }))
+    }
+
+    async fn search_block_index(&self, index: u64) -> Result<Vec<SearchResult>, ApiError> {
+        search_internals::search_block_index_internal(&self.read.get()?, index)
+    }
+
+    async fn search_hash(&self, hash: &str) -> Result<Vec<SearchResult>, ApiError> {
+        search_internals::search_hash_internal(&self.read.get()?, hash)
+    }
+
+    async fn search_script_hash(&self, hash: &str) -> Result<Vec<SearchResult>, ApiError> {
+        search_internals::search_script_hash_internal(&self.read.get()?, hash)
+    }
+
+    async fn search_address(&self, address: &str) -> Result<Vec<SearchResult>, ApiError> {
+        search_internals::search_address_internal(&self.read.get()?, address)
+    }
+
+    async fn search_name(&self, query: &str) -> Result<Vec<SearchResult>, ApiError> {
+        search_internals::search_name_internal(&self.read.get()?, query)
+    }
+
+    async fn list_daily_stats(
+        &self,
+        date_init: String,
+        date_end: String,
+    ) -> Result<Vec<DailyNetworkStats>, ApiError> {
+        stat_internals::list_daily_stats_internal(&self.read.get()?, date_init, date_end)
+    }
+
+    async fn get_total_blocks(&self) -> Result<u64, ApiError> {
+        let conn = self.read.get()?;
+        tokio::task::spawn_blocking(move || stat_internals::get_blocks_internal(&conn))
+            .await
+            .map_err(|e| ApiError::Internal(format!("Stats task failed: {}", e)))
+    }
+
+    async fn get_stats(&self, blocks: u64) -> Result<(ShrikeStats, NetworkStatistics), ApiError> {
+        stat_internals::get_stats_internal(&self.read, blocks).await
+    }
+}
+
+#[async_trait]
+impl WebhookStorage for SqliteStorage {
+    async fn get_webhook_secret(&self, id: u64) -> Result<Option<String>, ApiError> {
+        webhook_internals::get_webhook_secret_internal(&self.write.get()?, id)
+    }
+
+    async fn create_webhook(
+        &self,
+        request: WebhookRequest,
+        allow_private_urls: bool,
+    ) -> Result<Webhook, ApiError> {
+        webhook_internals::create_webhook_internal(&self.write.get()?, request, allow_private_urls)
+    }
+
+    async fn list_webhooks(&self) -> Result<Vec<Webhook>, ApiError> {
+        webhook_internals::list_webhooks_internal(&self.write.get()?)
+    }
+
+    async fn get_webhook(&self, id: u64) -> Result<Webhook, ApiError> {
+        webhook_internals::get_webhook_internal(&self.write.get()?, id)
+    }
+
+    async fn deactivate_webhook(&self, id: u64) -> Result<(), ApiError> {
+        webhook_internals::deactivate_webhook_internal(&self.write.get()?, id)
+    }
+
+    async fn queue_test_delivery(&self, id: u64) -> Result<u64, ApiError> {
+        webhook_internals::queue_test_delivery_internal(&self.write.get()?, id)
+    }
+
+    async fn list_deliveries(
+        &self,
+        id: u64,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<WebhookDelivery>>, ApiError> {
+        webhook_internals::list_deliveries_internal(&self.write.get()?, id, pagination)
+    }
+
+    async fn count_deliveries(&self, id: u64) -> Result<usize, ApiError> {
+        webhook_internals::count_deliveries_internal(&self.write.get()?, id)
+    }
+
+    async fn list_dead_letters(
+        &self,
+        id: u64,
+        pagination: &Pagination,
+    ) -> Result<Page<Vec<WebhookDeadLetter>>, ApiError> {
+        webhook_internals::list_dead_letters_internal(&self.write.get()?, id, pagination)
+    }
+
+    async fn count_dead_letters(&self, id: u64) -> Result<usize, ApiError> {
+        webhook_internals::count_dead_letters_internal(&self.write.get()?, id)
+    }
+
+    async fn get_due_deliveries(
+        &self,
+        now: u64,
+        limit: u32,
+    ) -> Result<Vec<PendingDelivery>, ApiError> {
+        webhook_internals::get_due_deliveries_internal(&self.write.get()?, now, limit)
+    }
+
+    async fn record_attempt(
+        &self,
+        delivery_id: u64,
+        attempts: u32,
+        result: Result<u16, (Option<u16>, String)>,
+        now: u64,
+    ) -> Result<(), ApiError> {
+        webhook_internals::record_attempt(&self.write.get()?, delivery_id, attempts, result, now)
+    }
+}
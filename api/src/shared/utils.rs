@@ -1,12 +1,14 @@
-use crate::error::Error;
+use crate::error::ApiError;
 use crate::shared::models::{
     PaginationAndFilterParams, PAGE_DEFAULT, PER_PAGE_DEFAULT, PER_PAGE_LIMIT,
 };
-use actix_web::HttpResponse;
+use crate::shared::pagination::{Cursor, Pagination};
+use actix_web::error::{JsonPayloadError, PathError, QueryPayloadError};
+use actix_web::HttpRequest;
 
 pub fn normalize_pagination(
     query_parameter: &PaginationAndFilterParams,
-) -> Result<(u32, u32, Option<String>, Option<String>), HttpResponse> {
+) -> Result<Pagination, ApiError> {
     let page = query_parameter.page.unwrap_or(PAGE_DEFAULT);
     let mut per_page = query_parameter.per_page.unwrap_or(PER_PAGE_DEFAULT);
 
@@ -15,9 +17,9 @@ pub fn normalize_pagination(
     }
 
     if per_page == 0 {
-        return Err(HttpResponse::BadRequest().json(Error {
-            error: "Per_page must be greater than zero.".to_string(),
-        }));
+        return Err(ApiError::BadRequest(
+            "Per_page must be greater than zero.".to_string(),
+        ));
     }
 
     let sort_by = query_parameter.sort_by.clone().filter(|s| !s.is_empty());
@@ -26,31 +28,74 @@ pub fn normalize_pagination(
         .clone()
         .filter(|s| s == "asc" || s == "desc");
 
-    Ok((page, per_page, sort_by, order))
+    // an empty cursor starts keyset pagination from the first page
+    if let Some(cursor) = &query_parameter.cursor {
+        let cursor = match cursor.is_empty() {
+            true => None,
+            false => Some(Cursor::decode(cursor)?),
+        };
+
+        return Ok(Pagination::Keyset {
+            cursor,
+            descending: cursor
+                .map(|c| c.descending)
+                .unwrap_or(order.as_deref() == Some("desc")),
+            per_page,
+        });
+    }
+
+    Ok(Pagination::Offset {
+        page,
+        per_page,
+        sort_by,
+        order,
+    })
 }
 
 pub fn normalize_filter(
     query_parameter: &PaginationAndFilterParams,
-) -> Result<(String, String), HttpResponse> {
+) -> Result<(String, String), ApiError> {
     let date_init = query_parameter
         .date_init
         .clone()
         .filter(|s| !s.is_empty())
         .ok_or_else(|| {
-            HttpResponse::BadRequest().json(Error {
-                error: "The 'date_init' parameter is required.".to_string(),
-            })
+            ApiError::BadRequest("The 'date_init' parameter is required.".to_string())
         })?;
 
     let date_end = query_parameter
         .date_end
         .clone()
         .filter(|s| !s.is_empty())
-        .ok_or_else(|| {
-            HttpResponse::BadRequest().json(Error {
-                error: "The 'date_end' parameter is required.".to_string(),
-            })
-        })?;
+        .ok_or_else(|| ApiError::BadRequest("The 'date_end' parameter is required.".to_string()))?;
 
     Ok((date_init, date_end))
 }
+
+// malformed path segments and query strings get the same JSON error body as everything else
+pub fn path_error_handler(err: PathError, _req: &HttpRequest) -> actix_web::Error {
+    let message = match &err {
+        PathError::Deserialize(inner) => inner.to_string(),
+        _ => err.to_string(),
+    };
+
+    ApiError::BadRequest(message).into()
+}
+
+pub fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let message = match &err {
+        QueryPayloadError::Deserialize(inner) => inner.to_string(),
+        _ => err.to_string(),
+    };
+
+    ApiError::BadRequest(message).into()
+}
+
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let message = match &err {
+        JsonPayloadError::Deserialize(inner) => inner.to_string(),
+        _ => err.to_string(),
+    };
+
+    ApiError::BadRequest(message).into()
+}
@@ -1,5 +1,10 @@
 use actix_web::{get, web, HttpResponse, Responder};
 
+use crate::error::ApiError;
+use crate::shared::models::PaginationAndFilterParams;
+use crate::shared::storage::ReadStorage;
+use crate::shared::utils::normalize_filter;
+
 use super::internals::CURRENT_NETWORK_STATISTICS;
 use super::internals::CURRENT_STATS;
 use super::models::NetworkStatistics;
@@ -35,6 +40,20 @@ async fn get_network_statistics() -> impl Responder {
     })
 }
 
+#[get("/v1/stat/daily")]
+async fn get_daily_stats(
+    storage: web::Data<dyn ReadStorage>,
+    query_parameter: web::Query<PaginationAndFilterParams>,
+) -> Result<HttpResponse, ApiError> {
+    let (date_init, date_end) = normalize_filter(&query_parameter)?;
+
+    let stats = storage.list_daily_stats(date_init, date_end).await?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(get_stats).service(get_network_statistics);
+    cfg.service(get_stats)
+        .service(get_network_statistics)
+        .service(get_daily_stats);
 }
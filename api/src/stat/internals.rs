@@ -1,15 +1,18 @@
-use actix_web::web;
 use once_cell::sync::Lazy;
-use r2d2::PooledConnection;
+use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
 use tokio::task;
 
 use std::sync::RwLock;
 
+use crate::error::ApiError;
 use crate::shared::models::GAS_PRECISION;
-use crate::ConnectionPool;
+use crate::shared::storage::ReadStorage;
 
-use super::models::{NetworkStatistics, ShrikeStats};
+use super::models::{DailyNetworkStats, NetworkStatistics, ShrikeStats};
+
+pub const NATIVE_CONTRACTS: u64 = 9; // fetch natives properly in future
 
 pub static CURRENT_NETWORK_STATISTICS: Lazy<RwLock<NetworkStatistics>> = Lazy::new(|| {
     let s = NetworkStatistics {
@@ -39,7 +42,13 @@ pub fn get_stat_internal<T: rusqlite::types::FromSql>(
     conn: &PooledConnection<SqliteConnectionManager>,
     sql: &str,
 ) -> Option<T> {
-    let mut stmt = conn.prepare(sql).expect("Failed to prepare statement");
+    let mut stmt = match conn.prepare(sql) {
+        Ok(stmt) => stmt,
+        Err(e) => {
+            eprintln!("Database error: {:?} in query: {}", e, sql);
+            return None;
+        }
+    };
     let total: Result<T, rusqlite::Error> = stmt.query_row([], |row| row.get(0));
 
     match total {
@@ -52,74 +61,91 @@ pub fn get_stat_internal<T: rusqlite::types::FromSql>(
     }
 }
 
-pub async fn set_stats_internal(pool: web::Data<ConnectionPool>) {
-    let conn1 = pool.connection.clone().get().unwrap();
-
-    let blocks = task::spawn_blocking(move || get_blocks_internal(&conn1))
-        .await
-        .unwrap();
+pub async fn set_stats_internal(storage: &dyn ReadStorage) -> Result<(), ApiError> {
+    let blocks = storage.get_total_blocks().await?;
 
     let current_block = CURRENT_STATS.read().unwrap().total_blocks;
 
     if blocks > current_block {
-        let conn2 = pool.connection.clone().get().unwrap();
-        let conn3 = pool.connection.clone().get().unwrap();
-        let conn4 = pool.connection.clone().get().unwrap();
-        let conn5 = pool.connection.clone().get().unwrap();
-        let conn6 = pool.connection.clone().get().unwrap();
-        let conn8 = pool.connection.clone().get().unwrap();
-        let conn9 = pool.connection.clone().get().unwrap();
-
-        let transactions = task::spawn_blocking(move || get_transactions_internal(&conn2));
-
-        let sysfees = task::spawn_blocking(move || get_sysfee_internal(&conn3));
-
-        let transfers = task::spawn_blocking(move || get_transfers_internal(&conn4));
-
-        let senders = task::spawn_blocking(move || get_senders_internal(&conn5));
+        let (stats, network) = storage.get_stats(blocks).await?;
 
-        let contracts = task::spawn_blocking(move || get_contracts_internal(&conn6));
-
-        let current_week_contracts =
-            task::spawn_blocking(move || get_contracts_current_week_internal(&conn8));
-
-        let current_week_transactions =
-            task::spawn_blocking(move || get_transactions_current_week_internal(&conn9));
-
-        let results = tokio::join!(
-            transactions,
-            sysfees,
-            transfers,
-            senders,
-            contracts,
-            current_week_contracts,
-            current_week_transactions,
-        );
-
-        let total_transactions = results.0.unwrap_or(0);
-        let total_contracts = results.4.unwrap_or(0);
-
-        {
-            let mut w = CURRENT_STATS.write().unwrap();
-
-            w.total_blocks = blocks;
-            w.total_transactions = total_transactions;
-            w.total_sysfee = results.1.unwrap_or(0.0);
-            w.total_transfers = results.2.unwrap_or(0);
-            w.total_senders = results.3.unwrap_or(0);
-            w.total_contracts = total_contracts;
-        }
-
-        {
-            let mut w = CURRENT_NETWORK_STATISTICS.write().unwrap();
-
-            w.total_transactions = total_transactions;
-            w.total_contracts = total_contracts;
-            w.current_week_contracts = results.5.unwrap_or(0);
-            w.current_week_transactions = results.6.unwrap_or(0);
-        }
+        *CURRENT_STATS.write().unwrap() = stats;
+        *CURRENT_NETWORK_STATISTICS.write().unwrap() = network;
     }
     println!("Stats refreshed. Current height is {}.", blocks);
+
+    Ok(())
+}
+
+// each total runs on its own connection, the slow ones scan whole tables
+pub async fn get_stats_internal(
+    pool: &Pool<SqliteConnectionManager>,
+    blocks: u64,
+) -> Result<(ShrikeStats, NetworkStatistics), ApiError> {
+    let conn2 = pool.get()?;
+    let conn3 = pool.get()?;
+    let conn4 = pool.get()?;
+    let conn5 = pool.get()?;
+    let conn6 = pool.get()?;
+    let conn8 = pool.get()?;
+    let conn9 = pool.get()?;
+    let conn10 = pool.get()?;
+    let conn11 = pool.get()?;
+
+    let transactions = task::spawn_blocking(move || get_transactions_internal(&conn2));
+
+    let sysfees = task::spawn_blocking(move || get_sysfee_internal(&conn3));
+
+    let transfers = task::spawn_blocking(move || get_transfers_internal(&conn4));
+
+    let senders = task::spawn_blocking(move || get_senders_internal(&conn5));
+
+    let contracts = task::spawn_blocking(move || get_contracts_internal(&conn6));
+
+    let current_week_contracts =
+        task::spawn_blocking(move || get_contracts_current_week_internal(&conn8));
+
+    let current_week_transactions =
+        task::spawn_blocking(move || get_transactions_current_week_internal(&conn9));
+
+    let addresses = task::spawn_blocking(move || get_addresses_internal(&conn10));
+
+    let current_week_addresses =
+        task::spawn_blocking(move || get_addresses_current_week_internal(&conn11));
+
+    let results = tokio::join!(
+        transactions,
+        sysfees,
+        transfers,
+        senders,
+        contracts,
+        current_week_contracts,
+        current_week_transactions,
+        addresses,
+        current_week_addresses,
+    );
+
+    let total_transactions = results.0.unwrap_or(0);
+    let total_contracts = results.4.unwrap_or(0);
+
+    Ok((
+        ShrikeStats {
+            total_blocks: blocks,
+            total_transactions,
+            total_sysfee: results.1.unwrap_or(0.0),
+            total_transfers: results.2.unwrap_or(0),
+            total_senders: results.3.unwrap_or(0),
+            total_contracts,
+        },
+        NetworkStatistics {
+            total_transactions,
+            total_addresses: results.7.unwrap_or(0),
+            total_contracts,
+            current_week_transactions: results.6.unwrap_or(0),
+            current_week_addresses: results.8.unwrap_or(0),
+            current_week_contracts: results.5.unwrap_or(0),
+        },
+    ))
 }
 
 pub fn get_blocks_internal(conn: &PooledConnection<SqliteConnectionManager>) -> u64 {
@@ -151,8 +177,7 @@ pub fn get_senders_internal(conn: &PooledConnection<SqliteConnectionManager>) ->
 
 pub fn get_contracts_internal(conn: &PooledConnection<SqliteConnectionManager>) -> u64 {
     let sql = "SELECT COALESCE(COUNT(*), 0) FROM contracts";
-    let native_contracts_count = 9; // fetch natives properly in future
-    get_stat_internal::<u64>(conn, sql).unwrap_or(0) + native_contracts_count
+    get_stat_internal::<u64>(conn, sql).unwrap_or(0) + NATIVE_CONTRACTS
 }
 
 pub fn get_contracts_current_week_internal(
@@ -174,3 +199,158 @@ pub fn get_transactions_current_week_internal(
         WHERE time >= strftime('%s', 'now', '-7 days') * 1000";
     get_stat_internal::<u64>(conn, sql).unwrap_or(0)
 }
+
+pub fn get_addresses_internal(conn: &PooledConnection<SqliteConnectionManager>) -> u64 {
+    let sql = "SELECT COALESCE(COUNT(*), 0) FROM address_first_seen";
+    get_stat_internal::<u64>(conn, sql).unwrap_or(0)
+}
+
+pub fn get_addresses_current_week_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> u64 {
+    let sql = "SELECT COALESCE(COUNT(*), 0)
+        FROM address_first_seen
+        WHERE date >= date('now', '-7 days')";
+    get_stat_internal::<u64>(conn, sql).unwrap_or(0)
+}
+
+pub fn list_daily_stats_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    date_init: String,
+    date_end: String,
+) -> Result<Vec<DailyNetworkStats>, ApiError> {
+    let sql = "
+        SELECT date, active_senders, new_addresses, transactions, transfers, fees, new_contracts
+        FROM daily_network_stats
+        WHERE date BETWEEN ? AND ?
+        ORDER BY date ASC";
+
+    let mut stmt = conn.prepare(sql)?;
+
+    let stats_iter = stmt.query_map(params![date_init, date_end], |row| {
+        Ok(DailyNetworkStats {
+            date: row.get(0)?,
+            active_senders: row.get(1)?,
+            new_addresses: row.get(2)?,
+            transactions: row.get(3)?,
+            transfers: row.get(4)?,
+            fees: row.get::<_, i64>(5)? as f64 / GAS_PRECISION,
+            new_contracts: row.get(6)?,
+        })
+    })?;
+
+    let stats = stats_iter.collect::<Result<Vec<DailyNetworkStats>, _>>()?;
+
+    if stats.is_empty() {
+        Err(ApiError::NotFound(
+            "No stats for that date range.".to_string(),
+        ))
+    } else {
+        Ok(stats)
+    }
+}
+
+#[test]
+fn test_list_daily_stats_internal() {
+    use crate::indexer::rpc::database::{
+        insert_test_notification, insert_test_transaction, memory_chain, Database,
+    };
+    use crate::shared::neo;
+    use crate::transaction::models::{Notification, State, StateValue, Transaction};
+
+    const HOUR: u64 = 3_600_000;
+    const START: u64 = 1_704_067_200_000; // 2024-01-01
+    const GAS: &str = "0xd2a4cff31913016155e38e474a2c06d08be276cf";
+    const ALICE: &str = "AQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+    const BOB: &str = "AgICAgICAgICAgICAgICAgICAgI=";
+    let (alice, bob) = (neo::base64_to_address(ALICE), neo::base64_to_address(BOB));
+
+    let conn = memory_chain(&[START + HOUR, START + 2 * HOUR, START + 25 * HOUR]);
+    insert_test_transaction(&conn, "0xaa", 1, &alice, "100", "10");
+    insert_test_notification(
+        &conn,
+        "0xaa",
+        GAS,
+        "Transfer",
+        &[("ByteString", ALICE), ("ByteString", BOB), ("Integer", "5")],
+    );
+    insert_test_transaction(&conn, "0xbb", 2, &alice, "200", "20");
+    insert_test_transaction(&conn, "0xcc", 3, &bob, "0", "5");
+    conn.execute(
+        "INSERT INTO contracts (block_index, hash, contract_type) VALUES (2, '0x01', '[]')",
+        [],
+    )
+    .unwrap();
+
+    // the indexer passes the batch it just stored, participants included
+    let transaction = |block_index, time, sender: &str, transfer: bool| Transaction {
+        index: 0,
+        hash: String::new(),
+        block_index,
+        timestamp: time,
+        vm_state: "HALT".to_string(),
+        size: 0,
+        version: 0,
+        nonce: 0,
+        sender: sender.to_string(),
+        sysfee: "0".to_string(),
+        netfee: "0".to_string(),
+        valid_until: 0,
+        signers: Vec::new(),
+        script: String::new(),
+        witnesses: Vec::new(),
+        stack_result: "[]".to_string(),
+        notifications: match transfer {
+            true => vec![Notification {
+                id: None,
+                contract: GAS.to_string(),
+                eventname: "Transfer".to_string(),
+                state: State {
+                    _type: "Array".to_string(),
+                    value: [ALICE, BOB, "5"]
+                        .iter()
+                        .map(|value| StateValue {
+                            _type: "ByteString".to_string(),
+                            value: Some(serde_json::json!(value)),
+                        })
+                        .collect(),
+                },
+            }],
+            false => Vec::new(),
+        },
+    };
+    Database::new(&conn)
+        .unwrap()
+        .update_daily_network_stats(&[
+            transaction(1, START + HOUR, &alice, true),
+            transaction(2, START + 2 * HOUR, &alice, false),
+            transaction(3, START + 25 * HOUR, &bob, false),
+        ])
+        .unwrap();
+
+    let stats = list_daily_stats_internal(&conn, "2024-01-01".into(), "2024-01-31".into()).unwrap();
+    let rows: Vec<_> = stats
+        .iter()
+        .map(|day| {
+            (
+                day.date.as_str(),
+                day.active_senders,
+                day.new_addresses,
+                day.transactions,
+                day.transfers,
+                day.new_contracts,
+            )
+        })
+        .collect();
+    // bob was first seen receiving on the first day, so sending on the second isn't new
+    assert_eq!(
+        rows,
+        vec![("2024-01-01", 1, 2, 2, 1, 1), ("2024-01-02", 1, 0, 1, 0, 0)]
+    );
+    assert_eq!(stats[0].fees, 330.0 / GAS_PRECISION);
+
+    assert!(matches!(
+        list_daily_stats_internal(&conn, "2023-01-01".into(), "2023-12-31".into()),
+        Err(ApiError::NotFound(_))
+    ));
+}
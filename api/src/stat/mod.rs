@@ -1,3 +1,4 @@
 pub mod controller;
 pub mod internals;
-mod models;
+pub mod models;
+pub mod postgres;
@@ -49,3 +49,14 @@ pub struct NetworkStatistics {
     pub current_week_addresses: u64,
     pub current_week_contracts: u64,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct DailyNetworkStats {
+    pub date: String,
+    pub active_senders: u64,
+    pub new_addresses: u64,
+    pub transactions: u64,
+    pub transfers: u64,
+    pub fees: f64, // sysfee + netfee in GAS
+    pub new_contracts: u64,
+}
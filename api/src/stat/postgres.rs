@@ -0,0 +1,145 @@
+use tokio_postgres::types::FromSqlOwned;
+use tokio_postgres::Client;
+
+use crate::error::ApiError;
+use crate::shared::models::GAS_PRECISION;
+use crate::shared::postgres::get_int;
+
+use super::internals::NATIVE_CONTRACTS;
+use super::models::{DailyNetworkStats, NetworkStatistics, ShrikeStats};
+
+const TRANSACTIONS_SQL: &str = "SELECT COALESCE(MAX(id), 0) FROM transactions";
+const SYSFEE_SQL: &str =
+    "SELECT COALESCE(SUM(sysfee::NUMERIC), 0)::DOUBLE PRECISION FROM transactions";
+const TRANSFERS_SQL: &str =
+    "SELECT COUNT(*) FROM transaction_notifications WHERE event_name = 'Transfer'";
+const SENDERS_SQL: &str = "SELECT COUNT(DISTINCT sender) FROM transactions";
+const CONTRACTS_SQL: &str = "SELECT COUNT(*) FROM contracts";
+const CONTRACTS_CURRENT_WEEK_SQL: &str = "
+    SELECT COUNT(*)
+    FROM contracts
+    INNER JOIN blocks ON blocks.id = contracts.block_index
+    WHERE time >= FLOOR(EXTRACT(EPOCH FROM now() - INTERVAL '7 days'))::BIGINT * 1000";
+const TRANSACTIONS_CURRENT_WEEK_SQL: &str = "
+    SELECT COUNT(*)
+    FROM transactions
+    INNER JOIN blocks ON blocks.id = transactions.block_index
+    WHERE time >= FLOOR(EXTRACT(EPOCH FROM now() - INTERVAL '7 days'))::BIGINT * 1000";
+const ADDRESSES_SQL: &str = "SELECT COUNT(*) FROM address_first_seen";
+const ADDRESSES_CURRENT_WEEK_SQL: &str = "
+    SELECT COUNT(*)
+    FROM address_first_seen
+    WHERE date >= to_char((now() AT TIME ZONE 'UTC') - INTERVAL '7 days', 'YYYY-MM-DD')";
+
+// a failing total is logged and left at zero, like the SQLite stats
+pub async fn get_stat_internal<T: FromSqlOwned>(client: &Client, sql: &str) -> Option<T> {
+    match client.query_one(sql, &[]).await {
+        Ok(row) => match row.try_get(0) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!("Database error: {:?} in query: {}", e, sql);
+                None
+            }
+        },
+        Err(e) => {
+            eprintln!("Database error: {:?} in query: {}", e, sql);
+            None
+        }
+    }
+}
+
+async fn get_count_internal(client: &Client, sql: &str) -> u64 {
+    get_stat_internal::<i64>(client, sql)
+        .await
+        .map_or(0, |count| count.max(0) as u64)
+}
+
+pub async fn get_blocks_internal(client: &Client) -> u64 {
+    get_count_internal(client, "SELECT COALESCE(MAX(id), 0) FROM blocks").await
+}
+
+// the queries are pipelined on the one connection rather than waiting on each other
+pub async fn get_stats_internal(
+    client: &Client,
+    blocks: u64,
+) -> Result<(ShrikeStats, NetworkStatistics), ApiError> {
+    let (
+        total_transactions,
+        sysfee,
+        total_transfers,
+        total_senders,
+        contracts,
+        current_week_contracts,
+        current_week_transactions,
+        total_addresses,
+        current_week_addresses,
+    ) = tokio::join!(
+        get_count_internal(client, TRANSACTIONS_SQL),
+        get_stat_internal::<f64>(client, SYSFEE_SQL),
+        get_count_internal(client, TRANSFERS_SQL),
+        get_count_internal(client, SENDERS_SQL),
+        get_count_internal(client, CONTRACTS_SQL),
+        get_count_internal(client, CONTRACTS_CURRENT_WEEK_SQL),
+        get_count_internal(client, TRANSACTIONS_CURRENT_WEEK_SQL),
+        get_count_internal(client, ADDRESSES_SQL),
+        get_count_internal(client, ADDRESSES_CURRENT_WEEK_SQL),
+    );
+
+    let total_contracts = contracts + NATIVE_CONTRACTS;
+
+    Ok((
+        ShrikeStats {
+            total_blocks: blocks,
+            total_transactions,
+            total_sysfee: sysfee.unwrap_or(0.0) / GAS_PRECISION,
+            total_transfers,
+            total_senders,
+            total_contracts,
+        },
+        NetworkStatistics {
+            total_transactions,
+            total_addresses,
+            total_contracts,
+            current_week_transactions,
+            current_week_addresses,
+            current_week_contracts,
+        },
+    ))
+}
+
+pub async fn list_daily_stats_internal(
+    client: &Client,
+    date_init: String,
+    date_end: String,
+) -> Result<Vec<DailyNetworkStats>, ApiError> {
+    let sql = "
+        SELECT date, active_senders, new_addresses, transactions, transfers, fees, new_contracts
+        FROM daily_network_stats
+        WHERE date BETWEEN $1 AND $2
+        ORDER BY date ASC";
+
+    let stats = client
+        .query(sql, &[&date_init, &date_end])
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(DailyNetworkStats {
+                date: row.try_get(0)?,
+                active_senders: get_int(row, 1)?,
+                new_addresses: get_int(row, 2)?,
+                transactions: get_int(row, 3)?,
+                transfers: get_int(row, 4)?,
+                fees: row.try_get::<_, i64>(5)? as f64 / GAS_PRECISION,
+                new_contracts: get_int(row, 6)?,
+            })
+        })
+        .collect::<Result<Vec<DailyNetworkStats>, ApiError>>()?;
+
+    if stats.is_empty() {
+        Err(ApiError::NotFound(
+            "No stats for that date range.".to_string(),
+        ))
+    } else {
+        Ok(stats)
+    }
+}
@@ -0,0 +1,97 @@
+use actix_web::{get, web, HttpResponse};
+
+use std::cmp::Reverse;
+
+use crate::error::ApiError;
+use crate::shared::checker;
+use crate::shared::export::{self, ExportFormat};
+use crate::shared::storage::ReadStorage;
+
+use super::models::{HolderSnapshot, HolderSnapshotParams, TokenOverview};
+
+#[get("/v1/tokens")]
+async fn list_tokens(storage: web::Data<dyn ReadStorage>) -> Result<HttpResponse, ApiError> {
+    let volumes = storage.get_volume_24h(None).await?;
+
+    let mut tokens: Vec<TokenOverview> = Vec::new();
+    for token in storage.list_token_contracts(None).await? {
+        let volume = volumes.get(&token.0).copied().unwrap_or(0);
+        tokens.push(storage.get_token_overview(token, volume).await?);
+    }
+
+    tokens.sort_by_key(|token| Reverse(token.holders));
+
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+#[get("/v1/tokens/{token}")]
+async fn get_token(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let token = path.into_inner();
+
+    if !checker::is_neo_script_hash(&token) {
+        return Err(ApiError::BadRequest("Invalid token hash.".to_string()));
+    }
+
+    let token_info = storage
+        .list_token_contracts(Some(&token))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| ApiError::NotFound("Token not found.".to_string()))?;
+
+    let volumes = storage.get_volume_24h(Some(&token)).await?;
+    let volume = volumes.get(&token).copied().unwrap_or(0);
+
+    let overview = storage.get_token_overview(token_info, volume).await?;
+
+    Ok(HttpResponse::Ok().json(overview))
+}
+
+#[get("/v1/tokens/{token}/holders")]
+async fn get_token_holders(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<String>,
+    query_parameter: web::Query<HolderSnapshotParams>,
+) -> Result<HttpResponse, ApiError> {
+    let token = path.into_inner();
+
+    if !checker::is_neo_script_hash(&token) {
+        return Err(ApiError::BadRequest("Invalid token hash.".to_string()));
+    }
+
+    let format = ExportFormat::parse(query_parameter.format.as_deref())?;
+
+    let block = storage.get_snapshot_block(query_parameter.block).await?;
+    let (symbol, decimals) = storage.get_token_info(&token).await?;
+
+    // snapshots for airdrops can be large, so exports stream every holder
+    if let Some(format) = format {
+        let name = format!("holders-{}", block);
+        let rows = storage.export_holders_at(token, block, decimals).await?;
+        return Ok(export::stream(format, &name, rows));
+    }
+
+    let holders = storage
+        .list_holders_at(token.clone(), block, decimals)
+        .await?;
+    let seeded = storage.get_seeded_height().await?;
+
+    Ok(HttpResponse::Ok().json(HolderSnapshot {
+        token_contract: token,
+        symbol,
+        decimals,
+        block_index: block,
+        exact: seeded.is_none_or(|height| block >= height),
+        holders: holders.len(),
+        list: holders,
+    }))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_tokens)
+        .service(get_token)
+        .service(get_token_holders);
+}
@@ -0,0 +1,300 @@
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+
+use std::collections::HashMap;
+
+use crate::address::internals as address_internals;
+use crate::error::ApiError;
+use crate::shared::events::parse_transfer;
+use crate::shared::export::format_amount;
+use crate::shared::models::NATIVE_TOKENS;
+use crate::token::models::{HolderBalance, TokenInfo, TokenOverview};
+use crate::transaction::models::{Notification, State, StateValue};
+
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+// native tokens never emit a Deploy, so they are not in the contracts table
+pub fn list_token_contracts_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: Option<&str>,
+) -> Result<Vec<TokenInfo>, ApiError> {
+    let mut tokens: Vec<_> = NATIVE_TOKENS
+        .iter()
+        .filter(|(hash, _, _)| token.is_none() || token == Some(*hash))
+        .map(|(hash, symbol, decimals)| {
+            (hash.to_string(), Some(symbol.to_string()), Some(*decimals))
+        })
+        .collect();
+
+    let sql = "
+        SELECT hash, symbol, decimals
+        FROM contracts
+        WHERE contract_type LIKE '%NEP-17%' AND (?1 IS NULL OR hash = ?1)
+        ORDER BY id ASC";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![token])?;
+
+    while let Some(row) = rows.next()? {
+        tokens.push((row.get(0)?, row.get(1)?, row.get(2)?));
+    }
+
+    Ok(tokens)
+}
+
+// transfer volume per token over the last day of indexed blocks, summed as i128 since
+// the raw amounts of 18 decimal tokens overflow SQLite integers
+pub fn get_volume_24h_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: Option<&str>,
+) -> Result<HashMap<String, i128>, ApiError> {
+    let sql = "
+        SELECT tn.id, tn.contract, tn.event_name, tn.state_type, nsv.type, nsv.value
+        FROM transactions t
+        INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+        INNER JOIN transaction_notification_state_values nsv ON tn.id = nsv.transaction_notification_id
+        WHERE t.block_index >= (
+                SELECT MIN(id) FROM blocks WHERE time >= (SELECT MAX(time) FROM blocks) - ?1
+            )
+            AND tn.event_name = 'Transfer'
+            AND (?2 IS NULL OR tn.contract = ?2)
+        ORDER BY tn.id, nsv.id";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![DAY_MILLIS, token])?;
+
+    let mut notifications: Vec<(u64, Notification)> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id: u64 = row.get(0)?;
+        let value = StateValue {
+            _type: row.get(4)?,
+            value: row
+                .get::<_, Option<String>>(5)?
+                .map(serde_json::Value::String),
+        };
+
+        match notifications.last_mut() {
+            Some((last, notification)) if *last == id => notification.state.value.push(value),
+            _ => notifications.push((
+                id,
+                Notification {
+                    id: Some(id),
+                    contract: row.get(1)?,
+                    eventname: row.get(2)?,
+                    state: State {
+                        _type: row.get(3)?,
+                        value: vec![value],
+                    },
+                },
+            )),
+        }
+    }
+
+    // only the amount of a well-formed Transfer counts, other integers are ids or payloads
+    let mut volumes = HashMap::new();
+    for (_, notification) in notifications {
+        if let Some((contract, _, _, qty)) = parse_transfer(&notification) {
+            *volumes.entry(contract).or_insert(0) += qty.parse::<i128>().unwrap_or(0);
+        }
+    }
+
+    Ok(volumes)
+}
+
+pub fn get_token_overview_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    (token, symbol, decimals): TokenInfo,
+    volume_24h: i128,
+) -> Result<TokenOverview, ApiError> {
+    let sql = "
+        SELECT
+            COALESCE(SUM(transfers), 0),
+            (SELECT supply FROM daily_token_stats WHERE token_contract = ?1
+                ORDER BY date DESC LIMIT 1)
+        FROM daily_token_stats
+        WHERE token_contract = ?1";
+
+    let (transfers, supply) = conn.query_row(sql, params![token], |row| {
+        Ok((row.get::<_, u64>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+
+    let sql = "SELECT COUNT(*) FROM address_balances WHERE token_contract = ? AND balance > 0";
+    let holders = conn.query_row(sql, params![token], |row| row.get::<_, u64>(0))?;
+
+    let price = address_internals::get_latest_price_internal(conn, &token)?;
+
+    Ok(TokenOverview {
+        token_contract: token,
+        symbol,
+        decimals,
+        supply,
+        holders,
+        transfers,
+        volume_24h: volume_24h.to_string(),
+        price_usd: price.as_ref().map(|(price, _)| *price),
+        price_date: price.map(|(_, date)| date),
+    })
+}
+
+// snapshots default to the last indexed block and can't look past it
+pub fn get_snapshot_block_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    block: Option<u64>,
+) -> Result<u64, ApiError> {
+    let sql = "SELECT MAX(id) FROM blocks";
+    let height = conn
+        .query_row(sql, [], |row| row.get::<_, Option<u64>>(0))?
+        .ok_or_else(|| ApiError::NotFound("No blocks indexed yet.".to_string()))?;
+
+    match block {
+        None => Ok(height),
+        Some(block) if block <= height => Ok(block),
+        Some(block) => Err(ApiError::BadRequest(format!(
+            "Block {} is beyond the indexed height {}.",
+            block, height
+        ))),
+    }
+}
+
+// balances below this height were seeded from end of day rows, on databases indexed before
+// every balance change was kept. None when the whole history is per block
+pub fn get_seeded_height_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Option<u64>, ApiError> {
+    let sql = "SELECT MAX(block_index) FROM balance_changes_seeded";
+    let height = conn.query_row(sql, [], |row| row.get::<_, Option<u64>>(0))?;
+
+    Ok(height)
+}
+
+// every address with a positive balance as of the block, taken from its last balance
+// change at or before it. balances only move on transfers, so fee burns and gas rewards
+// land with the next transfer of the holder
+pub fn export_holders_at_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: String,
+    block: u64,
+    decimals: Option<u8>,
+    emit: &mut dyn FnMut(HolderBalance) -> bool,
+) -> Result<(), ApiError> {
+    let sql = "
+        SELECT address, balance, block_index FROM (
+            SELECT address, balance, block_index,
+                ROW_NUMBER() OVER (PARTITION BY address ORDER BY block_index DESC) AS latest
+            FROM balance_changes
+            WHERE token_contract = ? AND block_index <= ?
+        )
+        WHERE latest = 1 AND balance > 0
+        ORDER BY balance DESC, address ASC";
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![token, block])?;
+
+    while let Some(row) = rows.next()? {
+        let balance: i64 = row.get(1)?;
+        let holder = HolderBalance {
+            address: row.get(0)?,
+            balance,
+            amount: decimals.map(|d| format_amount(&balance.to_string(), d)),
+            block_index: row.get(2)?,
+        };
+
+        if !emit(holder) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn list_holders_at_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: String,
+    block: u64,
+    decimals: Option<u8>,
+) -> Result<Vec<HolderBalance>, ApiError> {
+    let mut holders = Vec::new();
+    export_holders_at_internal(conn, token, block, decimals, &mut |holder| {
+        holders.push(holder);
+        true
+    })?;
+
+    if holders.is_empty() {
+        Err(ApiError::NotFound(
+            "No holders for that token at that block.".to_string(),
+        ))
+    } else {
+        Ok(holders)
+    }
+}
+
+#[test]
+fn test_list_holders_at_internal() {
+    use crate::indexer::rpc::database::{insert_test_block, memory_chain, Database};
+
+    const TOKEN: &str = "0xd2a4cff31913016155e38e474a2c06d08be276cf";
+    let holders_at = |conn, block| {
+        list_holders_at_internal(conn, TOKEN.to_string(), block, Some(8))
+            .map(|holders| {
+                holders
+                    .into_iter()
+                    .map(|holder| (holder.address, holder.balance, holder.block_index))
+                    .collect::<Vec<_>>()
+            })
+            .ok()
+    };
+
+    // an older database: its only balance for the day is the one after block 2
+    let conn = memory_chain(&[1000, 2000, 3000]);
+    conn.execute(
+        "INSERT INTO daily_address_balances (block_index, date, address, token_contract, balance)
+        VALUES (2, '1970-01-01', 'alice', ?, 70)",
+        [TOKEN],
+    )
+    .unwrap();
+    assert_eq!(get_seeded_height_internal(&conn).unwrap(), None);
+    assert_eq!(
+        Database::new(&conn)
+            .unwrap()
+            .seed_balance_changes()
+            .unwrap(),
+        1
+    );
+    assert_eq!(get_seeded_height_internal(&conn).unwrap(), Some(3));
+
+    // changes indexed after the upgrade are kept per block
+    insert_test_block(&conn, 4000);
+    insert_test_block(&conn, 5000);
+    conn.execute(
+        "INSERT INTO balance_changes (block_index, address, token_contract, balance)
+        VALUES (4, 'alice', ?1, 0), (4, 'bob', ?1, 70), (5, 'alice', ?1, 5)",
+        [TOKEN],
+    )
+    .unwrap();
+
+    assert_eq!(holders_at(&conn, 1), None);
+    assert_eq!(
+        holders_at(&conn, 2),
+        Some(vec![("alice".to_string(), 70, 2)])
+    );
+    // alice is down to zero at block 4, which drops the address from the holders
+    assert_eq!(holders_at(&conn, 4), Some(vec![("bob".to_string(), 70, 4)]));
+    assert_eq!(
+        holders_at(&conn, 5),
+        Some(vec![
+            ("bob".to_string(), 70, 4),
+            ("alice".to_string(), 5, 5)
+        ])
+    );
+
+    // seeding only ever happens once
+    assert_eq!(
+        Database::new(&conn)
+            .unwrap()
+            .seed_balance_changes()
+            .unwrap(),
+        0
+    );
+    assert_eq!(get_seeded_height_internal(&conn).unwrap(), Some(3));
+}
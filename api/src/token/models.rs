@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use crate::shared::export::ExportRow;
+use crate::shared::models::{Address, Hash160};
+
+// contract hash, symbol and decimals
+pub type TokenInfo = (Hash160, Option<String>, Option<u8>);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TokenOverview {
+    pub token_contract: Hash160,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub supply: Option<String>, // raw integer supply, None until it has been seeded
+    pub holders: u64,
+    pub transfers: u64,
+    pub volume_24h: String, // raw integer amount moved in the last 24h of indexed blocks
+    pub price_usd: Option<f64>,
+    pub price_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct HolderSnapshotParams {
+    pub block: Option<u64>, // defaults to the last indexed block
+    pub format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HolderSnapshot {
+    pub token_contract: Hash160,
+    pub symbol: Option<String>,
+    pub decimals: Option<u8>,
+    pub block_index: u64,
+    // false below the height an older database was upgraded at, where balances are the ones at
+    // the end of the block's day. exports don't carry it, check here before exporting
+    pub exact: bool,
+    pub holders: usize,
+    pub list: Vec<HolderBalance>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HolderBalance {
+    pub address: Address,
+    pub balance: i64,           // raw integer balance
+    pub amount: Option<String>, // balance shifted by the token decimals, when they are known
+    pub block_index: u64,       // block of the last balance change at or before the snapshot
+}
+
+impl ExportRow for HolderBalance {
+    const HEADER: &'static [&'static str] = &["address", "balance", "amount", "block_index"];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.address.clone(),
+            self.balance.to_string(),
+            self.amount.clone().unwrap_or_default(),
+            self.block_index.to_string(),
+        ]
+    }
+}
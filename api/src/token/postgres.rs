@@ -0,0 +1,209 @@
+use tokio_postgres::{Client, Row};
+
+use std::collections::HashMap;
+
+use crate::address::postgres as address_postgres;
+use crate::error::ApiError;
+use crate::shared::events::parse_transfer;
+use crate::shared::export::{format_amount, ExportSink};
+use crate::shared::models::NATIVE_TOKENS;
+use crate::shared::postgres::{get_int, get_opt_int, stream_rows, to_i64};
+use crate::token::models::{HolderBalance, TokenInfo, TokenOverview};
+use crate::transaction::models::{Notification, State, StateValue};
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+// last balance change of every holder at or before the block, see the SQLite version
+const HOLDERS_AT_SQL: &str = "
+    SELECT address, balance, block_index FROM (
+        SELECT address, balance, block_index,
+            ROW_NUMBER() OVER (PARTITION BY address ORDER BY block_index DESC) AS latest
+        FROM balance_changes
+        WHERE token_contract = $1 AND block_index <= $2
+    ) changes
+    WHERE latest = 1 AND balance > 0
+    ORDER BY balance DESC, address ASC";
+
+// native tokens never emit a Deploy, so they are not in the contracts table
+pub async fn list_token_contracts_internal(
+    client: &Client,
+    token: Option<&str>,
+) -> Result<Vec<TokenInfo>, ApiError> {
+    let mut tokens: Vec<_> = NATIVE_TOKENS
+        .iter()
+        .filter(|(hash, _, _)| token.is_none() || token == Some(*hash))
+        .map(|(hash, symbol, decimals)| {
+            (hash.to_string(), Some(symbol.to_string()), Some(*decimals))
+        })
+        .collect();
+
+    let sql = "
+        SELECT hash, symbol, decimals
+        FROM contracts
+        WHERE contract_type LIKE '%NEP-17%' AND ($1::TEXT IS NULL OR hash = $1)
+        ORDER BY id ASC";
+
+    for row in client.query(sql, &[&token]).await? {
+        tokens.push((row.try_get(0)?, row.try_get(1)?, get_opt_int(&row, 2)?));
+    }
+
+    Ok(tokens)
+}
+
+// transfer volume per token over the last day of indexed blocks, summed as i128 since
+// the raw amounts of 18 decimal tokens overflow BIGINT
+pub async fn get_volume_24h_internal(
+    client: &Client,
+    token: Option<&str>,
+) -> Result<HashMap<String, i128>, ApiError> {
+    let sql = "
+        SELECT tn.id, tn.contract, tn.event_name, tn.state_type, nsv.type, nsv.value
+        FROM transactions t
+        INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+        INNER JOIN transaction_notification_state_values nsv
+            ON tn.id = nsv.transaction_notification_id
+        WHERE t.block_index >= (
+                SELECT MIN(id) FROM blocks WHERE time >= (SELECT MAX(time) FROM blocks) - $1
+            )
+            AND tn.event_name = 'Transfer'
+            AND ($2::TEXT IS NULL OR tn.contract = $2)
+        ORDER BY tn.id, nsv.id";
+
+    let mut notifications: Vec<(u64, Notification)> = Vec::new();
+    for row in client.query(sql, &[&DAY_MILLIS, &token]).await? {
+        let id: u64 = get_int(&row, 0)?;
+        let value = StateValue {
+            _type: row.try_get(4)?,
+            value: row
+                .try_get::<_, Option<String>>(5)?
+                .map(serde_json::Value::String),
+        };
+
+        match notifications.last_mut() {
+            Some((last, notification)) if *last == id => notification.state.value.push(value),
+            _ => notifications.push((
+                id,
+                Notification {
+                    id: Some(id),
+                    contract: row.try_get(1)?,
+                    eventname: row.try_get(2)?,
+                    state: State {
+                        _type: row.try_get(3)?,
+                        value: vec![value],
+                    },
+                },
+            )),
+        }
+    }
+
+    // only the amount of a well-formed Transfer counts, other integers are ids or payloads
+    let mut volumes = HashMap::new();
+    for (_, notification) in notifications {
+        if let Some((contract, _, _, qty)) = parse_transfer(&notification) {
+            *volumes.entry(contract).or_insert(0) += qty.parse::<i128>().unwrap_or(0);
+        }
+    }
+
+    Ok(volumes)
+}
+
+pub async fn get_token_overview_internal(
+    client: &Client,
+    (token, symbol, decimals): TokenInfo,
+    volume_24h: i128,
+) -> Result<TokenOverview, ApiError> {
+    let sql = "
+        SELECT
+            COALESCE(SUM(transfers), 0)::BIGINT,
+            (SELECT supply FROM daily_token_stats WHERE token_contract = $1
+                ORDER BY date DESC LIMIT 1)
+        FROM daily_token_stats
+        WHERE token_contract = $1";
+
+    let row = client.query_one(sql, &[&token]).await?;
+    let (transfers, supply) = (get_int(&row, 0)?, row.try_get(1)?);
+
+    let sql = "SELECT COUNT(*) FROM address_balances WHERE token_contract = $1 AND balance > 0";
+    let holders = get_int(&client.query_one(sql, &[&token]).await?, 0)?;
+
+    let price = address_postgres::get_latest_price_internal(client, &token).await?;
+
+    Ok(TokenOverview {
+        token_contract: token,
+        symbol,
+        decimals,
+        supply,
+        holders,
+        transfers,
+        volume_24h: volume_24h.to_string(),
+        price_usd: price.as_ref().map(|(price, _)| *price),
+        price_date: price.map(|(_, date)| date),
+    })
+}
+
+// snapshots default to the last indexed block and can't look past it
+pub async fn get_snapshot_block_internal(
+    client: &Client,
+    block: Option<u64>,
+) -> Result<u64, ApiError> {
+    let sql = "SELECT MAX(id) FROM blocks";
+    let height: u64 = get_opt_int(&client.query_one(sql, &[]).await?, 0)?
+        .ok_or_else(|| ApiError::NotFound("No blocks indexed yet.".to_string()))?;
+
+    match block {
+        None => Ok(height),
+        Some(block) if block <= height => Ok(block),
+        Some(block) => Err(ApiError::BadRequest(format!(
+            "Block {} is beyond the indexed height {}.",
+            block, height
+        ))),
+    }
+}
+
+fn holder_balance(row: &Row, decimals: Option<u8>) -> Result<HolderBalance, ApiError> {
+    let balance: i64 = row.try_get(1)?;
+
+    Ok(HolderBalance {
+        address: row.try_get(0)?,
+        balance,
+        amount: decimals.map(|d| format_amount(&balance.to_string(), d)),
+        block_index: get_int(row, 2)?,
+    })
+}
+
+pub async fn export_holders_at_internal(
+    client: &Client,
+    token: String,
+    block: u64,
+    decimals: Option<u8>,
+    sink: &ExportSink<HolderBalance>,
+) -> Result<(), ApiError> {
+    let block = to_i64(block)?;
+
+    stream_rows(client, HOLDERS_AT_SQL, &[&token, &block], sink, |row| {
+        holder_balance(row, decimals)
+    })
+    .await
+}
+
+pub async fn list_holders_at_internal(
+    client: &Client,
+    token: String,
+    block: u64,
+    decimals: Option<u8>,
+) -> Result<Vec<HolderBalance>, ApiError> {
+    let holders = client
+        .query(HOLDERS_AT_SQL, &[&token, &to_i64(block)?])
+        .await?
+        .iter()
+        .map(|row| holder_balance(row, decimals))
+        .collect::<Result<Vec<HolderBalance>, _>>()?;
+
+    if holders.is_empty() {
+        Err(ApiError::NotFound(
+            "No holders for that token at that block.".to_string(),
+        ))
+    } else {
+        Ok(holders)
+    }
+}
@@ -1,146 +1,128 @@
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{get, web, HttpResponse};
 
-use crate::error::Error;
+use crate::error::ApiError;
 use crate::shared::checker;
-use crate::shared::models::{PagedResp, PaginationAndFilterParams};
+use crate::shared::export::{self, ExportFormat};
+use crate::shared::models::{AddressParam, PagedResp, PaginationAndFilterParams};
+use crate::shared::storage::ReadStorage;
 use crate::shared::utils::normalize_pagination;
-use crate::ConnectionPool;
-
-use super::internals;
 
 #[get("/v1/transaction/{hash}")]
 async fn get_transaction(
-    pool: web::Data<ConnectionPool>,
+    storage: web::Data<dyn ReadStorage>,
     path: web::Path<String>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let hash = path.into_inner();
 
     if !checker::is_neo_txid_hash(&hash) {
-        return HttpResponse::Ok().json(Error {
-            error: "Invalid transaction hash.".to_string(),
-        });
+        return Err(ApiError::BadRequest(
+            "Invalid transaction hash.".to_string(),
+        ));
     }
 
-    let conn = &pool.connection.get().unwrap();
-    let mut transaction = match internals::get_transaction_internal(conn, hash.clone()) {
-        Ok(tx) => tx,
-        Err(err) => return HttpResponse::NotFound().json(err),
-    };
-
-    let witnesses = match internals::get_witnesses(conn, hash.clone()) {
-        Ok(witnesses) => witnesses,
-        Err(err) => return HttpResponse::InternalServerError().json(err),
-    };
-    transaction.witnesses = witnesses;
+    let mut transaction = storage.get_transaction(hash.clone()).await?;
 
-    let signers = match internals::get_signers(conn, hash.clone()) {
-        Ok(signers) => signers,
-        Err(err) => return HttpResponse::InternalServerError().json(err),
-    };
-    transaction.signers = signers;
-
-    let notifications = match internals::get_transaction_notifications(conn, hash.clone()) {
-        Ok(notifs) => notifs,
-        Err(err) => return HttpResponse::InternalServerError().json(err),
-    };
+    transaction.witnesses = storage.get_transaction_witnesses(hash.clone()).await?;
+    transaction.signers = storage.get_signers(hash.clone()).await?;
 
     let mut enriched_notifications = Vec::new();
-    for mut notification in notifications {
-        let state_values =
-            match internals::get_notification_state_values(conn, notification.id.unwrap()) {
-                Ok(states) => states,
-                Err(err) => return HttpResponse::InternalServerError().json(err),
-            };
-        notification.state.value = state_values;
+    for mut notification in storage.get_transaction_notifications(hash.clone()).await? {
+        notification.state.value = storage
+            .get_notification_state_values(notification.id.unwrap())
+            .await?;
         enriched_notifications.push(notification);
     }
 
     transaction.notifications = enriched_notifications;
 
-    HttpResponse::Ok().json(transaction)
+    Ok(HttpResponse::Ok().json(transaction))
 }
 
-#[get("/v1/transaction/sender/{address}")]
-async fn get_sender_transactions(
-    pool: web::Data<ConnectionPool>,
+#[get("/v1/transaction/{hash}/events")]
+async fn get_transaction_events(
+    storage: web::Data<dyn ReadStorage>,
     path: web::Path<String>,
-    query_parameter: web::Query<PaginationAndFilterParams>,
-) -> impl Responder {
-    let address = path.into_inner();
+) -> Result<HttpResponse, ApiError> {
+    let hash = path.into_inner();
 
-    if !checker::is_neo_address(&address) {
-        return HttpResponse::Ok().json(Error {
-            error: "Invalid address.".to_string(),
-        });
+    if !checker::is_neo_txid_hash(&hash) {
+        return Err(ApiError::BadRequest(
+            "Invalid transaction hash.".to_string(),
+        ));
     }
 
-    let (page, per_page, sort_by, order) = match normalize_pagination(&query_parameter) {
-        Ok(result) => result,
-        Err(response) => return response,
-    };
+    storage.get_transaction(hash.clone()).await?;
 
-    let conn = &pool.connection.get().unwrap();
-
-    let transactions = internals::get_sender_transactions_internal(
-        conn,
-        address.clone(),
-        query_parameter.with_transfers.unwrap_or(false),
-        page,
-        per_page,
-        sort_by.as_deref(),
-        order.as_deref(),
-    );
-
-    match transactions {
-        Ok(txs) => HttpResponse::Ok().json(PagedResp::new(
-            txs,
-            internals::count_sender_transactions_internal(conn, address.clone()),
-        )),
-        Err(err) => HttpResponse::Ok().json(err),
+    let mut enriched_notifications = Vec::new();
+    for mut notification in storage.get_transaction_notifications(hash.clone()).await? {
+        notification.state.value = storage
+            .get_notification_state_values(notification.id.unwrap())
+            .await?;
+        enriched_notifications.push(notification);
     }
+
+    let events = storage.decode_notifications(enriched_notifications).await?;
+
+    Ok(HttpResponse::Ok().json(events))
+}
+
+#[get("/v1/transaction/sender/{address}")]
+async fn get_sender_transactions(
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<AddressParam>,
+    query_parameter: web::Query<PaginationAndFilterParams>,
+) -> Result<HttpResponse, ApiError> {
+    let address = path.into_inner().into_inner();
+    let pagination = normalize_pagination(&query_parameter)?;
+
+    let transactions = storage
+        .get_sender_transactions(
+            address.clone(),
+            query_parameter.with_transfers.unwrap_or(false),
+            &pagination,
+        )
+        .await?;
+
+    // cursor pages skip the COUNT(*) that makes deep scans slow
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(storage.count_sender_transactions(address.clone()).await?),
+    };
+
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(transactions, count)))
 }
 
 #[get("/v1/transaction/transfers/{address}")]
 async fn get_address_transfers(
-    pool: web::Data<ConnectionPool>,
-    path: web::Path<String>,
+    storage: web::Data<dyn ReadStorage>,
+    path: web::Path<AddressParam>,
     query_parameter: web::Query<PaginationAndFilterParams>,
-) -> impl Responder {
-    let address = path.into_inner();
+) -> Result<HttpResponse, ApiError> {
+    let address = path.into_inner().into_inner();
 
-    if !checker::is_neo_address(&address) {
-        return HttpResponse::Ok().json(Error {
-            error: "Invalid address.".to_string(),
-        });
+    // exports ignore paging and stream every transfer
+    if let Some(format) = ExportFormat::parse(query_parameter.format.as_deref())? {
+        let rows = storage.export_address_transfers(address).await?;
+        return Ok(export::stream(format, "transfers", rows));
     }
 
-    let (page, per_page, sort_by, order) = match normalize_pagination(&query_parameter) {
-        Ok(result) => result,
-        Err(response) => return response,
+    let pagination = normalize_pagination(&query_parameter)?;
+
+    let transfer_list = storage
+        .get_address_transfers(address.clone(), &pagination)
+        .await?;
+
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(storage.count_address_transfers(address.clone()).await?),
     };
 
-    let conn = &pool.connection.get().unwrap();
-
-    let transfer_list = internals::get_address_transfers_internal(
-        conn,
-        address.clone(),
-        page,
-        per_page,
-        sort_by.as_deref(),
-        order.as_deref(),
-    );
-
-    match transfer_list {
-        Ok(tl) => HttpResponse::Ok().json(PagedResp::new(
-            tl,
-            internals::count_address_transfers_internal(conn, address.clone()),
-        )),
-        Err(err) => HttpResponse::Ok().json(err),
-    }
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(transfer_list, count)))
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(get_transaction)
+        .service(get_transaction_events)
         .service(get_sender_transactions)
         .service(get_address_transfers);
 }
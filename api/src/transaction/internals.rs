@@ -2,23 +2,27 @@ use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::params;
 
+use std::collections::HashMap;
+
 use crate::block::internals;
 use crate::block::models::Witness;
-use crate::error::Error;
+use crate::contract::internals as contract_internals;
+use crate::error::ApiError;
 use crate::shared::events;
+use crate::shared::export::format_amount;
+use crate::shared::models::GAS_DECIMALS;
 use crate::shared::neo;
+use crate::shared::pagination::{Page, Pagination};
 use crate::transaction::models::{
-    Notification, Signer, State, StateValue, Transaction, TxDataList,
+    Notification, Signer, State, StateValue, Transaction, TransferExport, TxDataList,
 };
 
 pub fn get_transaction_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     hash: String,
-) -> Result<Transaction, Error> {
+) -> Result<Transaction, ApiError> {
     let sql = "SELECT * FROM transactions WHERE hash = ?";
-    let mut stmt = conn.prepare(sql).map_err(|err| Error {
-        error: format!("Failed to prepare transaction query: {}", err),
-    })?;
+    let mut stmt = conn.prepare(sql)?;
 
     let transaction_result = stmt
         .query_row([hash], |row| {
@@ -42,8 +46,11 @@ pub fn get_transaction_internal(
                 notifications: Vec::new(),
             })
         })
-        .map_err(|err| Error {
-            error: format!("Transaction does not exist: {}", err),
+        .map_err(|err| match err {
+            rusqlite::Error::QueryReturnedNoRows => {
+                ApiError::NotFound("Transaction does not exist.".to_string())
+            }
+            err => err.into(),
         })?;
 
     Ok(transaction_result)
@@ -52,26 +59,18 @@ pub fn get_transaction_internal(
 pub fn get_witnesses(
     conn: &PooledConnection<SqliteConnectionManager>,
     hash: String,
-) -> Result<Vec<Witness>, Error> {
+) -> Result<Vec<Witness>, ApiError> {
     let witness_sql = "SELECT invocation, verification FROM witnesses WHERE transaction_id = (SELECT id FROM transactions WHERE hash = ?)";
-    let mut stmt = conn.prepare(witness_sql).map_err(|err| Error {
-        error: format!("Failed to prepare witness query: {}", err),
-    })?;
+    let mut stmt = conn.prepare(witness_sql)?;
 
-    let witness_iter = stmt
-        .query_map([hash], |row| {
-            Ok(Witness {
-                invocation: row.get(0)?,
-                verification: row.get(1)?,
-            })
+    let witness_iter = stmt.query_map([hash], |row| {
+        Ok(Witness {
+            invocation: row.get(0)?,
+            verification: row.get(1)?,
         })
-        .map_err(|err| Error {
-            error: format!("Failed to query witnesses: {}", err),
-        })?;
+    })?;
 
-    let witnesses: Vec<Witness> = witness_iter
-        .filter_map(|witness| witness.ok()) // Ignorar erros individuais
-        .collect();
+    let witnesses = witness_iter.collect::<Result<Vec<Witness>, _>>()?;
 
     Ok(witnesses)
 }
@@ -79,29 +78,21 @@ pub fn get_witnesses(
 pub fn get_signers(
     conn: &PooledConnection<SqliteConnectionManager>,
     hash: String,
-) -> Result<Vec<Signer>, Error> {
+) -> Result<Vec<Signer>, ApiError> {
     let signer_sql = "SELECT id, account, scopes FROM signers WHERE transaction_id = (SELECT id FROM transactions WHERE hash = ?)";
-    let mut stmt_signer = conn.prepare(signer_sql).map_err(|err| Error {
-        error: format!("Failed to prepare signer query: {}", err),
+    let mut stmt_signer = conn.prepare(signer_sql)?;
+
+    let signer_iter = stmt_signer.query_map([hash], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
     })?;
 
-    let signer_iter = stmt_signer
-        .query_map([hash], |row| {
-            Ok((
-                row.get::<_, i64>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
-            ))
-        })
-        .map_err(|err| Error {
-            error: format!("Failed to execute signer query: {}", err),
-        })?;
-
     let mut signers = Vec::new();
     for result in signer_iter {
-        let (signer_id, account, scopes) = result.map_err(|err| Error {
-            error: format!("Failed to map signer row: {}", err),
-        })?;
+        let (signer_id, account, scopes) = result?;
 
         signers.push(Signer {
             account,
@@ -116,21 +107,14 @@ pub fn get_signers(
 pub fn get_allowed_contracts(
     conn: &PooledConnection<SqliteConnectionManager>,
     signer_id: i64,
-) -> Result<Option<Vec<String>>, Error> {
+) -> Result<Option<Vec<String>>, ApiError> {
     let allowed_contract_sql = "SELECT contract FROM allowed_contracts WHERE signer_id = ?";
-    let mut stmt_allowed_contract = conn.prepare(allowed_contract_sql).map_err(|err| Error {
-        error: format!("Failed to prepare allowed contracts query: {}", err),
-    })?;
+    let mut stmt_allowed_contract = conn.prepare(allowed_contract_sql)?;
 
-    let allowed_contract_iter = stmt_allowed_contract
-        .query_map([signer_id], |row| row.get::<_, String>(0))
-        .map_err(|err| Error {
-            error: format!("Failed to execute allowed contracts query: {}", err),
-        })?;
+    let allowed_contract_iter =
+        stmt_allowed_contract.query_map([signer_id], |row| row.get::<_, String>(0))?;
 
-    let allowed_contracts: Vec<String> = allowed_contract_iter
-        .filter_map(|result| result.ok())
-        .collect();
+    let allowed_contracts = allowed_contract_iter.collect::<Result<Vec<String>, _>>()?;
 
     if allowed_contracts.is_empty() {
         Ok(None)
@@ -142,24 +126,24 @@ pub fn get_allowed_contracts(
 pub fn get_transaction_notifications(
     conn: &PooledConnection<SqliteConnectionManager>,
     transaction_hash: String,
-) -> Result<Vec<Notification>, Error> {
+) -> Result<Vec<Notification>, ApiError> {
     let sql = "
         SELECT *
         FROM transaction_notifications
         WHERE transaction_hash = ?";
 
-    let mut stmt = conn.prepare(sql).unwrap();
-    let mut rows = stmt.query(params![transaction_hash]).unwrap();
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![transaction_hash])?;
 
     let mut notifications = Vec::new();
 
-    while let Some(row) = rows.next().unwrap() {
+    while let Some(row) = rows.next()? {
         notifications.push(Notification {
-            id: row.get(0).unwrap(),
-            contract: row.get(2).unwrap(),
-            eventname: row.get(3).unwrap(),
+            id: row.get(0)?,
+            contract: row.get(2)?,
+            eventname: row.get(3)?,
             state: State {
-                _type: row.get(4).unwrap(),
+                _type: row.get(4)?,
                 value: Vec::new(),
             },
         });
@@ -171,18 +155,18 @@ pub fn get_transaction_notifications(
 pub fn get_notification_state_values(
     conn: &PooledConnection<SqliteConnectionManager>,
     notification_id: u64,
-) -> Result<Vec<StateValue>, Error> {
+) -> Result<Vec<StateValue>, ApiError> {
     let sql = "
         SELECT type, value
         FROM transaction_notification_state_values
         WHERE transaction_notification_id = ?";
 
-    let mut stmt = conn.prepare(sql).unwrap();
-    let mut rows = stmt.query([notification_id]).unwrap();
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query([notification_id])?;
     let mut state_values = Vec::new();
 
-    while let Some(row) = rows.next().unwrap() {
-        let state_type: String = row.get(0).unwrap();
+    while let Some(row) = rows.next()? {
+        let state_type: String = row.get(0)?;
         let state_value: Option<String> = row.get(1).ok();
 
         let value = state_value.map(|v| serde_json::Value::String(v));
@@ -200,23 +184,9 @@ pub fn get_sender_transactions_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     address: String,
     with_transfers: bool,
-    page: u32,
-    per_page: u32,
-    sort_by: Option<&str>,
-    order: Option<&str>,
-) -> Result<Vec<Transaction>, Error> {
-    let order_clause = if let (Some(sort_by), Some(order)) = (sort_by, order) {
-        let valid_columns = vec!["id"];
-        if valid_columns.contains(&sort_by) {
-            format!("ORDER BY {} {}", sort_by, order)
-        } else {
-            return Err(Error {
-                error: format!("Invalid sort_by parameter: {}", sort_by),
-            });
-        }
-    } else {
-        String::new()
-    };
+    pagination: &Pagination,
+) -> Result<Page<Vec<Transaction>>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("t.id", &["id"])?;
     let with_transfer_clause = if (!with_transfers) {
         format!("AND (tn.event_name != 'Transfer' OR tn.id IS NULL)")
     } else {
@@ -228,148 +198,135 @@ pub fn get_sender_transactions_internal(
         FROM transactions t
         LEFT JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
         WHERE sender = ? 
-        {}
+        {} {}
         GROUP BY t.hash
-        {}
-        LIMIT ? OFFSET ?",
-        with_transfer_clause, order_clause
+        {}",
+        with_transfer_clause, keyset_clause, page_clause
     );
-    let mut stmt = conn.prepare(sql.as_str()).unwrap();
+    let mut stmt = conn.prepare(sql.as_str())?;
 
-    let mut rows = stmt
-        .query(params![address, per_page, page * per_page])
-        .unwrap();
+    let mut rows = stmt.query(params![address])?;
 
     let mut transactions = Vec::new();
-    while let Some(row) = rows.next().unwrap() {
+    while let Some(row) = rows.next()? {
         transactions.push(Transaction {
             timestamp: 0,
-            index: row.get(0).unwrap(),
-            hash: row.get(1).unwrap(),
-            block_index: row.get(2).unwrap(),
-            vm_state: row.get(3).unwrap(),
-            size: row.get(4).unwrap(),
-            version: row.get(5).unwrap(),
-            nonce: row.get(6).unwrap(),
-            sender: row.get(7).unwrap(),
-            sysfee: row.get(8).unwrap(),
-            netfee: row.get(9).unwrap(),
-            valid_until: row.get(10).unwrap(),
-            script: row.get(11).unwrap(),
-            stack_result: row.get(12).unwrap(),
+            index: row.get(0)?,
+            hash: row.get(1)?,
+            block_index: row.get(2)?,
+            vm_state: row.get(3)?,
+            size: row.get(4)?,
+            version: row.get(5)?,
+            nonce: row.get(6)?,
+            sender: row.get(7)?,
+            sysfee: row.get(8)?,
+            netfee: row.get(9)?,
+            valid_until: row.get(10)?,
+            script: row.get(11)?,
+            stack_result: row.get(12)?,
             signers: Vec::new(),
             witnesses: Vec::new(),
             notifications: Vec::new(),
         })
     }
 
-    match transactions.is_empty() {
-        false => Ok(transactions),
-        true => Err(Error {
-            error: "No transactions for that sender.".to_string(),
-        }),
+    match transactions.is_empty() && !pagination.has_cursor() {
+        false => Ok(pagination.finish(
+            transactions
+                .into_iter()
+                .map(|tx| (tx.index as i64, tx))
+                .collect(),
+        )),
+        true => Err(ApiError::NotFound(
+            "No transactions for that sender.".to_string(),
+        )),
     }
 }
 
 pub fn count_sender_transactions_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     address: String,
-) -> usize {
+) -> Result<usize, ApiError> {
     let sql = "
         SELECT COUNT(*)
         FROM transactions 
         WHERE sender = ?
     ";
 
-    conn.query_row(sql, params![address], |row| row.get::<_, usize>(0))
-        .unwrap_or(0)
+    let count = conn.query_row(sql, params![address], |row| row.get::<_, usize>(0))?;
+
+    Ok(count)
 }
 
 pub fn get_address_transfers_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     address: String,
-    page: u32,
-    per_page: u32,
-    sort_by: Option<&str>,
-    order: Option<&str>,
-) -> Result<TxDataList, Error> {
-    let order_clause = if let (Some(sort_by), Some(order)) = (sort_by, order) {
-        let valid_columns = vec!["id"];
-        if valid_columns.contains(&sort_by) {
-            format!("ORDER BY {} {}", sort_by, order)
-        } else {
-            return Err(Error {
-                error: format!("Invalid sort_by parameter: {}", sort_by),
-            });
-        }
-    } else {
-        String::new()
-    };
+    pagination: &Pagination,
+) -> Result<Page<TxDataList>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("t.id", &["id"])?;
 
     let sql = format!(
         "SELECT t.*
         FROM transactions t
         INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
         INNER JOIN transaction_notification_state_values nsv ON tn.id = nsv.transaction_notification_id
-        WHERE nsv.value = ? AND tn.event_name = 'Transfer'
+        WHERE nsv.value = ? AND tn.event_name = 'Transfer' {}
         GROUP BY t.hash
-        {} LIMIT ? OFFSET ?",
-        order_clause
+        {}",
+        keyset_clause, page_clause
     );
 
     let base64 = neo::address_to_base64(&address);
-    let mut stmt = conn.prepare(sql.as_str()).unwrap();
+    let mut stmt = conn.prepare(sql.as_str())?;
 
-    let mut rows = stmt
-        .query(params![base64, per_page, page * per_page])
-        .unwrap();
+    let mut rows = stmt.query(params![base64])?;
 
     let mut transactions = Vec::new();
-    while let Some(row) = rows.next().unwrap() {
+    while let Some(row) = rows.next()? {
         transactions.push(Transaction {
             timestamp: 0,
-            index: row.get(0).unwrap(),
-            hash: row.get(1).unwrap(),
-            block_index: row.get(2).unwrap(),
-            vm_state: row.get(3).unwrap(),
-            size: row.get(4).unwrap(),
-            version: row.get(5).unwrap(),
-            nonce: row.get(6).unwrap(),
-            sender: row.get(7).unwrap(),
-            sysfee: row.get(8).unwrap(),
-            netfee: row.get(9).unwrap(),
-            valid_until: row.get(10).unwrap(),
-            script: row.get(11).unwrap(),
-            stack_result: row.get(12).unwrap(),
+            index: row.get(0)?,
+            hash: row.get(1)?,
+            block_index: row.get(2)?,
+            vm_state: row.get(3)?,
+            size: row.get(4)?,
+            version: row.get(5)?,
+            nonce: row.get(6)?,
+            sender: row.get(7)?,
+            sysfee: row.get(8)?,
+            netfee: row.get(9)?,
+            valid_until: row.get(10)?,
+            script: row.get(11)?,
+            stack_result: row.get(12)?,
             signers: Vec::new(),
             witnesses: Vec::new(),
             notifications: Vec::new(),
         })
     }
 
+    let page = pagination.finish(
+        transactions
+            .into_iter()
+            .map(|tx| (tx.index as i64, tx))
+            .collect(),
+    );
+
     let mut tx_list = TxDataList {
         address: address.clone(),
         as_sender: Vec::new(),
         as_participant: Vec::new(),
     };
 
-    for transaction in transactions {
+    for transaction in page.list {
         let sender = transaction.clone().sender;
-        let block_time =
-            internals::get_block_time(conn, transaction.block_index.to_string()).unwrap();
-
-        let notifications = get_transaction_notifications(conn, transaction.hash.clone())
-            .unwrap_or_else(|_| Vec::new());
+        let block_time = internals::get_block_time(conn, transaction.block_index.to_string())?;
 
-        let enriched_notifications: Vec<Notification> = notifications
-            .into_iter()
-            .map(|mut notification| {
-                notification.state.value =
-                    get_notification_state_values(conn, notification.id.unwrap())
-                        .unwrap_or_else(|_| Vec::new());
-                notification
-            })
-            .collect();
+        let mut enriched_notifications = Vec::new();
+        for mut notification in get_transaction_notifications(conn, transaction.hash.clone())? {
+            notification.state.value =
+                get_notification_state_values(conn, notification.id.unwrap())?;
+            enriched_notifications.push(notification);
+        }
 
         let mut transaction_with_notifications = transaction.clone();
         transaction_with_notifications.notifications = enriched_notifications;
@@ -384,19 +341,24 @@ pub fn get_address_transfers_internal(
         }
     }
 
-    if tx_list.as_sender.is_empty() && tx_list.as_participant.is_empty() {
-        Err(Error {
-            error: "No transfers for that sender.".to_string(),
-        })
+    if tx_list.as_sender.is_empty() && tx_list.as_participant.is_empty() && !pagination.has_cursor()
+    {
+        Err(ApiError::NotFound(
+            "No transfers for that sender.".to_string(),
+        ))
     } else {
-        Ok(tx_list)
+        Ok(Page {
+            list: tx_list,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+        })
     }
 }
 
 pub fn count_address_transfers_internal(
     conn: &PooledConnection<SqliteConnectionManager>,
     address: String,
-) -> usize {
+) -> Result<usize, ApiError> {
     let sql = "
         SELECT COUNT(DISTINCT t.hash)
         FROM transactions t
@@ -407,6 +369,141 @@ pub fn count_address_transfers_internal(
 
     let base64 = neo::address_to_base64(&address);
 
-    conn.query_row(sql, params![base64], |row| row.get::<_, usize>(0))
-        .unwrap_or(0)
+    let count = conn.query_row(sql, params![base64], |row| row.get::<_, usize>(0))?;
+
+    Ok(count)
+}
+
+// walks every transfer of the address in block order, with no page limit
+pub fn export_address_transfers_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    address: String,
+    emit: &mut dyn FnMut(TransferExport) -> bool,
+) -> Result<(), ApiError> {
+    let sql = "
+        SELECT t.hash, t.block_index, t.sender, t.sysfee, t.netfee
+        FROM transactions t
+        INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+        INNER JOIN transaction_notification_state_values nsv ON tn.id = nsv.transaction_notification_id
+        WHERE nsv.value = ? AND tn.event_name = 'Transfer'
+        GROUP BY t.hash
+        ORDER BY t.id ASC";
+
+    let base64 = neo::address_to_base64(&address);
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![base64])?;
+
+    let mut tokens: HashMap<String, (Option<String>, Option<u8>)> = HashMap::new();
+
+    while let Some(row) = rows.next()? {
+        let hash: String = row.get(0)?;
+        let block_index: u64 = row.get(1)?;
+        let sender: String = row.get(2)?;
+        let sysfee: String = row.get(3)?;
+        let netfee: String = row.get(4)?;
+
+        let timestamp = internals::get_block_time(conn, block_index.to_string())?;
+
+        let mut fee = match sender == address {
+            true => {
+                let total = sysfee.parse::<i64>().unwrap_or(0) + netfee.parse::<i64>().unwrap_or(0);
+                Some(format_amount(&total.to_string(), GAS_DECIMALS))
+            }
+            false => None,
+        };
+
+        for mut notification in get_transaction_notifications(conn, hash.clone())? {
+            notification.state.value =
+                get_notification_state_values(conn, notification.id.unwrap())?;
+
+            let (contract, from, to, qty) = match events::parse_transfer(&notification) {
+                Some(parts) => parts,
+                None => continue,
+            };
+
+            let direction = match (from == address, to == address) {
+                (true, true) => "self",
+                (true, false) => "out",
+                (false, true) => "in",
+                (false, false) => continue,
+            };
+
+            if !tokens.contains_key(&contract) {
+                let info = contract_internals::get_token_info(conn, &contract)?;
+                tokens.insert(contract.clone(), info);
+            }
+            let (symbol, decimals) = tokens[&contract].clone();
+
+            let transfer = TransferExport {
+                timestamp,
+                block_index,
+                txid: hash.clone(),
+                token_contract: contract,
+                token_symbol: symbol,
+                direction: direction.to_string(),
+                from,
+                to,
+                amount: format_amount(&qty, decimals.unwrap_or(0)),
+                fee: fee.take().unwrap_or_else(|| "0".to_string()),
+            };
+
+            if !emit(transfer) {
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_keyset_page_past_the_end() {
+    use crate::indexer::rpc::database::{
+        insert_test_notification, insert_test_transaction, memory_chain,
+    };
+    use crate::shared::pagination::Cursor;
+
+    const ALICE: &str = "AQEBAQEBAQEBAQEBAQEBAQEBAQE=";
+    let alice = neo::base64_to_address(ALICE);
+
+    let conn = memory_chain(&[1000]);
+    insert_test_transaction(&conn, "0xaa", 1, &alice, "0", "0");
+    insert_test_notification(
+        &conn,
+        "0xaa",
+        "0xd2a4cff31913016155e38e474a2c06d08be276cf",
+        "Transfer",
+        &[("ByteString", ALICE), ("Any", "")],
+    );
+
+    let page = |key: Option<i64>| Pagination::Keyset {
+        cursor: key.map(|key| Cursor {
+            key,
+            descending: false,
+            backward: false,
+        }),
+        descending: false,
+        per_page: 10,
+    };
+
+    let first = get_sender_transactions_internal(&conn, alice.clone(), true, &page(None)).unwrap();
+    assert_eq!(first.list.len(), 1);
+    assert!(first.next_cursor.is_none());
+
+    // the row with id 1 was the last one, so the page after it is empty rather than missing
+    let past =
+        get_sender_transactions_internal(&conn, alice.clone(), true, &page(Some(1))).unwrap();
+    assert!(past.list.is_empty());
+    assert!(past.next_cursor.is_none());
+
+    let transfers = get_address_transfers_internal(&conn, alice.clone(), &page(Some(1))).unwrap();
+    assert!(transfers.list.as_sender.is_empty() && transfers.list.as_participant.is_empty());
+    assert!(transfers.next_cursor.is_none());
+
+    // without a cursor an address with nothing indexed is still not found
+    let carol = neo::base64_to_address("AwMDAwMDAwMDAwMDAwMDAwMDAwM=");
+    assert!(matches!(
+        get_sender_transactions_internal(&conn, carol, true, &page(None)),
+        Err(ApiError::NotFound(_))
+    ));
 }
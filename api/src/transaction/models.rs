@@ -1,4 +1,5 @@
 use crate::block::models::Witness;
+use crate::shared::export::ExportRow;
 use crate::shared::models::{Address, Hash160};
 use serde::{Deserialize, Serialize};
 
@@ -76,3 +77,48 @@ pub struct Signer {
     pub scopes: String,
     pub allowedcontracts: Option<Vec<String>>,
 }
+
+// one row per transfer touching the address, shaped for tax and accounting imports
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TransferExport {
+    pub timestamp: u64,
+    pub block_index: u64,
+    pub txid: String,
+    pub token_contract: Hash160,
+    pub token_symbol: Option<String>,
+    pub direction: String, // "in", "out" or "self"
+    pub from: Address,
+    pub to: Address,
+    pub amount: String, // decimal string, raw integer if the token decimals are unknown
+    pub fee: String,    // GAS, only on the first row of transactions the address sent
+}
+
+impl ExportRow for TransferExport {
+    const HEADER: &'static [&'static str] = &[
+        "timestamp",
+        "block_index",
+        "txid",
+        "token_contract",
+        "token_symbol",
+        "direction",
+        "from",
+        "to",
+        "amount",
+        "fee",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.timestamp.to_string(),
+            self.block_index.to_string(),
+            self.txid.clone(),
+            self.token_contract.clone(),
+            self.token_symbol.clone().unwrap_or_default(),
+            self.direction.clone(),
+            self.from.clone(),
+            self.to.clone(),
+            self.amount.clone(),
+            self.fee.clone(),
+        ]
+    }
+}
@@ -0,0 +1,413 @@
+use tokio_postgres::{Client, Row};
+
+use std::collections::HashMap;
+
+use crate::block::models::Witness;
+use crate::block::postgres as block_postgres;
+use crate::contract::postgres as contract_postgres;
+use crate::error::ApiError;
+use crate::shared::events;
+use crate::shared::export::{format_amount, ExportSink};
+use crate::shared::models::GAS_DECIMALS;
+use crate::shared::neo;
+use crate::shared::pagination::{Page, Pagination};
+use crate::shared::postgres::{get_int, page_sql, to_i64};
+use crate::transaction::models::{
+    Notification, Signer, State, StateValue, Transaction, TransferExport, TxDataList,
+};
+
+// a row of SELECT * FROM transactions
+pub fn transaction_row(row: &Row) -> Result<Transaction, ApiError> {
+    Ok(Transaction {
+        timestamp: 0,
+        index: get_int(row, 0)?,
+        hash: row.try_get(1)?,
+        block_index: get_int(row, 2)?,
+        vm_state: row.try_get(3)?,
+        size: get_int(row, 4)?,
+        version: get_int(row, 5)?,
+        nonce: get_int(row, 6)?,
+        sender: row.try_get(7)?,
+        sysfee: row.try_get(8)?,
+        netfee: row.try_get(9)?,
+        valid_until: get_int(row, 10)?,
+        script: row.try_get(11)?,
+        stack_result: row.try_get::<_, Option<String>>(12)?.unwrap_or_default(),
+        signers: Vec::new(),
+        witnesses: Vec::new(),
+        notifications: Vec::new(),
+    })
+}
+
+// transactions with a Transfer notification that names the address
+const TRANSFER_FILTER: &str = "
+    EXISTS (
+        SELECT 1
+        FROM transaction_notifications tn
+        INNER JOIN transaction_notification_state_values nsv
+            ON tn.id = nsv.transaction_notification_id
+        WHERE tn.transaction_hash = t.hash AND nsv.value = $1 AND tn.event_name = 'Transfer'
+    )";
+
+pub async fn get_transaction_internal(
+    client: &Client,
+    hash: String,
+) -> Result<Transaction, ApiError> {
+    let sql = "SELECT * FROM transactions WHERE hash = $1";
+
+    match client.query_opt(sql, &[&hash]).await? {
+        Some(row) => transaction_row(&row),
+        None => Err(ApiError::NotFound(
+            "Transaction does not exist.".to_string(),
+        )),
+    }
+}
+
+pub async fn get_witnesses(client: &Client, hash: String) -> Result<Vec<Witness>, ApiError> {
+    let sql = "
+        SELECT invocation, verification FROM witnesses
+        WHERE transaction_id = (SELECT id FROM transactions WHERE hash = $1)
+        ORDER BY id";
+
+    client
+        .query(sql, &[&hash])
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(Witness {
+                invocation: row.try_get(0)?,
+                verification: row.try_get(1)?,
+            })
+        })
+        .collect()
+}
+
+pub async fn get_signers(client: &Client, hash: String) -> Result<Vec<Signer>, ApiError> {
+    let sql = "
+        SELECT id, account, scopes FROM signers
+        WHERE transaction_id = (SELECT id FROM transactions WHERE hash = $1)
+        ORDER BY id";
+
+    let mut signers = Vec::new();
+    for row in client.query(sql, &[&hash]).await? {
+        signers.push(Signer {
+            account: row.try_get(1)?,
+            scopes: row.try_get(2)?,
+            allowedcontracts: get_allowed_contracts(client, row.try_get(0)?).await?,
+        });
+    }
+
+    Ok(signers)
+}
+
+pub async fn get_allowed_contracts(
+    client: &Client,
+    signer_id: i64,
+) -> Result<Option<Vec<String>>, ApiError> {
+    let sql = "SELECT contract FROM allowed_contracts WHERE signer_id = $1 ORDER BY id";
+
+    let allowed_contracts = client
+        .query(sql, &[&signer_id])
+        .await?
+        .iter()
+        .map(|row| row.try_get(0))
+        .collect::<Result<Vec<String>, _>>()?;
+
+    if allowed_contracts.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(allowed_contracts))
+    }
+}
+
+pub async fn get_transaction_notifications(
+    client: &Client,
+    transaction_hash: String,
+) -> Result<Vec<Notification>, ApiError> {
+    let sql = "
+        SELECT id, contract, event_name, state_type
+        FROM transaction_notifications
+        WHERE transaction_hash = $1
+        ORDER BY id";
+
+    client
+        .query(sql, &[&transaction_hash])
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(Notification {
+                id: Some(get_int(row, 0)?),
+                contract: row.try_get(1)?,
+                eventname: row.try_get(2)?,
+                state: State {
+                    _type: row.try_get(3)?,
+                    value: Vec::new(),
+                },
+            })
+        })
+        .collect()
+}
+
+pub async fn get_notification_state_values(
+    client: &Client,
+    notification_id: u64,
+) -> Result<Vec<StateValue>, ApiError> {
+    let sql = "
+        SELECT type, value
+        FROM transaction_notification_state_values
+        WHERE transaction_notification_id = $1
+        ORDER BY id";
+
+    client
+        .query(sql, &[&to_i64(notification_id)?])
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(StateValue {
+                _type: row.try_get(0)?,
+                value: row
+                    .try_get::<_, Option<String>>(1)?
+                    .map(serde_json::Value::String),
+            })
+        })
+        .collect()
+}
+
+// notifications of a transaction with their state values filled in
+async fn get_enriched_notifications(
+    client: &Client,
+    hash: String,
+) -> Result<Vec<Notification>, ApiError> {
+    let mut notifications = get_transaction_notifications(client, hash).await?;
+
+    for notification in notifications.iter_mut() {
+        notification.state.value =
+            get_notification_state_values(client, notification.id.unwrap()).await?;
+    }
+
+    Ok(notifications)
+}
+
+pub async fn get_sender_transactions_internal(
+    client: &Client,
+    address: String,
+    with_transfers: bool,
+    pagination: &Pagination,
+) -> Result<Page<Vec<Transaction>>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "t.id", &["id"])?;
+
+    // a transaction counts when it has no notifications or at least one that isn't a Transfer
+    let with_transfer_clause = match with_transfers {
+        true => "",
+        false => {
+            "AND (
+                NOT EXISTS (
+                    SELECT 1 FROM transaction_notifications tn WHERE tn.transaction_hash = t.hash
+                )
+                OR EXISTS (
+                    SELECT 1 FROM transaction_notifications tn
+                    WHERE tn.transaction_hash = t.hash AND tn.event_name != 'Transfer'
+                )
+            )"
+        }
+    };
+
+    let sql = format!(
+        "SELECT t.*
+        FROM transactions t
+        WHERE t.sender = $1
+        {} {}
+        {}",
+        with_transfer_clause, keyset_clause, page_clause
+    );
+
+    let transactions = client
+        .query(sql.as_str(), &[&address])
+        .await?
+        .iter()
+        .map(transaction_row)
+        .collect::<Result<Vec<Transaction>, _>>()?;
+
+    match transactions.is_empty() && !pagination.has_cursor() {
+        false => Ok(pagination.finish(
+            transactions
+                .into_iter()
+                .map(|tx| (tx.index as i64, tx))
+                .collect(),
+        )),
+        true => Err(ApiError::NotFound(
+            "No transactions for that sender.".to_string(),
+        )),
+    }
+}
+
+pub async fn count_sender_transactions_internal(
+    client: &Client,
+    address: String,
+) -> Result<usize, ApiError> {
+    let sql = "SELECT COUNT(*) FROM transactions WHERE sender = $1";
+
+    get_int(&client.query_one(sql, &[&address]).await?, 0)
+}
+
+pub async fn get_address_transfers_internal(
+    client: &Client,
+    address: String,
+    pagination: &Pagination,
+) -> Result<Page<TxDataList>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "t.id", &["id"])?;
+
+    let sql = format!(
+        "SELECT t.*
+        FROM transactions t
+        WHERE {} {}
+        {}",
+        TRANSFER_FILTER, keyset_clause, page_clause
+    );
+
+    let base64 = neo::address_to_base64(&address);
+    let transactions = client
+        .query(sql.as_str(), &[&base64])
+        .await?
+        .iter()
+        .map(transaction_row)
+        .collect::<Result<Vec<Transaction>, _>>()?;
+
+    let page = pagination.finish(
+        transactions
+            .into_iter()
+            .map(|tx| (tx.index as i64, tx))
+            .collect(),
+    );
+
+    let mut tx_list = TxDataList {
+        address: address.clone(),
+        as_sender: Vec::new(),
+        as_participant: Vec::new(),
+    };
+
+    for mut transaction in page.list {
+        let block_time =
+            block_postgres::get_block_time(client, transaction.block_index.to_string()).await?;
+        transaction.notifications =
+            get_enriched_notifications(client, transaction.hash.clone()).await?;
+
+        let as_sender = transaction.sender == address;
+        let mut tx_data = events::get_transfer_events(transaction);
+        tx_data.time = block_time;
+
+        if as_sender {
+            tx_list.as_sender.push(tx_data);
+        } else {
+            tx_list.as_participant.push(tx_data);
+        }
+    }
+
+    if tx_list.as_sender.is_empty() && tx_list.as_participant.is_empty() && !pagination.has_cursor()
+    {
+        Err(ApiError::NotFound(
+            "No transfers for that sender.".to_string(),
+        ))
+    } else {
+        Ok(Page {
+            list: tx_list,
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+        })
+    }
+}
+
+pub async fn count_address_transfers_internal(
+    client: &Client,
+    address: String,
+) -> Result<usize, ApiError> {
+    let sql = "
+        SELECT COUNT(DISTINCT t.hash)
+        FROM transactions t
+        INNER JOIN transaction_notifications tn ON tn.transaction_hash = t.hash
+        INNER JOIN transaction_notification_state_values nsv
+            ON tn.id = nsv.transaction_notification_id
+        WHERE nsv.value = $1";
+
+    let base64 = neo::address_to_base64(&address);
+
+    get_int(&client.query_one(sql, &[&base64]).await?, 0)
+}
+
+// walks every transfer of the address in block order, with no page limit. The transactions
+// are read up front, as the connection can't serve the lookups while a row stream is open
+pub async fn export_address_transfers_internal(
+    client: &Client,
+    address: String,
+    sink: &ExportSink<TransferExport>,
+) -> Result<(), ApiError> {
+    let sql = format!(
+        "SELECT t.hash, t.block_index, t.sender, t.sysfee, t.netfee
+        FROM transactions t
+        WHERE {}
+        ORDER BY t.id ASC",
+        TRANSFER_FILTER
+    );
+
+    let base64 = neo::address_to_base64(&address);
+    let rows = client.query(sql.as_str(), &[&base64]).await?;
+
+    let mut tokens: HashMap<String, (Option<String>, Option<u8>)> = HashMap::new();
+
+    for row in rows {
+        let hash: String = row.try_get(0)?;
+        let block_index: u64 = get_int(&row, 1)?;
+        let sender: String = row.try_get(2)?;
+        let sysfee: String = row.try_get(3)?;
+        let netfee: String = row.try_get(4)?;
+
+        let timestamp = block_postgres::get_block_time(client, block_index.to_string()).await?;
+
+        let mut fee = match sender == address {
+            true => {
+                let total = sysfee.parse::<i64>().unwrap_or(0) + netfee.parse::<i64>().unwrap_or(0);
+                Some(format_amount(&total.to_string(), GAS_DECIMALS))
+            }
+            false => None,
+        };
+
+        for notification in get_enriched_notifications(client, hash.clone()).await? {
+            let (contract, from, to, qty) = match events::parse_transfer(&notification) {
+                Some(parts) => parts,
+                None => continue,
+            };
+
+            let direction = match (from == address, to == address) {
+                (true, true) => "self",
+                (true, false) => "out",
+                (false, true) => "in",
+                (false, false) => continue,
+            };
+
+            if !tokens.contains_key(&contract) {
+                let info = contract_postgres::get_token_info(client, &contract).await?;
+                tokens.insert(contract.clone(), info);
+            }
+            let (symbol, decimals) = tokens[&contract].clone();
+
+            let transfer = TransferExport {
+                timestamp,
+                block_index,
+                txid: hash.clone(),
+                token_contract: contract,
+                token_symbol: symbol,
+                direction: direction.to_string(),
+                from,
+                to,
+                amount: format_amount(&qty, decimals.unwrap_or(0)),
+                fee: fee.take().unwrap_or_else(|| "0".to_string()),
+            };
+
+            if !sink.emit(transfer).await {
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+
+use crate::error::ApiError;
+use crate::shared::auth::{authorize_admin, bearer};
+use crate::shared::config::{AdminConfig, WebhookConfig};
+use crate::shared::models::{PagedResp, PaginationAndFilterParams};
+use crate::shared::storage::WebhookStorage;
+use crate::shared::utils::normalize_pagination;
+
+use super::internals;
+use super::models::WebhookRequest;
+
+#[post("/v1/webhooks")]
+async fn create_webhook(
+    storage: web::Data<dyn WebhookStorage>,
+    admin: web::Data<AdminConfig>,
+    settings: web::Data<WebhookConfig>,
+    request: HttpRequest,
+    body: web::Json<WebhookRequest>,
+) -> Result<HttpResponse, ApiError> {
+    authorize_admin(&admin, bearer(&request))?;
+
+    let webhook = storage
+        .create_webhook(body.into_inner(), settings.allow_private_urls)
+        .await?;
+
+    Ok(HttpResponse::Created().json(webhook))
+}
+
+#[get("/v1/webhooks")]
+async fn list_webhooks(
+    storage: web::Data<dyn WebhookStorage>,
+    admin: web::Data<AdminConfig>,
+    request: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    authorize_admin(&admin, bearer(&request))?;
+
+    let webhooks = storage.list_webhooks().await?;
+
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+#[get("/v1/webhooks/{id}")]
+async fn get_webhook(
+    storage: web::Data<dyn WebhookStorage>,
+    admin: web::Data<AdminConfig>,
+    request: HttpRequest,
+    path: web::Path<u64>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    internals::authorize_owner(storage.get_ref(), &admin, bearer(&request), id).await?;
+    let webhook = storage.get_webhook(id).await?;
+
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+#[delete("/v1/webhooks/{id}")]
+async fn delete_webhook(
+    storage: web::Data<dyn WebhookStorage>,
+    admin: web::Data<AdminConfig>,
+    request: HttpRequest,
+    path: web::Path<u64>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    internals::authorize_owner(storage.get_ref(), &admin, bearer(&request), id).await?;
+    storage.deactivate_webhook(id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/v1/webhooks/{id}/test")]
+async fn test_webhook(
+    storage: web::Data<dyn WebhookStorage>,
+    admin: web::Data<AdminConfig>,
+    request: HttpRequest,
+    path: web::Path<u64>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    internals::authorize_owner(storage.get_ref(), &admin, bearer(&request), id).await?;
+    let delivery_id = storage.queue_test_delivery(id).await?;
+
+    Ok(HttpResponse::Accepted().json(serde_json::json!({ "delivery_id": delivery_id })))
+}
+
+#[get("/v1/webhooks/{id}/deliveries")]
+async fn list_webhook_deliveries(
+    storage: web::Data<dyn WebhookStorage>,
+    admin: web::Data<AdminConfig>,
+    request: HttpRequest,
+    path: web::Path<u64>,
+    query_parameter: web::Query<PaginationAndFilterParams>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let pagination = normalize_pagination(&query_parameter)?;
+
+    internals::authorize_owner(storage.get_ref(), &admin, bearer(&request), id).await?;
+    storage.get_webhook(id).await?;
+    let deliveries = storage.list_deliveries(id, &pagination).await?;
+
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(storage.count_deliveries(id).await?),
+    };
+
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(deliveries, count)))
+}
+
+#[get("/v1/webhooks/{id}/dead-letters")]
+async fn list_webhook_dead_letters(
+    storage: web::Data<dyn WebhookStorage>,
+    admin: web::Data<AdminConfig>,
+    request: HttpRequest,
+    path: web::Path<u64>,
+    query_parameter: web::Query<PaginationAndFilterParams>,
+) -> Result<HttpResponse, ApiError> {
+    let id = path.into_inner();
+    let pagination = normalize_pagination(&query_parameter)?;
+
+    internals::authorize_owner(storage.get_ref(), &admin, bearer(&request), id).await?;
+    storage.get_webhook(id).await?;
+    let dead_letters = storage.list_dead_letters(id, &pagination).await?;
+
+    let count = match pagination.is_keyset() {
+        true => None,
+        false => Some(storage.count_dead_letters(id).await?),
+    };
+
+    Ok(HttpResponse::Ok().json(PagedResp::from_page(dead_letters, count)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_webhook)
+        .service(list_webhooks)
+        .service(get_webhook)
+        .service(delete_webhook)
+        .service(test_webhook)
+        .service(list_webhook_deliveries)
+        .service(list_webhook_dead_letters);
+}
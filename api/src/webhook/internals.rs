@@ -0,0 +1,659 @@
+use hmac::{Hmac, Mac};
+use hyper::client::connect::dns::Name;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use reqwest::Client;
+use rusqlite::{params, OptionalExtension, Row};
+use sha2::Sha256;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::ApiError;
+use crate::shared::auth::{authorize_admin, same_secret};
+use crate::shared::checker;
+use crate::shared::config::AdminConfig;
+use crate::shared::pagination::{Page, Pagination};
+use crate::shared::shutdown;
+use crate::shared::storage::WebhookStorage;
+use crate::webhook::models::{
+    PendingDelivery, Webhook, WebhookDeadLetter, WebhookDelivery, WebhookRequest, DELIVERY_HEADER,
+    SIGNATURE_HEADER, WEBHOOK_BATCH, WEBHOOK_MAX_ATTEMPTS, WEBHOOK_RETRY_BASE_MS,
+};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub fn generate_secret() -> Result<String, ApiError> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|err| ApiError::Internal(format!("Failed to generate secret: {}", err)))?;
+
+    Ok(hex::encode(bytes))
+}
+
+// receivers recompute this over the raw body with their secret
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body.as_bytes());
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// a webhook's own secret opens that webhook, the admin token every one. unknown ids are
+// refused the same way, so ids can't be probed
+pub async fn authorize_owner(
+    storage: &dyn WebhookStorage,
+    admin: &AdminConfig,
+    token: Option<&str>,
+    id: u64,
+) -> Result<(), ApiError> {
+    if authorize_admin(admin, token).is_ok() {
+        return Ok(());
+    }
+
+    match (storage.get_webhook_secret(id).await?, token) {
+        (Some(secret), Some(token)) if same_secret(&secret, token) => Ok(()),
+        _ => Err(ApiError::Unauthorized(
+            "The webhook's secret or the admin token is required.".to_string(),
+        )),
+    }
+}
+
+pub fn get_webhook_secret_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: u64,
+) -> Result<Option<String>, ApiError> {
+    let sql = "SELECT secret FROM webhooks WHERE id = ?";
+
+    Ok(conn.query_row(sql, [id], |row| row.get(0)).optional()?)
+}
+
+fn webhook_not_found(err: rusqlite::Error) -> ApiError {
+    match err {
+        rusqlite::Error::QueryReturnedNoRows => {
+            ApiError::NotFound("Webhook does not exist.".to_string())
+        }
+        err => err.into(),
+    }
+}
+
+fn webhook(row: &Row) -> rusqlite::Result<Webhook> {
+    Ok(Webhook {
+        id: row.get(0)?,
+        url: row.get(1)?,
+        secret: None,
+        address: row.get(2)?,
+        contract: row.get(3)?,
+        event_name: row.get(4)?,
+        min_amount: row.get(5)?,
+        active: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+pub fn payload(text: String) -> serde_json::Value {
+    serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text))
+}
+
+pub fn validate_request(
+    request: WebhookRequest,
+    allow_private_urls: bool,
+) -> Result<WebhookRequest, ApiError> {
+    let url = url::Url::parse(&request.url)
+        .map_err(|_| ApiError::BadRequest(format!("Invalid url: {}", request.url)))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(ApiError::BadRequest(
+            "Webhook urls must use http or https.".to_string(),
+        ));
+    }
+
+    // names are checked again when they resolve, see PublicResolver
+    let private = match url.host() {
+        Some(url::Host::Ipv4(ip)) => !checker::is_public_ip(ip.into()),
+        Some(url::Host::Ipv6(ip)) => !checker::is_public_ip(ip.into()),
+        Some(url::Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+        None => true,
+    };
+    if private && !allow_private_urls {
+        return Err(ApiError::BadRequest(
+            "Webhook urls must point to a public address.".to_string(),
+        ));
+    }
+
+    let address = match request.address.filter(|s| !s.is_empty()) {
+        Some(address) => {
+            Some(checker::normalize_neo_address(&address).map_err(ApiError::BadRequest)?)
+        }
+        None => None,
+    };
+
+    let contract = request.contract.filter(|s| !s.is_empty());
+    if let Some(contract) = &contract {
+        if !checker::is_neo_script_hash(contract) {
+            return Err(ApiError::BadRequest("Invalid contract hash.".to_string()));
+        }
+    }
+
+    let min_amount = request.min_amount.filter(|s| !s.is_empty());
+    if let Some(min_amount) = &min_amount {
+        if !min_amount.parse::<i128>().is_ok_and(|amount| amount >= 0) {
+            return Err(ApiError::BadRequest(
+                "The min_amount must be a raw integer amount.".to_string(),
+            ));
+        }
+    }
+
+    let event_name = request.event_name.filter(|s| !s.is_empty());
+
+    if address.is_none() && contract.is_none() && event_name.is_none() {
+        return Err(ApiError::BadRequest(
+            "Filter on an address, a contract or an event name.".to_string(),
+        ));
+    }
+
+    Ok(WebhookRequest {
+        url: url.to_string(),
+        address,
+        contract: contract.map(|c| c.to_lowercase()),
+        event_name,
+        min_amount,
+    })
+}
+
+pub fn create_webhook_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    request: WebhookRequest,
+    allow_private_urls: bool,
+) -> Result<Webhook, ApiError> {
+    let request = validate_request(request, allow_private_urls)?;
+    let secret = generate_secret()?;
+    let created_at = now_millis();
+
+    let sql = "
+        INSERT INTO webhooks (url, secret, address, contract, event_name, min_amount, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)";
+
+    conn.execute(
+        sql,
+        params![
+            request.url,
+            secret,
+            request.address,
+            request.contract,
+            request.event_name,
+            request.min_amount,
+            created_at
+        ],
+    )?;
+
+    Ok(Webhook {
+        id: conn.last_insert_rowid() as u64,
+        url: request.url,
+        secret: Some(secret),
+        address: request.address,
+        contract: request.contract,
+        event_name: request.event_name,
+        min_amount: request.min_amount,
+        active: true,
+        created_at,
+    })
+}
+
+pub fn list_webhooks_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<Webhook>, ApiError> {
+    let sql = "
+        SELECT id, url, address, contract, event_name, min_amount, active, created_at
+        FROM webhooks
+        WHERE active = 1
+        ORDER BY id ASC";
+
+    let mut stmt = conn.prepare(sql)?;
+    let webhooks = stmt
+        .query_map([], webhook)?
+        .collect::<Result<Vec<Webhook>, _>>()?;
+
+    if webhooks.is_empty() {
+        Err(ApiError::NotFound("No webhooks registered.".to_string()))
+    } else {
+        Ok(webhooks)
+    }
+}
+
+pub fn get_webhook_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: u64,
+) -> Result<Webhook, ApiError> {
+    let sql = "
+        SELECT id, url, address, contract, event_name, min_amount, active, created_at
+        FROM webhooks
+        WHERE id = ?";
+
+    conn.query_row(sql, [id], webhook)
+        .map_err(webhook_not_found)
+}
+
+// webhooks are deactivated rather than deleted, so their delivery logs stay readable
+pub fn deactivate_webhook_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: u64,
+) -> Result<(), ApiError> {
+    let updated = conn.execute(
+        "UPDATE webhooks SET active = 0 WHERE id = ? AND active = 1",
+        [id],
+    )?;
+    if updated == 0 {
+        return Err(ApiError::NotFound("Webhook does not exist.".to_string()));
+    }
+
+    conn.execute(
+        "UPDATE webhook_deliveries SET status = 'cancelled' WHERE webhook_id = ? AND status = 'pending'",
+        [id],
+    )?;
+
+    Ok(())
+}
+
+// body of a test delivery, shaped like a real one
+pub fn ping_payload(id: u64, block_index: u64) -> String {
+    serde_json::json!({
+        "webhook_id": id,
+        "block_index": block_index,
+        "events": [{ "topic": "ping", "data": {} }],
+    })
+    .to_string()
+}
+
+// a ping through the regular queue, signing and retries included
+pub fn queue_test_delivery_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: u64,
+) -> Result<u64, ApiError> {
+    let webhook = get_webhook_internal(conn, id)?;
+    if !webhook.active {
+        return Err(ApiError::Conflict("Webhook is not active.".to_string()));
+    }
+
+    let block_index: u64 =
+        conn.query_row("SELECT COALESCE(MAX(id), 0) FROM blocks", [], |row| {
+            row.get(0)
+        })?;
+
+    let now = now_millis();
+    conn.execute(
+        "INSERT INTO webhook_deliveries (
+            webhook_id, block_index, payload, status, created_at, next_attempt_at
+        ) VALUES (?, ?, ?, 'pending', ?, ?)",
+        params![id, block_index, ping_payload(id, block_index), now, now],
+    )?;
+
+    Ok(conn.last_insert_rowid() as u64)
+}
+
+pub fn list_deliveries_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: u64,
+    pagination: &Pagination,
+) -> Result<Page<Vec<WebhookDelivery>>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("id", &["id", "created_at"])?;
+
+    let sql = format!(
+        "SELECT id, webhook_id, block_index, status, attempts, response_status, error,
+            created_at, next_attempt_at, delivered_at, payload
+        FROM webhook_deliveries WHERE webhook_id = ? {} {}",
+        keyset_clause, page_clause
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([id])?;
+
+    let mut deliveries = Vec::new();
+    while let Some(row) = rows.next()? {
+        deliveries.push((
+            row.get::<_, i64>(0)?,
+            WebhookDelivery {
+                id: row.get(0)?,
+                webhook_id: row.get(1)?,
+                block_index: row.get(2)?,
+                status: row.get(3)?,
+                attempts: row.get(4)?,
+                response_status: row.get(5)?,
+                error: row.get(6)?,
+                created_at: row.get(7)?,
+                next_attempt_at: row.get(8)?,
+                delivered_at: row.get(9)?,
+                payload: payload(row.get(10)?),
+            },
+        ));
+    }
+
+    if deliveries.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No deliveries for that webhook.".to_string(),
+        ))
+    } else {
+        Ok(pagination.finish(deliveries))
+    }
+}
+
+pub fn count_deliveries_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: u64,
+) -> Result<usize, ApiError> {
+    let sql = "SELECT COUNT(*) FROM webhook_deliveries WHERE webhook_id = ?";
+
+    Ok(conn.query_row(sql, [id], |row| row.get(0))?)
+}
+
+pub fn list_dead_letters_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: u64,
+    pagination: &Pagination,
+) -> Result<Page<Vec<WebhookDeadLetter>>, ApiError> {
+    let (keyset_clause, page_clause) = pagination.sql("id", &["id", "failed_at"])?;
+
+    let sql = format!(
+        "SELECT id, delivery_id, webhook_id, error, failed_at, payload
+        FROM webhook_dead_letters WHERE webhook_id = ? {} {}",
+        keyset_clause, page_clause
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([id])?;
+
+    let mut dead_letters = Vec::new();
+    while let Some(row) = rows.next()? {
+        dead_letters.push((
+            row.get::<_, i64>(0)?,
+            WebhookDeadLetter {
+                id: row.get(0)?,
+                delivery_id: row.get(1)?,
+                webhook_id: row.get(2)?,
+                error: row.get(3)?,
+                failed_at: row.get(4)?,
+                payload: payload(row.get(5)?),
+            },
+        ));
+    }
+
+    if dead_letters.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No dead letters for that webhook.".to_string(),
+        ))
+    } else {
+        Ok(pagination.finish(dead_letters))
+    }
+}
+
+pub fn count_dead_letters_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: u64,
+) -> Result<usize, ApiError> {
+    let sql = "SELECT COUNT(*) FROM webhook_dead_letters WHERE webhook_id = ?";
+
+    Ok(conn.query_row(sql, [id], |row| row.get(0))?)
+}
+
+// resolves receiver hosts but drops private addresses, so a public name pointed at an
+// internal service, before or after it was registered, can't be used to reach it
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| checker::is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+// redirects are not followed, they could lead anywhere the url check didn't look
+pub fn delivery_client(allow_private_urls: bool) -> Client {
+    let builder = Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .redirect(Policy::none());
+    let builder = match allow_private_urls {
+        true => builder,
+        false => builder.dns_resolver(Arc::new(PublicResolver)),
+    };
+
+    builder.build().expect("Failed to build webhook client")
+}
+
+// the response status on success, or the status (if any) and the reason on failure
+pub async fn send_delivery(
+    client: &Client,
+    url: &str,
+    secret: &str,
+    delivery_id: u64,
+    body: String,
+) -> Result<u16, (Option<u16>, String)> {
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, sign(secret, &body))
+        .header(DELIVERY_HEADER, delivery_id.to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(|err| (None, err.to_string()))?;
+
+    let status = response.status();
+    match status.is_success() {
+        true => Ok(status.as_u16()),
+        false => Err((
+            Some(status.as_u16()),
+            format!("Receiver answered {}", status),
+        )),
+    }
+}
+
+// failed deliveries wait twice as long after every attempt
+pub fn retry_delay(attempts: u32) -> u64 {
+    WEBHOOK_RETRY_BASE_MS << (attempts - 1).min(16)
+}
+
+pub fn record_attempt(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    delivery_id: u64,
+    attempts: u32,
+    result: Result<u16, (Option<u16>, String)>,
+    now: u64,
+) -> Result<(), ApiError> {
+    let attempts = attempts + 1;
+
+    match result {
+        Ok(status) => {
+            conn.execute(
+                "UPDATE webhook_deliveries
+                SET status = 'delivered', attempts = ?, response_status = ?, error = NULL,
+                    delivered_at = ?
+                WHERE id = ?",
+                params![attempts, status, now, delivery_id],
+            )?;
+        }
+        Err((status, error)) if attempts >= WEBHOOK_MAX_ATTEMPTS => {
+            let tx = conn.unchecked_transaction()?;
+            conn.execute(
+                "UPDATE webhook_deliveries
+                SET status = 'failed', attempts = ?, response_status = ?, error = ?
+                WHERE id = ?",
+                params![attempts, status, error, delivery_id],
+            )?;
+            conn.execute(
+                "INSERT OR IGNORE INTO webhook_dead_letters (
+                    delivery_id, webhook_id, payload, error, failed_at
+                )
+                SELECT id, webhook_id, payload, error, ? FROM webhook_deliveries WHERE id = ?",
+                params![now, delivery_id],
+            )?;
+            tx.commit()?;
+        }
+        Err((status, error)) => {
+            conn.execute(
+                "UPDATE webhook_deliveries
+                SET attempts = ?, response_status = ?, error = ?, next_attempt_at = ?
+                WHERE id = ?",
+                params![
+                    attempts,
+                    status,
+                    error,
+                    now + retry_delay(attempts),
+                    delivery_id
+                ],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn get_due_deliveries_internal(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    now: u64,
+    limit: u32,
+) -> Result<Vec<PendingDelivery>, ApiError> {
+    let sql = "
+        SELECT d.id, d.attempts, d.payload, w.url, w.secret
+        FROM webhook_deliveries d
+        INNER JOIN webhooks w ON w.id = d.webhook_id
+        WHERE d.status = 'pending' AND d.next_attempt_at <= ? AND w.active = 1
+        ORDER BY d.id ASC
+        LIMIT ?";
+
+    let mut stmt = conn.prepare(sql)?;
+    let due = stmt
+        .query_map(params![now, limit], |row| {
+            Ok(PendingDelivery {
+                id: row.get(0)?,
+                attempts: row.get(1)?,
+                payload: row.get(2)?,
+                url: row.get(3)?,
+                secret: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<PendingDelivery>, _>>()?;
+
+    Ok(due)
+}
+
+// sends every due delivery once, failures are rescheduled with a growing delay. shutdown
+// abandons the pass, an interrupted delivery stays pending and goes out again on restart
+pub async fn deliver_pending_internal(
+    storage: &dyn WebhookStorage,
+    client: &Client,
+) -> Result<usize, ApiError> {
+    let due = storage
+        .get_due_deliveries(now_millis(), WEBHOOK_BATCH)
+        .await?;
+
+    let mut sent = 0;
+    for delivery in due {
+        let result = tokio::select! {
+            result = send_delivery(client, &delivery.url, &delivery.secret, delivery.id, delivery.payload) => result,
+            _ = shutdown::wait() => break,
+        };
+
+        storage
+            .record_attempt(delivery.id, delivery.attempts, result, now_millis())
+            .await?;
+        sent += 1;
+    }
+
+    Ok(sent)
+}
+
+// answers a single request with the given status and hands back what it received
+#[cfg(test)]
+async fn stand_in(status: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 4096];
+
+        // read the headers, then as much body as they announce
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            received.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&received).to_string();
+            if let Some(end) = text.find("\r\n\r\n") {
+                let length = text
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: "))
+                    .and_then(|length| length.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                if received.len() >= end + 4 + length || read == 0 {
+                    break;
+                }
+            }
+        }
+
+        let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", status);
+        socket.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&received).to_string()
+    });
+
+    (url, handle)
+}
+
+#[tokio::test]
+async fn test_send_delivery() {
+    let client = delivery_client(false);
+    let body = r#"{"webhook_id":1,"events":[]}"#.to_string();
+
+    let (url, handle) = stand_in("200 OK").await;
+    let result = send_delivery(&client, &url, "secret", 7, body.clone()).await;
+    let request = handle.await.unwrap();
+
+    assert_eq!(result, Ok(200));
+    assert!(request.starts_with("POST /hook"));
+    assert!(request.contains(&format!("{}: {}", SIGNATURE_HEADER, sign("secret", &body))));
+    assert!(request.contains(&format!("{}: 7", DELIVERY_HEADER)));
+    assert!(request.ends_with(&body));
+
+    let (url, handle) = stand_in("500 Internal Server Error").await;
+    let result = send_delivery(&client, &url, "secret", 8, body).await;
+    handle.await.unwrap();
+
+    assert_eq!(result.map_err(|(status, _)| status), Err(Some(500)));
+}
+
+#[test]
+fn test_validate_request_targets() {
+    let request = |url: &str| WebhookRequest {
+        url: url.to_string(),
+        address: None,
+        contract: None,
+        event_name: Some("Transfer".to_string()),
+        min_amount: None,
+    };
+
+    assert!(validate_request(request("https://hooks.example.com/shrike"), false).is_ok());
+    assert!(validate_request(request("http://169.254.169.254/latest"), false).is_err());
+    assert!(validate_request(request("http://[::1]:8080/hook"), false).is_err());
+    assert!(validate_request(request("http://localhost/hook"), false).is_err());
+    assert!(validate_request(request("http://localhost/hook"), true).is_ok());
+}
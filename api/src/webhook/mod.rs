@@ -0,0 +1,4 @@
+pub mod controller;
+pub mod internals;
+pub mod models;
+pub mod postgres;
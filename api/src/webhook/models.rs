@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::live::models::LiveMessage;
+use crate::shared::models::{Address, Hash160};
+
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 6; // the last retry waits about 5 minutes
+pub const WEBHOOK_RETRY_BASE_MS: u64 = 10_000; // doubled after every failed attempt
+pub const WEBHOOK_BATCH: u32 = 100; // deliveries sent per worker tick
+
+pub const SIGNATURE_HEADER: &str = "x-shrike-signature";
+pub const DELIVERY_HEADER: &str = "x-shrike-delivery";
+
+#[derive(Deserialize)]
+pub struct WebhookRequest {
+    pub url: String,
+    pub address: Option<String>,
+    pub contract: Option<String>,
+    pub event_name: Option<String>,
+    pub min_amount: Option<String>, // raw integer amount, transfers only
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Webhook {
+    pub id: u64,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>, // only shown once, when the webhook is created
+    pub address: Option<Address>,
+    pub contract: Option<Hash160>,
+    pub event_name: Option<String>,
+    pub min_amount: Option<String>,
+    pub active: bool,
+    pub created_at: u64,
+}
+
+impl Webhook {
+    // every filter that is set has to pass. transfers are only delivered as transfers,
+    // and address or amount filters never match other events
+    pub fn matches(&self, message: &LiveMessage) -> bool {
+        match message {
+            LiveMessage::Transfer(transfer) => {
+                self.address
+                    .as_ref()
+                    .is_none_or(|address| *address == transfer.from || *address == transfer.to)
+                    && self
+                        .contract
+                        .as_ref()
+                        .is_none_or(|contract| *contract == transfer.token_contract)
+                    && self
+                        .event_name
+                        .as_ref()
+                        .is_none_or(|name| name == "Transfer")
+                    && self.min_amount.as_ref().is_none_or(|min| {
+                        match (min.parse::<i128>(), transfer.amount.parse::<i128>()) {
+                            (Ok(min), Ok(amount)) => amount >= min,
+                            _ => false,
+                        }
+                    })
+            }
+            LiveMessage::Event(event) if event.eventname != "Transfer" => {
+                self.address.is_none()
+                    && self.min_amount.is_none()
+                    && self
+                        .contract
+                        .as_ref()
+                        .is_none_or(|contract| *contract == event.contract)
+                    && self
+                        .event_name
+                        .as_ref()
+                        .is_none_or(|name| *name == event.eventname)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WebhookDelivery {
+    pub id: u64,
+    pub webhook_id: u64,
+    pub block_index: u64,
+    pub status: String, // "pending", "delivered", "failed" or "cancelled"
+    pub attempts: u32,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub next_attempt_at: u64,
+    pub delivered_at: Option<u64>,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WebhookDeadLetter {
+    pub id: u64,
+    pub delivery_id: u64,
+    pub webhook_id: u64,
+    pub error: Option<String>,
+    pub failed_at: u64,
+    pub payload: serde_json::Value,
+}
+
+// a due delivery with what it takes to send it
+pub struct PendingDelivery {
+    pub id: u64,
+    pub attempts: u32,
+    pub payload: String,
+    pub url: String,
+    pub secret: String,
+}
+
+#[test]
+fn test_webhook_matches() {
+    use crate::live::models::{LiveNotification, LiveTransfer};
+
+    let webhook = Webhook {
+        id: 1,
+        url: "http://localhost/hook".to_string(),
+        secret: None,
+        address: Some("NL1JGjDe22U44R57ZXVSeRa4T7Jo1HDLF4".to_string()),
+        contract: None,
+        event_name: None,
+        min_amount: Some("100".to_string()),
+        active: true,
+        created_at: 0,
+    };
+
+    let transfer = |to: &str, amount: &str| {
+        LiveMessage::Transfer(LiveTransfer {
+            txid: "0x01".to_string(),
+            block_index: 1,
+            token_contract: "0xd2a4cff31913016155e38e474a2c06d08be276cf".to_string(),
+            from: "null".to_string(),
+            to: to.to_string(),
+            amount: amount.to_string(),
+        })
+    };
+    assert!(webhook.matches(&transfer("NL1JGjDe22U44R57ZXVSeRa4T7Jo1HDLF4", "100")));
+    assert!(!webhook.matches(&transfer("NL1JGjDe22U44R57ZXVSeRa4T7Jo1HDLF4", "99")));
+    assert!(!webhook.matches(&transfer("NMqTqe2V6PkwowUVZdwyfVZ2LsgSEbZ4Pr", "100")));
+
+    let event = LiveMessage::Event(LiveNotification {
+        txid: "0x01".to_string(),
+        block_index: 1,
+        contract: "0xd2a4cff31913016155e38e474a2c06d08be276cf".to_string(),
+        eventname: "Swap".to_string(),
+        state: serde_json::Value::Null,
+    });
+    assert!(!webhook.matches(&event));
+
+    let by_event = Webhook {
+        address: None,
+        min_amount: None,
+        event_name: Some("Swap".to_string()),
+        ..webhook
+    };
+    assert!(by_event.matches(&event));
+}
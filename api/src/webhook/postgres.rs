@@ -0,0 +1,356 @@
+use tokio_postgres::{Client, Row};
+
+use crate::error::ApiError;
+use crate::shared::pagination::{Page, Pagination};
+use crate::shared::postgres::{get_int, get_opt_int, page_sql, to_i64};
+use crate::webhook::internals::{
+    generate_secret, now_millis, payload, ping_payload, retry_delay, validate_request,
+};
+use crate::webhook::models::{
+    PendingDelivery, Webhook, WebhookDeadLetter, WebhookDelivery, WebhookRequest,
+    WEBHOOK_MAX_ATTEMPTS,
+};
+
+pub const WEBHOOK_COLUMNS: &str =
+    "id, url, address, contract, event_name, min_amount, active, created_at";
+
+// a row of WEBHOOK_COLUMNS, the secret is never read back
+pub fn webhook_row(row: &Row) -> Result<Webhook, ApiError> {
+    Ok(Webhook {
+        id: get_int(row, 0)?,
+        url: row.try_get(1)?,
+        secret: None,
+        address: row.try_get(2)?,
+        contract: row.try_get(3)?,
+        event_name: row.try_get(4)?,
+        min_amount: row.try_get(5)?,
+        active: row.try_get(6)?,
+        created_at: get_int(row, 7)?,
+    })
+}
+
+pub async fn get_webhook_secret_internal(
+    client: &Client,
+    id: u64,
+) -> Result<Option<String>, ApiError> {
+    let sql = "SELECT secret FROM webhooks WHERE id = $1";
+
+    match client.query_opt(sql, &[&to_i64(id)?]).await? {
+        Some(row) => Ok(Some(row.try_get(0)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn create_webhook_internal(
+    client: &Client,
+    request: WebhookRequest,
+    allow_private_urls: bool,
+) -> Result<Webhook, ApiError> {
+    let request = validate_request(request, allow_private_urls)?;
+    let secret = generate_secret()?;
+    let created_at = now_millis();
+
+    let sql = "
+        INSERT INTO webhooks (url, secret, address, contract, event_name, min_amount, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id";
+
+    let row = client
+        .query_one(
+            sql,
+            &[
+                &request.url,
+                &secret,
+                &request.address,
+                &request.contract,
+                &request.event_name,
+                &request.min_amount,
+                &to_i64(created_at)?,
+            ],
+        )
+        .await?;
+
+    Ok(Webhook {
+        id: get_int(&row, 0)?,
+        url: request.url,
+        secret: Some(secret),
+        address: request.address,
+        contract: request.contract,
+        event_name: request.event_name,
+        min_amount: request.min_amount,
+        active: true,
+        created_at,
+    })
+}
+
+pub async fn list_webhooks_internal(client: &Client) -> Result<Vec<Webhook>, ApiError> {
+    let sql = format!(
+        "SELECT {} FROM webhooks WHERE active ORDER BY id ASC",
+        WEBHOOK_COLUMNS
+    );
+
+    let webhooks = client
+        .query(sql.as_str(), &[])
+        .await?
+        .iter()
+        .map(webhook_row)
+        .collect::<Result<Vec<Webhook>, _>>()?;
+
+    if webhooks.is_empty() {
+        Err(ApiError::NotFound("No webhooks registered.".to_string()))
+    } else {
+        Ok(webhooks)
+    }
+}
+
+pub async fn get_webhook_internal(client: &Client, id: u64) -> Result<Webhook, ApiError> {
+    let sql = format!("SELECT {} FROM webhooks WHERE id = $1", WEBHOOK_COLUMNS);
+
+    match client.query_opt(sql.as_str(), &[&to_i64(id)?]).await? {
+        Some(row) => webhook_row(&row),
+        None => Err(ApiError::NotFound("Webhook does not exist.".to_string())),
+    }
+}
+
+// webhooks are deactivated rather than deleted, so their delivery logs stay readable
+pub async fn deactivate_webhook_internal(client: &Client, id: u64) -> Result<(), ApiError> {
+    let id = to_i64(id)?;
+
+    let updated = client
+        .execute(
+            "UPDATE webhooks SET active = FALSE WHERE id = $1 AND active",
+            &[&id],
+        )
+        .await?;
+    if updated == 0 {
+        return Err(ApiError::NotFound("Webhook does not exist.".to_string()));
+    }
+
+    client
+        .execute(
+            "UPDATE webhook_deliveries SET status = 'cancelled'
+            WHERE webhook_id = $1 AND status = 'pending'",
+            &[&id],
+        )
+        .await?;
+
+    Ok(())
+}
+
+// a ping through the regular queue, signing and retries included
+pub async fn queue_test_delivery_internal(client: &Client, id: u64) -> Result<u64, ApiError> {
+    let webhook = get_webhook_internal(client, id).await?;
+    if !webhook.active {
+        return Err(ApiError::Conflict("Webhook is not active.".to_string()));
+    }
+
+    let row = client
+        .query_one("SELECT COALESCE(MAX(id), 0) FROM blocks", &[])
+        .await?;
+    let block_index: u64 = get_int(&row, 0)?;
+    let payload = ping_payload(id, block_index);
+
+    let now = to_i64(now_millis())?;
+    let row = client
+        .query_one(
+            "INSERT INTO webhook_deliveries (
+                webhook_id, block_index, payload, status, created_at, next_attempt_at
+            ) VALUES ($1, $2, $3, 'pending', $4, $4)
+            RETURNING id",
+            &[&to_i64(id)?, &to_i64(block_index)?, &payload, &now],
+        )
+        .await?;
+
+    get_int(&row, 0)
+}
+
+pub async fn list_deliveries_internal(
+    client: &Client,
+    id: u64,
+    pagination: &Pagination,
+) -> Result<Page<Vec<WebhookDelivery>>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "id", &["id", "created_at"])?;
+
+    let sql = format!(
+        "SELECT id, webhook_id, block_index, status, attempts, response_status, error,
+            created_at, next_attempt_at, delivered_at, payload
+        FROM webhook_deliveries WHERE webhook_id = $1 {} {}",
+        keyset_clause, page_clause
+    );
+
+    let mut deliveries = Vec::new();
+    for row in client.query(sql.as_str(), &[&to_i64(id)?]).await? {
+        deliveries.push((
+            row.try_get::<_, i64>(0)?,
+            WebhookDelivery {
+                id: get_int(&row, 0)?,
+                webhook_id: get_int(&row, 1)?,
+                block_index: get_int(&row, 2)?,
+                status: row.try_get(3)?,
+                attempts: get_int(&row, 4)?,
+                response_status: get_opt_int(&row, 5)?,
+                error: row.try_get(6)?,
+                created_at: get_int(&row, 7)?,
+                next_attempt_at: get_int(&row, 8)?,
+                delivered_at: get_opt_int(&row, 9)?,
+                payload: payload(row.try_get(10)?),
+            },
+        ));
+    }
+
+    if deliveries.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No deliveries for that webhook.".to_string(),
+        ))
+    } else {
+        Ok(pagination.finish(deliveries))
+    }
+}
+
+pub async fn count_deliveries_internal(client: &Client, id: u64) -> Result<usize, ApiError> {
+    let sql = "SELECT COUNT(*) FROM webhook_deliveries WHERE webhook_id = $1";
+
+    get_int(&client.query_one(sql, &[&to_i64(id)?]).await?, 0)
+}
+
+pub async fn list_dead_letters_internal(
+    client: &Client,
+    id: u64,
+    pagination: &Pagination,
+) -> Result<Page<Vec<WebhookDeadLetter>>, ApiError> {
+    let (keyset_clause, page_clause) = page_sql(pagination, "id", &["id", "failed_at"])?;
+
+    let sql = format!(
+        "SELECT id, delivery_id, webhook_id, error, failed_at, payload
+        FROM webhook_dead_letters WHERE webhook_id = $1 {} {}",
+        keyset_clause, page_clause
+    );
+
+    let mut dead_letters = Vec::new();
+    for row in client.query(sql.as_str(), &[&to_i64(id)?]).await? {
+        dead_letters.push((
+            row.try_get::<_, i64>(0)?,
+            WebhookDeadLetter {
+                id: get_int(&row, 0)?,
+                delivery_id: get_int(&row, 1)?,
+                webhook_id: get_int(&row, 2)?,
+                error: row.try_get(3)?,
+                failed_at: get_int(&row, 4)?,
+                payload: payload(row.try_get(5)?),
+            },
+        ));
+    }
+
+    if dead_letters.is_empty() && !pagination.has_cursor() {
+        Err(ApiError::NotFound(
+            "No dead letters for that webhook.".to_string(),
+        ))
+    } else {
+        Ok(pagination.finish(dead_letters))
+    }
+}
+
+pub async fn count_dead_letters_internal(client: &Client, id: u64) -> Result<usize, ApiError> {
+    let sql = "SELECT COUNT(*) FROM webhook_dead_letters WHERE webhook_id = $1";
+
+    get_int(&client.query_one(sql, &[&to_i64(id)?]).await?, 0)
+}
+
+// the last failure marks the delivery and files its dead letter in one statement
+pub async fn record_attempt(
+    client: &Client,
+    delivery_id: u64,
+    attempts: u32,
+    result: Result<u16, (Option<u16>, String)>,
+    now: u64,
+) -> Result<(), ApiError> {
+    let attempts = attempts + 1;
+    let (delivery_id, now) = (to_i64(delivery_id)?, to_i64(now)?);
+
+    match result {
+        Ok(status) => {
+            client
+                .execute(
+                    "UPDATE webhook_deliveries
+                    SET status = 'delivered', attempts = $1, response_status = $2, error = NULL,
+                        delivered_at = $3
+                    WHERE id = $4",
+                    &[&i64::from(attempts), &i64::from(status), &now, &delivery_id],
+                )
+                .await?;
+        }
+        Err((status, error)) if attempts >= WEBHOOK_MAX_ATTEMPTS => {
+            client
+                .execute(
+                    "WITH failed AS (
+                        UPDATE webhook_deliveries
+                        SET status = 'failed', attempts = $1, response_status = $2, error = $3
+                        WHERE id = $4
+                        RETURNING id, webhook_id, payload, error
+                    )
+                    INSERT INTO webhook_dead_letters (
+                        delivery_id, webhook_id, payload, error, failed_at
+                    )
+                    SELECT id, webhook_id, payload, error, $5 FROM failed
+                    ON CONFLICT (delivery_id) DO NOTHING",
+                    &[
+                        &i64::from(attempts),
+                        &status.map(i64::from),
+                        &error,
+                        &delivery_id,
+                        &now,
+                    ],
+                )
+                .await?;
+        }
+        Err((status, error)) => {
+            let next_attempt_at = now + to_i64(retry_delay(attempts))?;
+
+            client
+                .execute(
+                    "UPDATE webhook_deliveries
+                    SET attempts = $1, response_status = $2, error = $3, next_attempt_at = $4
+                    WHERE id = $5",
+                    &[
+                        &i64::from(attempts),
+                        &status.map(i64::from),
+                        &error,
+                        &next_attempt_at,
+                        &delivery_id,
+                    ],
+                )
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn get_due_deliveries_internal(
+    client: &Client,
+    now: u64,
+    limit: u32,
+) -> Result<Vec<PendingDelivery>, ApiError> {
+    let sql = "
+        SELECT d.id, d.attempts, d.payload, w.url, w.secret
+        FROM webhook_deliveries d
+        INNER JOIN webhooks w ON w.id = d.webhook_id
+        WHERE d.status = 'pending' AND d.next_attempt_at <= $1 AND w.active
+        ORDER BY d.id ASC
+        LIMIT $2";
+
+    client
+        .query(sql, &[&to_i64(now)?, &i64::from(limit)])
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(PendingDelivery {
+                id: get_int(row, 0)?,
+                attempts: get_int(row, 1)?,
+                payload: row.try_get(2)?,
+                url: row.try_get(3)?,
+                secret: row.try_get(4)?,
+            })
+        })
+        .collect()
+}